@@ -0,0 +1,45 @@
+/* C interface to the overload-vibe-coding rules engine. See src/lib.rs. */
+#ifndef CHESS_H
+#define CHESS_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* A game: the position, the moves played and the result. */
+typedef struct ChessGame ChessGame;
+
+/* Return codes of chess_make_move. */
+#define CHESS_OK 0
+#define CHESS_ILLEGAL_MOVE 1
+#define CHESS_INVALID_ARGUMENT 2
+
+/* Create a game from the initial position. */
+ChessGame *chess_new(void);
+
+/* Create a game from a FEN string, or return NULL if it is not valid. */
+ChessGame *chess_from_fen(const char *fen);
+
+/* Release a game. NULL is ignored. */
+void chess_free(ChessGame *game);
+
+/* Play a move given in UCI ("e2e4", "e7e8q") or SAN ("Nf3"). */
+int chess_make_move(ChessGame *game, const char *move);
+
+/* Write the legal moves in UCI, separated by spaces, and return the length of the
+   full list. At most size - 1 bytes are written, followed by a NUL. */
+size_t chess_legal_moves(const ChessGame *game, char *buffer, size_t size);
+
+/* Write the position as FEN and return its full length, as chess_legal_moves does. */
+size_t chess_fen(const ChessGame *game, char *buffer, size_t size);
+
+/* Give the result: 0 in progress, 1 White wins, 2 Black wins, 3 drawn. */
+int chess_result(const ChessGame *game);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
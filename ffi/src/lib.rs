@@ -0,0 +1,206 @@
+//! # C API
+//!
+//! An `extern "C"` interface to the rules engine for C and C++ programs, declared in
+//! `include/chess.h`. A game is an opaque handle made by `chess_new` or
+//! `chess_from_fen` and released with `chess_free`:
+//!
+//! ```c
+//! ChessGame *game = chess_new();
+//! chess_make_move(game, "e2e4");
+//! char fen[128];
+//! chess_fen(game, fen, sizeof fen);
+//! chess_free(game);
+//! ```
+//!
+//! Text is returned the way `snprintf` does it: the functions write as much as fits,
+//! always NUL-terminated, and return the full length so the caller can retry with a
+//! bigger buffer. The crate is built as a shared and a static library, so a C program
+//! links `liboverload_vibe_coding_ffi` and includes the header; `tests/ffi.c` is one,
+//! and `tests/ffi.rs` compiles and runs it.
+
+use std::ffi::{CStr, c_char, c_int};
+
+use overload_vibe_coding::{Game, GameResult, GameState, Move};
+
+// Return codes of chess_make_move.
+const CHESS_OK: c_int = 0;
+const CHESS_ILLEGAL_MOVE: c_int = 1;
+const CHESS_INVALID_ARGUMENT: c_int = 2;
+
+// > create a game from the initial position
+#[unsafe(no_mangle)]
+pub extern "C" fn chess_new() -> *mut Game {
+    Box::into_raw(Box::new(Game::from_position(GameState::new())))
+}
+
+// > create a game from a FEN string, or return NULL if it is not valid
+///
+/// # Safety
+/// `fen` must be NULL or a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chess_from_fen(fen: *const c_char) -> *mut Game {
+    // SAFETY: the caller passes NULL or a NUL-terminated string.
+    let Some(fen) = (unsafe { text(fen) }) else {
+        return std::ptr::null_mut();
+    };
+    match GameState::from_fen(fen) {
+        Ok(start) => Box::into_raw(Box::new(Game::from_position(start))),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+// > release a game made by chess_new or chess_from_fen
+///
+/// # Safety
+/// `game` must be NULL or a handle from `chess_new` or `chess_from_fen` that has not been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chess_free(game: *mut Game) {
+    if !game.is_null() {
+        // SAFETY: the handle came from Box::into_raw and is freed only once.
+        drop(unsafe { Box::from_raw(game) });
+    }
+}
+
+// > play a move given in UCI or SAN, returning CHESS_OK, CHESS_ILLEGAL_MOVE or CHESS_INVALID_ARGUMENT
+///
+/// # Safety
+/// `game` must be a live handle and `mv` NULL or a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chess_make_move(game: *mut Game, mv: *const c_char) -> c_int {
+    // SAFETY: the caller passes a live handle, or NULL.
+    let (Some(game), Some(mv)) = (unsafe { game.as_mut() }, unsafe { text(mv) }) else {
+        return CHESS_INVALID_ARGUMENT;
+    };
+    let state = game.state();
+    match Move::from_uci(mv).or_else(|_| state.parse_san(mv)).and_then(|mv| game.play(mv)) {
+        Ok(_) => CHESS_OK,
+        Err(_) => CHESS_ILLEGAL_MOVE,
+    }
+}
+
+// > write the legal moves in UCI, separated by spaces, returning the length of the full list
+///
+/// # Safety
+/// `game` must be a live handle and `buffer` must have room for `size` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chess_legal_moves(game: *const Game, buffer: *mut c_char, size: usize) -> usize {
+    // SAFETY: the caller passes a live handle, or NULL.
+    let Some(game) = (unsafe { game.as_ref() }) else {
+        return 0;
+    };
+    let moves: Vec<String> = game.state().legal_moves().into_iter().map(Move::to_uci).collect();
+    // SAFETY: the caller gives the buffer's size.
+    unsafe { write_text(&moves.join(" "), buffer, size) }
+}
+
+// > write the position as FEN, returning the length of the full string
+///
+/// # Safety
+/// `game` must be a live handle and `buffer` must have room for `size` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chess_fen(game: *const Game, buffer: *mut c_char, size: usize) -> usize {
+    // SAFETY: the caller passes a live handle, or NULL.
+    let Some(game) = (unsafe { game.as_ref() }) else {
+        return 0;
+    };
+    // SAFETY: the caller gives the buffer's size.
+    unsafe { write_text(&game.state().to_fen(), buffer, size) }
+}
+
+// > give the result: 0 in progress, 1 White wins, 2 Black wins, 3 drawn
+///
+/// # Safety
+/// `game` must be a live handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn chess_result(game: *const Game) -> c_int {
+    // SAFETY: the caller passes a live handle, or NULL.
+    match unsafe { game.as_ref() }.map(|game| game.state().result()) {
+        Some(GameResult::WhiteWins) => 1,
+        Some(GameResult::BlackWins) => 2,
+        Some(GameResult::Draw(_)) => 3,
+        Some(GameResult::Ongoing) | None => 0,
+    }
+}
+
+// > borrow a C string as UTF-8, or None for NULL or invalid text
+unsafe fn text<'a>(text: *const c_char) -> Option<&'a str> {
+    if text.is_null() {
+        return None;
+    }
+    // SAFETY: the caller guarantees a NUL-terminated string.
+    unsafe { CStr::from_ptr(text) }.to_str().ok()
+}
+
+// > copy as much of the text as fits, NUL-terminated, and return its full length
+unsafe fn write_text(text: &str, buffer: *mut c_char, size: usize) -> usize {
+    if !buffer.is_null() && size > 0 {
+        let len = text.len().min(size - 1);
+        // SAFETY: the buffer has room for size bytes, and len + 1 <= size.
+        unsafe {
+            std::ptr::copy_nonoverlapping(text.as_ptr().cast::<c_char>(), buffer, len);
+            *buffer.add(len) = 0;
+        }
+    }
+    text.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    fn fen(game: *const Game) -> String {
+        let mut buffer = [0 as c_char; 128];
+        let len = unsafe { chess_fen(game, buffer.as_mut_ptr(), buffer.len()) };
+        let fen = unsafe { CStr::from_ptr(buffer.as_ptr()) }.to_str().unwrap().to_string();
+        assert_eq!(len, fen.len());
+        fen
+    }
+
+    #[test]
+    fn test_play_a_game() {
+        let game = chess_new();
+        for mv in ["f2f3", "e5", "g4", "Qh4#"] {
+            let mv = CString::new(mv).unwrap();
+            assert_eq!(unsafe { chess_make_move(game, mv.as_ptr()) }, CHESS_OK);
+        }
+        assert_eq!(fen(game), "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3");
+        assert_eq!(unsafe { chess_result(game) }, 2);
+        let mv = CString::new("e2e4").unwrap();
+        assert_eq!(unsafe { chess_make_move(game, mv.as_ptr()) }, CHESS_ILLEGAL_MOVE);
+        assert_eq!(unsafe { chess_make_move(game, std::ptr::null()) }, CHESS_INVALID_ARGUMENT);
+        unsafe { chess_free(game) };
+    }
+
+    #[test]
+    fn test_legal_moves_and_short_buffers() {
+        let start = CString::new("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        let game = unsafe { chess_from_fen(start.as_ptr()) };
+        assert!(!game.is_null());
+        let needed = unsafe { chess_legal_moves(game, std::ptr::null_mut(), 0) };
+        let mut buffer = vec![0 as c_char; needed + 1];
+        assert_eq!(unsafe { chess_legal_moves(game, buffer.as_mut_ptr(), buffer.len()) }, needed);
+        let moves = unsafe { CStr::from_ptr(buffer.as_ptr()) }.to_str().unwrap();
+        assert!(moves.split(' ').any(|mv| mv == "e1g1"));
+
+        let mut short = [1 as c_char; 5];
+        unsafe { chess_fen(game, short.as_mut_ptr(), short.len()) };
+        assert_eq!(unsafe { CStr::from_ptr(short.as_ptr()) }.to_str().unwrap(), "4k3/");
+        unsafe { chess_free(game) };
+
+        let bad = CString::new("8/8 w").unwrap();
+        assert!(unsafe { chess_from_fen(bad.as_ptr()) }.is_null());
+        assert_eq!(unsafe { chess_result(std::ptr::null()) }, 0);
+    }
+
+    #[test]
+    fn test_header_declares_every_function() {
+        let header = include_str!("../include/chess.h");
+        for function in ["chess_new", "chess_from_fen", "chess_free", "chess_make_move", "chess_legal_moves", "chess_fen", "chess_result"] {
+            assert!(header.contains(&format!(" {}(", function)) || header.contains(&format!("*{}(", function)), "{}", function);
+        }
+        for (name, value) in [("CHESS_OK", CHESS_OK), ("CHESS_ILLEGAL_MOVE", CHESS_ILLEGAL_MOVE), ("CHESS_INVALID_ARGUMENT", CHESS_INVALID_ARGUMENT)] {
+            assert!(header.contains(&format!("#define {} {}", name, value)), "{}", name);
+        }
+    }
+}
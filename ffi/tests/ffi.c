@@ -0,0 +1,41 @@
+/* Plays Fool's mate through the C API. Compiled, linked and run by tests/ffi.rs. */
+#include <stdio.h>
+#include <string.h>
+
+#include "chess.h"
+
+#define CHECK(condition)                                                    \
+    do {                                                                    \
+        if (!(condition)) {                                                 \
+            fprintf(stderr, "%s:%d: %s\n", __FILE__, __LINE__, #condition); \
+            return 1;                                                       \
+        }                                                                   \
+    } while (0)
+
+int main(void) {
+    const char *moves[] = {"f2f3", "e5", "g4", "Qh4#"};
+    char fen[128];
+    char legal[1024];
+    ChessGame *game = chess_new();
+    CHECK(game != NULL);
+    CHECK(chess_legal_moves(game, legal, sizeof legal) == strlen(legal));
+    CHECK(strstr(legal, "g1f3") != NULL);
+    for (size_t i = 0; i < sizeof moves / sizeof moves[0]; i++) {
+        CHECK(chess_make_move(game, moves[i]) == CHESS_OK);
+    }
+    CHECK(chess_make_move(game, "e2e4") == CHESS_ILLEGAL_MOVE);
+    CHECK(chess_make_move(game, NULL) == CHESS_INVALID_ARGUMENT);
+    CHECK(chess_result(game) == 2);
+    CHECK(chess_fen(game, fen, sizeof fen) == strlen(fen));
+    CHECK(strcmp(fen, "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3") == 0);
+    chess_free(game);
+
+    CHECK(chess_from_fen("8/8 w") == NULL);
+    game = chess_from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+    CHECK(game != NULL);
+    CHECK(chess_fen(game, fen, 5) == strlen("4k3/8/8/8/8/8/8/4K2R w K - 0 1"));
+    CHECK(strcmp(fen, "4k3/") == 0);
+    chess_free(game);
+    chess_free(NULL);
+    return 0;
+}
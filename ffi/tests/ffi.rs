@@ -0,0 +1,35 @@
+//! Builds `tests/ffi.c` against `include/chess.h` and the static library, so the
+//! header and the exported symbols are checked by a real C compiler and linker.
+
+#![cfg(unix)]
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// > find the static library cargo built for this test, which sits beside it in deps
+fn static_library() -> PathBuf {
+    let exe = std::env::current_exe().unwrap();
+    exe.with_file_name("liboverload_vibe_coding_ffi.a")
+}
+
+#[test]
+fn test_c_program_links_and_runs() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let library = static_library();
+    assert!(library.exists(), "{} was not built", library.display());
+    let program = Path::new(env!("CARGO_TARGET_TMPDIR")).join("ffi");
+    let compiler = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let status = Command::new(compiler)
+        .args(["-std=c99", "-Wall", "-Werror", "-I"])
+        .arg(root.join("include"))
+        .arg(root.join("tests/ffi.c"))
+        .arg(&library)
+        // What the Rust standard library needs from the system on Linux and macOS.
+        .args(["-lpthread", "-ldl", "-lm", "-o"])
+        .arg(&program)
+        .status()
+        .expect("a C compiler to build tests/ffi.c");
+    assert!(status.success(), "tests/ffi.c did not compile and link");
+    let output = Command::new(&program).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}
@@ -0,0 +1,173 @@
+//! # Annotate
+//!
+//! Runs the engine over every move of a game and writes what it thinks into the game
+//! tree, ready to export as PGN. Each move gets a comment with the evaluation after
+//! it, from White's point of view in pawns, in the `[%eval]` form that Lichess and
+//! most GUIs display. A move that scores worse than the engine's choice is marked by
+//! how much it gives away, and the engine's line is added as a variation:
+//!
+//! - `?!` (`$6`), an inaccuracy, loses half a pawn or more;
+//! - `?` (`$2`), a mistake, loses a pawn or more;
+//! - `??` (`$4`), a blunder, loses three pawns or more.
+//!
+//! A move is marked `!` (`$1`) when it is the engine's choice but only a deeper search
+//! finds it, and it is worth a pawn more than the first depth thought.
+//!
+//! ```text
+//! annotate                     # at the prompt: print the game so far, annotated
+//! annotate analysed.pgn        # or save it
+//! ```
+//!
+//! Every position is searched once: the score of a move is the negated score of the
+//! position it leads to, so the loss is the best score plus the score after the move.
+//! Comments, NAGs and variations already in the game are kept. The move that ends the
+//! game gets no evaluation.
+
+use crate::eval::{self, Evaluator};
+use crate::game::{Game, MoveNode};
+use crate::search::{self, Limits, SearchResult, Signals};
+use crate::{Colour, GameState};
+
+// Centipawn losses for each mark.
+const INACCURACY: i32 = eval::PAWN / 2;
+const MISTAKE: i32 = eval::PAWN;
+const BLUNDER: i32 = 3 * eval::PAWN;
+// How much a deeper search must raise the score of a move no shallow search found for it to be marked good.
+const GOOD: i32 = eval::PAWN;
+// Mate scores are counted as this many centipawns, so one missed mate is not worth millions.
+const MATE_SCORE: i32 = 100 * eval::PAWN;
+
+// The numeric annotation glyphs written for each mark.
+const NAG_GOOD: u8 = 1;
+const NAG_MISTAKE: u8 = 2;
+const NAG_BLUNDER: u8 = 4;
+const NAG_INACCURACY: u8 = 6;
+
+// > add what one search of a position says: the result, and whether the best move was only found deeper
+struct Verdict {
+    result: SearchResult,
+    // The best move at depth 1 is not the final one, and the score rose by at least GOOD since.
+    deep: bool,
+}
+
+// > search a position, noting whether the best move changed and the score rose after the first depth
+fn verdict(position: &GameState, limits: Limits, evaluator: &Evaluator, threads: usize) -> Verdict {
+    let mut first = None;
+    let result = search::iterative_deepening(position, limits, evaluator, threads, Signals::NONE, |iteration| {
+        first.get_or_insert((iteration.best_move, iteration.score));
+    });
+    let deep = first.is_some_and(|(mv, score)| mv != result.best_move && result.score - score >= GOOD);
+    Verdict { result, deep }
+}
+
+// > annotate every move of the game's main line with the engine's evaluation, marks for the moves that lose
+// > ground and the engine's line instead of them, calling progress after each position is searched
+pub fn annotate(game: &Game, limits: Limits, evaluator: &Evaluator, threads: usize, mut progress: impl FnMut(usize, usize)) -> Game {
+    let mut position = game.start().clone();
+    let mut verdicts = vec![verdict(&position, limits, evaluator, threads)];
+    progress(1, game.line().len() + 1);
+    for node in game.line() {
+        position.make_move(node.mv).expect("recorded moves are legal");
+        verdicts.push(verdict(&position, limits, evaluator, threads));
+        progress(verdicts.len(), game.line().len() + 1);
+    }
+
+    let mut annotated = Game::from_position(game.start().clone());
+    annotated.set_tags(game.tags().to_vec());
+    for (node, pair) in game.line().iter().zip(verdicts.windows(2)) {
+        let (before, after) = (&pair[0], &pair[1]);
+        let mover = annotated.state().current_player;
+        let mut node = node.clone();
+        let loss = centipawns(before.result.score) + centipawns(after.result.score);
+        let nag = match loss {
+            _ if before.result.best_move == Some(node.mv) => before.deep.then_some(NAG_GOOD),
+            loss if loss >= BLUNDER => Some(NAG_BLUNDER),
+            loss if loss >= MISTAKE => Some(NAG_MISTAKE),
+            loss if loss >= INACCURACY => Some(NAG_INACCURACY),
+            _ => None,
+        };
+        if let Some(nag) = nag.filter(|nag| !node.nags.contains(nag)) {
+            node.nags.push(nag);
+        }
+        if loss >= MISTAKE && before.result.best_move != Some(node.mv) {
+            node.variations.push(before.result.pv.iter().copied().map(MoveNode::new).collect());
+        }
+        // A finished game has nothing to evaluate.
+        if after.result.best_move.is_some() {
+            let eval = eval_comment(&after.result, mover.opponent());
+            node.comment = Some(match node.comment.take() {
+                Some(comment) => format!("{} {}", eval, comment),
+                None => eval,
+            });
+        }
+        annotated.play_node(node).expect("recorded moves are legal");
+    }
+    annotated.set_result(game.state().result);
+    annotated
+}
+
+// > give a score in centipawns for the side to move, with mates counted as a large but finite amount
+fn centipawns(score: i32) -> i32 {
+    score.clamp(-MATE_SCORE, MATE_SCORE)
+}
+
+// > write the evaluation of a position as a [%eval] comment from White's point of view,
+// > in pawns, or as #N for a mate in N (negative when Black mates)
+fn eval_comment(result: &SearchResult, to_move: Colour) -> String {
+    let sign = if to_move == Colour::White { 1 } else { -1 };
+    match result.mate_in() {
+        Some(moves) => format!("[%eval #{}]", sign * moves),
+        None => format!("[%eval {:.2}]", f64::from(sign * result.score) / 100.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Move, Square};
+
+    fn annotate_moves(fen: &str, moves: &[&str]) -> Game {
+        let mut game = Game::from_position(GameState::from_fen(fen).unwrap());
+        for mv in moves {
+            game.play(Move::from_uci(mv).unwrap()).unwrap();
+        }
+        annotate(&game, Limits::depth(2), &Evaluator::default(), 1, |_, _| {})
+    }
+
+    #[test]
+    fn test_blunder_gets_a_variation() {
+        // White hangs the queen instead of taking Black's; Black takes it.
+        let game = annotate_moves("4k3/8/8/3q4/4P3/8/8/3QK3 w - - 0 1", &["d1h5", "d5h5"]);
+        let line = game.line();
+        assert_eq!(line[0].nags, vec![NAG_BLUNDER]);
+        assert_eq!(line[0].variations[0][0].mv, Move::new(Square::E4, Square::D5));
+        assert!(line[0].comment.as_deref().unwrap().starts_with("[%eval -"), "{:?}", line[0].comment);
+        // Taking the queen back was best, so it is not marked.
+        assert!(line[1].nags.is_empty() && line[1].variations.is_empty());
+        let pgn = game.to_pgn();
+        assert!(pgn.contains("Qh5+ $4 {[%eval"), "{}", pgn);
+        assert!(pgn.contains("(1. exd5"), "{}", pgn);
+    }
+
+    #[test]
+    fn test_mate_ends_without_an_eval() {
+        let mut game = Game::from_position(GameState::from_fen("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1").unwrap());
+        game.play_node(MoveNode { comment: Some("back rank".to_string()), ..MoveNode::new(Move::new(Square::A1, Square::A8)) }).unwrap();
+        let game = annotate(&game, Limits::depth(2), &Evaluator::default(), 1, |_, _| {});
+        assert_eq!(game.line()[0].comment.as_deref(), Some("back rank"));
+        assert!(game.line()[0].nags.is_empty());
+        assert_eq!(game.to_pgn().lines().last(), Some("1. Ra8# {back rank} 1-0"));
+        // A move that walks into mate is a blunder, and the mate is seen coming.
+        let game = annotate_moves("6k1/5ppp/8/8/8/8/5PPP/R5K1 b - - 0 1", &["g8h8"]);
+        assert_eq!(game.line()[0].comment.as_deref(), Some("[%eval #1]"));
+        assert_eq!(game.line()[0].nags, vec![NAG_BLUNDER]);
+    }
+
+    #[test]
+    fn test_eval_comment() {
+        let result = SearchResult { best_move: None, score: 125, depth: 1, nodes: 0, time: Default::default(), pv: Vec::new() };
+        assert_eq!(eval_comment(&result, Colour::White), "[%eval 1.25]");
+        assert_eq!(eval_comment(&result, Colour::Black), "[%eval -1.25]");
+        assert_eq!(centipawns(search::MATE - 3), MATE_SCORE);
+    }
+}
@@ -0,0 +1,1452 @@
+//! # Command line
+//!
+//! The program as it is run from a terminal: the subcommands (`match`, `bench`, `epd`,
+//! `perft`), the `--uci`, `--serve` and other modes, and the prompt that shows the
+//! board and reads moves and commands. It is built on the library's public API, the
+//! same one any other program would use.
+
+use std::sync::Arc;
+
+use overload_vibe_coding::{
+    Book, BookChoice, BookEngine, BookOptions, Broadcast, Clocks, Colour, Endpoint, Engine, EngineError, Epd, Game, GameResult, GameState, InternalEngine,
+    MatchConfig, MctsEngine, Move, MoveKind, Network, PgnReader, PolyglotKeys, Square, UciEngine, UciSession, promotion_piece, run_match,
+};
+use overload_vibe_coding::{annotate, engine, eval, mate, network, perft, search, skill};
+
+#[cfg(feature = "dgt")]
+use crate::dgt;
+#[cfg(feature = "import")]
+use crate::error::ImportError;
+#[cfg(feature = "http")]
+use crate::http;
+#[cfg(feature = "import")]
+use crate::import;
+#[cfg(feature = "lichess")]
+use crate::lichess;
+use crate::puzzle::Puzzle;
+#[cfg(feature = "server")]
+use crate::server;
+use crate::{clipboard, diagram, json, puzzle};
+
+// How long and how deep the search behind "hint" goes.
+const HINT_TIME: std::time::Duration = std::time::Duration::from_secs(1);
+const HINT_DEPTH: u32 = 6;
+// How long and how deep "annotate" searches each position.
+const ANNOTATE_TIME: std::time::Duration = std::time::Duration::from_secs(1);
+const ANNOTATE_DEPTH: u32 = 5;
+// How many plies "puzzles" searches for a tactic, unless told otherwise.
+const PUZZLE_PLIES: u32 = 4;
+// How long and how deep the search behind --blunder-check goes.
+const BLUNDER_TIME: std::time::Duration = std::time::Duration::from_millis(500);
+const BLUNDER_DEPTH: u32 = 4;
+
+// > add a loop to display the board and accept moves
+pub fn run() {
+    let args: Vec<String> = std::env::args().collect();
+    let flag = |name: &str| args.iter().any(|arg| arg == name);
+    match args.get(1).map(String::as_str) {
+        Some("match") => match_command(&args[2..]),
+        // > measure the speed of the search by searching a fixed set of positions to a fixed depth
+        Some("bench") => bench_subcommand(&args[2..]),
+        // > run an EPD test suite such as WAC, counting the positions where the search finds a best move
+        Some("epd") => epd_subcommand(&args[2..]),
+        // > run perft from the command line, failing when a count is wrong, for regression checks
+        Some("perft") => {
+            if !perft_subcommand(&args[2..]) {
+                std::process::exit(1);
+            }
+        }
+        _ if flag("--uci") => uci_mode(),
+        #[cfg(feature = "lichess")]
+        _ if flag("--lichess-bot") => lichess_bot_mode(),
+        #[cfg(feature = "server")]
+        _ if flag("--serve") => serve_mode(&args),
+        #[cfg(feature = "http")]
+        _ if flag("--http") => http_mode(&args),
+        _ => play(&args),
+    }
+}
+
+// > speak UCI on stdin and stdout with --uci, so chess GUIs can use the program as an engine
+fn uci_mode() {
+    if let Err(err) = UciSession::new().run(std::io::BufReader::new(std::io::stdin()), &mut std::io::stdout()) {
+        eprintln!("UCI session failed: {}", err);
+    }
+}
+
+// > play on Lichess as a bot with --lichess-bot, taking the bot account's token from LICHESS_BOT_TOKEN
+#[cfg(feature = "lichess")]
+fn lichess_bot_mode() {
+    let Ok(token) = std::env::var("LICHESS_BOT_TOKEN") else {
+        println!("Set LICHESS_BOT_TOKEN to the bot account's API token.");
+        return;
+    };
+    let runtime = tokio::runtime::Runtime::new().expect("Failed to start the async runtime");
+    if let Err(err) = runtime.block_on(lichess::LichessBot::new(&token).run()) {
+        println!("{}", err);
+    }
+}
+
+// > host games over WebSockets with --serve <address>, giving each player --minutes on the clock
+#[cfg(feature = "server")]
+fn serve_mode(args: &[String]) {
+    let address = option(args, "--serve").map_or("127.0.0.1:8080", String::as_str);
+    let minutes = match args.iter().position(|arg| arg == "--minutes").map(|i| args.get(i + 1).and_then(|m| m.parse::<u64>().ok())) {
+        Some(Some(minutes)) => minutes,
+        Some(None) => {
+            println!("Usage: --minutes <whole minutes per player>");
+            return;
+        }
+        None => 10,
+    };
+    let runtime = tokio::runtime::Runtime::new().expect("Failed to start the async runtime");
+    if let Err(err) = runtime.block_on(server::serve(address, std::time::Duration::from_secs(minutes * 60))) {
+        println!("The server stopped: {}", err);
+    }
+}
+
+// > serve the REST API with --http <address>
+#[cfg(feature = "http")]
+fn http_mode(args: &[String]) {
+    let address = option(args, "--http").map_or("127.0.0.1:8000", String::as_str);
+    if let Err(err) = http::serve(address) {
+        println!("The HTTP server stopped: {}", err);
+    }
+}
+
+// > find the value given after an option on the command line
+fn option<'a>(args: &'a [String], name: &str) -> Option<&'a String> {
+    args.iter().position(|arg| arg == name).and_then(|i| args.get(i + 1))
+}
+
+// > play at the prompt, from the start or the position given by --fen, alone, against an engine or over the network
+fn play(args: &[String]) {
+    // > accept --fen "<fen>" on the command line to start from a custom position
+    let start = match args.iter().position(|arg| arg == "--fen") {
+        Some(i) => match args.get(i + 1).map(|fen| GameState::from_fen(fen)) {
+            Some(Ok(game_state)) => game_state,
+            Some(Err(err)) => {
+                println!("Invalid FEN: {}", err);
+                return;
+            }
+            None => {
+                println!("Usage: --fen \"<fen>\"");
+                return;
+            }
+        },
+        None => GameState::new(),
+    };
+    let mut game = Game::from_position(start);
+
+    // > play another copy of the program over TCP with --host <port> or --connect <address>
+    let network = match (args.iter().position(|arg| arg == "--host"), args.iter().position(|arg| arg == "--connect")) {
+        (Some(i), _) => match args.get(i + 1).and_then(|port| port.parse().ok()) {
+            Some(port) => Some(network::host(port, &mut game)),
+            None => {
+                println!("Usage: --host <port>");
+                return;
+            }
+        },
+        (None, Some(i)) => match args.get(i + 1) {
+            Some(address) => Some(network::connect(address, &mut game)),
+            None => {
+                println!("Usage: --connect <host:port>");
+                return;
+            }
+        },
+        (None, None) => None,
+    };
+    if let Some(result) = network {
+        if let Err(err) = result {
+            println!("{}", err);
+        }
+        return;
+    }
+
+    if let Some(session) = Session::from_args(args, game) {
+        session.run();
+    }
+}
+
+// The engine playing one side at the prompt, the side it plays and what is left on its clock.
+type Opponent = (Box<dyn Engine>, Colour, std::time::Duration);
+
+// > keep the game at the prompt together with everything the command line set up for it
+struct Session {
+    game: Game,
+    book: Option<std::sync::Arc<Book>>,
+    book_options: BookOptions,
+    evaluator: eval::Evaluator,
+    threads: usize,
+    skill: skill::Skill,
+    blunder_check: Option<i32>,
+    opponent: Option<Opponent>,
+    ponder: bool,
+    secret: Option<String>,
+    #[cfg(feature = "dgt")]
+    dgt: Option<dgt::DgtBoard<Box<dyn serialport::SerialPort>>>,
+    broadcast: Option<Broadcast>,
+}
+
+impl Session {
+    // > read the options for a game at the prompt, explaining any that are wrong and giving back None
+    fn from_args(args: &[String], mut game: Game) -> Option<Session> {
+        let option = |name: &str| option(args, name);
+
+        // > accept --book <book.bin> to consult an opening book, with --book-keys <file with Random64 numbers> for a
+        // > book made with other numbers than polyglot's
+        let book = match option("--book") {
+            Some(book_path) => {
+                let keys = match option("--book-keys") {
+                    Some(keys_path) => std::fs::read_to_string(keys_path)
+                        .map_err(|err| err.to_string())
+                        .and_then(|text| PolyglotKeys::from_source(&text).map_err(|err| err.to_string())),
+                    None => Ok(PolyglotKeys::standard()),
+                };
+                let book = keys.and_then(|keys| {
+                    let bytes = std::fs::read(book_path).map_err(|err| err.to_string())?;
+                    Book::from_bytes(&bytes, keys).map_err(|err| err.to_string())
+                });
+                match book {
+                    Ok(book) => Some(std::sync::Arc::new(book)),
+                    Err(err) => {
+                        println!("Could not open the book: {}", err);
+                        return None;
+                    }
+                }
+            }
+            None => None,
+        };
+
+        // > let the program's engine play from the book for --book-depth <moves>, choosing with --book-choice best|weighted|random
+        let book_options = BookOptions {
+            depth: match option("--book-depth").map(|depth| depth.parse()) {
+                None => BookOptions::default().depth,
+                Some(Ok(depth)) => depth,
+                Some(Err(_)) => {
+                    println!("Usage: --book-depth <moves>");
+                    return None;
+                }
+            },
+            choice: match option("--book-choice").map(String::as_str) {
+                None => BookOptions::default().choice,
+                Some("best") => BookChoice::Best,
+                Some("weighted") => BookChoice::Weighted,
+                Some("random") => BookChoice::Random,
+                Some(_) => {
+                    println!("Usage: --book-choice best|weighted|random");
+                    return None;
+                }
+            },
+        };
+
+        // > evaluate with an NNUE network for "go" with --nnue <file.nnue>
+        let evaluator = match option("--nnue") {
+            Some(path) => match Network::load(path) {
+                Ok(network) => {
+                    println!("Evaluating with {}.", network.description());
+                    eval::Evaluator::Nnue(Arc::new(network))
+                }
+                Err(err) => {
+                    println!("Could not load the network: {}", err);
+                    return None;
+                }
+            },
+            None => eval::Evaluator::default(),
+        };
+
+        // > search with more than one thread for "go" with --threads <n>
+        let threads = option("--threads").map_or(Some(1), |value| value.parse::<usize>().ok());
+        let Some(threads) = threads.filter(|threads| (1..=search::MAX_THREADS).contains(threads)) else {
+            println!("Usage: --threads <1-{}>", search::MAX_THREADS);
+            return None;
+        };
+
+        // > play below full strength, for "go" and the internal engine, with --skill <0-20> or --elo <800-2400>
+        let skill = match (option("--skill"), option("--elo")) {
+            (Some(level), _) => level.parse().ok().filter(|&level| level <= skill::MAX_LEVEL).map(skill::Skill::level),
+            (None, Some(elo)) => elo.parse().ok().filter(|elo| (skill::MIN_ELO..=skill::MAX_ELO).contains(elo)).map(skill::Skill::from_elo),
+            (None, None) => Some(skill::Skill::default()),
+        };
+        let Some(skill) = skill else {
+            println!("Usage: --skill <0-{}> or --elo <{}-{}>", skill::MAX_LEVEL, skill::MIN_ELO, skill::MAX_ELO);
+            return None;
+        };
+
+        // > with --blunder-check <centipawns>, warn when a move entered at the keyboard scores that much worse
+        // > than the best move, and offer to take it back before the opponent replies
+        let blunder_check = match option("--blunder-check").map(|value| value.parse::<i32>()) {
+            Some(Ok(threshold)) if threshold > 0 => Some(threshold),
+            Some(_) => {
+                println!("Usage: --blunder-check <centipawns>");
+                return None;
+            }
+            None => None,
+        };
+
+        // > play against an external UCI engine with --engine <path> [--engine-plays white|black] [--engine-time seconds]
+        // > [--engine-option Name=value ...], or against the program's own with --engine internal [--engine-type alphabeta|mcts];
+        // > with --ponder the engine thinks on the player's time too
+        let ponder = args.iter().any(|arg| arg == "--ponder");
+        let opponent = match option("--engine") {
+            Some(path) => {
+                let colour = match option("--engine-plays").map(String::as_str) {
+                    None | Some("black") => Colour::Black,
+                    Some("white") => Colour::White,
+                    Some(_) => {
+                        println!("Usage: --engine-plays white|black");
+                        return None;
+                    }
+                };
+                let Some(thinking_time) = option("--engine-time").map_or(Some(std::time::Duration::from_secs(60)), |value| {
+                    parse_seconds(value).filter(|time| !time.is_zero())
+                }) else {
+                    println!("Usage: --engine-time <seconds>");
+                    return None;
+                };
+                // Each --engine-option is sent to an external engine as a UCI setoption, such as "Skill Level=5".
+                let engine_options: Option<Vec<(&str, &str)>> = args
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, arg)| *arg == "--engine-option")
+                    .map(|(i, _)| args.get(i + 1).and_then(|setting| setting.split_once('=')))
+                    .collect();
+                let Some(engine_options) = engine_options else {
+                    println!("Usage: --engine-option <name>=<value>");
+                    return None;
+                };
+                let internal: Box<dyn Engine> = match option("--engine-type").map(String::as_str) {
+                    None | Some("alphabeta") => Box::new(InternalEngine::with_skill(skill)),
+                    Some("mcts") => Box::new(MctsEngine),
+                    Some(_) => {
+                        println!("Usage: --engine-type alphabeta|mcts");
+                        return None;
+                    }
+                };
+                let started: Result<Box<dyn Engine>, EngineError> = match (path.as_str(), &book) {
+                    ("internal", Some(book)) => Ok(Box::new(BookEngine::new(internal, book.clone(), book_options))),
+                    ("internal", None) => Ok(internal),
+                    (path, _) => UciEngine::start(path, &[]).and_then(|mut engine| {
+                        for (name, value) in engine_options {
+                            engine.set_option(name, value)?;
+                        }
+                        Ok(Box::new(engine) as Box<dyn Engine>)
+                    }),
+                };
+                match started.and_then(|mut engine| engine.new_game().map(|()| engine)) {
+                    Ok(engine) => {
+                        let mut tags = game.tags().to_vec();
+                        let side = if colour == Colour::White { "White" } else { "Black" };
+                        for (name, value) in tags.iter_mut() {
+                            if name == side {
+                                *value = engine.name();
+                            }
+                        }
+                        game.set_tags(tags);
+                        println!("Playing against {} as {:?}.", engine.name(), colour.opponent());
+                        Some((engine, colour, thinking_time))
+                    }
+                    Err(err) => {
+                        println!("Could not start the engine: {}", err);
+                        return None;
+                    }
+                }
+            }
+            None => None,
+        };
+
+        // > read moves from a DGT board with --dgt <serial port>
+        #[cfg(feature = "dgt")]
+        let dgt = match option("--dgt").map(|path| dgt::open(path)) {
+            Some(Ok(board)) => Some(board),
+            Some(Err(err)) => {
+                println!("{}", err);
+                return None;
+            }
+            None => None,
+        };
+
+        // > broadcast the game as it is played with --broadcast <file.pgn> [--broadcast-url http://host/path]
+        let broadcast = match (option("--broadcast"), option("--broadcast-url")) {
+            (Some(path), url) => match url.map(|url| Endpoint::from_url(url)).transpose() {
+                Ok(endpoint) => Some(Broadcast::new(path, endpoint)),
+                Err(err) => {
+                    println!("{}", err);
+                    return None;
+                }
+            },
+            (None, Some(_)) => {
+                println!("Usage: --broadcast <file.pgn> --broadcast-url <http://host/path>");
+                return None;
+            }
+            (None, None) => None,
+        };
+
+        Some(Session {
+            game,
+            book,
+            book_options,
+            evaluator,
+            threads,
+            skill,
+            blunder_check,
+            opponent,
+            ponder,
+            secret: option("--secret").cloned(),
+            #[cfg(feature = "dgt")]
+            dgt,
+            broadcast,
+        })
+    }
+
+    // > show the board after every move and take moves and commands until the player quits
+    fn run(mut self) {
+        loop {
+            self.show();
+
+            #[cfg(feature = "dgt")]
+            if self.dgt_turn() {
+                continue;
+            }
+            if self.engine_turn() {
+                continue;
+            }
+
+            let game_state = self.game.state();
+            if game_state.is_threefold_repetition() {
+                println!("This position has occurred three times. Type 'claim' to claim a draw.");
+            } else if game_state.halfmove_clock() >= 100 {
+                println!("Fifty moves have passed without a capture or pawn move. Type 'claim' to claim a draw.");
+            }
+
+            println!("Enter your move (e.g., 'Nf3', 'g1f3' or 'g1 f3') or 'quit' to exit:");
+            let mut input = String::new();
+            if std::io::stdin().read_line(&mut input).expect("Failed to read input") == 0 {
+                break;
+            }
+            if !self.command(input.trim()) {
+                break;
+            }
+        }
+    }
+
+    // > print the board, the material balance and whether the game is over or the side to move is in check,
+    // > sending the game to the broadcast first
+    fn show(&mut self) {
+        if let Some(broadcast) = self.broadcast.as_mut()
+            && let Err(err) = broadcast.update(&self.game)
+        {
+            println!("{}", err);
+        }
+        let game_state = self.game.state();
+        println!("{}", game_state);
+        // > show who is ahead in material under the board
+        let balance = eval::material_balance(game_state);
+        if balance != 0 {
+            let leader = if balance > 0 { Colour::White } else { Colour::Black };
+            println!("Material: {:?} +{}", leader, balance.abs() / eval::PAWN);
+        }
+        if game_state.result() != GameResult::Ongoing {
+            println!("Game over: {}. Type 'save <file>' to keep a PGN record, or 'quit' to exit.", game_state.result());
+        } else if game_state.is_in_check(game_state.side_to_move()) {
+            println!("{:?} is in check!", game_state.side_to_move());
+        }
+    }
+
+    // > tell whether the engine plays the side to move in a game still going on
+    fn engine_to_move(&self) -> bool {
+        let game_state = self.game.state();
+        game_state.result() == GameResult::Ongoing
+            && self.opponent.as_ref().is_some_and(|(_, colour, _)| *colour == game_state.side_to_move())
+    }
+
+    // > take the human's moves from the DGT board; if it fails, carry on with the keyboard
+    #[cfg(feature = "dgt")]
+    fn dgt_turn(&mut self) -> bool {
+        let game_state = self.game.state();
+        if game_state.result() != GameResult::Ongoing || self.engine_to_move() {
+            return false;
+        }
+        let Some(board) = self.dgt.as_mut() else {
+            return false;
+        };
+        println!("Waiting for {:?} to move on the board...", game_state.side_to_move());
+        match board.wait_for_move(game_state) {
+            Ok(mv) => play_move(&mut self.game, mv),
+            Err(err) => {
+                println!("{}. Moves are now entered from the keyboard.", err);
+                self.dgt = None;
+            }
+        }
+        true
+    }
+
+    // > let the engine move when it is its turn; if it fails, carry on with two human players
+    fn engine_turn(&mut self) -> bool {
+        if !self.engine_to_move() {
+            return false;
+        }
+        let Some((engine, _, time)) = self.opponent.as_mut() else {
+            return false;
+        };
+        println!("{} is thinking...", engine.name());
+        let clocks = Clocks { white: *time, black: *time, increment: std::time::Duration::ZERO };
+        let started = std::time::Instant::now();
+        match engine.best_move(&self.game, &clocks) {
+            Ok(mv) => {
+                // --engine-time is the engine's clock for the whole game, and it runs down as the engine thinks.
+                *time = time.saturating_sub(started.elapsed());
+                play_move(&mut self.game, mv);
+                if self.ponder {
+                    engine.ponder(&self.game, &Clocks { white: *time, black: *time, increment: std::time::Duration::ZERO });
+                }
+                #[cfg(feature = "dgt")]
+                if let Some(board) = self.dgt.as_mut() {
+                    println!("Make the move on the board.");
+                    if let Err(err) = board.wait_for_position(self.game.state()) {
+                        println!("{}. Moves are now entered from the keyboard.", err);
+                        self.dgt = None;
+                    }
+                }
+            }
+            Err(err) => {
+                println!("The engine stopped: {}. Both sides are now played from the keyboard.", err);
+                self.opponent = None;
+            }
+        }
+        true
+    }
+
+    // > carry out a line typed at the prompt, a command or a move; tell whether to carry on
+    fn command(&mut self, input: &str) -> bool {
+        if input.eq_ignore_ascii_case("quit") {
+            println!("Exiting the game. Goodbye!");
+            return false;
+        }
+
+        if input.eq_ignore_ascii_case("resign") {
+            if let Err(err) = self.game.resign(self.game.state().side_to_move()) {
+                println!("{}", err);
+            }
+        } else if input.eq_ignore_ascii_case("draw") {
+            self.draw();
+        } else if input.eq_ignore_ascii_case("claim") {
+            if let Err(err) = self.game.claim_draw() {
+                println!("{}", err);
+            }
+        } else if input.eq_ignore_ascii_case("undo") || input.eq_ignore_ascii_case("redo") {
+            self.step_through_history(input.eq_ignore_ascii_case("undo"));
+        } else if input.eq_ignore_ascii_case("threats") {
+            self.threats();
+        } else if input.eq_ignore_ascii_case("mobility") {
+            let game_state = self.game.state();
+            println!("White has {} legal moves, Black has {}.", game_state.mobility(Colour::White), game_state.mobility(Colour::Black));
+        } else if input.eq_ignore_ascii_case("moves") {
+            let moves: Vec<String> = self.game.state().legal_moves().into_iter().map(Move::to_uci).collect();
+            println!("{}", moves.join(" "));
+        } else if let Some(path) = input.strip_prefix("save ") {
+            self.save(path.trim());
+        } else if input == "go" || input.starts_with("go ") {
+            self.go(&input.split_whitespace().skip(1).collect::<Vec<_>>());
+        } else if input.eq_ignore_ascii_case("analyse") || input.starts_with("analyse ") {
+            self.analyse(input["analyse".len()..].trim());
+        } else if input.eq_ignore_ascii_case("annotate") || input.starts_with("annotate ") {
+            self.annotate(input["annotate".len()..].trim());
+        } else if let Some(n) = input.strip_prefix("matein ") {
+            self.mate_in(n.trim());
+        } else if let Some(args) = input.strip_prefix("perft ") {
+            // > "perft <depth>" counts the positions at the given depth of the legal move tree, to check move generation;
+            // > "perft divide <depth>" splits the count by the first move, and "perft suite <depth>" checks the standard positions;
+            // > the first moves are shared out among the --threads
+            perft_command(self.game.state(), &args.split_whitespace().collect::<Vec<_>>(), self.threads);
+        } else if input.eq_ignore_ascii_case("hint") {
+            self.hint();
+        } else if let Some((command, path)) = input.split_once(' ')
+            && (command == "send" || command == "receive")
+        {
+            self.correspondence(command == "send", path.trim());
+        } else if let Some(args) = input.strip_prefix("load ") {
+            self.load(args.trim());
+        } else if let Some(args) = input.strip_prefix("import ") {
+            self.import(args);
+        } else if let Some(args) = input.strip_prefix("games ") {
+            self.games(args.trim());
+        } else if input.eq_ignore_ascii_case("puzzles") || input.starts_with("puzzles ") {
+            self.puzzles(input.split_whitespace().skip(1).collect());
+        } else if let Some(args) = input.strip_prefix("puzzle ") {
+            self.puzzle(args.trim());
+        } else if input.eq_ignore_ascii_case("book") {
+            self.book();
+        } else if input.eq_ignore_ascii_case("epd") {
+            println!("{}", Epd::from_position(self.game.state()));
+        } else if input.eq_ignore_ascii_case("setup") {
+            self.setup();
+        } else if input.eq_ignore_ascii_case("copy fen") || input.eq_ignore_ascii_case("copy pgn") {
+            let text = if input.ends_with("fen") { self.game.state().to_fen() } else { self.game.to_pgn() };
+            match clipboard::copy(&text) {
+                Ok(()) => println!("Copied to the clipboard."),
+                Err(err) => println!("{}", err),
+            }
+        } else if input.eq_ignore_ascii_case("paste fen") {
+            match clipboard::paste().map(|text| GameState::from_fen(text.trim())) {
+                Ok(Ok(position)) => self.game = Game::from_position(position),
+                Ok(Err(err)) => println!("The clipboard does not hold a valid FEN: {}", err),
+                Err(err) => println!("{}", err),
+            }
+        } else if input.eq_ignore_ascii_case("json") {
+            println!("{}", json::state_json(&self.game));
+        } else if input.eq_ignore_ascii_case("fen") {
+            println!("{}", self.game.state().to_fen());
+        } else if input.eq_ignore_ascii_case("fen shredder") {
+            println!("{}", self.game.state().to_shredder_fen());
+        } else if input.eq_ignore_ascii_case("pins") {
+            let game_state = self.game.state();
+            for pin in game_state.pinned_pieces(game_state.side_to_move()) {
+                println!("{:?} is pinned by {:?}", pin.pinned, pin.pinner);
+            }
+        } else {
+            self.enter_move(input);
+        }
+        true
+    }
+
+    // > offer a draw, or accept the one the opponent offered
+    fn draw(&mut self) {
+        let player = self.game.state().side_to_move();
+        if self.game.state().draw_offer() == Some(player.opponent()) {
+            if let Err(err) = self.game.accept_draw(player) {
+                println!("{}", err);
+            }
+        } else if let Err(err) = self.game.offer_draw(player) {
+            println!("{}", err);
+        } else {
+            println!("{:?} offers a draw. Make your move; {:?} can type 'draw' to accept.", player, player.opponent());
+        }
+    }
+
+    // > take a move back, or play it again; against the engine, go back to the human's turn so it does not simply move again
+    fn step_through_history(&mut self, undo: bool) {
+        let mut taken = Vec::new();
+        while let Some(san) = step_through_history(&mut self.game, undo) {
+            taken.push(san);
+            if self.opponent.as_ref().is_none_or(|(_, colour, _)| *colour != self.game.state().side_to_move()) {
+                break;
+            }
+        }
+        match (taken.is_empty(), undo) {
+            (true, true) => println!("There is no move to take back."),
+            (true, false) => println!("There is no move to play again."),
+            (false, true) => println!("Took back {}.", taken.join(" ")),
+            (false, false) => println!("Played again {}.", taken.join(" ")),
+        }
+    }
+
+    // > list the pieces of the side to move that the opponent attacks, and by what
+    fn threats(&self) {
+        let game_state = self.game.state();
+        let player = game_state.side_to_move();
+        for (square, _) in game_state.pieces_of(player) {
+            let attackers = game_state.attackers_of(square, player.opponent());
+            if !attackers.is_empty() {
+                println!("{:?} is attacked by {:?}", square, attackers);
+            }
+        }
+    }
+
+    // Files ending in .bin use the compact binary format; anything else is PGN.
+    fn save(&self, path: &str) {
+        let contents = if path.ends_with(".bin") {
+            match self.game.save_binary() {
+                Ok(bytes) => bytes,
+                Err(err) => {
+                    println!("Could not save the game: {}", err);
+                    return;
+                }
+            }
+        } else {
+            self.game.to_pgn().into_bytes()
+        };
+        match std::fs::write(path, contents) {
+            Ok(()) => println!("Saved the game to {}.", path),
+            Err(err) => println!("Could not save the game: {}", err),
+        }
+    }
+
+    // > "go [depth N] [nodes N] [movetime ms] [infinite]" searches with iterative deepening, reporting
+    // > each depth, then plays the best move for the side to move; "go infinite" searches until Enter is pressed
+    fn go(&mut self, words: &[&str]) {
+        let game_state = self.game.state();
+        if let Some(mv) = self.book.as_ref().and_then(|book| book.choose(game_state, &self.book_options, engine::seed())) {
+            println!("Book move: {}", game_state.move_to_san(&mv));
+            play_move(&mut self.game, mv);
+            return;
+        }
+        let limits = match search::Limits::from_go(words, game_state.side_to_move()) {
+            Ok(limits) => search::Limits { skill: skill::Skill { seed: engine::seed(), ..self.skill }, ..limits },
+            Err(message) => {
+                println!("{}. Usage: go [depth <plies>] [nodes <positions>] [movetime <milliseconds>] [infinite]", message);
+                return;
+            }
+        };
+        match search_with_report(game_state, limits, &self.evaluator, self.threads).best_move {
+            Some(mv) => play_move(&mut self.game, mv),
+            None => println!("There is no move to play."),
+        }
+    }
+
+    // > "analyse" searches the current position until Enter is pressed, showing each depth, without playing a move;
+    // > "analyse <n>" looks at the position after the first n moves of the game instead, for reviewing a loaded game
+    fn analyse(&self, plies: &str) {
+        let position = match plies {
+            "" => self.game.state().clone(),
+            plies => match plies.parse::<usize>() {
+                Ok(plies) if plies <= self.game.history().len() => {
+                    let mut review = self.game.clone();
+                    review.truncate(plies);
+                    review.state().clone()
+                }
+                _ => {
+                    println!("Usage: analyse [<moves from the start, up to {}>]", self.game.history().len());
+                    return;
+                }
+            },
+        };
+        let limits = search::Limits { infinite: true, ..search::Limits::default() };
+        if let Some(mv) = search_with_report(&position, limits, &self.evaluator, self.threads).best_move {
+            println!("Best move: {}", position.move_to_san(&mv));
+        }
+    }
+
+    // > "annotate" runs the engine over every move of the game and prints it as PGN with evaluations, marks
+    // > for mistakes and better lines; "annotate <file>" saves it instead
+    fn annotate(&self, path: &str) {
+        let limits = search::Limits { depth: Some(ANNOTATE_DEPTH), ..search::Limits::movetime(ANNOTATE_TIME) };
+        let annotated = annotate::annotate(&self.game, limits, &self.evaluator, self.threads, |done, total| {
+            print!("\rAnnotating: {}/{} positions", done, total);
+            let _ = std::io::Write::flush(&mut std::io::stdout());
+        });
+        println!();
+        match path {
+            "" => println!("{}", annotated.to_pgn()),
+            path => match std::fs::write(path, annotated.to_pgn()) {
+                Ok(()) => println!("Saved the annotated game to {}.", path),
+                Err(err) => println!("Could not save the game: {}", err),
+            },
+        }
+    }
+
+    // > "matein <n>" lists every move that forces mate in at most n moves, for composing and checking puzzles
+    fn mate_in(&self, n: &str) {
+        let Some(n) = n.parse::<u32>().ok().filter(|n| (1..=mate::MAX_MOVES).contains(n)) else {
+            println!("Usage: matein <1-{}>", mate::MAX_MOVES);
+            return;
+        };
+        let game_state = self.game.state();
+        let keys = mate::key_moves(game_state, n);
+        if keys.is_empty() {
+            println!("There is no forced mate in {}.", n);
+        }
+        for (mv, moves) in keys {
+            println!("{} mates in {}", game_state.move_to_san(&mv), moves);
+        }
+    }
+
+    // > "hint" searches briefly at full strength and suggests a move, with the reply it expects, without playing it
+    fn hint(&self) {
+        let game_state = self.game.state();
+        let limits = search::Limits { depth: Some(HINT_DEPTH), ..search::Limits::movetime(HINT_TIME) };
+        let result = search::iterative_deepening(game_state, limits, &self.evaluator, self.threads, search::Signals::NONE, |_| {});
+        match hint(game_state, &result) {
+            Some(hint) => println!("{}", hint),
+            None => println!("There is no move to suggest."),
+        }
+    }
+
+    // > "send <file>" writes a signed correspondence move file and "receive <file>" takes the reply,
+    // > both keyed with the passphrase given by --secret
+    fn correspondence(&mut self, send: bool, path: &str) {
+        let Some(secret) = self.secret.as_deref() else {
+            println!("Start the program with --secret <passphrase> to play by correspondence.");
+            return;
+        };
+        if send {
+            match std::fs::write(path, self.game.to_move_file(secret)) {
+                Ok(()) => println!("Wrote the move file {}; send it to your opponent.", path),
+                Err(err) => println!("Could not write the move file: {}", err),
+            }
+            return;
+        }
+        match std::fs::read_to_string(path).map(|text| self.game.receive_move_file(&text, secret)) {
+            Ok(Ok(reply)) => {
+                match reply.moves().get(self.game.history().len()) {
+                    Some(mv) => println!("Received {} from {}.", mv.to_uci(), path),
+                    None => println!("Received {}: {}", path, reply.state().result()),
+                }
+                self.game = reply;
+            }
+            Ok(Err(err)) => println!("{}", err),
+            Err(err) => println!("Could not read the move file: {}", err),
+        }
+    }
+
+    // "load <file>" goes to the end of the game; "load <file> <n>" stops after the first n moves.
+    fn load(&mut self, args: &str) {
+        let (path, plies) = match args.rsplit_once(' ') {
+            Some((path, n)) if n.parse::<usize>().is_ok() => (path.trim(), n.parse().ok()),
+            _ => (args, None),
+        };
+        let loaded = std::fs::read(path).map(|bytes| {
+            if path.ends_with(".bin") {
+                Game::load_binary(&bytes).map_err(|err| err.to_string())
+            } else {
+                Game::from_pgn(&String::from_utf8_lossy(&bytes)).map_err(|err| err.to_string())
+            }
+        });
+        match loaded {
+            Ok(Ok(loaded)) => {
+                self.game = loaded;
+                if let Some(plies) = plies {
+                    self.game.truncate(plies);
+                }
+                println!("Loaded {} moves from {}.", self.game.history().len(), path);
+            }
+            Ok(Err(err)) => println!("Could not read the game: {}", err),
+            Err(err) => println!("Could not load the game: {}", err),
+        }
+    }
+
+    // > "import <lichess URL or ID>", "import chess.com <player> <URL or ID>" or "import <export.json> [ID]"
+    #[cfg(feature = "import")]
+    fn import(&mut self, args: &str) {
+        let words: Vec<&str> = args.split_whitespace().collect();
+        let download = |source: Result<import::Source, ImportError>| {
+            let runtime = tokio::runtime::Runtime::new().expect("Failed to start the async runtime");
+            source.and_then(|source| runtime.block_on(source.download())).map_err(|err| err.to_string())
+        };
+        let imported = match words.as_slice() {
+            [path, id @ ..] if std::path::Path::new(path).is_file() => std::fs::read_to_string(path)
+                .map_err(|err| err.to_string())
+                .and_then(|text| import::game_from_json(&text, id.first().copied()).map_err(|err| err.to_string())),
+            ["chess.com", player, id] => download(import::Source::chess_com(player, id)),
+            [id] => download(import::Source::lichess(id)),
+            _ => Err("Usage: import <lichess URL or ID> | import chess.com <player> <URL or ID> | import <export.json> [ID]".to_string()),
+        };
+        match imported {
+            Ok(imported) => {
+                self.game = imported;
+                let tag = |name: &str| self.game.tags().iter().find(|(tag, _)| tag == name).map_or("?", |(_, value)| value.as_str());
+                println!("Imported {} - {} ({} moves, {}).", tag("White"), tag("Black"), self.game.history().len(), self.game.state().result());
+            }
+            Err(err) => println!("{}", err),
+        }
+    }
+
+    // Without the import feature, "import ..." is read as a move like any other unknown command.
+    #[cfg(not(feature = "import"))]
+    fn import(&mut self, args: &str) {
+        self.enter_move(&format!("import {}", args));
+    }
+
+    // "games <file>" lists the games in a PGN database; "games <file> <k>" loads the k-th of them.
+    fn games(&mut self, args: &str) {
+        let (path, number) = match args.rsplit_once(' ') {
+            Some((path, k)) if k.parse::<usize>().is_ok() => (path.trim(), k.parse::<usize>().ok()),
+            _ => (args, None),
+        };
+        let file = match std::fs::File::open(path) {
+            Ok(file) => file,
+            Err(err) => {
+                println!("Could not open the database: {}", err);
+                return;
+            }
+        };
+        let mut records = PgnReader::new(std::io::BufReader::new(file));
+        let Some(k) = number else {
+            for (i, record) in records.enumerate() {
+                let tags = match record.map(|record| record.tags()) {
+                    Ok(Ok(tags)) => tags,
+                    Ok(Err(err)) => {
+                        println!("{}. {}", i + 1, err);
+                        continue;
+                    }
+                    Err(err) => {
+                        println!("Could not read the database: {}", err);
+                        break;
+                    }
+                };
+                let tag = |name: &str| tags.iter().find(|(n, _)| n == name).map_or("?".to_string(), |(_, value)| value.clone());
+                println!("{}. {} - {} {} ({})", i + 1, tag("White"), tag("Black"), tag("Result"), tag("Event"));
+            }
+            return;
+        };
+        match records.nth(k.saturating_sub(1)) {
+            Some(Ok(record)) => match record.game() {
+                Ok(loaded) => {
+                    self.game = loaded;
+                    println!("Loaded game {} with {} moves from {}.", k, self.game.history().len(), path);
+                }
+                Err(err) => println!("Could not read game {}: {}", k, err),
+            },
+            Some(Err(err)) => println!("Could not read the database: {}", err),
+            None => println!("There is no game {} in {}.", k, path),
+        }
+    }
+
+    // > "puzzles [<plies>]" looks for tactics in the game so far and prints them as Lichess puzzle CSV lines;
+    // > "puzzles [<plies>] <database.pgn> [<out.csv>]" looks through every game of a database instead
+    fn puzzles(&self, mut words: Vec<&str>) {
+        let plies = match words.first().map(|word| word.parse::<u32>()) {
+            Some(Ok(plies)) if plies > 0 => {
+                words.remove(0);
+                plies
+            }
+            _ => PUZZLE_PLIES,
+        };
+        // A database is read one game at a time.
+        let games: Box<dyn Iterator<Item = Result<Game, String>>> = match words.first() {
+            None => Box::new(std::iter::once(Ok(self.game.clone()))),
+            Some(path) => match std::fs::File::open(path) {
+                Ok(file) => Box::new(
+                    PgnReader::new(std::io::BufReader::new(file))
+                        .map(|record| record.map_err(|err| err.to_string()).and_then(|record| record.game().map_err(|err| err.to_string()))),
+                ),
+                Err(err) => {
+                    println!("Could not open the database: {}", err);
+                    return;
+                }
+            },
+        };
+        let mut lines = Vec::new();
+        for (i, loaded) in games.enumerate() {
+            match loaded {
+                Ok(loaded) => lines.extend(puzzle::extract(&loaded, plies, &self.evaluator).iter().map(Puzzle::to_csv_line)),
+                Err(err) => println!("Skipped game {}: {}", i + 1, err),
+            }
+        }
+        match words.get(1) {
+            Some(path) => match std::fs::write(path, lines.iter().map(|line| format!("{}\n", line)).collect::<String>()) {
+                Ok(()) => println!("Saved {} puzzles to {}.", lines.len(), path),
+                Err(err) => println!("Could not save the puzzles: {}", err),
+            },
+            None if lines.is_empty() => println!("No puzzles found."),
+            None => lines.iter().for_each(|line| println!("{}", line)),
+        }
+    }
+
+    // "puzzle <file> <k>" sets up the k-th puzzle of a Lichess puzzle database.
+    fn puzzle(&mut self, args: &str) {
+        let Some((path, k)) = args.rsplit_once(' ').and_then(|(path, k)| Some((path.trim(), k.parse::<usize>().ok()?))) else {
+            println!("Usage: puzzle <file> <number>");
+            return;
+        };
+        let file = match std::fs::File::open(path) {
+            Ok(file) => file,
+            Err(err) => {
+                println!("Could not open the puzzles: {}", err);
+                return;
+            }
+        };
+        let line = std::io::BufRead::lines(std::io::BufReader::new(file))
+            .map_while(Result::ok)
+            .filter(|line| !line.starts_with("PuzzleId,") && !line.trim().is_empty())
+            .nth(k.saturating_sub(1));
+        match line.map(|line| Puzzle::from_csv_line(&line)) {
+            Some(Ok(puzzle)) => {
+                self.game = Game::from_position(puzzle.position.clone());
+                play_move(&mut self.game, puzzle.moves[0]);
+                println!(
+                    "Puzzle {} (rating {}): {}. {:?} to move.",
+                    puzzle.id,
+                    puzzle.rating,
+                    puzzle.themes.join(", "),
+                    puzzle.start().side_to_move()
+                );
+            }
+            Some(Err(err)) => println!("Could not read puzzle {}: {}", k, err),
+            None => println!("There is no puzzle {} in {}.", k, path),
+        }
+    }
+
+    // > list the book moves for the position with their weights
+    fn book(&self) {
+        let game_state = self.game.state();
+        match &self.book {
+            Some(book) => {
+                for (mv, weight) in book.lookup(game_state) {
+                    println!("{} ({})", game_state.move_to_san(&mv), weight);
+                }
+            }
+            None => println!("No opening book. Start with --book <book.bin>."),
+        }
+    }
+
+    // "setup" reads a pasted board diagram, ended by an empty line, and starts a game from it.
+    fn setup(&mut self) {
+        println!("Paste a board diagram, rank 8 first, then an empty line:");
+        let mut diagram = String::new();
+        loop {
+            let mut line = String::new();
+            if std::io::stdin().read_line(&mut line).expect("Failed to read input") == 0 || line.trim().is_empty() {
+                break;
+            }
+            diagram.push_str(&line);
+        }
+        match diagram::parse(&diagram) {
+            Ok(position) => self.game = Game::from_position(position),
+            Err(err) => println!("Could not set up the position: {}", err),
+        }
+    }
+
+    // > play a move typed as UCI, SAN or two squares with an optional promotion letter; a single square holding
+    // > one of your pieces lists where it can go instead
+    fn enter_move(&mut self, input: &str) {
+        let game_state = self.game.state();
+        let parts: Vec<&str> = input.split_whitespace().collect();
+
+        if let [token] = parts.as_slice() {
+            if let Ok(square) = token.parse::<Square>() {
+                let (row, col) = square.to_row_col();
+                if game_state.board()[row][col].colour() == Some(game_state.side_to_move()) {
+                    println!("{:?} can move to {:?}", square, game_state.legal_moves_from(square));
+                    return;
+                }
+            }
+            match Move::from_uci(token).or_else(|_| game_state.parse_san(token)) {
+                Ok(mv) => play_checked_move(&mut self.game, mv, self.blunder_check, &self.evaluator, self.threads),
+                Err(err) => println!("Invalid move: {}", err),
+            }
+            return;
+        }
+
+        if parts.len() != 2 && parts.len() != 3 {
+            println!("Invalid input. Please enter a move like 'Nf3' or 'e2 e4' (or 'e7 e8 n' to under-promote).");
+            return;
+        }
+
+        let from_square = match parts[0].parse::<Square>() {
+            Ok(square) => square,
+            Err(err) => {
+                println!("{}", err);
+                return;
+            }
+        };
+
+        let to_square = match parts[1].parse::<Square>() {
+            Ok(square) => square,
+            Err(err) => {
+                println!("{}", err);
+                return;
+            }
+        };
+
+        let mut mv = Move::new(from_square, to_square);
+        if let Some(letter) = parts.get(2) {
+            match promotion_piece(letter, game_state.side_to_move()) {
+                Some(piece) => mv = mv.with_promotion(piece),
+                None => {
+                    println!("Invalid promotion piece: {}", letter);
+                    return;
+                }
+            }
+        }
+
+        play_checked_move(&mut self.game, mv, self.blunder_check, &self.evaluator, self.threads);
+    }
+}
+
+// > search with iterative deepening, printing the score, nodes, time and principal variation after each depth;
+// > an infinite search runs until Enter is pressed
+fn search_with_report(position: &GameState, limits: search::Limits, evaluator: &eval::Evaluator, threads: usize) -> search::SearchResult {
+    let stopped = std::sync::atomic::AtomicBool::new(false);
+    std::thread::scope(|scope| {
+        if limits.infinite {
+            println!("Press Enter to stop.");
+            scope.spawn(|| {
+                let _ = std::io::stdin().read_line(&mut String::new());
+                stopped.store(true, std::sync::atomic::Ordering::SeqCst);
+            });
+        }
+        let signals = search::Signals { stop: &|| stopped.load(std::sync::atomic::Ordering::SeqCst), ..search::Signals::NONE };
+        search::iterative_deepening(position, limits, evaluator, threads, signals, |iteration| {
+            let score = match iteration.mate_in() {
+                Some(moves) => format!("mate in {}", moves),
+                None => format!("{:+.2}", f64::from(iteration.score) / 100.0),
+            };
+            println!(
+                "depth {:2}  score {:>8}  nodes {:>9}  time {:6.2}s  {}",
+                iteration.depth,
+                score,
+                iteration.nodes,
+                iteration.time.as_secs_f64(),
+                line_to_san(position, &iteration.pv)
+            );
+        })
+    })
+}
+
+// > write a line of moves in SAN with move numbers, as it would be played from the position
+fn line_to_san(position: &GameState, line: &[Move]) -> String {
+    let mut position = position.clone();
+    let mut text = Vec::new();
+    for (i, &mv) in line.iter().enumerate() {
+        if position.side_to_move() == Colour::White {
+            text.push(format!("{}.", position.fullmove_number()));
+        } else if i == 0 {
+            text.push(format!("{}...", position.fullmove_number()));
+        }
+        text.push(position.move_to_san(&mv));
+        if position.make_move(mv).is_err() {
+            break;
+        }
+    }
+    text.join(" ")
+}
+
+// > describe a search result as a hint: the suggested move, the reply it expects and the score
+fn hint(position: &GameState, result: &search::SearchResult) -> Option<String> {
+    let mv = result.best_move?;
+    let score = match result.mate_in() {
+        Some(moves) => format!("mate in {}", moves),
+        None => format!("{:+.2}", f64::from(result.score) / 100.0),
+    };
+    let mut hint = format!("Hint: {} ({})", position.move_to_san(&mv), score);
+    if let Some(&reply) = result.pv.get(1) {
+        let mut after = position.clone();
+        after.make_move(mv).ok()?;
+        hint.push_str(&format!(", expecting {}", after.move_to_san(&reply)));
+    }
+    Some(hint)
+}
+
+// > take back the last move or play again the last one taken back, giving it in SAN
+fn step_through_history(game: &mut Game, undo: bool) -> Option<String> {
+    if undo {
+        let mv = game.undo()?;
+        Some(game.state().move_to_san(&mv))
+    } else {
+        let before = game.state().clone();
+        let mv = game.redo()?;
+        Some(before.move_to_san(&mv))
+    }
+}
+
+// > play a move entered at the prompt, describing it or explaining why it was rejected
+fn play_move(game: &mut Game, mv: Move) {
+    match game.state().classify_move(&mv) {
+        Ok(kind) => {
+            let san = game.state().move_to_san(&mv);
+            let player = game.state().side_to_move();
+            game.play(mv).expect("a classified move is legal");
+            if kind == MoveKind::default() {
+                println!("{:?} played {}", player, san);
+            } else {
+                println!("{:?} played {} ({})", player, san, kind);
+            }
+        }
+        Err(err) => println!("Invalid move: {}", err),
+    }
+}
+
+// > play a move entered at the keyboard; with a blunder threshold, warn if the move throws away that many
+// > centipawns and ask whether to take it back
+fn play_checked_move(game: &mut Game, mv: Move, blunder_check: Option<i32>, evaluator: &eval::Evaluator, threads: usize) {
+    let before = game.state().clone();
+    let plies = game.history().len();
+    play_move(game, mv);
+    let (Some(threshold), Some(&played)) = (blunder_check, game.moves().get(plies)) else {
+        return;
+    };
+    let Some(warning) = blunder_warning(&before, played, threshold, evaluator, threads) else {
+        return;
+    };
+    println!("{} Take it back? [y/N]", warning);
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_ok() && answer.trim().eq_ignore_ascii_case("y") {
+        game.truncate(plies);
+        println!("Took back {}.", before.move_to_san(&played));
+    }
+}
+
+// > compare a move with the best one by a quick search, and describe it if it scores more than the threshold worse
+fn blunder_warning(position: &GameState, mv: Move, threshold: i32, evaluator: &eval::Evaluator, threads: usize) -> Option<String> {
+    let limits = search::Limits { depth: Some(BLUNDER_DEPTH), ..search::Limits::movetime(BLUNDER_TIME) };
+    let best = search::iterative_deepening(position, limits, evaluator, threads, search::Signals::NONE, |_| {});
+    let best_move = best.best_move.filter(|&best_move| best_move != mv)?;
+    let mut after = position.clone();
+    after.make_move(mv).ok()?;
+    // The reply is searched a ply shallower, so both moves are looked at to the same depth.
+    let limits = search::Limits { depth: Some(best.depth.saturating_sub(1).max(1)), ..search::Limits::movetime(BLUNDER_TIME) };
+    let reply = search::iterative_deepening(&after, limits, evaluator, threads, search::Signals::NONE, |_| {});
+    if best.score + reply.score <= threshold {
+        return None;
+    }
+    let cost = match reply.mate_in() {
+        Some(moves) if moves > 0 => format!("lets {:?} mate in {}", after.side_to_move(), moves),
+        _ => format!("loses about {:.2} pawns", f64::from(best.score + reply.score) / 100.0),
+    };
+    Some(format!("Careful: {} {}; {} was better.", position.move_to_san(&mv), cost, position.move_to_san(&best_move)))
+}
+
+// > count the positions of the legal move tree for "perft [divide|suite] <depth>", sharing the first moves
+// > among the threads; tell whether the command was understood and every count checked was right
+fn perft_command(position: &GameState, words: &[&str], threads: usize) -> bool {
+    let (mode, depth) = match words {
+        [depth] => ("count", depth.parse::<u32>()),
+        [mode @ ("divide" | "suite"), depth] => (*mode, depth.parse::<u32>()),
+        _ => ("", "".parse::<u32>()),
+    };
+    let Ok(depth) = depth else {
+        println!("Usage: perft [divide|suite] <depth>");
+        return false;
+    };
+    let started = std::time::Instant::now();
+    let mut correct = true;
+    let nodes = match mode {
+        "divide" => {
+            let divided = perft::divide(position, depth, threads);
+            for (mv, nodes) in &divided {
+                println!("{}: {}", mv.to_uci(), nodes);
+            }
+            divided.iter().map(|(_, nodes)| nodes).sum()
+        }
+        "suite" => {
+            let mut total = 0;
+            for (name, fen, counts) in perft::SUITE {
+                let position = GameState::from_fen(fen).expect("the perft positions are valid");
+                for (depth, &expected) in (1..=depth).zip(counts) {
+                    let nodes = perft::parallel_perft(&position, depth, threads);
+                    let verdict = if nodes == expected { "ok" } else { "WRONG" };
+                    println!("{} depth {}: {} (expected {}) {}", name, depth, nodes, expected, verdict);
+                    correct &= nodes == expected;
+                    total += nodes;
+                }
+            }
+            total
+        }
+        _ => perft::parallel_perft(position, depth, threads),
+    };
+    let seconds = started.elapsed().as_secs_f64();
+    println!("perft {}: {} positions in {:.2}s ({:.0} per second)", depth, nodes, seconds, nodes as f64 / seconds.max(1e-9));
+    correct
+}
+
+// > run "perft [divide|suite] <depth> [--threads N] [--fen FEN]" from the command line, from the starting
+// > position unless a FEN is given; tell whether it ran and every count checked was right
+fn perft_subcommand(args: &[String]) -> bool {
+    let usage = "Usage: perft [divide|suite] <depth> [--threads N] [--fen FEN]";
+    let option = |name: &str| args.iter().position(|arg| arg == name).map(|i| args.get(i + 1));
+    let threads = match option("--threads") {
+        Some(value) => value.and_then(|value| value.parse::<usize>().ok()).filter(|threads| *threads >= 1),
+        None => Some(std::thread::available_parallelism().map_or(1, usize::from)),
+    };
+    let position = match option("--fen") {
+        Some(fen) => fen.and_then(|fen| GameState::from_fen(fen).ok()),
+        None => Some(GameState::new()),
+    };
+    let (Some(threads), Some(position)) = (threads, position) else {
+        println!("{}", usage);
+        return false;
+    };
+    // Everything before the first option is the perft command itself.
+    let words: Vec<&str> = args.iter().take_while(|arg| !arg.starts_with("--")).map(String::as_str).collect();
+    perft_command(&position, &words, threads)
+}
+
+// > run "bench [<depth>] [--threads N]", searching every bench position and printing the positions
+// > searched in all and how many a second
+fn bench_subcommand(args: &[String]) {
+    let usage = format!("Usage: bench [<depth>] [--threads 1-{}]", search::MAX_THREADS);
+    let option = |name: &str| args.iter().position(|arg| arg == name).map(|i| args.get(i + 1));
+    let threads = match option("--threads") {
+        Some(value) => value.and_then(|value| value.parse::<usize>().ok()).filter(|threads| (1..=search::MAX_THREADS).contains(threads)),
+        None => Some(1),
+    };
+    let depth = match args.first().filter(|arg| !arg.starts_with("--")) {
+        Some(depth) => depth.parse::<u32>().ok().filter(|&depth| depth > 0),
+        None => Some(search::BENCH_DEPTH),
+    };
+    let (Some(threads), Some(depth)) = (threads, depth) else {
+        println!("{}", usage);
+        return;
+    };
+    let started = std::time::Instant::now();
+    let mut count = 0;
+    let nodes = search::bench(&search::BENCH_POSITIONS, depth, &eval::Evaluator::default(), threads, |fen, result| {
+        count += 1;
+        let best_move = result.best_move.map_or("none".to_string(), Move::to_uci);
+        println!("Position {}/{}: {} nodes, best move {} ({})", count, search::BENCH_POSITIONS.len(), result.nodes, best_move, fen);
+    });
+    let seconds = started.elapsed().as_secs_f64();
+    println!("bench {}: {} nodes in {:.2}s ({:.0} nodes per second)", depth, nodes, seconds, nodes as f64 / seconds.max(1e-9));
+}
+
+// > run "epd <file> [--time seconds] [--threads N]", searching each position of the suite for the given time and
+// > printing each record with the engine's answer, then how many were solved
+fn epd_subcommand(args: &[String]) {
+    let usage = format!("Usage: epd <file> [--time seconds] [--threads 1-{}]", search::MAX_THREADS);
+    let option = |name: &str| args.iter().position(|arg| arg == name).map(|i| args.get(i + 1));
+    let threads = match option("--threads") {
+        Some(value) => value.and_then(|value| value.parse::<usize>().ok()).filter(|threads| (1..=search::MAX_THREADS).contains(threads)),
+        None => Some(1),
+    };
+    let time = match option("--time") {
+        Some(value) => value.and_then(|value| parse_seconds(value)).filter(|time| !time.is_zero()),
+        None => Some(std::time::Duration::from_secs(1)),
+    };
+    let (Some(path), Some(threads), Some(time)) = (args.first().filter(|arg| !arg.starts_with("--")), threads, time) else {
+        println!("{}", usage);
+        return;
+    };
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) => {
+            println!("Could not read {}: {}", path, err);
+            return;
+        }
+    };
+
+    let evaluator = eval::Evaluator::default();
+    let (mut solved, mut total) = (0, 0);
+    for (number, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let mut epd = match Epd::parse(line) {
+            Ok(epd) => epd,
+            Err(err) => {
+                println!("Line {}: {}", number + 1, err);
+                continue;
+            }
+        };
+        match epd.solve(search::Limits::movetime(time), &evaluator, threads) {
+            Ok(found) => {
+                total += 1;
+                solved += usize::from(found);
+                println!("{} {}", if found { "solved" } else { "missed" }, epd);
+            }
+            Err(err) => println!("Line {}: {}", number + 1, err),
+        }
+    }
+    println!("Solved {} of {}", solved, total);
+}
+
+// > read a number of seconds, which may have a fraction, turning away negative, infinite and NaN values
+fn parse_seconds(text: &str) -> Option<std::time::Duration> {
+    std::time::Duration::try_from_secs_f64(text.parse().ok()?).ok()
+}
+
+// > read a time control such as "60+1": seconds on the clock, which must be more than none, and an optional increment
+fn parse_time_control(tc: &str) -> Option<(std::time::Duration, std::time::Duration)> {
+    let (time, increment) = tc.split_once('+').unwrap_or((tc, "0"));
+    Some((parse_seconds(time).filter(|time| !time.is_zero())?, parse_seconds(increment)?))
+}
+
+// > run "match <engine> <engine> [--games N] [--tc seconds+increment] [--max-plies N] [--pgn file]",
+// > where each engine is "internal", "mcts" or the path of a UCI engine
+fn match_command(args: &[String]) {
+    let usage = "Usage: match <internal|mcts|engine path> <internal|mcts|engine path> [--games N] [--tc 60+1] [--max-plies N] [--pgn file]";
+    let option = |name: &str| args.iter().position(|arg| arg == name).map(|i| args.get(i + 1));
+    let number = |name: &str, default: u64| match option(name) {
+        Some(value) => value.and_then(|value| value.parse().ok()),
+        None => Some(default),
+    };
+    let time_control = match option("--tc") {
+        Some(value) => value.and_then(|tc| parse_time_control(tc)),
+        None => Some((std::time::Duration::from_secs(60), std::time::Duration::ZERO)),
+    };
+    let (Some(first), Some(second), Some(games), Some(max_plies), Some((time, increment))) =
+        (args.first(), args.get(1), number("--games", 2), number("--max-plies", 400), time_control)
+    else {
+        println!("{}", usage);
+        return;
+    };
+
+    let start = |spec: &str| -> Result<Box<dyn Engine>, EngineError> {
+        match spec {
+            "internal" => Ok(Box::new(InternalEngine::default())),
+            "mcts" => Ok(Box::new(MctsEngine)),
+            path => Ok(Box::new(UciEngine::start(path, &[])?)),
+        }
+    };
+    let (mut first, mut second) = match (start(first), start(second)) {
+        (Ok(first), Ok(second)) => (first, second),
+        (Err(err), _) | (_, Err(err)) => {
+            println!("{}", err);
+            return;
+        }
+    };
+
+    let config = MatchConfig {
+        games: games as usize,
+        time,
+        increment,
+        max_plies: max_plies as usize,
+    };
+    println!("{} vs {}, {} games", first.name(), second.name(), config.games);
+    let (games, score) = run_match(first.as_mut(), second.as_mut(), &config, |round, game| {
+        let termination = game.tags().iter().find(|(name, _)| name == "Termination").map_or("", |(_, value)| value.as_str());
+        println!("Game {}: {} ({}, {} moves)", round, game.state().result(), termination, game.history().len());
+    });
+    println!("{} wins, {} draws, {} losses for {}", score.wins, score.draws, score.losses, first.name());
+    match score.elo_difference() {
+        Some(elo) => println!("Elo difference: {:+.0}", elo),
+        None => println!("Elo difference: cannot be estimated from a one-sided score"),
+    }
+
+    if let Some(Some(path)) = option("--pgn") {
+        let pgn: Vec<String> = games.iter().map(Game::to_pgn).collect();
+        match std::fs::write(path, pgn.join("\n")) {
+            Ok(()) => println!("Saved the games to {}.", path),
+            Err(err) => println!("Could not save the games: {}", err),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hint() {
+        // White wins the queen; the hint also gives Black's expected reply.
+        let position = GameState::from_fen("4k3/8/8/3q4/4P3/8/8/3QK3 w - - 0 1").unwrap();
+        let text = hint(&position, &search::search(&position, 2)).unwrap();
+        assert!(text.starts_with("Hint: exd5 (+") && text.contains(", expecting "), "{}", text);
+        let mated = GameState::from_fen("7k/6Q1/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(hint(&mated, &search::search(&mated, 2)), None);
+    }
+
+    #[test]
+    fn test_blunder_warning() {
+        // Taking the queen is best; moving the white queen where the black one takes it loses it.
+        let position = GameState::from_fen("4k3/8/8/3q4/4P3/8/8/3QK3 w - - 0 1").unwrap();
+        let evaluator = eval::Evaluator::default();
+        let warning = blunder_warning(&position, Move::new(Square::D1, Square::H5), 200, &evaluator, 1).unwrap();
+        assert!(warning.starts_with("Careful: Qh5+ loses about") && warning.ends_with("exd5 was better."), "{}", warning);
+        assert_eq!(blunder_warning(&position, Move::new(Square::E4, Square::D5), 200, &evaluator, 1), None);
+        // Missing the win of the queen is a blunder too, but not past a high enough threshold.
+        assert!(blunder_warning(&position, Move::new(Square::E1, Square::F1), 200, &evaluator, 1).is_some());
+        assert_eq!(blunder_warning(&position, Move::new(Square::E1, Square::F1), 2000, &evaluator, 1), None);
+
+        let mate = GameState::from_fen("6k1/5ppp/8/8/8/8/5PPP/R5K1 b - - 0 1").unwrap();
+        let warning = blunder_warning(&mate, Move::new(Square::G8, Square::H8), 200, &evaluator, 1).unwrap();
+        assert!(warning.contains("lets White mate in 1"), "{}", warning);
+    }
+
+    #[test]
+    fn test_parse_seconds() {
+        assert_eq!(parse_seconds("2.5"), Some(std::time::Duration::from_millis(2500)));
+        assert_eq!(parse_seconds("0"), Some(std::time::Duration::ZERO));
+        for bad in ["-5", "inf", "NaN", "1e300", "soon"] {
+            assert_eq!(parse_seconds(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_parse_time_control() {
+        use std::time::Duration;
+        assert_eq!(parse_time_control("60+1"), Some((Duration::from_secs(60), Duration::from_secs(1))));
+        assert_eq!(parse_time_control("0.5"), Some((Duration::from_millis(500), Duration::ZERO)));
+        for bad in ["-60+1", "60+-1", "inf+0", "60+NaN", "0+1", "60+"] {
+            assert_eq!(parse_time_control(bad), None, "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,43 @@
+//! # Clipboard
+//!
+//! Copies text to and from the system clipboard, so positions and games can be moved
+//! to and from analysis boards such as lichess. Clipboard access needs the
+//! `clipboard` feature; without it every call returns `ClipboardError::NotBuiltIn`.
+
+use crate::error::ClipboardError;
+
+// > put text on the system clipboard
+#[cfg(feature = "clipboard")]
+pub(crate) fn copy(text: &str) -> Result<(), ClipboardError> {
+    let mut clipboard = arboard::Clipboard::new().map_err(|err| ClipboardError::Unavailable(err.to_string()))?;
+    clipboard.set_text(text).map_err(|err| ClipboardError::Unavailable(err.to_string()))
+}
+
+// > read text from the system clipboard
+#[cfg(feature = "clipboard")]
+pub(crate) fn paste() -> Result<String, ClipboardError> {
+    let mut clipboard = arboard::Clipboard::new().map_err(|err| ClipboardError::Unavailable(err.to_string()))?;
+    clipboard.get_text().map_err(|err| ClipboardError::Unavailable(err.to_string()))
+}
+
+#[cfg(not(feature = "clipboard"))]
+pub(crate) fn copy(_text: &str) -> Result<(), ClipboardError> {
+    Err(ClipboardError::NotBuiltIn)
+}
+
+#[cfg(not(feature = "clipboard"))]
+pub(crate) fn paste() -> Result<String, ClipboardError> {
+    Err(ClipboardError::NotBuiltIn)
+}
+
+// The system clipboard itself cannot be relied on where tests run.
+#[cfg(all(test, not(feature = "clipboard")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_not_built_in() {
+        assert_eq!(copy("8/8/8/8/8/8/8/8 w - - 0 1"), Err(ClipboardError::NotBuiltIn));
+        assert_eq!(paste(), Err(ClipboardError::NotBuiltIn));
+    }
+}
@@ -0,0 +1,283 @@
+//! # DGT Board
+//!
+//! Plays on a DGT electronic board, so moves are made with real pieces. Needs the
+//! `dgt` feature and the board's serial port:
+//!
+//! ```text
+//! overload-vibe-coding --dgt /dev/ttyUSB0 --engine internal
+//! ```
+//!
+//! The board reports every piece lifted or put down. After each change the program
+//! looks for the legal move that leaves the pieces exactly as they stand, so captures
+//! and en passant work whatever order the pieces are lifted in, and a promoted pawn is
+//! read from the piece put down in its place. Castling must start with the king, as the
+//! rules ask, since moving the rook first is a legal rook move of its own. When the engine moves, the move
+//! is shown on screen and the program waits until the operator has made it on the board.
+//!
+//! Messages from the board start with the message id with its top bit set, followed by
+//! the message length, header included, as two 7-bit bytes.
+
+use std::io::{Read, Write};
+use std::time::Duration;
+
+use overload_vibe_coding::{ChessPiece, Colour, GameState, Move};
+
+use crate::error::DgtError;
+
+// Commands sent to the board.
+const SEND_RESET: u8 = 0x40;
+const SEND_BOARD: u8 = 0x42;
+const SEND_UPDATES: u8 = 0x44;
+
+// Messages from the board, without the top bit.
+const BOARD_DUMP: u8 = 0x06;
+const FIELD_UPDATE: u8 = 0x0e;
+const MESSAGE_BIT: u8 = 0x80;
+
+const BAUD_RATE: u32 = 9600;
+
+type Board = [[ChessPiece; 8]; 8];
+
+// > add a connection to a DGT board that tracks where its pieces stand
+pub(crate) struct DgtBoard<P> {
+    port: P,
+    board: Board,
+}
+
+// > add the messages the board sends that the program uses
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Message {
+    Board(Board),
+    // The square, in the board's order from a8 to h1, and what is on it now.
+    FieldUpdate(usize, ChessPiece),
+    // Clock messages, serial numbers and the like, by message id.
+    Other(u8),
+}
+
+// > open the board on a serial port at the speed DGT boards use
+pub(crate) fn open(path: &str) -> Result<DgtBoard<Box<dyn serialport::SerialPort>>, DgtError> {
+    let port = serialport::new(path, BAUD_RATE)
+        .timeout(Duration::from_secs(3600))
+        .open()
+        .map_err(|err| DgtError::Io(err.to_string()))?;
+    DgtBoard::start(port)
+}
+
+impl<P: Read + Write> DgtBoard<P> {
+    // > reset the board, read where the pieces stand and ask to be told of every change
+    pub(crate) fn start(port: P) -> Result<DgtBoard<P>, DgtError> {
+        let mut dgt = DgtBoard { port, board: [[ChessPiece::Blank; 8]; 8] };
+        dgt.port.write_all(&[SEND_RESET, SEND_BOARD])?;
+        dgt.port.flush()?;
+        loop {
+            if let Message::Board(board) = dgt.read_message()? {
+                dgt.board = board;
+                break;
+            }
+        }
+        dgt.port.write_all(&[SEND_UPDATES])?;
+        dgt.port.flush()?;
+        Ok(dgt)
+    }
+
+    // > wait until the pieces show a legal move from the position, and give that move
+    pub(crate) fn wait_for_move(&mut self, position: &GameState) -> Result<Move, DgtError> {
+        loop {
+            if let Some(mv) = find_move(position, &self.board) {
+                return Ok(mv);
+            }
+            self.next_change()?;
+        }
+    }
+
+    // > wait until the pieces stand as in the position, after the operator plays the engine's move
+    pub(crate) fn wait_for_position(&mut self, position: &GameState) -> Result<(), DgtError> {
+        while self.board != *position.board() {
+            self.next_change()?;
+        }
+        Ok(())
+    }
+
+    // Apply the next change the board reports.
+    fn next_change(&mut self) -> Result<(), DgtError> {
+        match self.read_message()? {
+            Message::Board(board) => self.board = board,
+            Message::FieldUpdate(index, piece) => {
+                let (row, col) = square_of(index);
+                self.board[row][col] = piece;
+            }
+            Message::Other(_) => {}
+        }
+        Ok(())
+    }
+
+    fn read_message(&mut self) -> Result<Message, DgtError> {
+        let mut header = [0u8; 3];
+        self.port.read_exact(&mut header)?;
+        if header[0] & MESSAGE_BIT == 0 {
+            return Err(DgtError::InvalidMessage(header[0]));
+        }
+        let length = (usize::from(header[1]) << 7 | usize::from(header[2])).saturating_sub(header.len());
+        let mut body = vec![0u8; length];
+        self.port.read_exact(&mut body)?;
+        match (header[0] & !MESSAGE_BIT, body.as_slice()) {
+            (BOARD_DUMP, squares) if squares.len() == 64 => {
+                let mut board = [[ChessPiece::Blank; 8]; 8];
+                for (index, &code) in squares.iter().enumerate() {
+                    let (row, col) = square_of(index);
+                    board[row][col] = piece_from_code(code)?;
+                }
+                Ok(Message::Board(board))
+            }
+            (FIELD_UPDATE, &[index, code]) if index < 64 => Ok(Message::FieldUpdate(usize::from(index), piece_from_code(code)?)),
+            (BOARD_DUMP | FIELD_UPDATE, _) => Err(DgtError::InvalidMessage(header[0])),
+            (id, _) => Ok(Message::Other(id)),
+        }
+    }
+}
+
+// The board numbers its squares from a8 across to h8, then down to h1.
+fn square_of(index: usize) -> (usize, usize) {
+    (7 - index / 8, index % 8)
+}
+
+fn piece_from_code(code: u8) -> Result<ChessPiece, DgtError> {
+    use Colour::{Black, White};
+    Ok(match code {
+        0 => ChessPiece::Blank,
+        1 => ChessPiece::Pawn(White),
+        2 => ChessPiece::Rook(White),
+        3 => ChessPiece::Knight(White),
+        4 => ChessPiece::Bishop(White),
+        5 => ChessPiece::King(White),
+        6 => ChessPiece::Queen(White),
+        7 => ChessPiece::Pawn(Black),
+        8 => ChessPiece::Rook(Black),
+        9 => ChessPiece::Knight(Black),
+        10 => ChessPiece::Bishop(Black),
+        11 => ChessPiece::King(Black),
+        12 => ChessPiece::Queen(Black),
+        _ => return Err(DgtError::UnknownPiece(code)),
+    })
+}
+
+// > find the legal move that leaves the pieces as they stand on the board
+fn find_move(position: &GameState, board: &Board) -> Option<Move> {
+    position.legal_moves().into_iter().find(|&mv| {
+        let mut after = position.clone();
+        after.make_move(mv).is_ok() && after.board() == board
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use overload_vibe_coding::Square;
+    use std::io::Cursor;
+
+    // A board that replays what it was given and records the commands it was sent.
+    struct FakePort {
+        input: Cursor<Vec<u8>>,
+        sent: Vec<u8>,
+    }
+
+    impl Read for FakePort {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for FakePort {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.sent.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn code_of(piece: ChessPiece) -> u8 {
+        (0..=12).find(|&code| piece_from_code(code) == Ok(piece)).unwrap()
+    }
+
+    fn dump(position: &GameState) -> Vec<u8> {
+        let mut bytes = vec![MESSAGE_BIT | BOARD_DUMP, 0, 67];
+        bytes.extend((0..64).map(|index| {
+            let (row, col) = square_of(index);
+            code_of(position.board()[row][col])
+        }));
+        bytes
+    }
+
+    fn update(square: &str, piece: ChessPiece) -> Vec<u8> {
+        let bytes = square.as_bytes();
+        let index = (b'8' - bytes[1]) * 8 + (bytes[0] - b'a');
+        vec![MESSAGE_BIT | FIELD_UPDATE, 0, 5, index, code_of(piece)]
+    }
+
+    fn board(messages: &[Vec<u8>]) -> DgtBoard<FakePort> {
+        let port = FakePort { input: Cursor::new(messages.concat()), sent: Vec::new() };
+        DgtBoard::start(port).unwrap()
+    }
+
+    #[test]
+    fn test_move_from_field_updates() {
+        let position = GameState::new();
+        // A clock message in between is skipped.
+        let mut dgt = board(&[
+            dump(&position),
+            vec![MESSAGE_BIT | 0x0d, 0, 4, 0],
+            update("e2", ChessPiece::Blank),
+            update("e4", ChessPiece::Pawn(Colour::White)),
+        ]);
+        assert_eq!(dgt.port.sent, [SEND_RESET, SEND_BOARD, SEND_UPDATES]);
+        let mv = dgt.wait_for_move(&position).unwrap();
+        assert_eq!((mv.from, mv.to), (Square::E2, Square::E4));
+    }
+
+    #[test]
+    fn test_castling_and_capture() {
+        let position = GameState::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let mut dgt = board(&[
+            dump(&position),
+            update("e1", ChessPiece::Blank),
+            update("g1", ChessPiece::King(Colour::White)),
+            update("h1", ChessPiece::Blank),
+            update("f1", ChessPiece::Rook(Colour::White)),
+        ]);
+        let mv = dgt.wait_for_move(&position).unwrap();
+        assert!(position.classify_move(&mv).unwrap().castle && mv.to == Square::G1);
+
+        let position = GameState::from_fen("r3k2r/8/8/8/8/8/3p4/R3K2R w KQkq - 0 1").unwrap();
+        let mut dgt = board(&[
+            dump(&position),
+            update("d2", ChessPiece::Blank),
+            update("e1", ChessPiece::Blank),
+            update("d2", ChessPiece::King(Colour::White)),
+        ]);
+        let mv = dgt.wait_for_move(&position).unwrap();
+        assert_eq!((mv.from, mv.to), (Square::E1, Square::D2));
+    }
+
+    #[test]
+    fn test_wait_for_engine_move() {
+        let position = GameState::new();
+        let mut after = position.clone();
+        after.make_move(Move::new(Square::G1, Square::F3)).unwrap();
+        let mut dgt = board(&[dump(&position), update("g1", ChessPiece::Blank), update("f3", ChessPiece::Knight(Colour::White))]);
+        dgt.wait_for_position(&after).unwrap();
+        // The board has nothing more to say.
+        assert_eq!(dgt.wait_for_move(&after).map(|_| ()), Err(DgtError::Closed));
+    }
+
+    #[test]
+    fn test_invalid_messages() {
+        let port = FakePort { input: Cursor::new(vec![0x06, 0, 3]), sent: Vec::new() };
+        assert_eq!(DgtBoard::start(port).map(|_| ()), Err(DgtError::InvalidMessage(0x06)));
+        let mut bytes = dump(&GameState::new());
+        bytes[3] = 13;
+        let port = FakePort { input: Cursor::new(bytes), sent: Vec::new() };
+        assert_eq!(DgtBoard::start(port).map(|_| ()), Err(DgtError::UnknownPiece(13)));
+    }
+}
@@ -0,0 +1,179 @@
+//! # Board Diagrams
+//!
+//! Reads a position from a text diagram, for setting up teaching positions quickly.
+//! Both the program's own board display and plain letter grids are accepted:
+//!
+//! ```text
+//!   A B C D E F G H          r . b q k b . r
+//! 8 ♖ ♘ ♗ ♕ ♔ ♗ ♘ ♖          p p p p . p p p
+//! 7 ♙ ♙ ♙ ♙ ♙ ♙ ♙ ♙          . . n . . n . .
+//! ...                        . . . . p . . .
+//! ```
+//!
+//! Ranks run from the eighth down to the first. Letters are FEN pieces (uppercase
+//! for White), `.` or a digit marks empty squares, and rank labels, file labels,
+//! `|` borders, `+---+` rules and colour escape codes are ignored. The symbols follow
+//! the board display, where White has the solid pieces. The side to move is read
+//! from a "Current Player: Black" line when there is one and is White otherwise.
+//! Castling is allowed wherever a king and rook stand on their starting squares.
+
+use overload_vibe_coding::{Colour, GameState};
+
+use crate::error::DiagramError;
+
+// > set up a position from a pasted text diagram of the board
+pub(crate) fn parse(text: &str) -> Result<GameState, DiagramError> {
+    let mut ranks = Vec::new();
+    let mut to_move = 'w';
+    for line in text.lines() {
+        let line = strip_escapes(line);
+        let line = line.trim();
+        if let Some((_, player)) = line.split_once("Current Player:") {
+            if player.trim().eq_ignore_ascii_case("black") {
+                to_move = 'b';
+            }
+            continue;
+        }
+        let squares: String = line.chars().filter(|c| !c.is_whitespace() && *c != '|').collect();
+        let is_file_labels = squares.eq_ignore_ascii_case("abcdefgh");
+        let is_rule = squares.chars().all(|c| "+-=".contains(c));
+        if is_file_labels || is_rule {
+            continue;
+        }
+        ranks.push(read_rank(line, ranks.len())?);
+    }
+    if ranks.len() != 8 {
+        return Err(DiagramError::WrongRankCount(ranks.len()));
+    }
+
+    let home = |rank: &str, col: usize, piece: char| rank.as_bytes()[col] == piece as u8;
+    let mut castling = String::new();
+    for (rank, letters) in [(&ranks[7], "KQ"), (&ranks[0], "kq")] {
+        let (king, rook) = if letters == "KQ" { ('K', 'R') } else { ('k', 'r') };
+        if home(rank, 4, king) {
+            for (col, letter) in [(7, letters.as_bytes()[0]), (0, letters.as_bytes()[1])] {
+                if home(rank, col, rook) {
+                    castling.push(letter as char);
+                }
+            }
+        }
+    }
+    if castling.is_empty() {
+        castling.push('-');
+    }
+
+    let fen = format!("{} {} {} - 0 1", ranks.join("/"), to_move, castling);
+    GameState::from_fen(&fen).map_err(DiagramError::InvalidPosition)
+}
+
+// > read one rank of a diagram as eight FEN characters, with '1' for each empty square
+fn read_rank(line: &str, index: usize) -> Result<String, DiagramError> {
+    let label = (b'8' - index.min(7) as u8) as char;
+    // A label stands apart from the squares, so "8" alone is still a rank of eight empty squares.
+    let line = line.strip_prefix(label).filter(|rest| rest.starts_with(char::is_whitespace)).unwrap_or(line);
+    let line = line.strip_suffix(label).filter(|rest| rest.ends_with(char::is_whitespace)).unwrap_or(line);
+    let mut rank = String::new();
+    for c in line.chars().filter(|c| !c.is_whitespace() && *c != '|') {
+        match c {
+            '.' => rank.push('1'),
+            '1'..='8' => (0..c as usize - '0' as usize).for_each(|_| rank.push('1')),
+            'p' | 'n' | 'b' | 'r' | 'q' | 'k' | 'P' | 'N' | 'B' | 'R' | 'Q' | 'K' => rank.push(c),
+            _ => rank.push(symbol_letter(c).ok_or(DiagramError::InvalidSquare(c))?),
+        }
+    }
+    if rank.len() != 8 {
+        return Err(DiagramError::WrongRankLength(8 - index, line.trim().to_string()));
+    }
+    Ok(rank)
+}
+
+// > give the FEN letter for a chess symbol as the board display draws it
+fn symbol_letter(symbol: char) -> Option<char> {
+    let (letter, colour) = match symbol {
+        '♟' => ('p', Colour::White),
+        '♞' => ('n', Colour::White),
+        '♝' => ('b', Colour::White),
+        '♜' => ('r', Colour::White),
+        '♛' => ('q', Colour::White),
+        '♚' => ('k', Colour::White),
+        '♙' => ('p', Colour::Black),
+        '♘' => ('n', Colour::Black),
+        '♗' => ('b', Colour::Black),
+        '♖' => ('r', Colour::Black),
+        '♕' => ('q', Colour::Black),
+        '♔' => ('k', Colour::Black),
+        _ => return None,
+    };
+    match colour {
+        Colour::White => Some(letter.to_ascii_uppercase()),
+        Colour::Black => Some(letter),
+    }
+}
+
+// > remove terminal colour codes such as "\x1b[0m"
+fn strip_escapes(line: &str) -> String {
+    let mut text = String::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().take_while(|c| !c.is_ascii_alphabetic()).for_each(drop);
+        } else {
+            text.push(c);
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use overload_vibe_coding::{ChessPiece, FenError, Move, Square};
+
+    #[test]
+    fn test_own_display() {
+        let mut game_state = GameState::new();
+        game_state.make_move(Move::new(Square::E2, Square::E4)).unwrap();
+        let parsed = parse(&game_state.to_string()).unwrap();
+        assert_eq!(parsed.to_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1");
+    }
+
+    #[test]
+    fn test_letter_grid() {
+        let diagram = "
+            +-----------------+
+            | . . . . k . . r |
+            | . . . . . . . . |
+            | . . . . . . . . |
+            | . . . . . . . . |
+            | . . . . . . . . |
+            | . . . . . . . . |
+            | . . . . . P . . |
+            | R . . . K . . . |
+            +-----------------+
+              a b c d e f g h
+        ";
+        let game_state = parse(diagram).unwrap();
+        assert_eq!(game_state.to_fen(), "4k2r/8/8/8/8/8/5P2/R3K3 w Qk - 0 1");
+        assert_eq!(game_state.board()[1][5], ChessPiece::Pawn(Colour::White));
+
+        let compact = "4k3\n8\n8\n8\n8\n8\n8\n4K3\nCurrent Player: Black";
+        assert_eq!(parse(compact).unwrap().to_fen(), "4k3/8/8/8/8/8/8/4K3 b - - 0 1");
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(parse("4k3\n8\n4K3"), Err(DiagramError::WrongRankCount(3)));
+        assert_eq!(
+            parse("4k3\n8\n8\n8\n8\n8\n. . x . . . . .\n4K3"),
+            Err(DiagramError::InvalidSquare('x'))
+        );
+        assert_eq!(
+            parse("4k3\n8\n8\n8\n8\n8\n. . . .\n4K3"),
+            Err(DiagramError::WrongRankLength(2, ". . . .".to_string()))
+        );
+        assert!(matches!(
+            parse("8\n8\n8\n8\n8\n8\n8\n4K3"),
+            Err(DiagramError::InvalidPosition(FenError::IllegalPosition(_)))
+        ));
+    }
+}
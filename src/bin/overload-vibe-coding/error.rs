@@ -0,0 +1,181 @@
+//! Errors of the program's own parts: the board diagrams, puzzles and clipboard of
+//! the prompt, and the optional DGT board, game import and Lichess bot.
+
+use std::fmt;
+
+#[cfg(feature = "import")]
+use overload_vibe_coding::PgnError;
+use overload_vibe_coding::{ChessError, FenError};
+
+// > add an error enum for board diagrams that cannot be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum DiagramError {
+    WrongRankCount(usize),
+    // The rank number and the line as written.
+    WrongRankLength(usize, String),
+    InvalidSquare(char),
+    InvalidPosition(FenError),
+}
+
+impl fmt::Display for DiagramError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiagramError::WrongRankCount(count) => write!(f, "A diagram needs 8 ranks, not {}.", count),
+            DiagramError::WrongRankLength(rank, text) => write!(f, "Rank {} does not have 8 squares: '{}'.", rank, text),
+            DiagramError::InvalidSquare(c) => write!(f, "'{}' is not a piece or an empty square.", c),
+            DiagramError::InvalidPosition(err) => write!(f, "Invalid position: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for DiagramError {}
+
+// > add an error enum for when the system clipboard cannot be used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ClipboardError {
+    #[cfg(not(feature = "clipboard"))]
+    NotBuiltIn,
+    // The reason the system gave.
+    #[cfg(feature = "clipboard")]
+    Unavailable(String),
+}
+
+impl fmt::Display for ClipboardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(not(feature = "clipboard"))]
+            ClipboardError::NotBuiltIn => write!(f, "Clipboard support is not built in; rebuild with --features clipboard."),
+            #[cfg(feature = "clipboard")]
+            ClipboardError::Unavailable(reason) => write!(f, "The clipboard cannot be used: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ClipboardError {}
+
+// > add an error enum for games that cannot be downloaded or read from another site's export
+#[cfg(feature = "import")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ImportError {
+    // The connection failed or was cut off.
+    Http(String),
+    Status(u16),
+    InvalidSource(String),
+    InvalidJson(String),
+    // The ID that was asked for.
+    NotFound(String),
+    // How many games the export holds when no ID was given.
+    Ambiguous(usize),
+    UnsupportedVariant(String),
+    InvalidGame(PgnError),
+}
+
+#[cfg(feature = "import")]
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Http(reason) => write!(f, "Could not download the game: {}", reason),
+            ImportError::Status(status) => write!(f, "The site answered with status {}.", status),
+            ImportError::InvalidSource(text) => write!(f, "'{}' is not a game URL or ID.", text),
+            ImportError::InvalidJson(text) => write!(f, "The export is not a game: {}", text),
+            ImportError::NotFound(id) => write!(f, "There is no game {} in the export.", id),
+            ImportError::Ambiguous(count) => write!(f, "The export holds {} games; say which one by its ID.", count),
+            ImportError::UnsupportedVariant(variant) => write!(f, "The game is {}, which is not standard chess.", variant),
+            ImportError::InvalidGame(err) => write!(f, "The game cannot be replayed: {}", err),
+        }
+    }
+}
+
+#[cfg(feature = "import")]
+impl std::error::Error for ImportError {}
+
+// > add an error enum for talking to a DGT board
+#[cfg(feature = "dgt")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum DgtError {
+    // The reason the system gave.
+    Io(String),
+    Closed,
+    // The first byte of the message.
+    InvalidMessage(u8),
+    UnknownPiece(u8),
+}
+
+#[cfg(feature = "dgt")]
+impl From<std::io::Error> for DgtError {
+    fn from(err: std::io::Error) -> DgtError {
+        match err.kind() {
+            std::io::ErrorKind::UnexpectedEof => DgtError::Closed,
+            _ => DgtError::Io(err.to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "dgt")]
+impl fmt::Display for DgtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DgtError::Io(reason) => write!(f, "The DGT board cannot be used: {}", reason),
+            DgtError::Closed => write!(f, "The DGT board was disconnected."),
+            DgtError::InvalidMessage(byte) => write!(f, "The DGT board sent a message that cannot be read (0x{:02x}).", byte),
+            DgtError::UnknownPiece(code) => write!(f, "The DGT board reported an unknown piece code {}.", code),
+        }
+    }
+}
+
+#[cfg(feature = "dgt")]
+impl std::error::Error for DgtError {}
+
+// > add an error enum for talking to Lichess as a bot
+#[cfg(feature = "lichess")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum LichessError {
+    // The connection failed or was cut off.
+    Http(String),
+    // Lichess refused a request, with the status code and the body it sent.
+    Status(u16, String),
+    InvalidEvent(String),
+    InvalidPosition(FenError),
+    IllegalMove(String, ChessError),
+}
+
+#[cfg(feature = "lichess")]
+impl fmt::Display for LichessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LichessError::Http(reason) => write!(f, "Could not reach Lichess: {}", reason),
+            LichessError::Status(status, body) => write!(f, "Lichess answered {}: {}", status, body),
+            LichessError::InvalidEvent(text) => write!(f, "Lichess sent something unexpected: {}", text),
+            LichessError::InvalidPosition(err) => write!(f, "Lichess sent an invalid position: {}", err),
+            LichessError::IllegalMove(text, err) => write!(f, "Lichess sent the move {}, which cannot be played: {}", text, err),
+        }
+    }
+}
+
+#[cfg(feature = "lichess")]
+impl std::error::Error for LichessError {}
+
+// > add an error enum for lines of the Lichess puzzle database that cannot be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum PuzzleError {
+    WrongFieldCount(usize),
+    InvalidPosition(FenError),
+    // The move as written and why it was rejected.
+    InvalidMove(String, ChessError),
+    NoSolution,
+    InvalidRating(String),
+}
+
+impl fmt::Display for PuzzleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PuzzleError::WrongFieldCount(count) => write!(f, "A puzzle needs at least 9 fields, not {}.", count),
+            PuzzleError::InvalidPosition(err) => write!(f, "Invalid position: {}", err),
+            PuzzleError::InvalidMove(text, err) => write!(f, "{} cannot be played: {}", text, err),
+            PuzzleError::NoSolution => write!(f, "A puzzle needs the opponent's move and at least one reply."),
+            PuzzleError::InvalidRating(text) => write!(f, "Invalid rating: '{}'.", text),
+        }
+    }
+}
+
+impl std::error::Error for PuzzleError {}
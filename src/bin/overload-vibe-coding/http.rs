@@ -0,0 +1,346 @@
+//! # HTTP API
+//!
+//! A small REST API over HTTP/1.1, so other services can use the rules engine
+//! without linking Rust. Needs the `http` feature:
+//!
+//! ```text
+//! overload-vibe-coding --http 127.0.0.1:8000
+//! ```
+//!
+//! | Request                  | Body                      | Response                       |
+//! |--------------------------|---------------------------|--------------------------------|
+//! | `POST /games`            | optional `{"fen": "..."}` | 201 and the new game           |
+//! | `GET /games/{id}`        |                           | 200 and the game               |
+//! | `POST /games/{id}/moves` | `{"move": "e4"}`          | 200 and the game after the move |
+//!
+//! A game is returned as `{"id": "...", "pgn": "...", "state": {...}}`, where `state`
+//! is described in [`crate::json`] and includes the FEN and the legal moves. Moves
+//! may be UCI or SAN. Errors come back as `{"error": "..."}` with status 400 for a
+//! bad request or illegal move, 404 for an unknown game or path and 405 for the
+//! wrong method. Bodies over 64 KiB are turned away with 413 and request lines and
+//! headers over 16 KiB with 431, and a client that goes quiet for ten seconds is
+//! hung up on. Games are kept in memory and are lost when the server stops.
+//!
+//! Game ids are random, so one client cannot find or play in another's games
+//! by counting. At most 1,000 games are kept; a new one replaces the oldest
+//! finished game, or the oldest game if none has finished. At most 64
+//! connections are served at once, and any more are answered with 503. The
+//! server warns when it listens on an address other than loopback.
+
+use std::collections::{HashMap, VecDeque};
+use std::hash::{BuildHasher, RandomState};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use overload_vibe_coding::{Game, GameResult, GameState, Move};
+use serde_json::{Value, json};
+
+use crate::json;
+
+// The largest body accepted; a move or a FEN needs far less.
+const MAX_BODY: usize = 64 * 1024;
+// The most the request line and headers may take together.
+const MAX_HEADERS: u64 = 16 * 1024;
+// How long a read or write may wait on the client.
+const TIMEOUT: Duration = Duration::from_secs(10);
+// The most games kept at once.
+const MAX_GAMES: usize = 1000;
+// The most connections served at once.
+const MAX_CONNECTIONS: usize = 64;
+
+// > add an in-memory store of the games created through the API
+pub(crate) struct GameStore {
+    games: HashMap<String, Game>,
+    // The ids in the order the games were created, oldest first.
+    order: VecDeque<String>,
+    capacity: usize,
+    // Randomly keyed, so the ids it makes from a counter cannot be guessed.
+    keys: RandomState,
+    next_id: u64,
+}
+
+// > add an HTTP response: a status code and a JSON body
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Response {
+    pub(crate) status: u16,
+    pub(crate) body: Value,
+}
+
+impl Response {
+    fn error(status: u16, message: &str) -> Response {
+        Response { status, body: json!({"error": message}) }
+    }
+}
+
+impl GameStore {
+    pub(crate) fn new() -> GameStore {
+        GameStore::with_capacity(MAX_GAMES)
+    }
+
+    // > keep at most `capacity` games, replacing the oldest finished game, or else the oldest, to make room
+    pub(crate) fn with_capacity(capacity: usize) -> GameStore {
+        GameStore { games: HashMap::new(), order: VecDeque::new(), capacity, keys: RandomState::new(), next_id: 1 }
+    }
+
+    // > answer one request by method, path and body
+    pub(crate) fn route(&mut self, method: &str, path: &str, body: &str) -> Response {
+        let body: Value = match body.trim() {
+            "" => Value::Null,
+            text => match serde_json::from_str(text) {
+                Ok(body) => body,
+                Err(_) => return Response::error(400, "The body must be JSON."),
+            },
+        };
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        match (method, segments.as_slice()) {
+            ("POST", ["games"]) => self.create(&body),
+            ("GET", ["games", id]) => match self.games.get(*id) {
+                Some(game) => Response { status: 200, body: game_json(id, game) },
+                None => Response::error(404, "There is no such game."),
+            },
+            ("POST", ["games", id, "moves"]) => self.play(id, &body),
+            (_, ["games"] | ["games", _] | ["games", _, "moves"]) => Response::error(405, "That method is not allowed here."),
+            _ => Response::error(404, "There is nothing at that path."),
+        }
+    }
+
+    fn create(&mut self, body: &Value) -> Response {
+        let start = match body["fen"].as_str() {
+            Some(fen) => match GameState::from_fen(fen) {
+                Ok(start) => start,
+                Err(err) => return Response::error(400, &err.to_string()),
+            },
+            None => GameState::new(),
+        };
+        if self.games.len() >= self.capacity {
+            self.evict();
+        }
+        let id = self.new_id();
+        let game = Game::from_position(start);
+        let body = game_json(&id, &game);
+        self.games.insert(id.clone(), game);
+        self.order.push_back(id);
+        Response { status: 201, body }
+    }
+
+    // 128 bits from the keyed hash of a counter, written in hex.
+    fn new_id(&mut self) -> String {
+        let count = self.next_id;
+        self.next_id += 1;
+        format!("{:016x}{:016x}", self.keys.hash_one((count, 0)), self.keys.hash_one((count, 1)))
+    }
+
+    fn evict(&mut self) {
+        let finished = self.order.iter().position(|id| self.games[id].state().result() != GameResult::Ongoing);
+        if let Some(id) = self.order.remove(finished.unwrap_or(0)) {
+            self.games.remove(&id);
+        }
+    }
+
+    fn play(&mut self, id: &str, body: &Value) -> Response {
+        let Some(game) = self.games.get_mut(id) else {
+            return Response::error(404, "There is no such game.");
+        };
+        let Some(text) = body["move"].as_str() else {
+            return Response::error(400, "Give the move as {\"move\": \"e4\"}.");
+        };
+        let state = game.state();
+        match Move::from_uci(text).or_else(|_| state.parse_san(text)).and_then(|mv| game.play(mv)) {
+            Ok(_) => Response { status: 200, body: game_json(id, game) },
+            Err(err) => Response::error(400, &err.to_string()),
+        }
+    }
+}
+
+fn game_json(id: &str, game: &Game) -> Value {
+    let state: Value = serde_json::from_str(&json::state_json(game)).expect("state_json writes valid JSON");
+    json!({"id": id, "pgn": game.to_pgn(), "state": state})
+}
+
+// > serve the API until the process ends, one thread per connection up to MAX_CONNECTIONS
+pub(crate) fn serve(address: &str) -> std::io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    let local = listener.local_addr()?;
+    println!("Serving the HTTP API on http://{}", local);
+    if !local.ip().is_loopback() {
+        eprintln!("Warning: the HTTP API has no authentication, and {} can be reached from other machines.", local.ip());
+    }
+    let store = Arc::new(Mutex::new(GameStore::new()));
+    let open = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let mut stream = stream?;
+        if open.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            open.fetch_sub(1, Ordering::SeqCst);
+            // Answered on the listening thread, so it must not wait long on the client.
+            let _ = stream.set_write_timeout(Some(Duration::from_secs(1)));
+            let _ = write_response(&mut stream, &Response::error(503, "The server is busy; try again later."));
+            continue;
+        }
+        let (store, open) = (store.clone(), open.clone());
+        std::thread::spawn(move || {
+            // A client that hangs up part way through needs no answer.
+            let _ = handle_connection(stream, &store);
+            open.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+    Ok(())
+}
+
+fn handle_connection(mut stream: TcpStream, store: &Mutex<GameStore>) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let response = match read_request(&mut BufReader::new(&stream)) {
+        Ok((method, path, body)) => store.lock().expect("store lock").route(&method, &path, &body),
+        Err(response) => response,
+    };
+    write_response(&mut stream, &response)
+}
+
+// > read an HTTP/1.1 request line, headers and body, returning the method, path and body, or the error
+// > response for a request that is malformed or too large
+fn read_request(reader: &mut impl BufRead) -> Result<(String, String, String), Response> {
+    let bad_request = |message: &str| Response::error(400, message);
+    let mut header_room = MAX_HEADERS;
+    let line = read_header_line(reader, &mut header_room)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Err(bad_request("The request line is malformed."));
+    };
+    let (method, path) = (method.to_string(), path.split('?').next().unwrap_or(path).to_string());
+
+    let mut content_length = 0;
+    loop {
+        let header = read_header_line(reader, &mut header_room)?;
+        if header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.trim().eq_ignore_ascii_case("content-length")
+        {
+            content_length = value.trim().parse().map_err(|_| bad_request("Content-Length is not a number."))?;
+        }
+    }
+    if content_length > MAX_BODY {
+        return Err(Response::error(413, "The body is too large."));
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).map_err(|err| bad_request(&err.to_string()))?;
+    let body = String::from_utf8(body).map_err(|_| bad_request("The body is not UTF-8."))?;
+    Ok((method, path, body))
+}
+
+// > read the request line or a header, out of what room is left for them; an empty string is the end of the stream
+fn read_header_line(reader: &mut impl BufRead, room: &mut u64) -> Result<String, Response> {
+    let mut line = String::new();
+    let read = reader.by_ref().take(*room).read_line(&mut line).map_err(|err| Response::error(400, &err.to_string()))?;
+    *room -= read as u64;
+    if *room == 0 && !line.ends_with('\n') {
+        return Err(Response::error(431, "The request headers are too large."));
+    }
+    Ok(line)
+}
+
+fn write_response(writer: &mut impl Write, response: &Response) -> std::io::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        201 => "Created",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Content Too Large",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        _ => "Bad Request",
+    };
+    let body = response.body.to_string();
+    write!(
+        writer,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        reason,
+        body.len(),
+        body
+    )?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_routes() {
+        let mut store = GameStore::new();
+        let created = store.route("POST", "/games", "");
+        assert_eq!(created.status, 201);
+        let id = created.body["id"].as_str().unwrap();
+        assert_eq!(id.len(), 32);
+        assert_eq!(created.body["state"]["legal_moves"].as_array().unwrap().len(), 20);
+
+        let moved = store.route("POST", &format!("/games/{id}/moves"), r#"{"move": "e4"}"#);
+        assert_eq!(moved.status, 200);
+        assert_eq!(moved.body["state"]["fen"], "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+        let moved = store.route("POST", &format!("/games/{id}/moves"), r#"{"move": "e7e5"}"#);
+        assert!(moved.body["pgn"].as_str().unwrap().contains("1. e4 e5 *"));
+        assert_eq!(store.route("GET", &format!("/games/{id}"), "").body, moved.body);
+
+        let from_fen = store.route("POST", "/games/", r#"{"fen": "4k3/8/8/8/8/8/8/4K3 b - - 0 1"}"#);
+        assert_ne!(from_fen.body["id"], id);
+        assert_eq!(from_fen.body["state"]["turn"], "black");
+    }
+
+    #[test]
+    fn test_eviction() {
+        let mut store = GameStore::with_capacity(2);
+        let create = |store: &mut GameStore| store.route("POST", "/games", "").body["id"].as_str().unwrap().to_string();
+        let (first, second) = (create(&mut store), create(&mut store));
+        for mv in ["f3", "e5", "g4", "Qh4"] {
+            store.route("POST", &format!("/games/{second}/moves"), &json!({"move": mv}).to_string());
+        }
+        // The finished game goes before the older one still being played.
+        let third = create(&mut store);
+        assert_eq!(store.route("GET", &format!("/games/{second}"), "").status, 404);
+        assert_eq!(store.route("GET", &format!("/games/{first}"), "").status, 200);
+        create(&mut store);
+        assert_eq!(store.route("GET", &format!("/games/{first}"), "").status, 404);
+        assert_eq!(store.route("GET", &format!("/games/{third}"), "").status, 200);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut store = GameStore::new();
+        let id = store.route("POST", "/games", "").body["id"].as_str().unwrap().to_string();
+        let status = |store: &mut GameStore, method: &str, path: &str, body: &str| store.route(method, path, body).status;
+        assert_eq!(status(&mut store, "GET", "/games/9", ""), 404);
+        assert_eq!(status(&mut store, "POST", "/games/9/moves", r#"{"move": "e4"}"#), 404);
+        assert_eq!(status(&mut store, "GET", "/elsewhere", ""), 404);
+        assert_eq!(status(&mut store, "DELETE", &format!("/games/{id}"), ""), 405);
+        assert_eq!(status(&mut store, "POST", "/games", "{"), 400);
+        assert_eq!(status(&mut store, "POST", "/games", r#"{"fen": "8/8 w"}"#), 400);
+        assert_eq!(status(&mut store, "POST", &format!("/games/{id}/moves"), "{}"), 400);
+        let illegal = store.route("POST", &format!("/games/{id}/moves"), r#"{"move": "e5"}"#);
+        assert_eq!(illegal.status, 400);
+        assert!(illegal.body["error"].as_str().is_some());
+    }
+
+    #[test]
+    fn test_read_and_write() {
+        let request = "POST /games/1/moves?x=1 HTTP/1.1\r\nHost: here\r\ncontent-length: 15\r\n\r\n{\"move\": \"e4\"}\n";
+        let (method, path, body) = read_request(&mut Cursor::new(request)).unwrap();
+        assert_eq!((method.as_str(), path.as_str(), body.as_str()), ("POST", "/games/1/moves", "{\"move\": \"e4\"}\n"));
+        assert!(read_request(&mut Cursor::new("\r\n")).is_err());
+        let status = |request: &str| read_request(&mut Cursor::new(request)).unwrap_err().status;
+        assert_eq!(status("POST /games HTTP/1.1\r\nContent-Length: 1000000000000000\r\n\r\n{}"), 413);
+        assert_eq!(status(&format!("GET /games/1 HTTP/1.1\r\nCookie: {}\r\n\r\n", "x".repeat(20_000))), 431);
+        assert_eq!(status(&format!("GET /{} HTTP/1.1\r\n\r\n", "x".repeat(20_000))), 431);
+
+        let mut written = Vec::new();
+        write_response(&mut written, &Response::error(404, "No")).unwrap();
+        let written = String::from_utf8(written).unwrap();
+        assert!(written.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        assert!(written.ends_with("Content-Length: 14\r\nConnection: close\r\n\r\n{\"error\":\"No\"}"));
+    }
+}
@@ -0,0 +1,235 @@
+//! # Game Import
+//!
+//! Brings finished games in from Lichess and Chess.com, either by downloading them
+//! or from the JSON their exports produce. Needs the `import` feature:
+//!
+//! ```text
+//! import https://lichess.org/q7ZvsdUF        # or just the ID, q7ZvsdUF
+//! import chess.com hikaru https://www.chess.com/game/live/104281230527
+//! import games.json 104281230527             # a saved export, picking one game
+//! ```
+//!
+//! Lichess exports a single game by its ID. Chess.com only publishes a player's games
+//! month by month, so its games are found by searching the player's monthly archives,
+//! newest first. Both exports carry enough to rebuild a PGN, which is then replayed
+//! through the rules engine like any other PGN.
+
+use overload_vibe_coding::{Game, pgn_date};
+use reqwest::Client;
+use serde_json::Value;
+
+use crate::error::ImportError;
+
+const LICHESS: &str = "https://lichess.org";
+const CHESS_COM_API: &str = "https://api.chess.com/pub";
+// Chess.com refuses requests that do not say who is asking.
+const USER_AGENT: &str = concat!("overload-vibe-coding/", env!("CARGO_PKG_VERSION"));
+
+// > add where a game can be downloaded from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Source {
+    Lichess(String),
+    ChessCom { player: String, id: String },
+}
+
+impl Source {
+    // > read a Lichess game URL or its 8-character ID
+    pub(crate) fn lichess(text: &str) -> Result<Source, ImportError> {
+        let path = text.trim().trim_start_matches("https://").trim_start_matches("lichess.org/");
+        // URLs may add the player's half of a 12-character ID, or /black to flip the board.
+        let id: String = path.split(['/', '#', '?']).next().unwrap_or_default().chars().take(8).collect();
+        if id.len() == 8 && id.chars().all(|c| c.is_ascii_alphanumeric()) {
+            Ok(Source::Lichess(id))
+        } else {
+            Err(ImportError::InvalidSource(text.to_string()))
+        }
+    }
+
+    // > read a Chess.com game URL or its numeric ID, for a game played by the given player
+    pub(crate) fn chess_com(player: &str, text: &str) -> Result<Source, ImportError> {
+        let id = text.trim().trim_end_matches('/').rsplit('/').next().unwrap_or_default();
+        if !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()) {
+            Ok(Source::ChessCom { player: player.to_lowercase(), id: id.to_string() })
+        } else {
+            Err(ImportError::InvalidSource(text.to_string()))
+        }
+    }
+
+    // > download the game
+    pub(crate) async fn download(&self) -> Result<Game, ImportError> {
+        let client = Client::builder().user_agent(USER_AGENT).build().map_err(http_error)?;
+        match self {
+            Source::Lichess(id) => {
+                let url = format!("{}/game/export/{}", LICHESS, id);
+                let text = get(client.get(url).header("Accept", "application/json")).await?;
+                game_from_json(&text, Some(id))
+            }
+            Source::ChessCom { player, id } => {
+                let text = get(client.get(format!("{}/player/{}/games/archives", CHESS_COM_API, player))).await?;
+                let archives = read_json(&text)?;
+                let archives = archives["archives"].as_array().ok_or_else(|| ImportError::InvalidJson(text.clone()))?;
+                for archive in archives.iter().rev().filter_map(Value::as_str) {
+                    match game_from_json(&get(client.get(archive)).await?, Some(id)) {
+                        Err(ImportError::NotFound(_)) => continue,
+                        found => return found,
+                    }
+                }
+                Err(ImportError::NotFound(id.clone()))
+            }
+        }
+    }
+}
+
+// > read a game from a Lichess or Chess.com JSON export, choosing the one with the given ID
+// > when the export holds several: a Chess.com monthly archive or Lichess's NDJSON
+pub(crate) fn game_from_json(text: &str, id: Option<&str>) -> Result<Game, ImportError> {
+    let games = match serde_json::from_str::<Value>(text) {
+        Ok(Value::Object(mut object)) => match object.remove("games") {
+            Some(Value::Array(games)) => games,
+            _ => vec![Value::Object(object)],
+        },
+        Ok(other) => return Err(ImportError::InvalidJson(other.to_string())),
+        Err(_) => text.lines().filter(|line| !line.trim().is_empty()).map(read_json).collect::<Result<_, _>>()?,
+    };
+    let mut matching: Vec<Value> = match id {
+        Some(id) => games.into_iter().filter(|game| has_id(game, id)).collect(),
+        None => games,
+    };
+    match matching.len() {
+        1 => game_from_value(&matching.remove(0)),
+        0 => Err(ImportError::NotFound(id.unwrap_or_default().to_string())),
+        count => Err(ImportError::Ambiguous(count)),
+    }
+}
+
+// Lichess games have an "id"; Chess.com games are known by the number at the end of their "url".
+fn has_id(game: &Value, id: &str) -> bool {
+    game["id"].as_str() == Some(id) || game["url"].as_str().is_some_and(|url| url.rsplit('/').next() == Some(id))
+}
+
+// > turn one exported game into a Game, using its PGN when the export includes it
+fn game_from_value(game: &Value) -> Result<Game, ImportError> {
+    let pgn = match game["pgn"].as_str() {
+        Some(pgn) => pgn.to_string(),
+        None => lichess_pgn(game)?,
+    };
+    Game::from_pgn(&pgn).map_err(ImportError::InvalidGame)
+}
+
+// > rebuild a PGN from Lichess's JSON, which gives the moves as space-separated SAN
+fn lichess_pgn(game: &Value) -> Result<String, ImportError> {
+    let moves = game["moves"].as_str().ok_or_else(|| ImportError::InvalidJson(game.to_string()))?;
+    let variant = game["variant"].as_str().unwrap_or("standard");
+    if variant != "standard" && variant != "fromPosition" {
+        return Err(ImportError::UnsupportedVariant(variant.to_string()));
+    }
+    let player = |colour: &str| {
+        let player = &game["players"][colour];
+        match (player["user"]["name"].as_str(), player["aiLevel"].as_u64()) {
+            (Some(name), _) => name.to_string(),
+            (None, Some(level)) => format!("Lichess AI level {}", level),
+            (None, None) => "?".to_string(),
+        }
+    };
+    let result = match (game["winner"].as_str(), game["status"].as_str()) {
+        (Some("white"), _) => "1-0",
+        (Some("black"), _) => "0-1",
+        (None, Some("draw" | "stalemate")) => "1/2-1/2",
+        _ => "*",
+    };
+    let rated = if game["rated"].as_bool() == Some(true) { "Rated" } else { "Casual" };
+    let speed = game["speed"].as_str().unwrap_or("correspondence");
+
+    let mut tags = vec![
+        ("Event", format!("{} {} game", rated, speed)),
+        ("Site", format!("{}/{}", LICHESS, game["id"].as_str().unwrap_or("?"))),
+        ("Date", game["createdAt"].as_u64().map_or("????.??.??".to_string(), |ms| pgn_date(ms / 1000))),
+        ("Round", "-".to_string()),
+        ("White", player("white")),
+        ("Black", player("black")),
+        ("Result", result.to_string()),
+    ];
+    if let Some(fen) = game["initialFen"].as_str() {
+        tags.push(("SetUp", "1".to_string()));
+        tags.push(("FEN", fen.to_string()));
+    }
+    let mut text: String = tags.iter().map(|(name, value)| format!("[{} \"{}\"]\n", name, value)).collect();
+    text.push_str(&format!("\n{} {}\n", moves, result));
+    Ok(text)
+}
+
+async fn get(request: reqwest::RequestBuilder) -> Result<String, ImportError> {
+    let response = request.send().await.map_err(http_error)?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(ImportError::Status(status.as_u16()));
+    }
+    response.text().await.map_err(http_error)
+}
+
+fn http_error(err: reqwest::Error) -> ImportError {
+    ImportError::Http(err.to_string())
+}
+
+fn read_json(text: &str) -> Result<Value, ImportError> {
+    serde_json::from_str(text).map_err(|_| ImportError::InvalidJson(text.trim().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use overload_vibe_coding::GameResult;
+
+    #[test]
+    fn test_sources() {
+        let expected = Ok(Source::Lichess("q7ZvsdUF".to_string()));
+        assert_eq!(Source::lichess("q7ZvsdUF"), expected);
+        assert_eq!(Source::lichess("https://lichess.org/q7ZvsdUFxyz1"), expected);
+        assert_eq!(Source::lichess("lichess.org/q7ZvsdUF/black#12"), expected);
+        assert!(Source::lichess("https://lichess.org/").is_err());
+        assert_eq!(
+            Source::chess_com("Hikaru", "https://www.chess.com/game/live/104281230527"),
+            Ok(Source::ChessCom { player: "hikaru".to_string(), id: "104281230527".to_string() })
+        );
+        assert!(Source::chess_com("hikaru", "not-a-game").is_err());
+    }
+
+    #[test]
+    fn test_lichess_json() {
+        let json = r#"{"id":"q7ZvsdUF","rated":true,"variant":"standard","speed":"blitz","createdAt":1514505150384,
+            "status":"mate","winner":"black","players":{"white":{"user":{"name":"Lance5500"}},"black":{"aiLevel":3}},
+            "moves":"f3 e5 g4 Qh4#"}"#;
+        let game = game_from_json(json, None).unwrap();
+        assert_eq!(game.moves().len(), 4);
+        assert_eq!(game.state().result(), GameResult::BlackWins);
+        let tag = |name: &str| game.tags().iter().find(|(tag, _)| tag == name).map(|(_, value)| value.clone());
+        assert_eq!(tag("Event").as_deref(), Some("Rated blitz game"));
+        assert_eq!(tag("Date").as_deref(), Some("2017.12.28"));
+        assert_eq!(tag("Black").as_deref(), Some("Lichess AI level 3"));
+        assert_eq!(tag("Site").as_deref(), Some("https://lichess.org/q7ZvsdUF"));
+
+        let chess960 = r#"{"id":"abcdefgh","variant":"chess960","moves":"e4"}"#;
+        assert_eq!(game_from_json(chess960, None), Err(ImportError::UnsupportedVariant("chess960".to_string())));
+    }
+
+    #[test]
+    fn test_lichess_ndjson_and_pgn_in_json() {
+        let ndjson = "{\"id\":\"aaaaaaaa\",\"moves\":\"e4 e5\"}\n{\"id\":\"bbbbbbbb\",\"pgn\":\"1. d4 d5 2. c4 *\"}\n";
+        assert_eq!(game_from_json(ndjson, Some("bbbbbbbb")).unwrap().moves().len(), 3);
+        assert_eq!(game_from_json(ndjson, None), Err(ImportError::Ambiguous(2)));
+        assert_eq!(game_from_json(ndjson, Some("cccccccc")), Err(ImportError::NotFound("cccccccc".to_string())));
+    }
+
+    #[test]
+    fn test_chess_com_archive() {
+        let archive = r#"{"games":[
+            {"url":"https://www.chess.com/game/live/1","pgn":"[White \"a\"]\n[Black \"b\"]\n\n1. e4 e5 1-0"},
+            {"url":"https://www.chess.com/game/live/2","pgn":"[White \"c\"]\n[Black \"d\"]\n\n1. e4 e5 2. Ke2 0-1"}]}"#;
+        let game = game_from_json(archive, Some("2")).unwrap();
+        assert_eq!(game.moves().len(), 3);
+        assert_eq!(game.state().result(), GameResult::BlackWins);
+        assert!(matches!(game_from_json("[1, 2]", None), Err(ImportError::InvalidJson(_))));
+        let broken = r#"{"pgn":"1. e4 e4 *"}"#;
+        assert!(matches!(game_from_json(broken, None), Err(ImportError::InvalidGame(_))));
+    }
+}
@@ -0,0 +1,189 @@
+//! # JSON State
+//!
+//! A fixed JSON description of a game for web frontends, so a page talking to the
+//! engine over WASM or HTTP can draw the board and offer only legal moves without
+//! knowing the rules itself:
+//!
+//! ```text
+//! {
+//!   "version": 1,
+//!   "fen": "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1",
+//!   "board": [["r", "n", ...], ..., ["R", "N", ...]],
+//!   "turn": "black",
+//!   "legal_moves": [{"from": "a7", "to": "a6", "uci": "a7a6", "san": "a6"}, ...],
+//!   "last_move": {"from": "e2", "to": "e4", "uci": "e2e4", "san": "e4"},
+//!   "check": false,
+//!   "result": "*",
+//!   "draw_reason": null,
+//!   "draw_offer": null
+//! }
+//! ```
+//!
+//! `board` lists the ranks from the eighth down to the first, each from the a-file
+//! to the h-file, with FEN letters for pieces and `null` for empty squares.
+//! `result` is a PGN result token. `draw_reason` is one of `stalemate`,
+//! `insufficient_material`, `threefold_repetition`, `agreement`, `fifty_move_rule`,
+//! `fivefold_repetition` or `seventy_five_move_rule` once the game is drawn.
+//! Fields are only ever added, and `version` goes up if one has to change.
+
+use overload_vibe_coding::{Colour, DrawReason, Game, GameResult, GameState, Move, piece_to_char};
+
+const VERSION: u32 = 1;
+
+// > describe the game as JSON for a web frontend: board, legal moves, last move, check and result
+pub(crate) fn state_json(game: &Game) -> String {
+    let state = game.state();
+    let ranks: Vec<String> = state
+        .board()
+        .iter()
+        .rev()
+        .map(|row| {
+            let squares: Vec<String> = row
+                .iter()
+                .map(|&piece| piece_to_char(piece).map_or("null".to_string(), |c| quote(&c.to_string())))
+                .collect();
+            format!("[{}]", squares.join(", "))
+        })
+        .collect();
+    let legal_moves: Vec<String> = state.legal_moves().iter().map(|mv| move_json(state, mv)).collect();
+
+    let moves = game.moves();
+    let last_move = match moves.split_last() {
+        Some((last, earlier)) => {
+            let mut before = game.start().clone();
+            for &mv in earlier {
+                before.make_move(mv).expect("recorded moves are legal");
+            }
+            move_json(&before, last)
+        }
+        None => "null".to_string(),
+    };
+
+    let result = match state.result() {
+        GameResult::WhiteWins => "1-0",
+        GameResult::BlackWins => "0-1",
+        GameResult::Draw(_) => "1/2-1/2",
+        GameResult::Ongoing => "*",
+    };
+    let draw_reason = match state.result() {
+        GameResult::Draw(reason) => quote(draw_reason_name(reason)),
+        _ => "null".to_string(),
+    };
+
+    let fields = [
+        ("version", VERSION.to_string()),
+        ("fen", quote(&state.to_fen())),
+        ("board", format!("[{}]", ranks.join(", "))),
+        ("turn", quote(colour_name(state.side_to_move()))),
+        ("legal_moves", format!("[{}]", legal_moves.join(", "))),
+        ("last_move", last_move),
+        ("check", state.is_in_check(state.side_to_move()).to_string()),
+        ("result", quote(result)),
+        ("draw_reason", draw_reason),
+        ("draw_offer", state.draw_offer().map_or("null".to_string(), |colour| quote(colour_name(colour)))),
+    ];
+    let fields: Vec<String> = fields.iter().map(|(name, value)| format!("  {}: {}", quote(name), value)).collect();
+    format!("{{\n{}\n}}", fields.join(",\n"))
+}
+
+// > describe a move from the given position as a JSON object with squares, UCI and SAN
+fn move_json(position: &GameState, mv: &Move) -> String {
+    format!(
+        "{{\"from\": {}, \"to\": {}, \"uci\": {}, \"san\": {}}}",
+        quote(&mv.from.to_string()),
+        quote(&mv.to.to_string()),
+        quote(&mv.to_uci()),
+        quote(&position.move_to_san(mv))
+    )
+}
+
+fn colour_name(colour: Colour) -> &'static str {
+    match colour {
+        Colour::White => "white",
+        Colour::Black => "black",
+    }
+}
+
+fn draw_reason_name(reason: DrawReason) -> &'static str {
+    match reason {
+        DrawReason::Stalemate => "stalemate",
+        DrawReason::InsufficientMaterial => "insufficient_material",
+        DrawReason::ThreefoldRepetition => "threefold_repetition",
+        DrawReason::Agreement => "agreement",
+        DrawReason::FiftyMoveRule => "fifty_move_rule",
+        DrawReason::FivefoldRepetition => "fivefold_repetition",
+        DrawReason::SeventyFiveMoveRule => "seventy_five_move_rule",
+    }
+}
+
+// > write a string as a JSON string literal, escaping quotes, backslashes and control characters
+fn quote(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            _ if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use overload_vibe_coding::Square;
+    use serde_json::Value;
+
+    fn parse(game: &Game) -> Value {
+        serde_json::from_str(&state_json(game)).unwrap()
+    }
+
+    #[test]
+    fn test_start_position() {
+        let json = parse(&Game::from_position(GameState::new()));
+        assert_eq!(json["version"], 1);
+        assert_eq!(json["fen"], "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(json["board"][0][0], "r");
+        assert_eq!(json["board"][7][4], "K");
+        assert_eq!(json["board"][4][4], Value::Null);
+        assert_eq!(json["turn"], "white");
+        assert_eq!(json["legal_moves"].as_array().unwrap().len(), 20);
+        assert_eq!(json["last_move"], Value::Null);
+        assert_eq!(json["check"], false);
+        assert_eq!(json["result"], "*");
+        assert_eq!(json["draw_reason"], Value::Null);
+    }
+
+    #[test]
+    fn test_after_moves() {
+        let mut game = Game::from_position(GameState::new());
+        for (from, to) in [(Square::F2, Square::F3), (Square::E7, Square::E5), (Square::G2, Square::G4), (Square::D8, Square::H4)] {
+            game.play(Move::new(from, to)).unwrap();
+        }
+        let json = parse(&game);
+        assert_eq!(
+            json["last_move"],
+            serde_json::json!({"from": "d8", "to": "h4", "uci": "d8h4", "san": "Qh4#"})
+        );
+        assert_eq!(json["check"], true);
+        assert_eq!(json["result"], "0-1");
+        assert_eq!(json["legal_moves"].as_array().unwrap().len(), 0);
+
+        let mut game = Game::from_position(GameState::new());
+        game.play(Move::new(Square::E2, Square::E4)).unwrap();
+        game.offer_draw(Colour::Black).unwrap();
+        game.accept_draw(Colour::White).unwrap();
+        let json = parse(&game);
+        assert_eq!(json["result"], "1/2-1/2");
+        assert_eq!(json["draw_reason"], "agreement");
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("a \"b\" \\ c\n\t"), "\"a \\\"b\\\" \\\\ c\\n\\u0009\"");
+    }
+}
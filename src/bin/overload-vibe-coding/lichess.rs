@@ -0,0 +1,225 @@
+//! # Lichess Bot
+//!
+//! Plays on Lichess as a bot account through the Bot API, using the program's own
+//! engine to choose moves. Needs the `lichess` feature and a bot account's API token:
+//!
+//! ```text
+//! LICHESS_BOT_TOKEN=lip_... overload-vibe-coding --lichess-bot
+//! ```
+//!
+//! The bot accepts standard chess challenges, declines other variants, and plays
+//! every game it is in at the same time. Lichess sends events as newline-delimited
+//! JSON, with empty lines to keep the connection open.
+
+use std::time::Duration;
+
+use overload_vibe_coding::eval::Evaluator;
+use overload_vibe_coding::search::{self, Limits, Signals};
+use overload_vibe_coding::{Colour, GameState, Move};
+use reqwest::{Client, Response};
+use serde_json::Value;
+
+use crate::error::LichessError;
+
+const LICHESS: &str = "https://lichess.org";
+
+// > add a client for the Lichess Bot API that plays with the local engine
+#[derive(Clone)]
+pub(crate) struct LichessBot {
+    client: Client,
+    token: String,
+}
+
+impl LichessBot {
+    pub(crate) fn new(token: &str) -> LichessBot {
+        LichessBot { client: Client::new(), token: token.to_string() }
+    }
+
+    // > accept challenges and play each game that starts, until the event stream closes
+    pub(crate) async fn run(&self) -> Result<(), LichessError> {
+        let account = self.send(self.client.get(format!("{}/api/account", LICHESS))).await?;
+        let account = read_json(&account.text().await.map_err(http_error)?)?;
+        let bot_id = account["id"].as_str().ok_or_else(|| LichessError::InvalidEvent(account.to_string()))?.to_string();
+        println!("Connected to Lichess as {}.", bot_id);
+
+        let mut events = EventStream::new(self.send(self.client.get(format!("{}/api/stream/event", LICHESS))).await?);
+        while let Some(event) = events.next().await? {
+            match event["type"].as_str() {
+                Some("challenge") => {
+                    let Some((id, accept)) = challenge_reply(&event) else { continue };
+                    let path = if accept { "accept" } else { "decline" };
+                    let request = self.client.post(format!("{}/api/challenge/{}/{}", LICHESS, id, path));
+                    let request = if accept { request } else { request.form(&[("reason", "variant")]) };
+                    if let Err(err) = self.send(request).await {
+                        eprintln!("Could not {} challenge {}: {}", path, id, err);
+                    }
+                }
+                Some("gameStart") => {
+                    let Some(game_id) = event["game"]["gameId"].as_str().or(event["game"]["id"].as_str()) else { continue };
+                    let (bot, game_id, bot_id) = (self.clone(), game_id.to_string(), bot_id.clone());
+                    tokio::spawn(async move {
+                        if let Err(err) = bot.play_game(&game_id, &bot_id).await {
+                            eprintln!("Game {} stopped: {}", game_id, err);
+                        }
+                    });
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    // > follow one game's state stream, moving whenever it is the bot's turn
+    async fn play_game(&self, game_id: &str, bot_id: &str) -> Result<(), LichessError> {
+        let stream = self.client.get(format!("{}/api/bot/game/stream/{}", LICHESS, game_id));
+        let mut events = EventStream::new(self.send(stream).await?);
+        let mut setup = None;
+        while let Some(event) = events.next().await? {
+            let state = match event["type"].as_str() {
+                Some("gameFull") => {
+                    let colour = if event["white"]["id"].as_str() == Some(bot_id) { Colour::White } else { Colour::Black };
+                    setup = Some((event["initialFen"].as_str().unwrap_or("startpos").to_string(), colour));
+                    &event["state"]
+                }
+                Some("gameState") => &event,
+                _ => continue,
+            };
+            let Some((initial_fen, colour)) = &setup else { continue };
+            if state["status"].as_str() != Some("started") {
+                break;
+            }
+            let (initial_fen, moves, colour) = (initial_fen.clone(), state["moves"].as_str().unwrap_or("").to_string(), *colour);
+            let limits = clock_limits(state, colour);
+            // The search is CPU-bound, so it runs off the runtime's workers to keep the
+            // event stream and the other games' streams flowing while the bot thinks.
+            let reply = tokio::task::spawn_blocking(move || move_to_play(&initial_fen, &moves, colour, limits))
+                .await
+                .expect("the search thread panicked");
+            if let Some(mv) = reply? {
+                let url = format!("{}/api/bot/game/{}/move/{}", LICHESS, game_id, mv.to_uci());
+                self.send(self.client.post(url)).await?;
+            }
+        }
+        Ok(())
+    }
+
+    // > send a request with the bot's token, turning error statuses into errors
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<Response, LichessError> {
+        let response = request.bearer_auth(&self.token).send().await.map_err(http_error)?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(LichessError::Status(status.as_u16(), body));
+        }
+        Ok(response)
+    }
+}
+
+// > read newline-delimited JSON from a response, one event at a time
+struct EventStream {
+    response: Response,
+    buffer: Vec<u8>,
+}
+
+impl EventStream {
+    fn new(response: Response) -> EventStream {
+        EventStream { response, buffer: Vec::new() }
+    }
+
+    async fn next(&mut self) -> Result<Option<Value>, LichessError> {
+        loop {
+            while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = self.buffer.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line);
+                // Empty lines only keep the connection alive.
+                if !line.trim().is_empty() {
+                    return read_json(&line).map(Some);
+                }
+            }
+            match self.response.chunk().await.map_err(http_error)? {
+                Some(chunk) => self.buffer.extend_from_slice(&chunk),
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+fn http_error(err: reqwest::Error) -> LichessError {
+    LichessError::Http(err.to_string())
+}
+
+fn read_json(text: &str) -> Result<Value, LichessError> {
+    serde_json::from_str(text).map_err(|_| LichessError::InvalidEvent(text.trim().to_string()))
+}
+
+// > decide whether to accept a challenge event: only standard chess is played
+fn challenge_reply(event: &Value) -> Option<(String, bool)> {
+    let challenge = &event["challenge"];
+    let id = challenge["id"].as_str()?.to_string();
+    Some((id, challenge["variant"]["key"].as_str() == Some("standard")))
+}
+
+// > plan the time for a move from the clock times in a game state, given in milliseconds
+fn clock_limits(state: &Value, colour: Colour) -> Limits {
+    let (time, increment) = match colour {
+        Colour::White => ("wtime", "winc"),
+        Colour::Black => ("btime", "binc"),
+    };
+    let millis = |field: &str| state[field].as_u64().map(Duration::from_millis);
+    match millis(time) {
+        Some(remaining) => Limits::clock(remaining, millis(increment).unwrap_or_default(), None),
+        // Correspondence games have no clock.
+        None => Limits::default(),
+    }
+}
+
+// > replay a game's moves and choose the bot's reply if it is the bot's turn
+fn move_to_play(initial_fen: &str, moves: &str, colour: Colour, limits: Limits) -> Result<Option<Move>, LichessError> {
+    let mut position = match initial_fen {
+        "startpos" => GameState::new(),
+        fen => GameState::from_fen(fen).map_err(LichessError::InvalidPosition)?,
+    };
+    for text in moves.split_whitespace() {
+        let illegal = |err| LichessError::IllegalMove(text.to_string(), err);
+        position.make_move(Move::from_uci(text).map_err(illegal)?).map_err(illegal)?;
+    }
+    if position.side_to_move() != colour {
+        return Ok(None);
+    }
+    Ok(search::iterative_deepening(&position, limits, &Evaluator::default(), 1, Signals::NONE, |_| {}).best_move)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use overload_vibe_coding::Square;
+    use serde_json::json;
+
+    #[test]
+    fn test_challenge_reply() {
+        let standard = json!({"type": "challenge", "challenge": {"id": "abc", "variant": {"key": "standard"}}});
+        assert_eq!(challenge_reply(&standard), Some(("abc".to_string(), true)));
+        let atomic = json!({"type": "challenge", "challenge": {"id": "def", "variant": {"key": "atomic"}}});
+        assert_eq!(challenge_reply(&atomic), Some(("def".to_string(), false)));
+        assert_eq!(challenge_reply(&json!({"type": "challenge"})), None);
+    }
+
+    #[test]
+    fn test_move_to_play() {
+        let limits = Limits::depth(2);
+        assert_eq!(move_to_play("startpos", "e2e4", Colour::White, limits), Ok(None));
+        let reply = move_to_play("startpos", "e2e4 e7e5 g1f3 d8g5", Colour::White, limits).unwrap();
+        assert_eq!(reply.map(|mv| (mv.from, mv.to)), Some((Square::F3, Square::G5)));
+        let mate = move_to_play("7k/8/6K1/8/8/8/8/1Q6 w - - 0 1", "", Colour::White, limits).unwrap();
+        assert_eq!(mate.map(Move::to_uci), Some("b1b8".to_string()));
+        assert!(matches!(move_to_play("startpos", "e2e5", Colour::Black, limits), Err(LichessError::IllegalMove(..))));
+        assert!(matches!(move_to_play("8/8 w", "", Colour::White, limits), Err(LichessError::InvalidPosition(_))));
+    }
+
+    #[test]
+    fn test_clock_limits() {
+        let state = json!({"wtime": 60000, "btime": 3000, "winc": 0, "binc": 1000});
+        assert_eq!(clock_limits(&state, Colour::Black), Limits::clock(Duration::from_secs(3), Duration::from_secs(1), None));
+        assert_eq!(clock_limits(&json!({}), Colour::White), Limits::default());
+    }
+}
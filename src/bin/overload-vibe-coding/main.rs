@@ -0,0 +1,23 @@
+//! The `overload-vibe-coding` program: the prompt, the subcommands and the modes that
+//! talk to other programs, built on the library's public API like any other consumer.
+
+mod cli;
+mod clipboard;
+#[cfg(feature = "dgt")]
+mod dgt;
+mod diagram;
+mod error;
+#[cfg(feature = "http")]
+mod http;
+#[cfg(feature = "import")]
+mod import;
+mod json;
+#[cfg(feature = "lichess")]
+mod lichess;
+mod puzzle;
+#[cfg(feature = "server")]
+mod server;
+
+fn main() {
+    cli::run();
+}
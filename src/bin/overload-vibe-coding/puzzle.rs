@@ -0,0 +1,304 @@
+//! # Puzzles
+//!
+//! Tactics puzzles in the format of the public Lichess puzzle database, one per CSV line:
+//!
+//! `00sHx,q3k1nr/1pp1nQpp/3p4/1P2p3/4P3/B1PP1b2/B5PP/5K2 b k - 0 17,e8d7 a2e6 d7d8 f7f8,1760,80,83,72,mate mateIn2 middlegame short,https://lichess.org/yyznGmXs/black#34,Italian_Game`
+//!
+//! The fields are the puzzle id, a FEN, the moves in UCI, the rating and its deviation,
+//! popularity, number of plays, themes, the game the puzzle came from and opening tags.
+//! The FEN is the position before the opponent's move; the first move is the
+//! opponent's and the rest are the solution. The database starts with a header line
+//! naming the fields, which should be skipped.
+//!
+//! Puzzles can also be extracted from played games. After each move the engine looks
+//! for a tactic of a given depth that the move allowed: the side to move must be
+//! winning, by two pawns or a mate, and two pawns better off than before, and every
+//! other move must do at least two pawns worse than the winning one. The puzzle starts from the position before the move, with the engine's line
+//! as the solution, and its themes are guessed from the solution: mates, material
+//! won, forks, promotions and the solution's length. Extracted puzzles are written in
+//! the same CSV format, with a made-up id and a rating guessed from the depth.
+//!
+//! ```text
+//! puzzles                      # at the prompt: look for puzzles in the game so far
+//! puzzles 6 games.pgn out.csv  # or search six plies deep in a database of games
+//! ```
+
+use overload_vibe_coding::eval::{self, Evaluator};
+use overload_vibe_coding::search::{self, Limits, SearchResult, Signals};
+use overload_vibe_coding::{ChessPiece, Game, GameResult, GameState, Move, PieceKind};
+
+use crate::error::PuzzleError;
+
+// How far ahead the side to move must be for a puzzle: a clear win, or more.
+const WINNING: i32 = 2 * eval::PAWN;
+const CRUSHING: i32 = 6 * eval::PAWN;
+// Mate scores are counted as this many centipawns when comparing.
+const MATE_SCORE: i32 = 100 * eval::PAWN;
+// The rating of a one-move puzzle, and how much each further move of the solution adds.
+const BASE_RATING: u32 = 1000;
+const RATING_PER_MOVE: u32 = 300;
+
+// > add a struct for a puzzle from the Lichess puzzle database
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Puzzle {
+    pub(crate) id: String,
+    // The position before the opponent's move that sets up the puzzle.
+    pub(crate) position: GameState,
+    pub(crate) moves: Vec<Move>,
+    pub(crate) rating: u32,
+    pub(crate) themes: Vec<String>,
+    pub(crate) game_url: String,
+}
+
+impl Puzzle {
+    // > parse one line of the Lichess puzzle CSV, checking that the moves can be played
+    pub(crate) fn from_csv_line(line: &str) -> Result<Puzzle, PuzzleError> {
+        let fields: Vec<&str> = line.trim_end().split(',').collect();
+        // Older exports stop after the game URL; newer ones add opening tags.
+        if fields.len() < 9 {
+            return Err(PuzzleError::WrongFieldCount(fields.len()));
+        }
+        let position = GameState::from_fen(fields[1]).map_err(PuzzleError::InvalidPosition)?;
+
+        let mut moves = Vec::new();
+        let mut replay = position.clone();
+        for uci in fields[2].split_whitespace() {
+            let invalid = |err| PuzzleError::InvalidMove(uci.to_string(), err);
+            let mv = Move::from_uci(uci).map_err(invalid)?;
+            replay.make_move(mv).map_err(invalid)?;
+            moves.push(mv);
+        }
+        if moves.len() < 2 {
+            return Err(PuzzleError::NoSolution);
+        }
+
+        Ok(Puzzle {
+            id: fields[0].to_string(),
+            position,
+            moves,
+            rating: fields[3].parse().map_err(|_| PuzzleError::InvalidRating(fields[3].to_string()))?,
+            themes: fields[7].split_whitespace().map(str::to_string).collect(),
+            game_url: fields[8].to_string(),
+        })
+    }
+
+    // > give the position the solver sees, after the opponent's move
+    pub(crate) fn start(&self) -> GameState {
+        let mut start = self.position.clone();
+        start.make_move(self.moves[0]).expect("puzzle moves were checked when read");
+        start
+    }
+
+    // > give the moves of the solution, the solver's and the replies alternating
+    #[cfg(test)]
+    pub(crate) fn solution(&self) -> &[Move] {
+        &self.moves[1..]
+    }
+
+    // > write the puzzle as a line of the Lichess puzzle CSV, with no popularity or plays yet
+    pub(crate) fn to_csv_line(&self) -> String {
+        let moves: Vec<String> = self.moves.iter().map(|mv| mv.to_uci()).collect();
+        format!("{},{},{},{},0,0,0,{},{},", self.id, self.position.to_fen(), moves.join(" "), self.rating, self.themes.join(" "), self.game_url)
+    }
+
+    // > check a move against the solution, accepting any mate when the solution mates
+    #[cfg(test)]
+    pub(crate) fn is_correct(&self, position: &GameState, ply: usize, mv: Move) -> bool {
+        let Some(&expected) = self.solution().get(ply) else {
+            return false;
+        };
+        if mv.from == expected.from && mv.to == expected.to && mv.promotion == expected.promotion {
+            return true;
+        }
+        // Lichess accepts an alternative move that mates at once.
+        let mut after = position.clone();
+        ply + 1 == self.solution().len() && after.make_move(mv).is_ok() && after.is_checkmate()
+    }
+}
+
+// > look through a game for moves that allowed a tactic, searching the given number of plies,
+// > and make a puzzle of each where the winning line is the only one
+pub(crate) fn extract(game: &Game, plies: u32, evaluator: &Evaluator) -> Vec<Puzzle> {
+    let search = |position: &GameState, plies: u32| {
+        search::iterative_deepening(position, Limits::depth(plies.max(1)), evaluator, 1, Signals::NONE, |_| {})
+    };
+    let game_url = game.tags().iter().find(|(name, _)| name == "Site").map(|(_, site)| site.clone()).filter(|site| site.starts_with("http"));
+    let mut puzzles = Vec::new();
+    let mut position = game.start().clone();
+    let mut before = search(&position, plies);
+    for mv in game.moves() {
+        let start = position.clone();
+        position.make_move(mv).expect("recorded moves are legal");
+        if position.result() != GameResult::Ongoing {
+            break;
+        }
+        let after = search(&position, plies);
+        // The move must have thrown the game away, not merely left it lost.
+        let allowed = centipawns(after.score) >= WINNING && centipawns(after.score) + centipawns(before.score) >= WINNING;
+        if allowed && let Some(solution) = unique_solution(&position, &after, plies, &search) {
+            let mut moves = vec![mv];
+            moves.extend(solution);
+            let winner_moves = moves.len() as u32 / 2;
+            puzzles.push(Puzzle {
+                id: format!("{:08x}", position.position_key() >> 32),
+                themes: themes(&position, &moves[1..], &after),
+                rating: BASE_RATING + RATING_PER_MOVE * (winner_moves - 1),
+                position: start,
+                moves,
+                game_url: game_url.clone().unwrap_or_default(),
+            });
+        }
+        before = after;
+    }
+    puzzles
+}
+
+// > give the winning line if every other move does at least WINNING worse, cut to end on the winner's move
+fn unique_solution(position: &GameState, best: &SearchResult, plies: u32, search: &dyn Fn(&GameState, u32) -> SearchResult) -> Option<Vec<Move>> {
+    let best_move = best.best_move?;
+    for mv in position.legal_moves().into_iter().filter(|&mv| mv != best_move) {
+        let mut after = position.clone();
+        after.make_move(mv).expect("legal moves can be played");
+        let score = if after.result() == GameResult::Ongoing { -search(&after, plies - 1).score } else { -search::terminal_score(&after, 0) };
+        if centipawns(score) > centipawns(best.score) - WINNING {
+            return None;
+        }
+    }
+    let mut solution = best.pv.clone();
+    solution.truncate((solution.len() - 1) / 2 * 2 + 1);
+    Some(solution)
+}
+
+// > guess the themes of a puzzle from its solution, using the Lichess theme names
+fn themes(position: &GameState, solution: &[Move], result: &SearchResult) -> Vec<String> {
+    let mut themes = Vec::new();
+    match result.mate_in() {
+        Some(moves) => themes.extend(["mate".to_string(), format!("mateIn{}", moves)]),
+        None if result.score >= CRUSHING => themes.push("crushing".to_string()),
+        None => themes.push("advantage".to_string()),
+    }
+    let winner = position.side_to_move();
+    let first = solution[0];
+    let mut after = position.clone();
+    after.make_move(first).expect("solution moves are legal");
+    // A fork attacks two valuable pieces at once with the piece just moved.
+    let forked = after
+        .pieces_of(winner.opponent())
+        .filter(|&(square, piece)| piece.kind() != Some(PieceKind::Pawn) && after.attackers_of(square, winner).contains(&first.to))
+        .count();
+    let (row, col) = first.to.to_row_col();
+    let captures = position.board()[row][col] != ChessPiece::Blank;
+    if result.mate_in().is_none() && forked >= 2 {
+        themes.push("fork".to_string());
+    }
+    if result.mate_in().is_none() && captures && solution.len() == 1 {
+        themes.push("hangingPiece".to_string());
+    }
+    if solution.iter().step_by(2).any(|mv| mv.promotion.is_some()) {
+        themes.push("promotion".to_string());
+    }
+    themes.push(
+        match solution.len().div_ceil(2) {
+            1 => "oneMove",
+            2 => "short",
+            3 => "long",
+            _ => "veryLong",
+        }
+        .to_string(),
+    );
+    themes
+}
+
+// > give a score in centipawns, with mates counted as a large but finite amount
+fn centipawns(score: i32) -> i32 {
+    score.clamp(-MATE_SCORE, MATE_SCORE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use overload_vibe_coding::{ChessError, Colour, Square};
+
+    const LINE: &str = "00sHx,q3k1nr/1pp1nQpp/3p4/1P2p3/4P3/B1PP1b2/B5PP/5K2 b k - 0 17,e8d7 a2e6 d7d8 f7f8,1760,80,83,72,mate mateIn2 middlegame short,https://lichess.org/yyznGmXs/black#34,Italian_Game Italian_Game_Classical_Variation";
+
+    #[test]
+    fn test_parse_line() {
+        let puzzle = Puzzle::from_csv_line(LINE).unwrap();
+        assert_eq!(puzzle.id, "00sHx");
+        assert_eq!(puzzle.rating, 1760);
+        assert_eq!(puzzle.themes, vec!["mate", "mateIn2", "middlegame", "short"]);
+        assert_eq!(puzzle.game_url, "https://lichess.org/yyznGmXs/black#34");
+        assert_eq!(puzzle.moves[0], Move::new(Square::E8, Square::D7));
+        assert_eq!(puzzle.solution().len(), 3);
+
+        let start = puzzle.start();
+        assert_eq!(start.side_to_move(), Colour::White);
+        assert_eq!(start.to_fen(), "q5nr/1ppknQpp/3p4/1P2p3/4P3/B1PP1b2/B5PP/5K2 w - - 1 18");
+    }
+
+    #[test]
+    fn test_is_correct() {
+        let puzzle = Puzzle::from_csv_line(LINE).unwrap();
+        let mut position = puzzle.start();
+        assert!(!puzzle.is_correct(&position, 0, Move::new(Square::F7, Square::G7)));
+        assert!(puzzle.is_correct(&position, 0, Move::new(Square::A2, Square::E6)));
+        position.make_move(puzzle.solution()[0]).unwrap();
+        position.make_move(puzzle.solution()[1]).unwrap();
+        assert!(puzzle.is_correct(&position, 2, Move::new(Square::F7, Square::F8)));
+        assert!(!puzzle.is_correct(&position, 3, Move::new(Square::F7, Square::F8)));
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let puzzle = Puzzle::from_csv_line(LINE).unwrap();
+        let line = puzzle.to_csv_line();
+        assert!(line.starts_with("00sHx,q3k1nr/1pp1nQpp/3p4/1P2p3/4P3/B1PP1b2/B5PP/5K2 b k - 0 17,e8d7 a2e6 d7d8 f7f8,1760,"));
+        assert_eq!(Puzzle::from_csv_line(&line), Ok(puzzle));
+    }
+
+    fn game(fen: &str, moves: &[&str]) -> Game {
+        let mut game = Game::from_position(GameState::from_fen(fen).unwrap());
+        for mv in moves {
+            game.play(Move::from_uci(mv).unwrap()).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    fn test_extract_hanging_queen() {
+        // Black puts the queen where only the pawn can take it.
+        let game = game("4k3/3q4/8/8/8/8/4P3/1N2K3 b - - 0 1", &["d7d3"]);
+        let puzzles = extract(&game, 2, &Evaluator::default());
+        assert_eq!(puzzles.len(), 1);
+        let puzzle = &puzzles[0];
+        assert_eq!(puzzle.moves, vec![Move::new(Square::D7, Square::D3), Move::new(Square::E2, Square::D3)]);
+        assert_eq!(puzzle.themes, vec!["advantage", "hangingPiece", "oneMove"]);
+        assert_eq!(puzzle.rating, BASE_RATING);
+        assert_eq!(Puzzle::from_csv_line(&puzzle.to_csv_line()).as_ref(), Ok(puzzle));
+    }
+
+    #[test]
+    fn test_extract_mate_but_not_a_game_already_won() {
+        // White was a rook up all along, but only Kg8 allows mate.
+        let game = game("6k1/5ppp/8/8/8/8/5PPP/R5K1 b - - 0 1", &["g8f8", "g1f1", "f8g8", "f1e1"]);
+        let puzzles = extract(&game, 2, &Evaluator::default());
+        assert_eq!(puzzles.len(), 1, "{:?}", puzzles);
+        assert_eq!(puzzles[0].solution(), &[Move::new(Square::A1, Square::A8)]);
+        assert_eq!(puzzles[0].themes, vec!["mate", "mateIn1", "oneMove"]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(Puzzle::from_csv_line("PuzzleId,FEN,Moves"), Err(PuzzleError::WrongFieldCount(3)));
+        let line = LINE.replace("e8d7 a2e6", "e8d7 a2e7");
+        assert_eq!(
+            Puzzle::from_csv_line(&line),
+            Err(PuzzleError::InvalidMove("a2e7".to_string(), ChessError::IllegalGeometry(overload_vibe_coding::ChessPiece::Bishop(Colour::White))))
+        );
+        let line = LINE.replace("e8d7 a2e6 d7d8 f7f8", "e8d7");
+        assert_eq!(Puzzle::from_csv_line(&line), Err(PuzzleError::NoSolution));
+        let line = LINE.replace(",1760,", ",high,");
+        assert_eq!(Puzzle::from_csv_line(&line), Err(PuzzleError::InvalidRating("high".to_string())));
+        assert!(matches!(Puzzle::from_csv_line(&LINE.replace(" b k ", " x k ")), Err(PuzzleError::InvalidPosition(_))));
+    }
+}
@@ -0,0 +1,413 @@
+//! # WebSocket Server
+//!
+//! Hosts games over WebSockets so two browsers or two programs can play each other,
+//! with the server checking every move. Needs the `server` feature:
+//!
+//! ```text
+//! overload-vibe-coding --serve 0.0.0.0:8080 --minutes 5
+//! ```
+//!
+//! Every message is a JSON object with a `type`. Clients send:
+//!
+//! | Message                                          | Effect                                        |
+//! |--------------------------------------------------|-----------------------------------------------|
+//! | `{"type": "join", "game": "g1", "name": "Anna"}` | join a game, creating it if need be           |
+//! | `{"type": "move", "move": "e2e4"}`               | play a move in UCI or SAN                     |
+//! | `{"type": "resign"}`                             | resign                                        |
+//! | `{"type": "chat", "text": "Good luck!"}`         | send a message to everyone in the game        |
+//! | `{"type": "clock"}`                              | ask for the time left on both clocks          |
+//!
+//! The first two to join a game play White and Black; anyone after them watches.
+//! The server answers with `welcome` (your colour), `joined` and `left` (other
+//! people), `state` (the game as described in [`crate::json`], plus the clocks),
+//! `chat`, `clock` and `error` messages. The clocks start with White's first move,
+//! and a player whose time runs out loses.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures_util::{SinkExt, StreamExt};
+use overload_vibe_coding::{Colour, Game, GameResult, GameState, Move};
+use serde_json::{Value, json};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc::{UnboundedSender, unbounded_channel};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::json;
+
+pub(crate) type ClientId = u64;
+
+// > add the server's view of all games: who is in which game and in what seat
+pub(crate) struct Lobby {
+    time: Duration,
+    rooms: HashMap<String, Room>,
+    members: HashMap<ClientId, Member>,
+}
+
+struct Member {
+    room: String,
+    name: String,
+    seat: Option<Colour>,
+}
+
+struct Room {
+    game: Game,
+    clients: Vec<ClientId>,
+    white: Option<ClientId>,
+    black: Option<ClientId>,
+    clock: Clock,
+}
+
+// > add a chess clock: time left for each side, and when the running side's clock was started
+struct Clock {
+    white: Duration,
+    black: Duration,
+    running_since: Option<Instant>,
+}
+
+impl Clock {
+    fn remaining(&self, colour: Colour, to_move: Colour, now: Instant) -> Duration {
+        let left = match colour {
+            Colour::White => self.white,
+            Colour::Black => self.black,
+        };
+        match self.running_since {
+            Some(since) if colour == to_move => left.saturating_sub(now - since),
+            _ => left,
+        }
+    }
+
+    // Stops the mover's clock and starts the other side's.
+    fn punch(&mut self, mover: Colour, now: Instant) {
+        let left = self.remaining(mover, mover, now);
+        match mover {
+            Colour::White => self.white = left,
+            Colour::Black => self.black = left,
+        }
+        self.running_since = Some(now);
+    }
+
+    fn to_json(&self, to_move: Colour, now: Instant) -> Value {
+        json!({
+            "white_ms": self.remaining(Colour::White, to_move, now).as_millis() as u64,
+            "black_ms": self.remaining(Colour::Black, to_move, now).as_millis() as u64,
+            "running": self.running_since.map(|_| colour_name(to_move)),
+        })
+    }
+}
+
+impl Lobby {
+    pub(crate) fn new(time: Duration) -> Lobby {
+        Lobby { time, rooms: HashMap::new(), members: HashMap::new() }
+    }
+
+    // > handle one message from a client, returning the messages to send and who to send them to
+    pub(crate) fn handle(&mut self, client: ClientId, text: &str, now: Instant) -> Vec<(ClientId, String)> {
+        let message: Value = serde_json::from_str(text).unwrap_or(Value::Null);
+        let kind = message["type"].as_str().unwrap_or("");
+        if kind == "join" {
+            return self.join(client, &message, now);
+        }
+        let Some(member) = self.members.get(&client) else {
+            return error(client, "Join a game first.");
+        };
+        let room = self.rooms.get_mut(&member.room).expect("members belong to a room");
+        let to_move = room.game.state().side_to_move();
+        match kind {
+            "move" => {
+                if member.seat != Some(to_move) {
+                    return error(client, "It is not your turn.");
+                }
+                // A flag that has fallen but not yet been seen by tick ends the game now.
+                if room.clock.running_since.is_some() && room.clock.remaining(to_move, to_move, now).is_zero() {
+                    flag(room, to_move);
+                    return room.broadcast(state_message(room, now));
+                }
+                let text = message["move"].as_str().unwrap_or("");
+                let state = room.game.state();
+                let played = Move::from_uci(text)
+                    .or_else(|_| state.parse_san(text))
+                    .and_then(|mv| room.game.play(mv));
+                if let Err(err) = played {
+                    return error(client, &err.to_string());
+                }
+                room.clock.punch(to_move, now);
+                if room.game.state().result() != GameResult::Ongoing {
+                    room.clock.running_since = None;
+                }
+                room.broadcast(state_message(room, now))
+            }
+            "resign" => match member.seat.map(|colour| room.game.resign(colour)) {
+                Some(Ok(())) => {
+                    room.clock.running_since = None;
+                    room.broadcast(state_message(room, now))
+                }
+                Some(Err(err)) => error(client, &err.to_string()),
+                None => error(client, "Only players can resign."),
+            },
+            "chat" => {
+                let text = message["text"].as_str().unwrap_or("");
+                room.broadcast(json!({"type": "chat", "from": member.name, "text": text}))
+            }
+            "clock" => {
+                let clock = json!({"type": "clock", "clock": room.clock.to_json(to_move, now)});
+                vec![(client, clock.to_string())]
+            }
+            _ => error(client, "Unknown message type."),
+        }
+    }
+
+    fn join(&mut self, client: ClientId, message: &Value, now: Instant) -> Vec<(ClientId, String)> {
+        if self.members.contains_key(&client) {
+            return error(client, "You have already joined a game.");
+        }
+        let Some(id) = message["game"].as_str().filter(|id| !id.is_empty()) else {
+            return error(client, "Say which game to join.");
+        };
+        let name = message["name"].as_str().unwrap_or("Anonymous").to_string();
+        let time = self.time;
+        let room = self.rooms.entry(id.to_string()).or_insert_with(|| Room {
+            game: Game::from_position(GameState::new()),
+            clients: Vec::new(),
+            white: None,
+            black: None,
+            clock: Clock { white: time, black: time, running_since: None },
+        });
+        let seat = if room.white.is_none() {
+            room.white = Some(client);
+            Some(Colour::White)
+        } else if room.black.is_none() {
+            room.black = Some(client);
+            Some(Colour::Black)
+        } else {
+            None
+        };
+
+        let colour = seat.map(colour_name);
+        let mut outgoing = room.broadcast(json!({"type": "joined", "name": name, "colour": colour}));
+        room.clients.push(client);
+        outgoing.push((client, json!({"type": "welcome", "game": id, "colour": colour}).to_string()));
+        outgoing.push((client, state_message(room, now).to_string()));
+        self.members.insert(client, Member { room: id.to_string(), name, seat });
+        outgoing
+    }
+
+    // > take a client out of its game when it disconnects, freeing its seat
+    pub(crate) fn leave(&mut self, client: ClientId) -> Vec<(ClientId, String)> {
+        let Some(member) = self.members.remove(&client) else {
+            return Vec::new();
+        };
+        let room = self.rooms.get_mut(&member.room).expect("members belong to a room");
+        room.clients.retain(|&other| other != client);
+        match member.seat {
+            Some(Colour::White) => room.white = None,
+            Some(Colour::Black) => room.black = None,
+            None => {}
+        }
+        if room.clients.is_empty() {
+            self.rooms.remove(&member.room);
+            return Vec::new();
+        }
+        room.broadcast(json!({"type": "left", "name": member.name, "colour": member.seat.map(colour_name)}))
+    }
+
+    // > end any game whose running clock has reached zero
+    pub(crate) fn tick(&mut self, now: Instant) -> Vec<(ClientId, String)> {
+        let mut outgoing = Vec::new();
+        for room in self.rooms.values_mut() {
+            let to_move = room.game.state().side_to_move();
+            if room.clock.running_since.is_some() && room.clock.remaining(to_move, to_move, now).is_zero() {
+                flag(room, to_move);
+                outgoing.extend(room.broadcast(state_message(room, now)));
+            }
+        }
+        outgoing
+    }
+}
+
+impl Room {
+    fn broadcast(&self, message: Value) -> Vec<(ClientId, String)> {
+        let text = message.to_string();
+        self.clients.iter().map(|&client| (client, text.clone())).collect()
+    }
+}
+
+// > stop the clocks and give the game to the side whose time did not run out
+fn flag(room: &mut Room, colour: Colour) {
+    room.clock.running_since = None;
+    match colour {
+        Colour::White => room.clock.white = Duration::ZERO,
+        Colour::Black => room.clock.black = Duration::ZERO,
+    }
+    room.game.set_result(match colour {
+        Colour::White => GameResult::BlackWins,
+        Colour::Black => GameResult::WhiteWins,
+    });
+}
+
+fn state_message(room: &Room, now: Instant) -> Value {
+    let game: Value = serde_json::from_str(&json::state_json(&room.game)).expect("state_json writes valid JSON");
+    let to_move = room.game.state().side_to_move();
+    json!({"type": "state", "game": game, "clock": room.clock.to_json(to_move, now)})
+}
+
+fn error(client: ClientId, message: &str) -> Vec<(ClientId, String)> {
+    vec![(client, json!({"type": "error", "message": message}).to_string())]
+}
+
+fn colour_name(colour: Colour) -> &'static str {
+    match colour {
+        Colour::White => "white",
+        Colour::Black => "black",
+    }
+}
+
+type Clients = Arc<Mutex<HashMap<ClientId, UnboundedSender<String>>>>;
+
+// > accept WebSocket connections and pass their messages through the lobby until the process ends
+pub(crate) async fn serve(address: &str, time: Duration) -> std::io::Result<()> {
+    let listener = TcpListener::bind(address).await?;
+    println!("Serving games on ws://{}", listener.local_addr()?);
+    let lobby = Arc::new(Mutex::new(Lobby::new(time)));
+    let clients: Clients = Arc::default();
+
+    let (ticking_lobby, ticking_clients) = (lobby.clone(), clients.clone());
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_millis(100));
+        loop {
+            interval.tick().await;
+            let outgoing = ticking_lobby.lock().expect("lobby lock").tick(Instant::now());
+            deliver(&ticking_clients, outgoing);
+        }
+    });
+
+    let mut next_id = 0;
+    loop {
+        let (stream, _) = listener.accept().await?;
+        next_id += 1;
+        tokio::spawn(connection(stream, next_id, lobby.clone(), clients.clone()));
+    }
+}
+
+// > relay one client's messages to the lobby and the lobby's replies back to it
+async fn connection(stream: TcpStream, client: ClientId, lobby: Arc<Mutex<Lobby>>, clients: Clients) {
+    let Ok(socket) = tokio_tungstenite::accept_async(stream).await else {
+        return;
+    };
+    let (mut sink, mut incoming) = socket.split();
+    let (sender, mut receiver) = unbounded_channel::<String>();
+    clients.lock().expect("clients lock").insert(client, sender);
+    tokio::spawn(async move {
+        while let Some(text) = receiver.recv().await {
+            if sink.send(Message::text(text)).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    while let Some(Ok(message)) = incoming.next().await {
+        match message {
+            Message::Text(text) => {
+                let outgoing = lobby.lock().expect("lobby lock").handle(client, &text, Instant::now());
+                deliver(&clients, outgoing);
+            }
+            Message::Close(_) => break,
+            _ => {}
+        }
+    }
+    clients.lock().expect("clients lock").remove(&client);
+    let outgoing = lobby.lock().expect("lobby lock").leave(client);
+    deliver(&clients, outgoing);
+}
+
+fn deliver(clients: &Clients, outgoing: Vec<(ClientId, String)>) {
+    let clients = clients.lock().expect("clients lock");
+    for (client, text) in outgoing {
+        if let Some(sender) = clients.get(&client) {
+            // A client that has gone away is cleaned up by its own connection task.
+            let _ = sender.send(text);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(outgoing: &[(ClientId, String)]) -> Vec<(ClientId, Value)> {
+        outgoing.iter().map(|(client, text)| (*client, serde_json::from_str(text).unwrap())).collect()
+    }
+
+    fn join(lobby: &mut Lobby, client: ClientId, name: &str, now: Instant) -> Vec<(ClientId, Value)> {
+        parse(&lobby.handle(client, &json!({"type": "join", "game": "g1", "name": name}).to_string(), now))
+    }
+
+    #[test]
+    fn test_join_and_play() {
+        let now = Instant::now();
+        let mut lobby = Lobby::new(Duration::from_secs(300));
+        let welcome = join(&mut lobby, 1, "Anna", now);
+        assert_eq!(welcome[0], (1, json!({"type": "welcome", "game": "g1", "colour": "white"})));
+        assert_eq!(welcome[1].1["type"], "state");
+        let joined = join(&mut lobby, 2, "Ben", now);
+        assert_eq!(joined[0], (1, json!({"type": "joined", "name": "Ben", "colour": "black"})));
+        assert_eq!(joined[1].1["colour"], "black");
+        assert_eq!(join(&mut lobby, 3, "Cleo", now)[2].1["colour"], Value::Null);
+
+        // Only the side to move may move, and only legally.
+        let reply = parse(&lobby.handle(2, r#"{"type": "move", "move": "e7e5"}"#, now));
+        assert_eq!(reply, vec![(2, json!({"type": "error", "message": "It is not your turn."}))]);
+        let reply = parse(&lobby.handle(1, r#"{"type": "move", "move": "e2e5"}"#, now));
+        assert_eq!(reply[0].1["type"], "error");
+        let reply = parse(&lobby.handle(1, r#"{"type": "move", "move": "e4"}"#, now));
+        assert_eq!(reply.len(), 3);
+        assert_eq!(reply[2].1["game"]["last_move"]["uci"], "e2e4");
+        assert_eq!(reply[2].1["clock"]["running"], "black");
+
+        let later = now + Duration::from_secs(10);
+        let reply = parse(&lobby.handle(2, r#"{"type": "move", "move": "e7e5"}"#, later));
+        assert_eq!(reply[0].1["clock"]["black_ms"], 290_000);
+        assert_eq!(reply[0].1["clock"]["white_ms"], 300_000);
+
+        let reply = parse(&lobby.handle(3, r#"{"type": "chat", "text": "Nice"}"#, later));
+        assert_eq!(reply[0].1, json!({"type": "chat", "from": "Cleo", "text": "Nice"}));
+        let reply = parse(&lobby.handle(3, r#"{"type": "resign"}"#, later));
+        assert_eq!(reply[0].1["message"], "Only players can resign.");
+        let reply = parse(&lobby.handle(2, r#"{"type": "resign"}"#, later));
+        assert_eq!(reply[0].1["game"]["result"], "1-0");
+    }
+
+    #[test]
+    fn test_flag_and_leave() {
+        let now = Instant::now();
+        let mut lobby = Lobby::new(Duration::from_secs(60));
+        join(&mut lobby, 1, "Anna", now);
+        join(&mut lobby, 2, "Ben", now);
+        assert!(lobby.tick(now + Duration::from_secs(600)).is_empty());
+        lobby.handle(1, r#"{"type": "move", "move": "d4"}"#, now);
+        assert!(lobby.tick(now + Duration::from_secs(59)).is_empty());
+        let reply = parse(&lobby.tick(now + Duration::from_secs(60)));
+        assert_eq!(reply.len(), 2);
+        assert_eq!(reply[0].1["game"]["result"], "1-0");
+        assert_eq!(reply[0].1["clock"]["black_ms"], 0);
+        assert!(lobby.tick(now + Duration::from_secs(61)).is_empty());
+
+        let reply = parse(&lobby.leave(2));
+        assert_eq!(reply, vec![(1, json!({"type": "left", "name": "Ben", "colour": "black"}))]);
+        assert_eq!(join(&mut lobby, 3, "Cleo", now)[1].1["colour"], "black");
+        assert!(lobby.leave(1).len() == 1 && lobby.leave(3).is_empty());
+        assert!(lobby.rooms.is_empty());
+    }
+
+    #[test]
+    fn test_bad_messages() {
+        let now = Instant::now();
+        let mut lobby = Lobby::new(Duration::from_secs(60));
+        assert_eq!(parse(&lobby.handle(1, "hello", now))[0].1["message"], "Join a game first.");
+        assert_eq!(parse(&lobby.handle(1, r#"{"type": "join"}"#, now))[0].1["message"], "Say which game to join.");
+        join(&mut lobby, 1, "Anna", now);
+        assert_eq!(parse(&lobby.handle(1, r#"{"type": "dance"}"#, now))[0].1["message"], "Unknown message type.");
+        assert_eq!(join(&mut lobby, 1, "Anna", now)[0].1["message"], "You have already joined a game.");
+    }
+}
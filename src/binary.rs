@@ -0,0 +1,356 @@
+//! # Binary Save Format
+//!
+//! A compact encoding of a whole game, small enough to put in a URL or a network
+//! message. All numbers are big-endian:
+//!
+//! | Bytes | Contents                                                          |
+//! |-------|-------------------------------------------------------------------|
+//! | 2     | magic `CG`                                                        |
+//! | 1     | format version                                                    |
+//! | 1     | 1 if a starting position follows, 0 for the initial position      |
+//! | 40    | starting position, if present: 32 bytes of board, 2 per byte, then |
+//! |       | side to move, castling rook files for White and for Black (kingside|
+//! |       | and queenside file in 4 bits each, 15 for none), en passant square,|
+//! |       | half-move clock and full-move number (two bytes each)              |
+//! | 1 + n | tag count, then each name and value as a length and UTF-8 bytes   |
+//! | 2 + 2n| move count, then each move as from, to and promotion in 16 bits    |
+//! | 2     | result and any outstanding draw offer                              |
+//!
+//! A game that does not fit these fields, such as one with more than 255 tags or a
+//! tag longer than 255 bytes, is refused with [`BinaryError::DoesNotFit`] rather than
+//! cut short, so every saved game loads back exactly as it was.
+//!
+//! Loading replays the moves through the rules engine. Comments, NAGs and
+//! variations are not stored; use PGN for annotated games.
+
+use crate::error::BinaryError;
+use crate::game::Game;
+use crate::{CastlingRights, ChessPiece, Colour, DrawReason, GameResult, GameState, Move, Position, Square};
+
+const MAGIC: &[u8; 2] = b"CG";
+const VERSION: u8 = 1;
+const NO_ROOK: u8 = 15;
+
+impl Game {
+    // > save the game in the compact binary format
+    pub fn save_binary(&self) -> Result<Vec<u8>, BinaryError> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+
+        let start = self.start();
+        if *start == GameState::new() {
+            bytes.push(0);
+        } else {
+            bytes.push(1);
+            for row in 0..8 {
+                for pair in start.board[row].chunks(2) {
+                    bytes.push(piece_code(pair[0]) << 4 | piece_code(pair[1]));
+                }
+            }
+            bytes.push(u8::from(start.current_player == Colour::Black));
+            for colour in [Colour::White, Colour::Black] {
+                let file = |kingside| start.castling.rook_col(colour, kingside).map_or(NO_ROOK, |col| col as u8);
+                bytes.push(file(true) << 4 | file(false));
+            }
+            bytes.push(start.en_passant.map_or(u8::MAX, |square| square as u8));
+            bytes.extend(fit::<u16>(start.halfmove_clock(), "half-move clock")?.to_be_bytes());
+            bytes.extend(fit::<u16>(start.fullmove_number(), "full-move number")?.to_be_bytes());
+        }
+
+        bytes.push(fit(self.tags().len(), "number of tags")?);
+        for (name, value) in self.tags() {
+            push_text(&mut bytes, name)?;
+            push_text(&mut bytes, value)?;
+        }
+
+        let moves = self.moves();
+        bytes.extend(fit::<u16>(moves.len(), "number of moves")?.to_be_bytes());
+        for mv in moves {
+            let promotion = match mv.promotion {
+                Some(ChessPiece::Knight(_)) => 1,
+                Some(ChessPiece::Bishop(_)) => 2,
+                Some(ChessPiece::Rook(_)) => 3,
+                Some(ChessPiece::Queen(_)) => 4,
+                _ => 0,
+            };
+            let encoded = (mv.from as u16) << 9 | (mv.to as u16) << 3 | promotion;
+            bytes.extend(encoded.to_be_bytes());
+        }
+
+        let state = self.state();
+        bytes.push(result_code(state.result));
+        bytes.push(match state.draw_offer {
+            None => 0,
+            Some(Colour::White) => 1,
+            Some(Colour::Black) => 2,
+        });
+        Ok(bytes)
+    }
+
+    // > load a game saved with save_binary, replaying its moves through the rules engine
+    pub fn load_binary(bytes: &[u8]) -> Result<Game, BinaryError> {
+        let mut reader = Reader { bytes, pos: 0 };
+        if reader.take(2)? != MAGIC {
+            return Err(BinaryError::NotASavedGame);
+        }
+        let version = reader.byte()?;
+        if version != VERSION {
+            return Err(BinaryError::UnsupportedVersion(version));
+        }
+
+        let start = match reader.byte()? {
+            0 => GameState::new(),
+            1 => {
+                let mut board = [[ChessPiece::Blank; 8]; 8];
+                for row in board.iter_mut() {
+                    for pair in row.chunks_mut(2) {
+                        let byte = reader.byte()?;
+                        pair[0] = piece_from_code(byte >> 4)?;
+                        pair[1] = piece_from_code(byte & 15)?;
+                    }
+                }
+                let flags = reader.byte()?;
+                let mut castling = CastlingRights::NONE;
+                for colour in [Colour::White, Colour::Black] {
+                    let files = reader.byte()?;
+                    for (kingside, file) in [(true, files >> 4), (false, files & 15)] {
+                        match file {
+                            NO_ROOK => {}
+                            0..8 => castling.set(colour, kingside, Some(usize::from(file))),
+                            _ => return Err(BinaryError::InvalidData),
+                        }
+                    }
+                }
+                let en_passant = match reader.byte()? {
+                    u8::MAX => None,
+                    index if index < 64 => Some(Square::from_row_col(usize::from(index % 8), usize::from(index / 8))),
+                    _ => return Err(BinaryError::InvalidData),
+                };
+                let position = Position {
+                    board,
+                    current_player: if flags & 1 == 0 { Colour::White } else { Colour::Black },
+                    castling,
+                    en_passant,
+                    halfmove_clock: u32::from(reader.u16()?),
+                    fullmove_number: u32::from(reader.u16()?),
+                    ..Position::new()
+                };
+                // Going through FEN checks the position just as a typed-in one would be.
+                GameState::from_fen(&position.to_fen()).map_err(BinaryError::InvalidPosition)?
+            }
+            _ => return Err(BinaryError::InvalidData),
+        };
+        let mut game = Game::from_position(start);
+
+        let tag_count = reader.byte()?;
+        let mut tags = Vec::new();
+        for _ in 0..tag_count {
+            tags.push((reader.text()?, reader.text()?));
+        }
+        game.set_tags(tags);
+
+        let move_count = reader.u16()?;
+        for ply in 0..move_count {
+            let encoded = reader.u16()?;
+            let square = |index: u16| Square::from_row_col(usize::from(index % 8), usize::from(index / 8));
+            let mut mv = Move::new(square(encoded >> 9 & 63), square(encoded >> 3 & 63));
+            let colour = game.state().current_player;
+            match encoded & 7 {
+                0 => {}
+                1 => mv = mv.with_promotion(ChessPiece::Knight(colour)),
+                2 => mv = mv.with_promotion(ChessPiece::Bishop(colour)),
+                3 => mv = mv.with_promotion(ChessPiece::Rook(colour)),
+                4 => mv = mv.with_promotion(ChessPiece::Queen(colour)),
+                _ => return Err(BinaryError::InvalidData),
+            }
+            game.play(mv).map_err(|err| BinaryError::IllegalMove(usize::from(ply) + 1, err))?;
+        }
+
+        let result = result_from_code(reader.byte()?)?;
+        game.set_result(result);
+        match reader.byte()? {
+            0 => {}
+            1 => game.offer_draw(Colour::White).map_err(|_| BinaryError::InvalidData)?,
+            2 => game.offer_draw(Colour::Black).map_err(|_| BinaryError::InvalidData)?,
+            _ => return Err(BinaryError::InvalidData),
+        }
+        if reader.pos != bytes.len() {
+            return Err(BinaryError::InvalidData);
+        }
+        Ok(game)
+    }
+}
+
+// > read bytes in order, failing cleanly when the data runs out
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, count: usize) -> Result<&[u8], BinaryError> {
+        let end = self.pos + count;
+        let slice = self.bytes.get(self.pos..end).ok_or(BinaryError::Truncated)?;
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn byte(&mut self) -> Result<u8, BinaryError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, BinaryError> {
+        Ok(u16::from_be_bytes([self.byte()?, self.byte()?]))
+    }
+
+    fn text(&mut self) -> Result<String, BinaryError> {
+        let len = usize::from(self.byte()?);
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| BinaryError::InvalidData)
+    }
+}
+
+// > write a string as a length byte and up to 255 bytes of UTF-8
+fn push_text(bytes: &mut Vec<u8>, text: &str) -> Result<(), BinaryError> {
+    bytes.push(fit(text.len(), "tag text")?);
+    bytes.extend(text.as_bytes());
+    Ok(())
+}
+
+// > narrow a number to the width of its field, naming the field when it is too big
+fn fit<T>(value: impl TryInto<T>, field: &'static str) -> Result<T, BinaryError> {
+    value.try_into().map_err(|_| BinaryError::DoesNotFit(field))
+}
+
+// > give the 4-bit code of a piece: 0 for empty, 1-6 for White's pawn to king and 9-14 for Black's
+fn piece_code(piece: ChessPiece) -> u8 {
+    let (kind, colour) = match piece {
+        ChessPiece::Pawn(colour) => (1, colour),
+        ChessPiece::Knight(colour) => (2, colour),
+        ChessPiece::Bishop(colour) => (3, colour),
+        ChessPiece::Rook(colour) => (4, colour),
+        ChessPiece::Queen(colour) => (5, colour),
+        ChessPiece::King(colour) => (6, colour),
+        ChessPiece::Blank => return 0,
+    };
+    match colour {
+        Colour::White => kind,
+        Colour::Black => kind | 8,
+    }
+}
+
+fn piece_from_code(code: u8) -> Result<ChessPiece, BinaryError> {
+    let colour = if code & 8 == 0 { Colour::White } else { Colour::Black };
+    match code & 7 {
+        0 if code == 0 => Ok(ChessPiece::Blank),
+        1 => Ok(ChessPiece::Pawn(colour)),
+        2 => Ok(ChessPiece::Knight(colour)),
+        3 => Ok(ChessPiece::Bishop(colour)),
+        4 => Ok(ChessPiece::Rook(colour)),
+        5 => Ok(ChessPiece::Queen(colour)),
+        6 => Ok(ChessPiece::King(colour)),
+        _ => Err(BinaryError::InvalidData),
+    }
+}
+
+const DRAW_REASONS: [DrawReason; 7] = [
+    DrawReason::Stalemate,
+    DrawReason::InsufficientMaterial,
+    DrawReason::ThreefoldRepetition,
+    DrawReason::Agreement,
+    DrawReason::FiftyMoveRule,
+    DrawReason::FivefoldRepetition,
+    DrawReason::SeventyFiveMoveRule,
+];
+
+// > give the result code: 0 in progress, 1 White wins, 2 Black wins, 3 and up for each kind of draw
+fn result_code(result: GameResult) -> u8 {
+    match result {
+        GameResult::Ongoing => 0,
+        GameResult::WhiteWins => 1,
+        GameResult::BlackWins => 2,
+        GameResult::Draw(reason) => 3 + DRAW_REASONS.iter().position(|&r| r == reason).expect("every reason is listed") as u8,
+    }
+}
+
+fn result_from_code(code: u8) -> Result<GameResult, BinaryError> {
+    match code {
+        0 => Ok(GameResult::Ongoing),
+        1 => Ok(GameResult::WhiteWins),
+        2 => Ok(GameResult::BlackWins),
+        _ => DRAW_REASONS
+            .get(usize::from(code) - 3)
+            .map(|&reason| GameResult::Draw(reason))
+            .ok_or(BinaryError::InvalidData),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut game = Game::from_pgn("[White \"Anna\"]\n[Black \"Ben\"]\n1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. Ba4 Nf6 5. O-O *").unwrap();
+        game.offer_draw(Colour::Black).unwrap();
+        let bytes = game.save_binary().unwrap();
+        assert!(bytes.len() < 60, "{} bytes", bytes.len());
+        assert_eq!(Game::load_binary(&bytes).unwrap(), game);
+    }
+
+    #[test]
+    fn test_round_trip_from_position_with_result() {
+        let start = GameState::from_fen("4k3/P7/8/8/8/8/8/4K2R w K - 3 40").unwrap();
+        let mut game = Game::from_position(start);
+        game.play(Move::new(Square::A7, Square::A8).with_promotion(ChessPiece::Knight(Colour::White)))
+            .unwrap();
+        game.resign(Colour::Black).unwrap();
+        let loaded = Game::load_binary(&game.save_binary().unwrap()).unwrap();
+        assert_eq!(loaded, game);
+        assert_eq!(loaded.state().result, GameResult::WhiteWins);
+        assert_eq!(loaded.state().board[7][0], ChessPiece::Knight(Colour::White));
+    }
+
+    #[test]
+    fn test_round_trip_inner_castling_rook() {
+        let start = GameState::from_fen("1r2k3/8/8/8/8/8/8/4K1R1 w Gb - 0 1").unwrap();
+        let game = Game::from_position(start);
+        let loaded = Game::load_binary(&game.save_binary().unwrap()).unwrap();
+        assert_eq!(loaded.start().to_fen(), "1r2k3/8/8/8/8/8/8/4K1R1 w Kq - 0 1");
+        assert_eq!(loaded.start().castling.rook_col(Colour::White, true), Some(6));
+    }
+
+    #[test]
+    fn test_save_refuses_what_does_not_fit() {
+        let start = GameState::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 300").unwrap();
+        let game = Game::from_position(start);
+        assert_eq!(Game::load_binary(&game.save_binary().unwrap()).unwrap().start().fullmove_number(), 300);
+        let far = Game::from_position(GameState::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 70000").unwrap());
+        assert_eq!(far.save_binary(), Err(BinaryError::DoesNotFit("full-move number")));
+
+        let mut tagged = Game::from_position(GameState::new());
+        tagged.set_tags(vec![("Annotator".to_string(), "x".repeat(256))]);
+        assert_eq!(tagged.save_binary(), Err(BinaryError::DoesNotFit("tag text")));
+        tagged.set_tags((0..256).map(|i| (format!("Tag{}", i), String::new())).collect());
+        assert_eq!(tagged.save_binary(), Err(BinaryError::DoesNotFit("number of tags")));
+    }
+
+    #[test]
+    fn test_load_errors() {
+        let bytes = Game::from_position(GameState::new()).save_binary().unwrap();
+        assert_eq!(Game::load_binary(b"XX\x01"), Err(BinaryError::NotASavedGame));
+        assert_eq!(Game::load_binary(b"CG\x09"), Err(BinaryError::UnsupportedVersion(9)));
+        assert_eq!(Game::load_binary(b"CG\x00"), Err(BinaryError::UnsupportedVersion(0)));
+        assert_eq!(Game::load_binary(&bytes[..bytes.len() - 1]), Err(BinaryError::Truncated));
+
+        let mut extra = bytes.clone();
+        extra.push(0);
+        assert_eq!(Game::load_binary(&extra), Err(BinaryError::InvalidData));
+
+        // Replace the empty move list with one illegal move, e2-e5.
+        let mut illegal = bytes[..bytes.len() - 4].to_vec();
+        illegal.extend(1u16.to_be_bytes());
+        illegal.extend(((Square::E2 as u16) << 9 | (Square::E5 as u16) << 3).to_be_bytes());
+        illegal.extend([0, 0]);
+        assert!(matches!(Game::load_binary(&illegal), Err(BinaryError::IllegalMove(1, _))));
+    }
+}
@@ -0,0 +1,444 @@
+//! # Bitboards
+//!
+//! A bitboard is a `u64` with one bit per square, bit `row * 8 + col`, so a1 is bit 0,
+//! h1 bit 7 and h8 bit 63. A whole set of squares, such as every square a piece
+//! attacks, can then be tested or combined with one machine instruction.
+//!
+//! The squares a knight, king or pawn attacks never depend on the other pieces, so
+//! they are looked up in tables built at compile time. A bishop, rook or queen stops at
+//! the first piece in each direction, so its attacks depend on which squares in its
+//! path are occupied. Walking the rays square by square is slow; magic bitboards look
+//! the answer up instead. The occupied squares on a slider's rays are multiplied by a
+//! "magic" number chosen for that square, and the top bits of the product index a
+//! table holding the attacks for that arrangement of blockers:
+//!
+//! ```text
+//! attacks = table[offset + ((occupied & mask) * magic) >> shift]
+//! ```
+//!
+//! The magic numbers were found once by trying sparse random numbers until one sent
+//! every arrangement of blockers to a slot with the right attacks, and are written out
+//! below. Every table here, the magic ones included, is filled in by `const fn` at
+//! compile time, so there is nothing to do at startup and no lock to take on lookup;
+//! a magic number that stopped fitting would fail the build.
+//!
+//! [`BETWEEN`] and [`LINE`] give, for two squares on a rank, file or diagonal, the
+//! squares between them and the whole line through them, which is what move generation
+//! needs to tell which moves block a check or keep a pinned piece on its pin.
+//!
+//! [`Bitboards`] stores a whole board this way, one bitboard for each kind of piece of
+//! each colour, as an alternative to the array the rules engine keeps.
+
+use crate::board::{self, BoardRepr};
+use crate::{ChessPiece, Colour, PieceKind, Square};
+
+pub(crate) type Bitboard = u64;
+
+const ROOK_DIRECTIONS: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+const BISHOP_DIRECTIONS: [(i32, i32); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+const KNIGHT_STEPS: [(i32, i32); 8] = [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+const KING_STEPS: [(i32, i32); 8] = [(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)];
+
+pub(crate) static KNIGHT_ATTACKS: [Bitboard; 64] = step_attacks(&KNIGHT_STEPS);
+pub(crate) static KING_ATTACKS: [Bitboard; 64] = step_attacks(&KING_STEPS);
+// The squares a pawn of each colour attacks, White first.
+pub(crate) static PAWN_ATTACKS: [[Bitboard; 64]; 2] = [step_attacks(&[(1, -1), (1, 1)]), step_attacks(&[(-1, -1), (-1, 1)])];
+
+// The squares strictly between two squares on a line, and the whole line through them; empty for squares on no common line.
+pub(crate) static BETWEEN: [[Bitboard; 64]; 64] = lines(false);
+pub(crate) static LINE: [[Bitboard; 64]; 64] = lines(true);
+
+// The magic number for each square from a1 to h8.
+const ROOK_MAGIC_NUMBERS: [u64; 64] = [
+    0x2080_0840_0210_2080, 0x0140_0040_9000_6000, 0x9500_1143_0008_2000, 0x4300_0610_0008_2100,
+    0x0100_1100_0408_0002, 0x2080_0400_0180_0200, 0x2100_0600_4084_2b00, 0x0900_0142_3200_8500,
+    0x1102_0020_8100_4200, 0x4302_0020_4a08_8100, 0x0500_8080_2000_1000, 0x0011_0010_0009_0020,
+    0x0185_0010_0500_0800, 0x9101_0004_0009_0002, 0x0141_0044_4100_0200, 0x0c00_8007_0018_c080,
+    0x4000_2080_0040_0082, 0x0000_2100_4000_8100, 0x0160_8080_1000_2004, 0x0008_0080_0880_1000,
+    0x0440_8280_0401_2800, 0x0000_8080_0200_0400, 0x4003_4400_0210_0801, 0x0000_0600_0100_5494,
+    0x8000_8000_8020_4002, 0x0200_4000_4020_1000, 0x0000_1000_8020_0088, 0x4800_0825_0010_0100,
+    0x0008_0800_8004_0081, 0x0022_0022_0029_1004, 0x0020_0204_0008_0110, 0x8d01_0042_0001_0084,
+    0x6080_4000_8080_0026, 0x2040_0800_2020_1000, 0x0200_2000_8280_1002, 0x3100_1000_8480_0802,
+    0x0400_0800_8080_0400, 0x0480_8002_0080_0400, 0x1000_1102_3400_1008, 0x0010_8000_5880_0900,
+    0x0380_0020_0040_4000, 0x0210_4000_8111_0020, 0x0020_0402_0010_1001, 0x0110_1000_0800_8080,
+    0x0008_0040_2004_0400, 0x0120_0200_0400_8080, 0x0000_0130_0844_0012, 0x1448_1050_8102_0024,
+    0x8100_4000_8000_2080, 0x00c0_0020_0040_8080, 0x0223_6000_8030_0880, 0x0c10_0080_0800_1080,
+    0x5104_0080_0800_0480, 0x0012_0004_1008_0200, 0x4422_0008_0401_0200, 0x0a00_8000_4900_2080,
+    0x0404_2880_0011_0441, 0x0000_1020_8200_4102, 0x0800_4020_0100_0811, 0x0082_2108_8410_0101,
+    0x2002_0084_2090_0802, 0x0005_0004_0008_0201, 0x1042_0001_0408_0082, 0x4402_0080_4024_1102,
+];
+const BISHOP_MAGIC_NUMBERS: [u64; 64] = [
+    0x0502_4204_0802_0044, 0x0026_1003_2200_9208, 0x8a10_4420_9020_0080, 0x0808_2080_2a08_2010,
+    0x0824_5040_0500_2024, 0x3000_8211_4001_1000, 0x8029_0808_0269_2030, 0x00c0_444c_1008_0210,
+    0x0000_4208_0144_0084, 0x0008_4410_20c9_0700, 0x0809_9001_0218_2896, 0x8328_880a_0428_0020,
+    0x0000_0110_4004_0240, 0x0000_0104_1a42_2008, 0x00a0_0988_3802_1181, 0x0002_0300_4812_0800,
+    0x8240_0006_0808_1110, 0x0002_0860_4204_2101, 0x0102_0101_0204_0500, 0xc008_0004_2200_2020,
+    0x4104_1102_0202_0000, 0x0009_0008_0888_0400, 0x4200_4081_0410_0400, 0x4000_24c1_9203_1003,
+    0x1202_9002_4104_4804, 0x0002_0800_2021_0418, 0x0200_8800_1080_2400, 0x0010_0400_2044_0008,
+    0x0410_8402_2080_2002, 0x2048_0020_4202_0120, 0x0001_0040_1208_0402, 0x0000_8200_0a21_0420,
+    0x1204_020a_8020_2008, 0x4001_1008_29d0_0102, 0x0006_4250_0838_0020, 0x0007_8404_0018_0210,
+    0x0040_0202_0200_2080, 0x0020_0402_404a_8810, 0x0488_0801_2000_4100, 0x10c2_2400_4000_2201,
+    0x1008_1809_0444_0800, 0x4004_1404_0410_0211, 0x2000_8a08_0406_1200, 0x8200_0020_1800_8109,
+    0x8a00_4801_0440_1400, 0x0a21_020c_0810_0100, 0x010a_2404_142a_8182, 0x0048_2208_4204_2044,
+    0x0010_4804_0420_0400, 0x000c_8188_9090_1000, 0x8010_0044_0404_04c3, 0x0a01_0048_8404_0000,
+    0x0081_0044_2182_0040, 0x5000_0410_0448_4018, 0x2391_1011_4104_0800, 0x2182_1002_1200_4120,
+    0x1000_4208_0c01_0460, 0x0008_9024_0124_1040, 0x0040_a040_8048_0800, 0x0000_0021_0020_8800,
+    0x0a00_4000_0445_0400, 0x1080_2040_0404_1420, 0x4002_4288_0204_0041, 0x2002_0448_0801_0020,
+];
+
+const ROOK_TABLE_SIZE: usize = table_size(&ROOK_DIRECTIONS);
+const BISHOP_TABLE_SIZE: usize = table_size(&BISHOP_DIRECTIONS);
+// Filling in the rook table's hundred thousand slots is slow enough for the compiler to ask.
+#[allow(long_running_const_eval)]
+static ROOK_MAGICS: Magics<ROOK_TABLE_SIZE> = Magics::new(&ROOK_DIRECTIONS, &ROOK_MAGIC_NUMBERS);
+static BISHOP_MAGICS: Magics<BISHOP_TABLE_SIZE> = Magics::new(&BISHOP_DIRECTIONS, &BISHOP_MAGIC_NUMBERS);
+
+pub(crate) const fn bit(row: usize, col: usize) -> Bitboard {
+    1 << (row * 8 + col)
+}
+
+// > build the table of squares attacked from each square by a piece that takes single steps
+const fn step_attacks(steps: &[(i32, i32)]) -> [Bitboard; 64] {
+    let mut attacks = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        let (row, col) = ((square / 8) as i32, (square % 8) as i32);
+        let mut i = 0;
+        while i < steps.len() {
+            let (to_row, to_col) = (row + steps[i].0, col + steps[i].1);
+            if to_row >= 0 && to_row < 8 && to_col >= 0 && to_col < 8 {
+                attacks[square] |= bit(to_row as usize, to_col as usize);
+            }
+            i += 1;
+        }
+        square += 1;
+    }
+    attacks
+}
+
+// > build, for each pair of squares on a rank, file or diagonal, the squares strictly between them,
+// > or with whole set the entire line through them from edge to edge
+const fn lines(whole: bool) -> [[Bitboard; 64]; 64] {
+    let mut lines = [[0; 64]; 64];
+    let mut from = 0;
+    while from < 64 {
+        let (row, col) = ((from / 8) as i32, (from % 8) as i32);
+        let mut i = 0;
+        while i < KING_STEPS.len() {
+            let (d_row, d_col) = KING_STEPS[i];
+            let line = ray(row, col, d_row, d_col) | ray(row, col, -d_row, -d_col) | bit(row as usize, col as usize);
+            let (mut r, mut c) = (row + d_row, col + d_col);
+            let mut between = 0;
+            while r >= 0 && r < 8 && c >= 0 && c < 8 {
+                lines[from][(r * 8 + c) as usize] = if whole { line } else { between };
+                between |= bit(r as usize, c as usize);
+                r += d_row;
+                c += d_col;
+            }
+            i += 1;
+        }
+        from += 1;
+    }
+    lines
+}
+
+// > give the squares from one square to the edge of the board in one direction, not counting the square itself
+const fn ray(row: i32, col: i32, d_row: i32, d_col: i32) -> Bitboard {
+    let mut squares = 0;
+    let (mut r, mut c) = (row + d_row, col + d_col);
+    while r >= 0 && r < 8 && c >= 0 && c < 8 {
+        squares |= bit(r as usize, c as usize);
+        r += d_row;
+        c += d_col;
+    }
+    squares
+}
+
+// > give the squares attacked by a pawn of the given colour
+pub(crate) fn pawn_attacks(colour: Colour, square: usize) -> Bitboard {
+    PAWN_ATTACKS[colour as usize][square]
+}
+
+// > give the squares a rook on the square attacks, stopping at the first piece each way
+pub(crate) fn rook_attacks(square: usize, occupied: Bitboard) -> Bitboard {
+    ROOK_MAGICS.attacks(square, occupied)
+}
+
+// > give the squares a bishop on the square attacks, stopping at the first piece each way
+pub(crate) fn bishop_attacks(square: usize, occupied: Bitboard) -> Bitboard {
+    BISHOP_MAGICS.attacks(square, occupied)
+}
+
+// > add the bitboards of a board: every occupied square, and the squares of each kind of piece of one colour
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct Pieces {
+    pub(crate) occupied: Bitboard,
+    pub(crate) pawns: Bitboard,
+    pub(crate) knights: Bitboard,
+    // Bishops and queens, which both move diagonally.
+    pub(crate) diagonal: Bitboard,
+    // Rooks and queens, which both move along ranks and files.
+    pub(crate) straight: Bitboard,
+    pub(crate) kings: Bitboard,
+}
+
+impl Pieces {
+    // > gather the bitboards of the given colour's pieces, and of every occupied square, from a board
+    pub(crate) fn new(board: &[[ChessPiece; 8]; 8], colour: Colour) -> Pieces {
+        let mut pieces = Pieces::default();
+        for (row, rank) in board.iter().enumerate() {
+            for (col, &piece) in rank.iter().enumerate() {
+                if piece == ChessPiece::Blank {
+                    continue;
+                }
+                let square = bit(row, col);
+                pieces.occupied |= square;
+                if piece.colour() != Some(colour) {
+                    continue;
+                }
+                match piece {
+                    ChessPiece::Pawn(_) => pieces.pawns |= square,
+                    ChessPiece::Knight(_) => pieces.knights |= square,
+                    ChessPiece::Bishop(_) => pieces.diagonal |= square,
+                    ChessPiece::Rook(_) => pieces.straight |= square,
+                    ChessPiece::Queen(_) => (pieces.diagonal, pieces.straight) = (pieces.diagonal | square, pieces.straight | square),
+                    ChessPiece::King(_) => pieces.kings |= square,
+                    ChessPiece::Blank => {}
+                }
+            }
+        }
+        pieces
+    }
+
+    // > give every square holding one of the colour's own pieces
+    pub(crate) fn own(&self) -> Bitboard {
+        self.pawns | self.knights | self.diagonal | self.straight | self.kings
+    }
+
+    // > give the pieces attacking the square, looking from it with each kind of piece in turn
+    // A pawn of ours attacks the square if a pawn of the other colour on the square would attack it.
+    pub(crate) fn attackers(&self, square: usize, colour: Colour) -> Bitboard {
+        (KNIGHT_ATTACKS[square] & self.knights)
+            | (KING_ATTACKS[square] & self.kings)
+            | (pawn_attacks(colour.opponent(), square) & self.pawns)
+            | (bishop_attacks(square, self.occupied) & self.diagonal)
+            | (rook_attacks(square, self.occupied) & self.straight)
+    }
+}
+
+// > add a board kept as one bitboard for each kind of piece of each colour
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Bitboards {
+    // White's pawns to king, then Black's, in the order of PieceKind.
+    pieces: [Bitboard; 12],
+}
+
+impl Bitboards {
+    // > copy a board from any other representation
+    pub fn from_board(board: &impl BoardRepr) -> Bitboards {
+        let mut bitboards = Bitboards::default();
+        for square in Square::ALL {
+            bitboards.set_piece(square, board.piece_at(square));
+        }
+        bitboards
+    }
+
+    fn index(kind: PieceKind, colour: Colour) -> usize {
+        colour as usize * 6 + kind as usize
+    }
+}
+
+impl BoardRepr for Bitboards {
+    fn piece_at(&self, square: Square) -> ChessPiece {
+        let square_bit = board::bit(square);
+        [Colour::White, Colour::Black]
+            .into_iter()
+            .flat_map(|colour| PieceKind::ALL.map(|kind| (kind, colour)))
+            .find(|&(kind, colour)| self.pieces[Bitboards::index(kind, colour)] & square_bit != 0)
+            .map_or(ChessPiece::Blank, |(kind, colour)| ChessPiece::new(kind, colour))
+    }
+
+    fn set_piece(&mut self, square: Square, piece: ChessPiece) {
+        let square_bit = board::bit(square);
+        for bits in &mut self.pieces {
+            *bits &= !square_bit;
+        }
+        if let (Some(kind), Some(colour)) = (piece.kind(), piece.colour()) {
+            self.pieces[Bitboards::index(kind, colour)] |= square_bit;
+        }
+    }
+
+    fn is_occupied(&self, square: Square) -> bool {
+        self.occupied() & board::bit(square) != 0
+    }
+
+    fn occupied(&self) -> Bitboard {
+        self.pieces.iter().fold(0, |occupied, bits| occupied | bits)
+    }
+
+    fn occupied_by(&self, colour: Colour) -> Bitboard {
+        let start = colour as usize * 6;
+        self.pieces[start..start + 6].iter().fold(0, |occupied, bits| occupied | bits)
+    }
+}
+
+// > iterate over the squares of a bitboard, lowest first
+pub(crate) fn squares(mut bits: Bitboard) -> impl Iterator<Item = usize> {
+    core::iter::from_fn(move || {
+        let square = bits.trailing_zeros() as usize;
+        bits &= bits.wrapping_sub(1);
+        (square < 64).then_some(square)
+    })
+}
+
+// > add the magic lookup for one kind of slider: for each square its mask, magic, shift and place in the table
+struct Magics<const N: usize> {
+    masks: [Bitboard; 64],
+    magics: [u64; 64],
+    shifts: [u32; 64],
+    offsets: [usize; 64],
+    table: [Bitboard; N],
+}
+
+impl<const N: usize> Magics<N> {
+    fn attacks(&self, square: usize, occupied: Bitboard) -> Bitboard {
+        let index = ((occupied & self.masks[square]).wrapping_mul(self.magics[square]) >> self.shifts[square]) as usize;
+        self.table[self.offsets[square] + index]
+    }
+
+    // > fill in the table for every square from its magic number, failing if two arrangements of blockers
+    // > that leave different attacks land in the same slot
+    const fn new(directions: &[(i32, i32); 4], magics: &[u64; 64]) -> Magics<N> {
+        let mut lookup = Magics { masks: [0; 64], magics: *magics, shifts: [0; 64], offsets: [0; 64], table: [0; N] };
+        let mut offset = 0;
+        let mut square = 0;
+        while square < 64 {
+            let mask = relevant_squares(square, directions);
+            let shift = 64 - mask.count_ones();
+            (lookup.masks[square], lookup.shifts[square], lookup.offsets[square]) = (mask, shift, offset);
+            // Every subset of the mask, by the carry-rippler trick. A slider always attacks something,
+            // so a slot still holding nothing has not been written.
+            let mut blockers: Bitboard = 0;
+            loop {
+                let attacks = slide(square, blockers, directions);
+                let slot = offset + (blockers.wrapping_mul(magics[square]) >> shift) as usize;
+                assert!(lookup.table[slot] == 0 || lookup.table[slot] == attacks, "a magic number does not fit its square");
+                lookup.table[slot] = attacks;
+                blockers = blockers.wrapping_sub(mask) & mask;
+                if blockers == 0 {
+                    break;
+                }
+            }
+            offset += 1 << (64 - shift);
+            square += 1;
+        }
+        assert!(offset == N, "the table is the wrong size");
+        lookup
+    }
+}
+
+// > add up the slots the table for one kind of slider needs, one for each arrangement of blockers on each square
+const fn table_size(directions: &[(i32, i32); 4]) -> usize {
+    let mut size = 0;
+    let mut square = 0;
+    while square < 64 {
+        size += 1 << relevant_squares(square, directions).count_ones();
+        square += 1;
+    }
+    size
+}
+
+// > give the squares whose occupancy can change a slider's attacks: its rays, less the edge square at the end of each
+const fn relevant_squares(square: usize, directions: &[(i32, i32); 4]) -> Bitboard {
+    let (row, col) = ((square / 8) as i32, (square % 8) as i32);
+    let mut mask = 0;
+    let mut i = 0;
+    while i < directions.len() {
+        let (d_row, d_col) = directions[i];
+        let (mut r, mut c) = (row + d_row, col + d_col);
+        while r + d_row >= 0 && r + d_row < 8 && c + d_col >= 0 && c + d_col < 8 {
+            mask |= bit(r as usize, c as usize);
+            r += d_row;
+            c += d_col;
+        }
+        i += 1;
+    }
+    mask
+}
+
+// > walk each ray from the square to the first occupied square, which is attacked too
+const fn slide(square: usize, occupied: Bitboard, directions: &[(i32, i32); 4]) -> Bitboard {
+    let (row, col) = ((square / 8) as i32, (square % 8) as i32);
+    let mut attacks = 0;
+    let mut i = 0;
+    while i < directions.len() {
+        let (d_row, d_col) = directions[i];
+        let (mut r, mut c) = (row + d_row, col + d_col);
+        while r >= 0 && r < 8 && c >= 0 && c < 8 {
+            attacks |= bit(r as usize, c as usize);
+            if occupied & bit(r as usize, c as usize) != 0 {
+                break;
+            }
+            r += d_row;
+            c += d_col;
+        }
+        i += 1;
+    }
+    attacks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zobrist::split_mix64;
+    use crate::{GameState, Square};
+
+    fn index(square: Square) -> usize {
+        let (row, col) = square.to_row_col();
+        row * 8 + col
+    }
+
+    #[test]
+    fn test_magics_match_walking_the_rays() {
+        let mut random = 42;
+        for square in 0..64 {
+            for _ in 0..20 {
+                let occupied = split_mix64(&mut random) & split_mix64(&mut random);
+                assert_eq!(rook_attacks(square, occupied), slide(square, occupied, &ROOK_DIRECTIONS));
+                assert_eq!(bishop_attacks(square, occupied), slide(square, occupied, &BISHOP_DIRECTIONS));
+            }
+        }
+    }
+
+    #[test]
+    fn test_step_attacks() {
+        assert_eq!(KNIGHT_ATTACKS[index(Square::A1)], bit(1, 2) | bit(2, 1));
+        assert_eq!(KING_ATTACKS[index(Square::E4)].count_ones(), 8);
+        assert_eq!(pawn_attacks(Colour::White, index(Square::E4)), bit(4, 3) | bit(4, 5));
+        assert_eq!(pawn_attacks(Colour::Black, index(Square::A7)), bit(5, 1));
+        assert_eq!(rook_attacks(index(Square::A1), 0).count_ones(), 14);
+    }
+
+    #[test]
+    fn test_lines() {
+        let (a1, c3, h8, b3) = (index(Square::A1), index(Square::C3), index(Square::H8), index(Square::B3));
+        assert_eq!(BETWEEN[a1][c3], bit(1, 1));
+        assert_eq!(BETWEEN[c3][a1], bit(1, 1));
+        assert_eq!(BETWEEN[a1][index(Square::A2)], 0);
+        assert_eq!(LINE[c3][h8], (0..8).fold(0, |line, i| line | bit(i, i)));
+        // A knight's move apart is on no line at all.
+        assert_eq!((BETWEEN[a1][b3], LINE[a1][b3]), (0, 0));
+        // The whole e-file.
+        assert_eq!(LINE[index(Square::E1)][index(Square::E4)], 0x1010_1010_1010_1010);
+    }
+
+    #[test]
+    fn test_attackers() {
+        let position = GameState::from_fen("4k3/8/8/3q4/4P3/8/8/3QK3 w - - 0 1").unwrap();
+        let white = Pieces::new(&position.board, Colour::White);
+        let black = Pieces::new(&position.board, Colour::Black);
+        assert_eq!(squares(white.attackers(index(Square::D5), Colour::White)).collect::<Vec<_>>(), vec![index(Square::D1), index(Square::E4)]);
+        assert_eq!(black.attackers(index(Square::D1), Colour::Black), bit(4, 3));
+        assert_eq!(black.attackers(index(Square::E1), Colour::Black), 0);
+    }
+}
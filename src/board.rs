@@ -0,0 +1,162 @@
+//! # Board Representations
+//!
+//! The rules engine keeps its board as an 8x8 array of pieces, which is simple to
+//! read and to change a square at a time. Bitboards, one `u64` for each kind of piece,
+//! answer questions about whole sets of squares much faster. [`BoardRepr`] is what the
+//! two have in common, so code can be written once for either and the two can be
+//! checked against each other:
+//!
+//! ```
+//! use overload_vibe_coding::{Bitboards, BoardRepr, ChessPiece, Colour, GameState, Move, Square};
+//!
+//! let position = GameState::new();
+//! let mut array = *position.board();
+//! let mut bitboards = Bitboards::from_board(&array);
+//! let mv = position.complete_move(Move::new(Square::E2, Square::E4));
+//! array.apply_move(mv);
+//! bitboards.apply_move(mv);
+//! assert_eq!(bitboards.piece_at(Square::E4), ChessPiece::Pawn(Colour::White));
+//! assert_eq!(array.occupied(), bitboards.occupied());
+//! ```
+//!
+//! Occupancy comes back as a bitboard, with bit `rank * 8 + file` set for each
+//! occupied square, so a1 is bit 0 and h8 bit 63. A board representation only holds
+//! pieces: the side to move, castling rights and the rest belong to a `Position`.
+
+use crate::bitboard::{self, Bitboard};
+use crate::{ChessPiece, Colour, Move, Square};
+
+// > add a trait for the ways a board can be stored: reading and changing squares,
+// > asking which squares are occupied and moving pieces for a move
+pub trait BoardRepr {
+    fn piece_at(&self, square: Square) -> ChessPiece;
+
+    // > put a piece on a square, replacing what was there; a blank clears the square
+    fn set_piece(&mut self, square: Square, piece: ChessPiece);
+
+    fn is_occupied(&self, square: Square) -> bool {
+        self.piece_at(square) != ChessPiece::Blank
+    }
+
+    // > give the squares holding a piece of either colour
+    fn occupied(&self) -> Bitboard {
+        Square::ALL.iter().filter(|&&square| self.is_occupied(square)).fold(0, |bits, &square| bits | bit(square))
+    }
+
+    // > give the squares holding a piece of one colour
+    fn occupied_by(&self, colour: Colour) -> Bitboard {
+        Square::ALL.iter().filter(|&&square| self.piece_at(square).colour() == Some(colour)).fold(0, |bits, &square| bits | bit(square))
+    }
+
+    // > move the pieces for a move with its flags filled in, giving back the piece captured
+    // The move is not checked. For castling, the rook is the mover's nearest one beyond the king in the
+    // direction it moves, which is always the castling rook when the castling is legal.
+    fn apply_move(&mut self, mv: Move) -> ChessPiece {
+        let piece = self.piece_at(mv.from);
+        let captured = if mv.en_passant {
+            let taken = Square::from_file_rank(mv.to.file(), mv.from.rank()).expect("the captured pawn is beside the mover");
+            let pawn = self.piece_at(taken);
+            self.set_piece(taken, ChessPiece::Blank);
+            pawn
+        } else if mv.castling {
+            ChessPiece::Blank
+        } else {
+            self.piece_at(mv.to)
+        };
+
+        // The rook is lifted first, as in Chess960 the king may land on the square it started from.
+        let castling_rook = if mv.castling {
+            let kingside = mv.to.file() > mv.from.file();
+            let step = if kingside { 1 } else { -1 };
+            let rook = ChessPiece::Rook(piece.colour().expect("only a king castles"));
+            let rook_from = (1..8)
+                .map_while(|distance| mv.from.offset(step * distance, 0))
+                .find(|&square| self.piece_at(square) == rook)
+                .expect("a castling king has its rook beside it");
+            self.set_piece(rook_from, ChessPiece::Blank);
+            Square::from_file_rank(crate::rules::castling_rook_target(kingside), mv.from.rank())
+        } else {
+            None
+        };
+
+        self.set_piece(mv.from, ChessPiece::Blank);
+        self.set_piece(mv.landing(), mv.promotion.unwrap_or(piece));
+        if let Some(rook_to) = castling_rook {
+            self.set_piece(rook_to, ChessPiece::Rook(piece.colour().expect("only a king castles")));
+        }
+        captured
+    }
+}
+
+// > store the board as an array of ranks, as the rules engine does
+impl BoardRepr for [[ChessPiece; 8]; 8] {
+    fn piece_at(&self, square: Square) -> ChessPiece {
+        let (row, col) = square.to_row_col();
+        self[row][col]
+    }
+
+    fn set_piece(&mut self, square: Square, piece: ChessPiece) {
+        let (row, col) = square.to_row_col();
+        self[row][col] = piece;
+    }
+}
+
+// > give a square's bit in an occupancy bitboard
+pub(crate) fn bit(square: Square) -> Bitboard {
+    bitboard::bit(square.rank(), square.file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zobrist::split_mix64;
+    use crate::{Bitboards, GameState, Position};
+
+    fn assert_same(array: &[[ChessPiece; 8]; 8], bitboards: &Bitboards, position: &Position) {
+        assert_eq!(array, position.board());
+        for square in Square::ALL {
+            assert_eq!(bitboards.piece_at(square), array.piece_at(square), "{} in {}", square, position.to_fen());
+        }
+        assert_eq!(bitboards.occupied(), array.occupied());
+        assert_eq!(bitboards.occupied_by(Colour::White), array.occupied_by(Colour::White));
+        assert_eq!(bitboards.occupied_by(Colour::Black), array.occupied_by(Colour::Black));
+    }
+
+    #[test]
+    fn test_representations_agree() {
+        let mut random = 7;
+        // Positions with castling both ways, en passant and promotions close at hand, one of them Chess960.
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+            "1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1R2K1R1 w GBgb - 0 1",
+        ] {
+            for _ in 0..20 {
+                let mut position = *GameState::from_fen(fen).unwrap().position();
+                let mut array = *position.board();
+                let mut bitboards = Bitboards::from_board(&array);
+                for _ in 0..40 {
+                    let moves = position.legal_moves();
+                    if moves.is_empty() {
+                        break;
+                    }
+                    let mv = moves[(split_mix64(&mut random) % moves.len() as u64) as usize];
+                    let captured = array.apply_move(mv);
+                    assert_eq!(bitboards.apply_move(mv), captured);
+                    position.apply_move(mv);
+                    assert_same(&array, &bitboards, &position);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_occupancy() {
+        let board = *GameState::new().board();
+        assert_eq!(board.occupied(), 0xffff_0000_0000_ffff);
+        assert_eq!(board.occupied_by(Colour::White), 0xffff);
+        assert!(board.is_occupied(Square::D8) && !board.is_occupied(Square::D4));
+        assert_eq!(bit(Square::H1), 1 << 7);
+    }
+}
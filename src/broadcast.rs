@@ -0,0 +1,176 @@
+//! # Broadcast
+//!
+//! Publishes a game while it is being played, so viewers can follow it with the
+//! usual broadcast tools. After every move the whole PGN is written to a file, which
+//! grows move by move, and can also be POSTed to an HTTP endpoint:
+//!
+//! ```text
+//! overload-vibe-coding --broadcast live.pgn --broadcast-url http://localhost:8080/round1
+//! ```
+//!
+//! The file is written to a temporary name first and then renamed, so a tool polling
+//! it never reads half a game. Only plain `http://` endpoints are supported.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::error::BroadcastError;
+use crate::game::Game;
+
+// How long to wait for the endpoint before giving up on a push.
+const PUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+// > add the address of an HTTP endpoint that receives the PGN
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoint {
+    // "host:port", as given to TcpStream::connect.
+    address: String,
+    host: String,
+    path: String,
+}
+
+impl Endpoint {
+    // > parse an http:// URL, defaulting to port 80 and path /
+    pub fn from_url(url: &str) -> Result<Endpoint, BroadcastError> {
+        let rest = url.strip_prefix("http://").ok_or_else(|| BroadcastError::InvalidUrl(url.to_string()))?;
+        let (host, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        if host.is_empty() {
+            return Err(BroadcastError::InvalidUrl(url.to_string()));
+        }
+        let address = if host.contains(':') { host.to_string() } else { format!("{}:80", host) };
+        Ok(Endpoint { address, host: host.to_string(), path: path.to_string() })
+    }
+
+    // > POST the PGN to the endpoint, failing unless it answers with a 2xx status
+    pub fn push(&self, pgn: &str) -> Result<(), BroadcastError> {
+        let mut stream = TcpStream::connect(&self.address)?;
+        stream.set_read_timeout(Some(PUSH_TIMEOUT))?;
+        stream.set_write_timeout(Some(PUSH_TIMEOUT))?;
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/x-chess-pgn\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.path,
+            self.host,
+            pgn.len(),
+            pgn
+        )?;
+        stream.flush()?;
+
+        let mut status_line = String::new();
+        BufReader::new(stream).read_line(&mut status_line)?;
+        let status = status_line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|code| code.parse::<u16>().ok())
+            .ok_or(BroadcastError::InvalidResponse)?;
+        match status {
+            200..=299 => Ok(()),
+            _ => Err(BroadcastError::Status(status)),
+        }
+    }
+}
+
+// > add a broadcast that republishes a game whenever it changes
+#[derive(Debug)]
+pub struct Broadcast {
+    path: PathBuf,
+    endpoint: Option<Endpoint>,
+    published: Option<String>,
+}
+
+impl Broadcast {
+    pub fn new(path: impl Into<PathBuf>, endpoint: Option<Endpoint>) -> Broadcast {
+        Broadcast { path: path.into(), endpoint, published: None }
+    }
+
+    // > publish the game if anything changed since last time; true if it was published
+    pub fn update(&mut self, game: &Game) -> Result<bool, BroadcastError> {
+        let pgn = game.to_pgn();
+        if self.published.as_ref() == Some(&pgn) {
+            return Ok(false);
+        }
+        let mut temporary = self.path.clone().into_os_string();
+        temporary.push(".tmp");
+        std::fs::write(&temporary, &pgn)?;
+        std::fs::rename(&temporary, &self.path)?;
+        // A failed push is retried on the next update, but the file is already current.
+        if let Some(endpoint) = &self.endpoint {
+            endpoint.push(&pgn)?;
+        }
+        self.published = Some(pgn);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GameState, Move};
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_endpoint_from_url() {
+        let endpoint = Endpoint::from_url("http://example.com").unwrap();
+        assert_eq!((endpoint.address.as_str(), endpoint.path.as_str()), ("example.com:80", "/"));
+        let endpoint = Endpoint::from_url("http://localhost:8080/round/1").unwrap();
+        assert_eq!((endpoint.address.as_str(), endpoint.host.as_str()), ("localhost:8080", "localhost:8080"));
+        assert_eq!(endpoint.path, "/round/1");
+        assert!(matches!(Endpoint::from_url("https://example.com"), Err(BroadcastError::InvalidUrl(_))));
+        assert!(matches!(Endpoint::from_url("http:///games"), Err(BroadcastError::InvalidUrl(_))));
+    }
+
+    // Accept one request, answer with the given status line and return what was sent.
+    fn fake_endpoint(status: &'static str) -> (Endpoint, std::thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = Endpoint::from_url(&format!("http://{}/live", listener.local_addr().unwrap())).unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0; 4096];
+            while !String::from_utf8_lossy(&request).ends_with("*\n") {
+                let read = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..read]);
+            }
+            stream.write_all(format!("{}\r\nContent-Length: 0\r\n\r\n", status).as_bytes()).unwrap();
+            String::from_utf8(request).unwrap()
+        });
+        (endpoint, server)
+    }
+
+    #[test]
+    fn test_update_writes_file_and_pushes() {
+        let path = std::env::temp_dir().join(format!("broadcast-{}.pgn", std::process::id()));
+        let (endpoint, server) = fake_endpoint("HTTP/1.1 204 No Content");
+        let mut broadcast = Broadcast::new(&path, Some(endpoint));
+        let mut game = Game::from_position(GameState::new());
+        game.play(Move::from_uci("e2e4").unwrap()).unwrap();
+
+        assert_eq!(broadcast.update(&game), Ok(true));
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /live HTTP/1.1\r\n"));
+        assert!(request.contains("Content-Type: application/x-chess-pgn\r\n"));
+        assert!(request.ends_with("\n\n1. e4 *\n"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), game.to_pgn());
+        // Nothing has changed, so nothing is published and no connection is made.
+        assert_eq!(broadcast.update(&game), Ok(false));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_rejected_push() {
+        let path = std::env::temp_dir().join(format!("broadcast-rejected-{}.pgn", std::process::id()));
+        let (endpoint, server) = fake_endpoint("HTTP/1.1 500 Internal Server Error");
+        let mut broadcast = Broadcast::new(&path, Some(endpoint));
+        let game = Game::from_position(GameState::new());
+        assert_eq!(broadcast.update(&game), Err(BroadcastError::Status(500)));
+        server.join().unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), game.to_pgn());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
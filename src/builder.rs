@@ -0,0 +1,183 @@
+//! # Position Builder
+//!
+//! Sets up a position piece by piece, for tests, a board editor or a puzzle, where
+//! writing a FEN string by hand would be awkward:
+//!
+//! ```
+//! use overload_vibe_coding::{ChessPiece, Colour, PositionBuilder, Square};
+//!
+//! let position = PositionBuilder::new()
+//!     .piece(Square::E1, ChessPiece::King(Colour::White))
+//!     .piece(Square::H1, ChessPiece::Rook(Colour::White))
+//!     .piece(Square::E8, ChessPiece::King(Colour::Black))
+//!     .castling(Colour::White, true)
+//!     .build()
+//!     .unwrap();
+//! assert_eq!(position.to_fen(), "4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+//! ```
+//!
+//! Nothing is checked until [`PositionBuilder::build`], which rejects the positions
+//! FEN parsing does: a castling right without its king and rook at home, an en passant
+//! square with no pawn that could just have made a double push, and positions that
+//! could not arise in a game, such as a missing king or the side not to move in check.
+
+use alloc::vec::Vec;
+
+use crate::error::SetupError;
+use crate::fen;
+use crate::{CastlingRights, ChessPiece, Colour, GameState, Position, Square};
+
+// > add a builder that places pieces, sets the side to move, castling rights, en passant square and clocks,
+// > and checks the position only when it is built
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionBuilder {
+    board: [[ChessPiece; 8]; 8],
+    side_to_move: Colour,
+    // The side and whether it is the kingside right, for each castling right asked for.
+    castling: Vec<(Colour, bool)>,
+    en_passant: Option<Square>,
+    halfmove_clock: u32,
+    fullmove_number: u32,
+}
+
+impl PositionBuilder {
+    // > start from an empty board with White to move, no castling rights and the clocks at the start
+    pub fn new() -> PositionBuilder {
+        PositionBuilder {
+            board: [[ChessPiece::Blank; 8]; 8],
+            side_to_move: Colour::White,
+            castling: Vec::new(),
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+        }
+    }
+
+    // > put a piece on a square, replacing what was there; a blank clears the square
+    pub fn piece(mut self, square: Square, piece: ChessPiece) -> PositionBuilder {
+        let (row, col) = square.to_row_col();
+        self.board[row][col] = piece;
+        self
+    }
+
+    pub fn side_to_move(mut self, colour: Colour) -> PositionBuilder {
+        self.side_to_move = colour;
+        self
+    }
+
+    // > give a side the right to castle on one side, with the outermost rook there
+    pub fn castling(mut self, colour: Colour, kingside: bool) -> PositionBuilder {
+        self.castling.push((colour, kingside));
+        self
+    }
+
+    // > set the square a pawn has just skipped over with a double push
+    pub fn en_passant(mut self, square: Square) -> PositionBuilder {
+        self.en_passant = Some(square);
+        self
+    }
+
+    pub fn clocks(mut self, halfmove_clock: u32, fullmove_number: u32) -> PositionBuilder {
+        self.halfmove_clock = halfmove_clock;
+        self.fullmove_number = fullmove_number;
+        self
+    }
+
+    // > check the position and make it ready to play from
+    pub fn build(self) -> Result<GameState, SetupError> {
+        let mut castling = CastlingRights::NONE;
+        for (colour, kingside) in self.castling {
+            let rook_col = fen::castling_rook(&self.board, colour, kingside).ok_or(SetupError::NoCastlingRook(colour, kingside))?;
+            castling.set(colour, kingside, Some(rook_col));
+            castling.set_king_col(colour, fen::castling_king(&self.board, colour).expect("a castling rook has its king"));
+        }
+        if let Some(square) = self.en_passant.filter(|&square| !fen::is_en_passant_square(&self.board, self.side_to_move, square)) {
+            return Err(SetupError::InvalidEnPassant(square));
+        }
+        if self.fullmove_number == 0 {
+            return Err(SetupError::InvalidFullmoveNumber);
+        }
+
+        let position = Position {
+            board: self.board,
+            current_player: self.side_to_move,
+            castling,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            ..Position::new()
+        };
+        position.finish_setup().map(GameState::from_position).map_err(SetupError::IllegalPosition)
+    }
+}
+
+impl Default for PositionBuilder {
+    fn default() -> PositionBuilder {
+        PositionBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ChessError;
+    use crate::{GameResult, Move};
+
+    fn kings() -> PositionBuilder {
+        PositionBuilder::new().piece(Square::E1, ChessPiece::King(Colour::White)).piece(Square::E8, ChessPiece::King(Colour::Black))
+    }
+
+    #[test]
+    fn test_build_matches_fen() {
+        let built = kings()
+            .piece(Square::A1, ChessPiece::Rook(Colour::White))
+            .piece(Square::H8, ChessPiece::Rook(Colour::Black))
+            .piece(Square::D5, ChessPiece::Pawn(Colour::White))
+            .piece(Square::E5, ChessPiece::Pawn(Colour::Black))
+            .side_to_move(Colour::White)
+            .castling(Colour::White, false)
+            .castling(Colour::Black, true)
+            .en_passant(Square::E6)
+            .clocks(0, 30)
+            .build()
+            .unwrap();
+        let fen = "4k2r/8/8/3Pp3/8/8/8/R3K3 w Qk e6 0 30";
+        assert_eq!(built, GameState::from_fen(fen).unwrap());
+        assert_eq!(built.to_fen(), fen);
+    }
+
+    #[test]
+    fn test_built_position_can_be_played() {
+        let mut game_state = kings().piece(Square::H1, ChessPiece::Rook(Colour::White)).castling(Colour::White, true).build().unwrap();
+        game_state.make_move(Move::new(Square::E1, Square::G1)).unwrap();
+        assert_eq!(game_state.to_fen(), "4k3/8/8/8/8/8/8/5RK1 b - - 1 1");
+
+        let mated = PositionBuilder::new()
+            .piece(Square::H8, ChessPiece::King(Colour::Black))
+            .piece(Square::G7, ChessPiece::Queen(Colour::White))
+            .piece(Square::G6, ChessPiece::King(Colour::White))
+            .side_to_move(Colour::Black)
+            .build()
+            .unwrap();
+        assert_eq!(mated.result(), GameResult::WhiteWins);
+    }
+
+    #[test]
+    fn test_build_errors() {
+        assert_eq!(kings().castling(Colour::White, true).build(), Err(SetupError::NoCastlingRook(Colour::White, true)));
+        assert_eq!(
+            kings().piece(Square::H8, ChessPiece::Rook(Colour::Black)).piece(Square::E8, ChessPiece::Blank).piece(Square::D8, ChessPiece::King(Colour::Black)).castling(Colour::Black, false).build(),
+            Err(SetupError::NoCastlingRook(Colour::Black, false))
+        );
+        assert_eq!(kings().en_passant(Square::D6).build(), Err(SetupError::InvalidEnPassant(Square::D6)));
+        assert_eq!(kings().clocks(0, 0).build(), Err(SetupError::InvalidFullmoveNumber));
+        assert_eq!(
+            PositionBuilder::new().piece(Square::E1, ChessPiece::King(Colour::White)).build(),
+            Err(SetupError::IllegalPosition(ChessError::WrongKingCount(Colour::Black, 0)))
+        );
+        assert_eq!(
+            kings().piece(Square::E4, ChessPiece::Rook(Colour::White)).build(),
+            Err(SetupError::IllegalPosition(ChessError::SideNotToMoveInCheck))
+        );
+    }
+}
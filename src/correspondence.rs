@@ -0,0 +1,291 @@
+//! # Correspondence
+//!
+//! Playing a game by passing files back and forth, by email or a shared folder. After
+//! each move the player sends a move file holding the whole game, and the opponent
+//! loads it, checks it and replies with a move file of their own:
+//!
+//! ```text
+//! ; overload-vibe-coding correspondence 1
+//! [Event "Casual game"]
+//! ...
+//!
+//! 1. e4 *
+//! ; signature 5d41402abc4b2a76b9719d911017c592...
+//! ```
+//!
+//! The lines starting with `;` are PGN comments, so a move file is also an ordinary
+//! PGN file. The signature is an HMAC-SHA256 of everything above it, keyed with a
+//! passphrase both players share, so a file edited along the way is rejected. A reply
+//! must also repeat the game exactly as it was sent and add a single legal move, or
+//! end the game without one: by resigning, accepting a draw that was offered, or
+//! claiming a draw the rules allow. As both players hold the passphrase, the reply is
+//! taken to speak only for the side that was to move.
+
+use crate::GameResult;
+use crate::error::CorrespondenceError;
+use crate::game::Game;
+
+const HEADER: &str = "; overload-vibe-coding correspondence 1\n";
+const SIGNATURE: &str = "; signature ";
+
+impl Game {
+    // > write the game as a move file signed with the shared passphrase
+    pub fn to_move_file(&self, secret: &str) -> String {
+        let body = format!("{}{}", HEADER, self.to_pgn());
+        let signature = hex(&hmac_sha256(secret.as_bytes(), body.as_bytes()));
+        format!("{}{}{}\n", body, SIGNATURE, signature)
+    }
+
+    // > read a move file, checking its signature and that every move in it is legal
+    pub fn from_move_file(text: &str, secret: &str) -> Result<Game, CorrespondenceError> {
+        let text = text.replace("\r\n", "\n");
+        if !text.starts_with(HEADER) {
+            return Err(CorrespondenceError::NotAMoveFile);
+        }
+        let start = text.trim_end().rfind(SIGNATURE).ok_or(CorrespondenceError::Unsigned)?;
+        let (body, signature) = text.split_at(start);
+        let expected = hex(&hmac_sha256(secret.as_bytes(), body.as_bytes()));
+        if !constant_time_eq(signature[SIGNATURE.len()..].trim().as_bytes(), expected.as_bytes()) {
+            return Err(CorrespondenceError::BadSignature);
+        }
+        Game::from_pgn(&body[HEADER.len()..]).map_err(CorrespondenceError::InvalidGame)
+    }
+
+    // > take the opponent's reply: the game as we sent it plus exactly one new move
+    pub fn receive_move_file(&self, text: &str, secret: &str) -> Result<Game, CorrespondenceError> {
+        let reply = Game::from_move_file(text, secret)?;
+        let (ours, theirs) = (self.moves(), reply.moves());
+        if reply.start() != self.start() || !theirs.starts_with(&ours) {
+            return Err(CorrespondenceError::DifferentGame);
+        }
+        let new_move = match theirs.len() - ours.len() {
+            1 => Some(theirs[ours.len()]),
+            0 if reply.state().result != self.state().result => None,
+            0 => return Err(CorrespondenceError::NoNewMove),
+            extra => return Err(CorrespondenceError::TooManyMoves(extra)),
+        };
+
+        // Play the move here, so the reply can only change the result as the board or the player replying decides.
+        let replier = self.state().side_to_move();
+        let mut expected = self.state().clone();
+        if let Some(mv) = new_move {
+            expected.make_move(mv).expect("the moves of a move file are checked as it is read");
+        }
+        let result = reply.state().result;
+        let earned = result == expected.result || {
+            match result {
+                // Only the player replying may resign, and only for themselves.
+                GameResult::WhiteWins | GameResult::BlackWins => expected.resign(replier).is_ok() && expected.result == result,
+                // A draw must accept one that was offered and still stands, or be claimed on the player's own turn.
+                GameResult::Draw(_) => {
+                    expected.accept_draw(replier).is_ok() || new_move.is_none() && expected.claim_draw().is_ok()
+                }
+                GameResult::Ongoing => false,
+            }
+        };
+        if !earned {
+            return Err(CorrespondenceError::UnearnedResult(result));
+        }
+        Ok(reply)
+    }
+}
+
+// > compare two byte strings in a time that depends only on their lengths, so a signature cannot be guessed a byte at a time
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |difference, (x, y)| difference | (x ^ y)) == 0
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// > add HMAC (RFC 2104) over SHA-256
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; 64];
+    if key.len() > block.len() {
+        block[..32].copy_from_slice(&sha256(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let mut inner: Vec<u8> = block.iter().map(|byte| byte ^ 0x36).collect();
+    inner.extend_from_slice(message);
+    let mut outer: Vec<u8> = block.iter().map(|byte| byte ^ 0x5c).collect();
+    outer.extend_from_slice(&sha256(&inner));
+    sha256(&outer)
+}
+
+// The first 32 bits of the fractional parts of the cube roots of the first 64 primes.
+const ROUND_CONSTANTS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+// > add SHA-256 (FIPS 180-4), so signing needs no extra dependency
+fn sha256(message: &[u8]) -> [u8; 32] {
+    let mut hash: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+    ];
+    let mut padded = message.to_vec();
+    padded.push(0x80);
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+    padded.extend_from_slice(&(message.len() as u64 * 8).to_be_bytes());
+
+    for chunk in padded.chunks(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in chunk.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = hash;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choice = (e & f) ^ (!e & g);
+            let t1 = h.wrapping_add(s1).wrapping_add(choice).wrapping_add(ROUND_CONSTANTS[i]).wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(majority);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (word, value) in hash.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *word = word.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0u8; 32];
+    for (bytes, word) in digest.chunks_mut(4).zip(hash) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Colour, DrawReason, GameState, Move, Square};
+
+    #[test]
+    fn test_sha256_and_hmac() {
+        assert_eq!(hex(&sha256(b"")), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        assert_eq!(hex(&sha256(b"abc")), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        // RFC 4231, test case 2.
+        assert_eq!(
+            hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    fn game_after(moves: &[&str]) -> Game {
+        let mut game = Game::from_position(GameState::new());
+        for mv in moves {
+            game.play(Move::from_uci(mv).unwrap()).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    fn test_move_file_round_trip() {
+        let game = game_after(&["e2e4"]);
+        let file = game.to_move_file("open sesame");
+        assert!(file.starts_with(HEADER));
+        assert_eq!(Game::from_move_file(&file, "open sesame").unwrap(), game);
+        assert_eq!(Game::from_move_file(&file, "wrong"), Err(CorrespondenceError::BadSignature));
+        // The file is still PGN that other programs can read.
+        assert_eq!(Game::from_pgn(&file).unwrap().moves(), game.moves());
+    }
+
+    #[test]
+    fn test_tampering_is_detected() {
+        let file = game_after(&["e2e4"]).to_move_file("key");
+        let edited = file.replace("1. e4", "1. d4");
+        assert_eq!(Game::from_move_file(&edited, "key"), Err(CorrespondenceError::BadSignature));
+        let unsigned = &file[..file.find(SIGNATURE).unwrap()];
+        assert_eq!(Game::from_move_file(unsigned, "key"), Err(CorrespondenceError::Unsigned));
+        assert_eq!(Game::from_move_file("1. e4 *", "key"), Err(CorrespondenceError::NotAMoveFile));
+    }
+
+    #[test]
+    fn test_receive_reply() {
+        let sent = game_after(&["e2e4"]);
+        let reply = game_after(&["e2e4", "e7e5"]);
+        let received = sent.receive_move_file(&reply.to_move_file("key"), "key").unwrap();
+        assert_eq!(received.moves(), reply.moves());
+
+        let rewritten = game_after(&["d2d4", "e7e5"]).to_move_file("key");
+        assert_eq!(sent.receive_move_file(&rewritten, "key"), Err(CorrespondenceError::DifferentGame));
+        assert_eq!(sent.receive_move_file(&sent.to_move_file("key"), "key"), Err(CorrespondenceError::NoNewMove));
+        let two_moves = game_after(&["e2e4", "e7e5", "g1f3"]).to_move_file("key");
+        assert_eq!(sent.receive_move_file(&two_moves, "key"), Err(CorrespondenceError::TooManyMoves(2)));
+
+        let mut resigned = sent.clone();
+        resigned.resign(Colour::Black).unwrap();
+        let received = sent.receive_move_file(&resigned.to_move_file("key"), "key").unwrap();
+        assert_eq!(received.state().result, GameResult::WhiteWins);
+    }
+
+    #[test]
+    fn test_reply_can_only_end_the_game_for_its_sender() {
+        // Black is to reply, so Black cannot resign for White.
+        let sent = game_after(&["e2e4"]);
+        let mut forged = sent.clone();
+        forged.resign(Colour::White).unwrap();
+        assert_eq!(
+            sent.receive_move_file(&forged.to_move_file("key"), "key"),
+            Err(CorrespondenceError::UnearnedResult(GameResult::BlackWins))
+        );
+        let mut forged = game_after(&["e2e4", "e7e5"]);
+        forged.set_result(GameResult::BlackWins);
+        assert!(sent.receive_move_file(&forged.to_move_file("key"), "key").is_err());
+
+        // Nor agree a draw that nobody offered.
+        let agreed = GameResult::Draw(DrawReason::Agreement);
+        let mut unoffered = sent.clone();
+        unoffered.set_result(agreed);
+        assert_eq!(
+            sent.receive_move_file(&unoffered.to_move_file("key"), "key"),
+            Err(CorrespondenceError::UnearnedResult(agreed))
+        );
+
+        // A draw White offered with its move can be accepted.
+        let mut offered = Game::from_position(GameState::new());
+        offered.offer_draw(Colour::White).unwrap();
+        offered.play(Move::new(Square::E2, Square::E4)).unwrap();
+        let mut accepted = offered.clone();
+        accepted.accept_draw(Colour::Black).unwrap();
+        let received = offered.receive_move_file(&accepted.to_move_file("key"), "key").unwrap();
+        assert_eq!(received.state().result, agreed);
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"5d41", b"5d41"));
+        assert!(!constant_time_eq(b"5d41", b"5d40"));
+        assert!(!constant_time_eq(b"5d41", b"5d4"));
+    }
+
+    #[test]
+    fn test_illegal_move_is_rejected() {
+        let body = format!("{}1. e4 e4 *\n", HEADER);
+        let file = format!("{}{}{}\n", body, SIGNATURE, hex(&hmac_sha256(b"key", body.as_bytes())));
+        assert!(matches!(Game::from_move_file(&file, "key"), Err(CorrespondenceError::InvalidGame(_))));
+    }
+}
@@ -0,0 +1,482 @@
+//! # Engines
+//!
+//! Something that chooses moves: the program's own engine, its Monte Carlo tree search,
+//! or an external engine such as Stockfish driven over UCI as a child process. Both answer through the
+//! [`Engine`] trait, so a match or a game against the human does not care which it has.
+//! The program's own engines can be given an opening book to play from first.
+//! The alpha-beta engine can also ponder: after moving it goes on searching the position
+//! after the reply it expects, and keeps that work if the reply is played.
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, channel};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::book::{Book, BookOptions};
+use crate::error::EngineError;
+use crate::game::Game;
+use crate::eval::Evaluator;
+use crate::nnue::Network;
+use crate::search::{self, Limits, SearchResult, Signals};
+use crate::skill::Skill;
+use crate::{Colour, GameResult, GameState, Move, mcts};
+
+// How long an engine may take to start up or to answer "isready".
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+// Extra time allowed for a move to arrive after the clock runs out, for pipes and process switches.
+const MOVE_GRACE: Duration = Duration::from_millis(500);
+
+// > add the time each side has left and the increment added after each move
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Clocks {
+    pub white: Duration,
+    pub black: Duration,
+    pub increment: Duration,
+}
+
+impl Clocks {
+    pub fn remaining(&self, colour: Colour) -> Duration {
+        match colour {
+            Colour::White => self.white,
+            Colour::Black => self.black,
+        }
+    }
+
+    // > charge a move's thinking time to a side, adding the increment; false if the flag fell
+    pub fn charge(&mut self, colour: Colour, used: Duration) -> bool {
+        let left = match colour {
+            Colour::White => &mut self.white,
+            Colour::Black => &mut self.black,
+        };
+        match left.checked_sub(used) {
+            Some(rest) => {
+                *left = rest + self.increment;
+                true
+            }
+            None => {
+                *left = Duration::ZERO;
+                false
+            }
+        }
+    }
+}
+
+// > add a trait for anything that can choose a move in a game
+pub trait Engine {
+    fn name(&self) -> String;
+    fn new_game(&mut self) -> Result<(), EngineError>;
+    fn best_move(&mut self, game: &Game, clocks: &Clocks) -> Result<Move, EngineError>;
+
+    // > think on the opponent's time, after the engine has moved, about the reply it expects;
+    // > engines that cannot ponder ignore this
+    fn ponder(&mut self, _game: &Game, _clocks: &Clocks) {}
+}
+
+// > add the program's own engine as an Engine
+#[derive(Default)]
+pub struct InternalEngine {
+    skill: Skill,
+    evaluator: Evaluator,
+    // The reply expected to the last move chosen: the second move of its principal variation.
+    expected: Option<Move>,
+    pondering: Option<Pondering>,
+}
+
+// > add a search running on the opponent's time, in the position after the reply the engine expects
+struct Pondering {
+    position: GameState,
+    stop: Arc<AtomicBool>,
+    ponderhit: Arc<AtomicBool>,
+    search: JoinHandle<SearchResult>,
+}
+
+impl InternalEngine {
+    pub fn with_skill(skill: Skill) -> InternalEngine {
+        InternalEngine { skill, evaluator: Evaluator::default(), expected: None, pondering: None }
+    }
+
+    // > evaluate positions with an NNUE network instead of the handcrafted terms
+    pub fn with_network(mut self, network: Arc<Network>) -> InternalEngine {
+        self.evaluator = Evaluator::Nnue(network);
+        self
+    }
+
+    // > give the limits for a move from the clock, at the engine's strength
+    fn limits(&self, clocks: &Clocks, side: Colour) -> Limits {
+        let skill = Skill { seed: seed(), ..self.skill };
+        Limits { skill, ..Limits::clock(clocks.remaining(side), clocks.increment, None) }
+    }
+
+    // > end any search on the opponent's time, giving its result if the position is the one it expected
+    fn stop_pondering(&mut self, position: &GameState) -> Option<SearchResult> {
+        let pondering = self.pondering.take()?;
+        let hit = pondering.position.position_key() == position.position_key();
+        // A ponderhit starts the clock and lets the search go on; anything else stops it.
+        let signal = if hit { &pondering.ponderhit } else { &pondering.stop };
+        signal.store(true, Ordering::SeqCst);
+        let result = pondering.search.join().expect("the pondering search panicked");
+        hit.then_some(result)
+    }
+
+    // > stop any search on the opponent's time and throw its result away
+    fn cancel_pondering(&mut self) {
+        if let Some(pondering) = self.pondering.take() {
+            pondering.stop.store(true, Ordering::SeqCst);
+            let _ = pondering.search.join();
+        }
+    }
+}
+
+impl Engine for InternalEngine {
+    fn name(&self) -> String {
+        match self.skill.is_limited() {
+            true => format!("{} (skill level {})", env!("CARGO_PKG_NAME"), self.skill.level),
+            false => env!("CARGO_PKG_NAME").to_string(),
+        }
+    }
+
+    fn new_game(&mut self) -> Result<(), EngineError> {
+        self.cancel_pondering();
+        self.expected = None;
+        Ok(())
+    }
+
+    fn best_move(&mut self, game: &Game, clocks: &Clocks) -> Result<Move, EngineError> {
+        let position = game.state();
+        let result = match self.stop_pondering(position) {
+            Some(result) => result,
+            None => {
+                search::iterative_deepening(position, self.limits(clocks, position.current_player), &self.evaluator, 1, Signals::NONE, |_| {})
+            }
+        };
+        self.expected = result.pv.get(1).copied();
+        result.best_move.ok_or(EngineError::NoMove)
+    }
+
+    fn ponder(&mut self, game: &Game, clocks: &Clocks) {
+        let Some(reply) = self.expected.take() else { return };
+        let mut position = game.state().clone();
+        if position.make_move(reply).is_err() || position.result != GameResult::Ongoing {
+            return;
+        }
+        // The clock is the one the engine will have once the reply is played; it starts at the ponderhit.
+        let limits = Limits { ponder: true, ..self.limits(clocks, position.current_player) };
+        let stop = Arc::new(AtomicBool::new(false));
+        let ponderhit = Arc::new(AtomicBool::new(false));
+        let search = {
+            let (position, stop, ponderhit, evaluator) = (position.clone(), stop.clone(), ponderhit.clone(), self.evaluator.clone());
+            std::thread::spawn(move || {
+                let signals = Signals { stop: &|| stop.load(Ordering::SeqCst), ponderhit: &|| ponderhit.load(Ordering::SeqCst) };
+                search::iterative_deepening(&position, limits, &evaluator, 1, signals, |_| {})
+            })
+        };
+        self.pondering = Some(Pondering { position, stop, ponderhit, search });
+    }
+}
+
+impl Drop for InternalEngine {
+    fn drop(&mut self) {
+        self.cancel_pondering();
+    }
+}
+
+// > add the program's Monte Carlo tree search as an Engine
+pub struct MctsEngine;
+
+impl Engine for MctsEngine {
+    fn name(&self) -> String {
+        format!("{} MCTS", env!("CARGO_PKG_NAME"))
+    }
+
+    fn new_game(&mut self) -> Result<(), EngineError> {
+        Ok(())
+    }
+
+    fn best_move(&mut self, game: &Game, clocks: &Clocks) -> Result<Move, EngineError> {
+        let position = game.state();
+        // The tree search has no depths to finish, so it simply stops at the planned time.
+        let limits = Limits::clock(clocks.remaining(position.current_player), clocks.increment, None);
+        let time = limits.soft_time.unwrap_or_default();
+        mcts::search(position, mcts::Limit::Time(time), seed()).best_move.ok_or(EngineError::NoMove)
+    }
+}
+
+// > add an engine that plays from an opening book while it can, and asks another engine after that
+pub struct BookEngine {
+    engine: Box<dyn Engine>,
+    book: Arc<Book>,
+    options: BookOptions,
+}
+
+impl BookEngine {
+    pub fn new(engine: Box<dyn Engine>, book: Arc<Book>, options: BookOptions) -> BookEngine {
+        BookEngine { engine, book, options }
+    }
+}
+
+impl Engine for BookEngine {
+    fn name(&self) -> String {
+        self.engine.name()
+    }
+
+    fn new_game(&mut self) -> Result<(), EngineError> {
+        self.engine.new_game()
+    }
+
+    fn best_move(&mut self, game: &Game, clocks: &Clocks) -> Result<Move, EngineError> {
+        match self.book.choose(game.state(), &self.options, seed()) {
+            Some(mv) => Ok(mv),
+            None => self.engine.best_move(game, clocks),
+        }
+    }
+
+    fn ponder(&mut self, game: &Game, clocks: &Clocks) {
+        self.engine.ponder(game, clocks);
+    }
+}
+
+// > give a different random seed each time, so games against the program's engines differ
+pub fn seed() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(1, |elapsed| elapsed.as_nanos() as u64)
+}
+
+// > add an external UCI engine running as a child process
+pub struct UciEngine {
+    name: String,
+    child: Child,
+    stdin: ChildStdin,
+    // Lines from the engine, read on their own thread so waiting for them can time out.
+    lines: Receiver<String>,
+}
+
+impl UciEngine {
+    // > start an engine and complete the "uci" handshake
+    pub fn start(program: &str, args: &[&str]) -> Result<UciEngine, EngineError> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| EngineError::Io(err.to_string()))?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let (sender, lines) = channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut engine = UciEngine { name: program.to_string(), child, stdin, lines };
+        engine.send("uci")?;
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        loop {
+            let line = engine.receive(deadline)?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = name.trim().to_string();
+            } else if line.trim() == "uciok" {
+                return Ok(engine);
+            }
+        }
+    }
+
+    // > set a UCI option, such as "Skill Level"
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), EngineError> {
+        self.send(&format!("setoption name {} value {}", name, value))
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), EngineError> {
+        writeln!(self.stdin, "{}", command).and_then(|_| self.stdin.flush()).map_err(|_| EngineError::Crashed)
+    }
+
+    fn receive(&self, deadline: Instant) -> Result<String, EngineError> {
+        match self.lines.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(line) => Ok(line),
+            Err(RecvTimeoutError::Timeout) => Err(EngineError::Timeout),
+            Err(RecvTimeoutError::Disconnected) => Err(EngineError::Crashed),
+        }
+    }
+
+    fn wait_ready(&mut self) -> Result<(), EngineError> {
+        self.send("isready")?;
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        while self.receive(deadline)?.trim() != "readyok" {}
+        Ok(())
+    }
+}
+
+impl Engine for UciEngine {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn new_game(&mut self) -> Result<(), EngineError> {
+        self.send("ucinewgame")?;
+        self.wait_ready()
+    }
+
+    fn best_move(&mut self, game: &Game, clocks: &Clocks) -> Result<Move, EngineError> {
+        self.send(&position_command(game))?;
+        let millis = |duration: Duration| duration.as_millis();
+        self.send(&format!(
+            "go wtime {} btime {} winc {} binc {}",
+            millis(clocks.white),
+            millis(clocks.black),
+            millis(clocks.increment),
+            millis(clocks.increment)
+        ))?;
+        let deadline = Instant::now() + clocks.remaining(game.state().current_player) + MOVE_GRACE;
+        loop {
+            let line = self.receive(deadline)?;
+            if let Some(rest) = line.strip_prefix("bestmove") {
+                let text = rest.split_whitespace().next().unwrap_or("");
+                return match text {
+                    "" | "0000" | "(none)" => Err(EngineError::NoMove),
+                    _ => Move::from_uci(text).map_err(|_| EngineError::InvalidMove(text.to_string())),
+                };
+            }
+        }
+    }
+}
+
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        // Give the engine a moment to exit by itself before it is killed.
+        let deadline = Instant::now() + Duration::from_millis(500);
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// > describe a game as a UCI "position" command: the start and the moves since
+fn position_command(game: &Game) -> String {
+    let mut command = if *game.start() == GameState::new() {
+        "position startpos".to_string()
+    } else {
+        format!("position fen {}", game.start().to_fen())
+    };
+    let moves = game.moves();
+    if !moves.is_empty() {
+        command.push_str(" moves");
+        for mv in moves {
+            command.push(' ');
+            command.push_str(&mv.to_uci());
+        }
+    }
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Square;
+
+    #[test]
+    fn test_clocks() {
+        let mut clocks = Clocks { white: Duration::from_secs(10), black: Duration::from_secs(10), increment: Duration::from_secs(1) };
+        assert!(clocks.charge(Colour::White, Duration::from_secs(3)));
+        assert_eq!(clocks.remaining(Colour::White), Duration::from_secs(8));
+        assert!(!clocks.charge(Colour::Black, Duration::from_secs(11)));
+        assert_eq!(clocks.remaining(Colour::Black), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_position_command() {
+        let mut game = Game::from_position(GameState::new());
+        assert_eq!(position_command(&game), "position startpos");
+        game.play(Move::new(Square::E2, Square::E4)).unwrap();
+        assert_eq!(position_command(&game), "position startpos moves e2e4");
+        let game = Game::from_position(GameState::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap());
+        assert_eq!(position_command(&game), "position fen 4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+    }
+
+    #[test]
+    fn test_book_engine() {
+        let source: Vec<String> = crate::zobrist::KEYS.iter().map(|key| format!("0x{:016x}", key)).collect();
+        let keys = crate::book::PolyglotKeys::from_source(&source.join(",")).unwrap();
+        // The only book move is 1. a3, which the engine would never choose by itself.
+        let mut bytes = keys.key(&GameState::new()).to_be_bytes().to_vec();
+        bytes.extend((1u16 << 9 | 2 << 3).to_be_bytes());
+        bytes.extend([0, 1, 0, 0, 0, 0]);
+        let book = Arc::new(Book::from_bytes(&bytes, keys).unwrap());
+        let mut engine = BookEngine::new(Box::new(InternalEngine::default()), book, BookOptions::default());
+        assert_eq!(engine.name(), InternalEngine::default().name());
+
+        let clocks = Clocks { white: Duration::from_secs(5), black: Duration::from_secs(5), increment: Duration::ZERO };
+        let mut game = Game::from_position(GameState::new());
+        let mv = engine.best_move(&game, &clocks).unwrap();
+        assert_eq!((mv.from, mv.to), (Square::A2, Square::A3));
+        // Out of the book, the engine chooses.
+        game.play(mv).unwrap();
+        let mv = engine.best_move(&game, &clocks).unwrap();
+        assert!(game.state().legal_moves().contains(&mv));
+    }
+
+    #[test]
+    fn test_pondering() {
+        let clocks = Clocks { white: Duration::from_secs(3), black: Duration::from_secs(3), increment: Duration::ZERO };
+        let mut engine = InternalEngine::default();
+        let mut game = Game::from_position(GameState::from_fen("4k3/8/8/3q4/8/8/8/3QK3 w - - 0 1").unwrap());
+        game.play(engine.best_move(&game, &clocks).unwrap()).unwrap();
+        // However deep the search got, the engine expects some reply.
+        let expected = *engine.expected.get_or_insert(game.state().legal_moves()[0]);
+        engine.ponder(&game, &clocks);
+        assert!(engine.pondering.is_some() && engine.expected.is_none());
+        // The expected reply is played, so the pondering search gives the move.
+        game.play(expected).unwrap();
+        let mv = engine.best_move(&game, &clocks).unwrap();
+        assert!(game.state().legal_moves().contains(&mv) && engine.pondering.is_none());
+
+        // Any other reply stops it, and the engine searches afresh.
+        game.play(mv).unwrap();
+        let expected = *engine.expected.get_or_insert(game.state().legal_moves()[0]);
+        engine.ponder(&game, &clocks);
+        let other = game.state().legal_moves().into_iter().find(|&mv| mv != expected).unwrap();
+        game.play(other).unwrap();
+        let mv = engine.best_move(&game, &clocks).unwrap();
+        assert!(game.state().legal_moves().contains(&mv) && engine.pondering.is_none());
+    }
+
+    // A stand-in engine written in shell that always answers e2e4.
+    #[cfg(unix)]
+    const FAKE_ENGINE: &str = "while read line; do case $line in \
+        uci) echo 'id name Fake'; echo uciok;; \
+        isready) echo readyok;; \
+        go*) echo 'info depth 1'; echo 'bestmove e2e4';; \
+        quit) exit;; esac; done";
+
+    #[test]
+    #[cfg(unix)]
+    fn test_uci_engine() {
+        let mut engine = UciEngine::start("sh", &["-c", FAKE_ENGINE]).unwrap();
+        assert_eq!(engine.name(), "Fake");
+        engine.new_game().unwrap();
+        let clocks = Clocks { white: Duration::from_secs(5), black: Duration::from_secs(5), increment: Duration::ZERO };
+        let mv = engine.best_move(&Game::from_position(GameState::new()), &clocks).unwrap();
+        assert_eq!((mv.from, mv.to), (Square::E2, Square::E4));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_uci_engine_failures() {
+        assert!(matches!(UciEngine::start("/nonexistent/engine", &[]), Err(EngineError::Io(_))));
+        assert!(matches!(UciEngine::start("sh", &["-c", "exit 0"]), Err(EngineError::Crashed)));
+        let mut silent = UciEngine::start("sh", &["-c", "read line; echo uciok; sleep 5"]).unwrap();
+        let clocks = Clocks { white: Duration::from_millis(100), black: Duration::ZERO, increment: Duration::ZERO };
+        let result = silent.best_move(&Game::from_position(GameState::new()), &clocks);
+        assert_eq!(result, Err(EngineError::Timeout));
+    }
+}
@@ -0,0 +1,213 @@
+//! # Engine Matches
+//!
+//! Plays a series of games between two engines, alternating colours, and reports the
+//! score with an Elo estimate:
+//!
+//! ```text
+//! overload-vibe-coding match internal /usr/bin/stockfish --games 10 --tc 60+1 --pgn match.pgn
+//! ```
+//!
+//! Each side has a clock; a side whose flag falls, who sends an illegal move or whose
+//! engine stops answering loses. A game still going after `max_plies` half-moves is
+//! adjudicated a draw. Each game is recorded with a `Termination` tag saying how it ended.
+
+use std::time::{Duration, Instant};
+
+use crate::engine::{Clocks, Engine};
+use crate::game::Game;
+use crate::{Colour, DrawReason, GameResult, GameState};
+
+// > add the settings for a match: how many games, the time control and when to adjudicate
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchConfig {
+    pub games: usize,
+    pub time: Duration,
+    pub increment: Duration,
+    pub max_plies: usize,
+}
+
+// > add the match score from the first engine's point of view
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MatchScore {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+impl MatchScore {
+    // > estimate the first engine's Elo advantage from its score, if it neither won nor lost every point
+    pub fn elo_difference(&self) -> Option<f64> {
+        let games = (self.wins + self.draws + self.losses) as f64;
+        let score = (self.wins as f64 + self.draws as f64 / 2.0) / games;
+        // Adding zero turns the -0 of an even score into 0.
+        (score > 0.0 && score < 1.0).then(|| -400.0 * (1.0 / score - 1.0).log10() + 0.0)
+    }
+}
+
+// > play one game between two engines with clocks, returning the finished game
+pub fn play_game<'a>(white: &mut (dyn Engine + 'a), black: &mut (dyn Engine + 'a), config: &MatchConfig) -> Game {
+    let mut game = Game::from_position(GameState::new());
+    let mut clocks = Clocks { white: config.time, black: config.time, increment: config.increment };
+    let mut termination = "normal";
+    for engine in [&mut *white, &mut *black] {
+        if engine.new_game().is_err() {
+            termination = "rules infraction";
+        }
+    }
+
+    while termination == "normal" && game.state().result == GameResult::Ongoing {
+        if game.moves().len() >= config.max_plies {
+            game.set_result(GameResult::Draw(DrawReason::Agreement));
+            termination = "adjudication";
+            break;
+        }
+        let colour = game.state().current_player;
+        let engine = match colour {
+            Colour::White => &mut *white,
+            Colour::Black => &mut *black,
+        };
+        let started = Instant::now();
+        let choice = engine.best_move(&game, &clocks);
+        let in_time = clocks.charge(colour, started.elapsed());
+        match choice {
+            Ok(mv) if in_time && game.play(mv).is_ok() => {}
+            Ok(_) if in_time => termination = "rules infraction",
+            Err(crate::error::EngineError::Timeout) | Ok(_) => termination = "time forfeit",
+            Err(_) => termination = "rules infraction",
+        }
+        if termination != "normal" {
+            game.set_result(match colour {
+                Colour::White => GameResult::BlackWins,
+                Colour::Black => GameResult::WhiteWins,
+            });
+        }
+    }
+
+    let mut tags = game.tags().to_vec();
+    for (name, value) in [("White", white.name()), ("Black", black.name())] {
+        if let Some(tag) = tags.iter_mut().find(|(tag, _)| tag == name) {
+            tag.1 = value;
+        }
+    }
+    tags.push(("Termination".to_string(), termination.to_string()));
+    game.set_tags(tags);
+    game
+}
+
+// > play a match, alternating colours, and return the games with the first engine's score
+pub fn run_match<'a>(
+    first: &mut (dyn Engine + 'a),
+    second: &mut (dyn Engine + 'a),
+    config: &MatchConfig,
+    mut on_game: impl FnMut(usize, &Game),
+) -> (Vec<Game>, MatchScore) {
+    let mut games = Vec::new();
+    let mut score = MatchScore::default();
+    for round in 1..=config.games {
+        let first_is_white = round % 2 == 1;
+        let mut game = if first_is_white {
+            play_game(first, second, config)
+        } else {
+            play_game(second, first, config)
+        };
+        let mut tags = game.tags().to_vec();
+        for (name, value) in tags.iter_mut() {
+            match name.as_str() {
+                "Event" => *value = "Engine match".to_string(),
+                "Round" => *value = round.to_string(),
+                _ => {}
+            }
+        }
+        game.set_tags(tags);
+
+        match (game.state().result, first_is_white) {
+            (GameResult::WhiteWins, true) | (GameResult::BlackWins, false) => score.wins += 1,
+            (GameResult::WhiteWins, false) | (GameResult::BlackWins, true) => score.losses += 1,
+            _ => score.draws += 1,
+        }
+        on_game(round, &game);
+        games.push(game);
+    }
+    (games, score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::InternalEngine;
+    use crate::error::EngineError;
+    use crate::{Move, Square};
+
+    // An engine that plays the same moves every game, or fails in a chosen way.
+    struct Scripted {
+        moves: Vec<(Square, Square)>,
+        failure: EngineError,
+    }
+
+    impl Engine for Scripted {
+        fn name(&self) -> String {
+            "Scripted".to_string()
+        }
+
+        fn new_game(&mut self) -> Result<(), EngineError> {
+            Ok(())
+        }
+
+        fn best_move(&mut self, game: &Game, _clocks: &Clocks) -> Result<Move, EngineError> {
+            let ply = game.moves().len() / 2;
+            self.moves.get(ply).map(|&(from, to)| Move::new(from, to)).ok_or(self.failure.clone())
+        }
+    }
+
+    fn config(games: usize, max_plies: usize) -> MatchConfig {
+        MatchConfig { games, time: Duration::from_secs(10), increment: Duration::ZERO, max_plies }
+    }
+
+    #[test]
+    fn test_fools_mate() {
+        let mut white = Scripted { moves: vec![(Square::F2, Square::F3), (Square::G2, Square::G4)], failure: EngineError::NoMove };
+        let mut black = Scripted { moves: vec![(Square::E7, Square::E5), (Square::D8, Square::H4)], failure: EngineError::NoMove };
+        let game = play_game(&mut white, &mut black, &config(1, 100));
+        assert_eq!(game.state().result, GameResult::BlackWins);
+        assert!(game.tags().contains(&("Termination".to_string(), "normal".to_string())));
+        assert!(game.tags().contains(&("White".to_string(), "Scripted".to_string())));
+    }
+
+    #[test]
+    fn test_forfeits_and_adjudication() {
+        let mut slow = Scripted { moves: vec![], failure: EngineError::Timeout };
+        let mut internal = InternalEngine::default();
+        let game = play_game(&mut slow, &mut internal, &config(1, 100));
+        assert_eq!(game.state().result, GameResult::BlackWins);
+        assert!(game.tags().contains(&("Termination".to_string(), "time forfeit".to_string())));
+
+        let mut illegal = Scripted { moves: vec![(Square::E2, Square::E5)], failure: EngineError::NoMove };
+        let game = play_game(&mut illegal, &mut InternalEngine::default(), &config(1, 100));
+        assert!(game.tags().contains(&("Termination".to_string(), "rules infraction".to_string())));
+
+        let game = play_game(&mut InternalEngine::default(), &mut InternalEngine::default(), &config(1, 6));
+        assert_eq!(game.moves().len(), 6);
+        assert_eq!(game.state().result, GameResult::Draw(DrawReason::Agreement));
+        assert!(game.tags().contains(&("Termination".to_string(), "adjudication".to_string())));
+    }
+
+    #[test]
+    fn test_run_match_alternates_colours() {
+        let mut crasher = Scripted { moves: vec![], failure: EngineError::Crashed };
+        let mut rounds = Vec::new();
+        let (games, score) = run_match(&mut crasher, &mut InternalEngine::default(), &config(3, 4), |round, _| rounds.push(round));
+        assert_eq!(rounds, vec![1, 2, 3]);
+        assert!(games[1].tags().contains(&("Round".to_string(), "2".to_string())));
+        assert!(games[1].tags().contains(&("Black".to_string(), "Scripted".to_string())));
+        // Rounds 1 and 3 are lost at once with White; in round 2 the internal engine plays first and the crasher loses after.
+        assert_eq!(score, MatchScore { wins: 0, draws: 0, losses: 3 });
+    }
+
+    #[test]
+    fn test_elo_difference() {
+        assert_eq!(MatchScore { wins: 5, draws: 0, losses: 5 }.elo_difference(), Some(0.0));
+        let elo = MatchScore { wins: 3, draws: 0, losses: 1 }.elo_difference().unwrap();
+        assert!((elo - 190.85).abs() < 0.01, "{}", elo);
+        assert_eq!(MatchScore { wins: 2, draws: 0, losses: 0 }.elo_difference(), None);
+    }
+}
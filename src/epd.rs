@@ -0,0 +1,278 @@
+//! # EPD
+//!
+//! Extended Position Description, the format of test suites such as WAC and STS.
+//! A record is the first four FEN fields followed by operations, each an opcode,
+//! its operands and a semicolon:
+//!
+//! `r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - bm Qxf7#; id "Scholar's mate";`
+//!
+//! `bm` and `am` list best and avoided moves in SAN, `id` names the position and
+//! `ce` is an evaluation in centipawns for the side to move. The `hmvc` and `fmvn`
+//! operations carry the clocks that plain FEN would give in its last two fields.
+//!
+//! [`Epd::solve`] runs a record of a test suite through the search: it is solved when
+//! the move found is one of the `bm` moves and none of the `am` moves. The search's
+//! move, score and depth are written back as `pm`, `ce` and `acd`, so a suite can be
+//! saved with the engine's answers.
+
+use std::fmt;
+
+use crate::error::{ChessError, EpdError};
+use crate::eval::Evaluator;
+use crate::search::{self, Limits, Signals};
+use crate::{GameState, Move};
+
+// EPD writes a mate as this less the plies to it.
+const MATE: i32 = 32_767;
+
+// > add a struct for an EPD record: a position and its operations in order
+#[derive(Debug, Clone, PartialEq)]
+pub struct Epd {
+    pub(crate) position: GameState,
+    pub(crate) operations: Vec<(String, Vec<String>)>,
+}
+
+impl Epd {
+    // > describe a position as EPD, keeping its clocks in hmvc and fmvn operations
+    pub fn from_position(position: &GameState) -> Epd {
+        Epd {
+            position: position.clone(),
+            operations: vec![
+                ("hmvc".to_string(), vec![position.halfmove_clock().to_string()]),
+                ("fmvn".to_string(), vec![position.fullmove_number().to_string()]),
+            ],
+        }
+    }
+
+    // > parse one EPD record
+    pub fn parse(line: &str) -> Result<Epd, EpdError> {
+        let mut rest = line.trim();
+        let mut fields = Vec::new();
+        for _ in 0..4 {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            fields.push(&rest[..end]);
+            rest = rest[end..].trim_start();
+        }
+        let mut position = GameState::from_fen(&fields.join(" ")).map_err(EpdError::InvalidPosition)?;
+
+        let mut operations = Vec::new();
+        let mut chars = rest.chars().peekable();
+        loop {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            if chars.peek().is_none() {
+                break;
+            }
+            let mut opcode = String::new();
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != ';') {
+                opcode.push(c);
+            }
+            let valid = opcode.starts_with(|c: char| c.is_ascii_alphabetic())
+                && opcode.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid {
+                return Err(EpdError::InvalidOperation(opcode));
+            }
+
+            let mut operands = Vec::new();
+            loop {
+                while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                match chars.next() {
+                    None | Some(';') => break,
+                    Some('"') => {
+                        let mut operand = String::new();
+                        loop {
+                            match chars.next() {
+                                Some('"') => break,
+                                Some(c) => operand.push(c),
+                                None => return Err(EpdError::UnterminatedString),
+                            }
+                        }
+                        operands.push(operand);
+                    }
+                    Some(c) => {
+                        let mut operand = c.to_string();
+                        while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != ';') {
+                            operand.push(c);
+                        }
+                        operands.push(operand);
+                    }
+                }
+            }
+            operations.push((opcode, operands));
+        }
+
+        let clock = |opcode: &str| {
+            operations
+                .iter()
+                .find(|(name, _)| name == opcode)
+                .and_then(|(_, operands)| operands.first())
+                .map(|operand| operand.parse().map_err(|_| EpdError::InvalidOperation(format!("{} {}", opcode, operand))))
+                .transpose()
+        };
+        if let Some(halfmove_clock) = clock("hmvc")? {
+            position.halfmove_clock = halfmove_clock;
+        }
+        if let Some(fullmove_number) = clock("fmvn")? {
+            position.fullmove_number = fullmove_number;
+        }
+        Ok(Epd { position, operations })
+    }
+
+    // > give the operands of an operation, if the record has it
+    pub fn operands(&self, opcode: &str) -> Option<&[String]> {
+        self.operations
+            .iter()
+            .find(|(name, _)| name == opcode)
+            .map(|(_, operands)| operands.as_slice())
+    }
+
+    // > add an operation, replacing any with the same opcode
+    pub fn set(&mut self, opcode: &str, operands: Vec<String>) {
+        match self.operations.iter_mut().find(|(name, _)| name == opcode) {
+            Some(operation) => operation.1 = operands,
+            None => self.operations.push((opcode.to_string(), operands)),
+        }
+    }
+
+    // > give the best moves listed by bm
+    pub fn best_moves(&self) -> Result<Vec<Move>, ChessError> {
+        self.moves("bm")
+    }
+
+    // > give the moves to avoid listed by am
+    pub fn avoid_moves(&self) -> Result<Vec<Move>, ChessError> {
+        self.moves("am")
+    }
+
+    fn moves(&self, opcode: &str) -> Result<Vec<Move>, ChessError> {
+        let operands = self.operands(opcode).unwrap_or_default();
+        operands.iter().map(|san| self.position.parse_san(san)).collect()
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.operands("id")?.first().map(String::as_str)
+    }
+
+    // > give the centipawn evaluation from ce, from the point of view of the side to move
+    pub fn centipawns(&self) -> Option<i32> {
+        self.operands("ce")?.first()?.parse().ok()
+    }
+
+    // > search the position and tell whether the move found is one of bm and none of am, recording the
+    // > move, score and depth reached in pm, ce and acd
+    pub fn solve(&mut self, limits: Limits, evaluator: &Evaluator, threads: usize) -> Result<bool, ChessError> {
+        let (best, avoid) = (self.best_moves()?, self.avoid_moves()?);
+        let result = search::iterative_deepening(&self.position, limits, evaluator, threads, Signals::NONE, |_| {});
+        // With no legal moves there is nothing to find.
+        let Some(mv) = result.best_move else {
+            return Ok(false);
+        };
+        let score = match result.mate_in() {
+            Some(_) => result.score.signum() * (MATE - (search::MATE - result.score.abs())),
+            None => result.score,
+        };
+        self.set("pm", vec![self.position.move_to_san(&mv)]);
+        self.set("ce", vec![score.to_string()]);
+        self.set("acd", vec![result.depth.to_string()]);
+        Ok((best.is_empty() || best.contains(&mv)) && !avoid.contains(&mv))
+    }
+}
+
+impl fmt::Display for Epd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fen = self.position.to_fen();
+        let fields: Vec<&str> = fen.split(' ').take(4).collect();
+        write!(f, "{}", fields.join(" "))?;
+        for (opcode, operands) in &self.operations {
+            write!(f, " {}", opcode)?;
+            for operand in operands {
+                // Names and comments are strings; moves and numbers are written bare.
+                let string = opcode == "id"
+                    || (opcode.len() == 2 && opcode.starts_with('c') && opcode != "ce")
+                    || operand.is_empty()
+                    || operand.contains(|c: char| c.is_whitespace() || c == ';');
+                if string {
+                    write!(f, " \"{}\"", operand)?;
+                } else {
+                    write!(f, " {}", operand)?;
+                }
+            }
+            write!(f, ";")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Square;
+
+    const WAC_001: &str = "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\";";
+
+    #[test]
+    fn test_parse_test_suite_record() {
+        let epd = Epd::parse(WAC_001).unwrap();
+        assert_eq!(epd.id(), Some("WAC.001"));
+        let best = epd.best_moves().unwrap();
+        assert_eq!((best[0].from, best[0].to), (Square::G3, Square::G6));
+        assert!(epd.avoid_moves().unwrap().is_empty());
+        assert_eq!(epd.centipawns(), None);
+        assert_eq!(epd.to_string(), WAC_001);
+    }
+
+    #[test]
+    fn test_operations_and_clocks() {
+        let line = "4k3/8/8/8/8/8/8/R3K3 w Q - am Ra8+ Kd1; ce -25; c0 \"a comment\"; hmvc 7; fmvn 40;";
+        let mut epd = Epd::parse(line).unwrap();
+        assert_eq!(epd.avoid_moves().unwrap().len(), 2);
+        assert_eq!(epd.centipawns(), Some(-25));
+        assert_eq!(epd.operands("c0"), Some(&["a comment".to_string()][..]));
+        assert_eq!((epd.position.halfmove_clock(), epd.position.fullmove_number()), (7, 40));
+        assert_eq!(epd.to_string(), line);
+
+        epd.set("ce", vec!["31".to_string()]);
+        epd.set("bm", vec!["O-O-O+".to_string()]);
+        assert_eq!(epd.centipawns(), Some(31));
+        assert_eq!(epd.best_moves().unwrap()[0].to, Square::C1);
+    }
+
+    #[test]
+    fn test_from_position() {
+        let mut position = GameState::new();
+        position.make_move(Move::new(Square::G1, Square::F3)).unwrap();
+        let epd = Epd::from_position(&position);
+        assert_eq!(
+            epd.to_string(),
+            "rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKB1R b KQkq - hmvc 1; fmvn 1;"
+        );
+        assert_eq!(Epd::parse(&epd.to_string()).unwrap().position.to_fen(), position.to_fen());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(Epd::parse("8/8 w - -"), Err(EpdError::InvalidPosition(_))));
+        assert_eq!(
+            Epd::parse("4k3/8/8/8/8/8/8/4K3 w - - 1bm e4;"),
+            Err(EpdError::InvalidOperation("1bm".to_string()))
+        );
+        assert_eq!(Epd::parse("4k3/8/8/8/8/8/8/4K3 w - - id \"open;"), Err(EpdError::UnterminatedString));
+        assert_eq!(
+            Epd::parse("4k3/8/8/8/8/8/8/4K3 w - - hmvc x;"),
+            Err(EpdError::InvalidOperation("hmvc x".to_string()))
+        );
+        let epd = Epd::parse("4k3/8/8/8/8/8/8/4K3 w - - bm Qh5;").unwrap();
+        assert_eq!(epd.best_moves(), Err(ChessError::NoMatchingMove("Qh5".to_string())));
+    }
+
+    #[test]
+    fn test_solve() {
+        let mut epd = Epd::parse("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - bm Qxf7#; id \"mate\";").unwrap();
+        assert_eq!(epd.solve(Limits::depth(2), &Evaluator::default(), 1), Ok(true));
+        assert_eq!(epd.operands("pm"), Some(&["Qxf7#".to_string()][..]));
+        assert_eq!(epd.centipawns(), Some(MATE - 1));
+        assert!(epd.operands("acd").is_some());
+
+        let mut epd = Epd::parse("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - am Qxf7#;").unwrap();
+        assert_eq!(epd.solve(Limits::depth(2), &Evaluator::default(), 1), Ok(false));
+    }
+}
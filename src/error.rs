@@ -0,0 +1,431 @@
+//! # Errors
+//!
+//! Every way a move, a claim or some input can be rejected, so that callers can
+//! match on the kind of failure instead of parsing a message.
+//! Problems reading FEN, PGN and EPD text, board diagrams, opening books, saved games or puzzles have their own error enums so the parsers can say exactly what was wrong,
+//! as do engines, the clipboard, the network, broadcasts, correspondence move files, game imports, DGT boards and NNUE networks.
+
+use alloc::string::String;
+use core::fmt;
+
+use crate::{ChessPiece, Colour, Square};
+
+// > add an error enum for everything the rules engine can reject
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChessError {
+    NoPieceAtSource,
+    WrongTurn,
+    SameSquare,
+    IllegalGeometry(ChessPiece),
+    PathBlocked(ChessPiece),
+    CaptureOwnPiece,
+    CaptureKing,
+    PawnBlocked,
+    PawnMustCaptureDiagonally,
+    CastlingNotAllowed,
+    NoRookToCastle,
+    CastlingPathBlocked,
+    CastlingThroughCheck,
+    InvalidPromotion,
+    LeavesKingInCheck,
+    GameOver,
+    NoDrawToClaim,
+    NoDrawOffer,
+    InvalidSquare(String),
+    InvalidNotation(String),
+    NoMatchingMove(String),
+    AmbiguousMove(String),
+    WrongKingCount(Colour, usize),
+    SideNotToMoveInCheck,
+    PawnOnBackRank(Square),
+}
+
+impl fmt::Display for ChessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChessError::NoPieceAtSource => write!(f, "No piece at the source square."),
+            ChessError::WrongTurn => write!(f, "It's not your turn."),
+            ChessError::SameSquare => write!(f, "The piece must move to a different square."),
+            ChessError::IllegalGeometry(piece) => write!(f, "{} cannot move like that.", piece_name(*piece)),
+            ChessError::PathBlocked(piece) => write!(f, "{} cannot jump over other pieces.", piece_name(*piece)),
+            ChessError::CaptureOwnPiece => write!(f, "You cannot capture your own piece."),
+            ChessError::CaptureKing => write!(f, "A king can never be captured."),
+            ChessError::PawnBlocked => write!(f, "A pawn cannot capture straight ahead."),
+            ChessError::PawnMustCaptureDiagonally => write!(f, "A pawn can only move diagonally when capturing."),
+            ChessError::CastlingNotAllowed => write!(f, "Castling is no longer allowed on that side."),
+            ChessError::NoRookToCastle => write!(f, "There is no rook to castle with."),
+            ChessError::CastlingPathBlocked => write!(f, "The squares between the king and rook must be empty."),
+            ChessError::CastlingThroughCheck => write!(f, "The king cannot castle out of, through or into check."),
+            ChessError::InvalidPromotion => write!(
+                f,
+                "Only a pawn reaching the last rank can be promoted, to a queen, rook, bishop or knight of its own colour."
+            ),
+            ChessError::LeavesKingInCheck => write!(f, "That move would leave your king in check."),
+            ChessError::GameOver => write!(f, "The game is over."),
+            ChessError::NoDrawToClaim => write!(f, "There is no draw to claim."),
+            ChessError::NoDrawOffer => write!(f, "Your opponent has not offered a draw."),
+            ChessError::InvalidSquare(square) => write!(f, "Invalid square: {}", square),
+            ChessError::InvalidNotation(text) => write!(f, "'{}' is not a move in algebraic notation.", text),
+            ChessError::NoMatchingMove(text) => write!(f, "There is no legal move matching '{}'.", text),
+            ChessError::AmbiguousMove(text) => write!(f, "'{}' could mean more than one move; add the file or rank it starts from.", text),
+            ChessError::WrongKingCount(colour, count) => {
+                write!(f, "{:?} must have exactly one king, not {}.", colour, count)
+            }
+            ChessError::SideNotToMoveInCheck => write!(f, "The side that has just moved cannot be in check."),
+            ChessError::PawnOnBackRank(square) => write!(f, "There cannot be a pawn on {:?}.", square),
+        }
+    }
+}
+
+impl core::error::Error for ChessError {}
+
+// > add an error enum describing what is wrong with a FEN string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+    WrongFieldCount(usize),
+    WrongRankCount(usize),
+    // The rank number (1-8) and its text.
+    WrongRankLength(usize, String),
+    InvalidPiece(char),
+    InvalidSideToMove(String),
+    InvalidCastling(String),
+    InvalidEnPassant(String),
+    InvalidHalfmoveClock(String),
+    InvalidFullmoveNumber(String),
+    IllegalPosition(ChessError),
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FenError::WrongFieldCount(count) => write!(f, "A FEN string needs 4 or 6 fields, not {}.", count),
+            FenError::WrongRankCount(count) => write!(f, "The board needs 8 ranks separated by '/', not {}.", count),
+            FenError::WrongRankLength(rank, text) => write!(f, "Rank {} ('{}') does not describe 8 squares.", rank, text),
+            FenError::InvalidPiece(c) => write!(f, "'{}' is not a piece letter.", c),
+            FenError::InvalidSideToMove(text) => write!(f, "The side to move must be 'w' or 'b', not '{}'.", text),
+            FenError::InvalidCastling(text) => write!(f, "Invalid castling rights: '{}'.", text),
+            FenError::InvalidEnPassant(text) => write!(f, "Invalid en passant square: '{}'.", text),
+            FenError::InvalidHalfmoveClock(text) => write!(f, "Invalid half-move clock: '{}'.", text),
+            FenError::InvalidFullmoveNumber(text) => write!(f, "Invalid full-move number: '{}'.", text),
+            FenError::IllegalPosition(err) => write!(f, "Illegal position: {}", err),
+        }
+    }
+}
+
+impl core::error::Error for FenError {}
+
+// > add an error enum for positions set up piece by piece that cannot be played from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SetupError {
+    // The side, and whether it was the kingside right, that has no king and rook to castle with.
+    NoCastlingRook(Colour, bool),
+    InvalidEnPassant(Square),
+    InvalidFullmoveNumber,
+    IllegalPosition(ChessError),
+}
+
+impl fmt::Display for SetupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SetupError::NoCastlingRook(colour, kingside) => {
+                let side = if *kingside { "kingside" } else { "queenside" };
+                write!(f, "{:?} cannot castle {}: the king and a rook are not on their starting squares.", colour, side)
+            }
+            SetupError::InvalidEnPassant(square) => write!(f, "{} is not just behind a pawn that has made a double push.", square),
+            SetupError::InvalidFullmoveNumber => write!(f, "The full-move number starts at 1."),
+            SetupError::IllegalPosition(err) => write!(f, "Illegal position: {}", err),
+        }
+    }
+}
+
+impl core::error::Error for SetupError {}
+
+// > add an error enum for PGN files that cannot be read or replayed
+#[cfg(feature = "pgn")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PgnError {
+    NoGame,
+    InvalidTag(String),
+    UnterminatedComment,
+    UnbalancedVariation,
+    MisplacedVariation,
+    InvalidFen(FenError),
+    // The numbered move as written, such as "12... Nf3", and why it was rejected.
+    IllegalMove(String, ChessError),
+}
+
+#[cfg(feature = "pgn")]
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PgnError::NoGame => write!(f, "There is no game in the PGN text."),
+            PgnError::InvalidTag(text) => write!(f, "Invalid tag: {}", text),
+            PgnError::UnterminatedComment => write!(f, "A comment is missing its closing '}}'."),
+            PgnError::UnbalancedVariation => write!(f, "The brackets around a variation do not match."),
+            PgnError::MisplacedVariation => write!(f, "A variation must follow the move it replaces."),
+            PgnError::InvalidFen(err) => write!(f, "Invalid FEN tag: {}", err),
+            PgnError::IllegalMove(text, err) => write!(f, "{} cannot be played: {}", text, err),
+        }
+    }
+}
+
+#[cfg(feature = "pgn")]
+impl core::error::Error for PgnError {}
+
+// > add an error enum for EPD records that cannot be read
+#[cfg(feature = "engine")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EpdError {
+    InvalidPosition(FenError),
+    InvalidOperation(String),
+    UnterminatedString,
+}
+
+#[cfg(feature = "engine")]
+impl fmt::Display for EpdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EpdError::InvalidPosition(err) => write!(f, "Invalid position: {}", err),
+            EpdError::InvalidOperation(text) => write!(f, "Invalid operation: '{}'.", text),
+            EpdError::UnterminatedString => write!(f, "A string operand is missing its closing quote."),
+        }
+    }
+}
+
+#[cfg(feature = "engine")]
+impl core::error::Error for EpdError {}
+
+// > add an error enum for opening books that cannot be read
+#[cfg(feature = "engine")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BookError {
+    WrongKeyCount(usize),
+    TruncatedEntry,
+}
+
+#[cfg(feature = "engine")]
+impl fmt::Display for BookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BookError::WrongKeyCount(count) => write!(f, "Polyglot keys need 781 random numbers, not {}.", count),
+            BookError::TruncatedEntry => write!(f, "The book ends part way through an entry."),
+        }
+    }
+}
+
+#[cfg(feature = "engine")]
+impl core::error::Error for BookError {}
+
+// > add an error enum for games saved in the binary format that cannot be loaded
+#[cfg(feature = "pgn")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BinaryError {
+    NotASavedGame,
+    UnsupportedVersion(u8),
+    Truncated,
+    InvalidData,
+    InvalidPosition(FenError),
+    // The number of the move, counting from 1, and why it was rejected.
+    IllegalMove(usize, ChessError),
+    // Saving only: the field that is too big for the format.
+    DoesNotFit(&'static str),
+}
+
+#[cfg(feature = "pgn")]
+impl fmt::Display for BinaryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BinaryError::NotASavedGame => write!(f, "This is not a saved game."),
+            BinaryError::UnsupportedVersion(version) => write!(f, "Saved games of version {} are not supported.", version),
+            BinaryError::Truncated => write!(f, "The saved game is cut short."),
+            BinaryError::InvalidData => write!(f, "The saved game is corrupt."),
+            BinaryError::InvalidPosition(err) => write!(f, "The starting position is invalid: {}", err),
+            BinaryError::IllegalMove(ply, err) => write!(f, "Move {} of the saved game is illegal: {}", ply, err),
+            BinaryError::DoesNotFit(field) => write!(f, "The {} does not fit in the binary format.", field),
+        }
+    }
+}
+
+#[cfg(feature = "pgn")]
+impl core::error::Error for BinaryError {}
+
+// > add an error enum for engines that fail to choose a move
+#[cfg(feature = "engine")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EngineError {
+    // The engine could not be started, with the reason the system gave.
+    Io(String),
+    Crashed,
+    Timeout,
+    NoMove,
+    InvalidMove(String),
+}
+
+#[cfg(feature = "engine")]
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineError::Io(reason) => write!(f, "The engine could not be started: {}", reason),
+            EngineError::Crashed => write!(f, "The engine stopped running."),
+            EngineError::Timeout => write!(f, "The engine did not answer in time."),
+            EngineError::NoMove => write!(f, "The engine has no move to play."),
+            EngineError::InvalidMove(text) => write!(f, "The engine sent '{}', which is not a move.", text),
+        }
+    }
+}
+
+#[cfg(feature = "engine")]
+impl core::error::Error for EngineError {}
+
+// > add an error enum for publishing a game as it is played
+#[cfg(feature = "network")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BroadcastError {
+    // Writing the file or reaching the endpoint failed, with the reason the system gave.
+    Io(String),
+    InvalidUrl(String),
+    InvalidResponse,
+    Status(u16),
+}
+
+#[cfg(feature = "network")]
+impl From<std::io::Error> for BroadcastError {
+    fn from(err: std::io::Error) -> BroadcastError {
+        BroadcastError::Io(err.to_string())
+    }
+}
+
+#[cfg(feature = "network")]
+impl fmt::Display for BroadcastError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BroadcastError::Io(reason) => write!(f, "The broadcast failed: {}", reason),
+            BroadcastError::InvalidUrl(url) => write!(f, "'{}' is not an http:// URL.", url),
+            BroadcastError::InvalidResponse => write!(f, "The broadcast endpoint did not answer with HTTP."),
+            BroadcastError::Status(status) => write!(f, "The broadcast endpoint answered with status {}.", status),
+        }
+    }
+}
+
+#[cfg(feature = "network")]
+impl core::error::Error for BroadcastError {}
+
+// > add an error enum for move files that cannot be trusted or do not follow the game
+#[cfg(feature = "pgn")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CorrespondenceError {
+    NotAMoveFile,
+    Unsigned,
+    BadSignature,
+    InvalidGame(PgnError),
+    DifferentGame,
+    NoNewMove,
+    // How many moves were added instead of one.
+    TooManyMoves(usize),
+    // A result the player replying could not have brought about, such as the other side resigning.
+    UnearnedResult(crate::GameResult),
+}
+
+#[cfg(feature = "pgn")]
+impl fmt::Display for CorrespondenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CorrespondenceError::NotAMoveFile => write!(f, "This is not a correspondence move file."),
+            CorrespondenceError::Unsigned => write!(f, "The move file has no signature."),
+            CorrespondenceError::BadSignature => {
+                write!(f, "The signature does not match: the file was changed or the passphrase is different.")
+            }
+            CorrespondenceError::InvalidGame(err) => write!(f, "The move file holds an invalid game: {}", err),
+            CorrespondenceError::DifferentGame => write!(f, "The move file does not continue this game."),
+            CorrespondenceError::NoNewMove => write!(f, "The move file has no new move."),
+            CorrespondenceError::TooManyMoves(count) => write!(f, "The move file adds {} moves instead of one.", count),
+            CorrespondenceError::UnearnedResult(result) => {
+                write!(f, "The move file ends the game ({}) in a way your opponent cannot decide alone.", result)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "pgn")]
+impl core::error::Error for CorrespondenceError {}
+
+// > add an error enum for games played over TCP
+#[cfg(feature = "network")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NetworkError {
+    // The reason the system gave.
+    Io(String),
+    Closed,
+    InvalidFrame,
+    UnsupportedVersion(u8),
+    InvalidPosition(FenError),
+    // The move in UCI and why it was rejected.
+    IllegalMove(String, ChessError),
+}
+
+#[cfg(feature = "network")]
+impl From<std::io::Error> for NetworkError {
+    fn from(err: std::io::Error) -> NetworkError {
+        match err.kind() {
+            std::io::ErrorKind::UnexpectedEof => NetworkError::Closed,
+            _ => NetworkError::Io(err.to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "network")]
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkError::Io(reason) => write!(f, "The connection failed: {}", reason),
+            NetworkError::Closed => write!(f, "The other player closed the connection."),
+            NetworkError::InvalidFrame => write!(f, "The other player sent something that is not part of the protocol."),
+            NetworkError::UnsupportedVersion(version) => write!(f, "The other player speaks version {} of the protocol.", version),
+            NetworkError::InvalidPosition(err) => write!(f, "The other player sent an invalid position: {}", err),
+            NetworkError::IllegalMove(text, err) => write!(f, "The other player sent the move {}, which cannot be played: {}", text, err),
+        }
+    }
+}
+
+#[cfg(feature = "network")]
+impl core::error::Error for NetworkError {}
+
+// > add an error enum for NNUE network files that cannot be loaded
+#[cfg(feature = "engine")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NnueError {
+    // The reason the system gave.
+    Io(String),
+    UnsupportedVersion(u32),
+    // The description stored in the file, which names its architecture.
+    UnsupportedArchitecture(String),
+    Truncated,
+}
+
+#[cfg(feature = "engine")]
+impl fmt::Display for NnueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NnueError::Io(reason) => write!(f, "The network cannot be read: {}", reason),
+            NnueError::UnsupportedVersion(version) => write!(f, "Networks of version 0x{:08x} are not supported.", version),
+            NnueError::UnsupportedArchitecture(description) => write!(f, "Only HalfKP 256x2-32-32 networks are supported, not {}.", description),
+            NnueError::Truncated => write!(f, "The network file is cut short."),
+        }
+    }
+}
+
+#[cfg(feature = "engine")]
+impl core::error::Error for NnueError {}
+
+fn piece_name(piece: ChessPiece) -> &'static str {
+    match piece {
+        ChessPiece::Pawn(_) => "A pawn",
+        ChessPiece::Knight(_) => "A knight",
+        ChessPiece::Bishop(_) => "A bishop",
+        ChessPiece::Rook(_) => "A rook",
+        ChessPiece::Queen(_) => "A queen",
+        ChessPiece::King(_) => "A king",
+        ChessPiece::Blank => "An empty square",
+    }
+}
+
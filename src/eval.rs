@@ -0,0 +1,563 @@
+//! # Evaluation
+//!
+//! Judges a position without looking ahead, in centipawns (hundredths of a pawn).
+//! Material counts with the classical values, pawn 1, knight and bishop 3, rook 5 and
+//! queen 9, plus a small bonus for having the move:
+//!
+//! ```text
+//! evaluate(&Position::new()) == 10    // level material, and the side to move is a tempo up
+//! ```
+//!
+//! On top of material, each piece scores by where it stands (piece-square tables):
+//! knights like the centre, rooks like the seventh rank, and so on. Every term has a
+//! middlegame and an endgame value, blended by how much material is left, so the king
+//! hides behind its pawns while the queens are on and walks to the centre once they are
+//! gone. Pawn structure counts doubled pawns against and passed pawns for, more the
+//! further they have advanced, and the king is safer with pawns in front of it and no
+//! open files beside it. The sizes of these terms are set by [`Weights`].
+//!
+//! On x86_64 the material and piece-square values are added up with SSE2, eight squares
+//! at a time, and the pawn terms are worked out a whole board of pawns at a time with
+//! bitboards. Elsewhere the square-by-square version is used instead; the tests check
+//! that the two give the same scores.
+//!
+//! An [`Evaluator`] chooses between this handcrafted evaluation and an NNUE network
+//! (see [`Network`]) at runtime.
+//!
+//! Scores are from the point of view of the side to move, so a search can negate
+//! them from one ply to the next.
+
+use std::sync::Arc;
+
+use crate::nnue::Network;
+use crate::{ChessPiece, Colour, Position};
+
+pub const PAWN: i32 = 100;
+pub const KNIGHT: i32 = 300;
+pub const BISHOP: i32 = 300;
+pub const ROOK: i32 = 500;
+pub const QUEEN: i32 = 900;
+// Worth having the move: about a tenth of a pawn.
+pub(crate) const TEMPO: i32 = 10;
+
+// > give the material value of a piece in centipawns; kings are not counted
+pub fn piece_value(piece: ChessPiece) -> i32 {
+    match piece {
+        ChessPiece::Pawn(_) => PAWN,
+        ChessPiece::Knight(_) => KNIGHT,
+        ChessPiece::Bishop(_) => BISHOP,
+        ChessPiece::Rook(_) => ROOK,
+        ChessPiece::Queen(_) => QUEEN,
+        ChessPiece::King(_) | ChessPiece::Blank => 0,
+    }
+}
+
+// > add up the material one side has on the board
+pub fn material(position: &Position, colour: Colour) -> i32 {
+    position.pieces_of(colour).map(|(_, piece)| piece_value(piece)).sum()
+}
+
+// > give White's material minus Black's, for showing who is ahead
+pub fn material_balance(position: &Position) -> i32 {
+    material(position, Colour::White) - material(position, Colour::Black)
+}
+
+// A middlegame and an endgame value, in centipawns.
+pub type Score = (i32, i32);
+
+// > add the weights of the evaluation terms beyond material and piece-square tables
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Weights {
+    // For each pawn on a file beyond the first.
+    pub doubled_pawn: Score,
+    // For a pawn with no enemy pawn in front of it on its own or a neighbouring file, by how many ranks it has advanced.
+    pub passed_pawn: [Score; 8],
+    // For each of the king's own pawns on the two ranks in front of it.
+    pub king_shield: Score,
+    // For each file beside or under the king with none of its own pawns.
+    pub king_open_file: Score,
+}
+
+impl Default for Weights {
+    fn default() -> Weights {
+        Weights {
+            doubled_pawn: (-10, -20),
+            passed_pawn: [(0, 0), (5, 10), (5, 15), (10, 25), (20, 45), (35, 75), (60, 120), (0, 0)],
+            king_shield: (10, 0),
+            king_open_file: (-20, 0),
+        }
+    }
+}
+
+// How much each piece counts towards the middlegame; the starting position has 24.
+const KNIGHT_PHASE: i32 = 1;
+const BISHOP_PHASE: i32 = 1;
+const ROOK_PHASE: i32 = 2;
+const QUEEN_PHASE: i32 = 4;
+const MAX_PHASE: i32 = 24;
+
+// Piece-square tables, as White sees the board: a8 first, h1 last. Black uses them upside down.
+const PAWN_MIDDLEGAME: [i32; 64] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+    50,  50,  50,  50,  50,  50,  50,  50,
+    10,  10,  20,  30,  30,  20,  10,  10,
+     5,   5,  10,  25,  25,  10,   5,   5,
+     0,   0,   0,  20,  20,   0,   0,   0,
+     5,  -5, -10,   0,   0, -10,  -5,   5,
+     5,  10,  10, -20, -20,  10,  10,   5,
+     0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+const PAWN_ENDGAME: [i32; 64] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+    80,  80,  80,  80,  80,  80,  80,  80,
+    50,  50,  50,  50,  50,  50,  50,  50,
+    30,  30,  30,  30,  30,  30,  30,  30,
+    20,  20,  20,  20,  20,  20,  20,  20,
+    10,  10,  10,  10,  10,  10,  10,  10,
+     5,   5,   5,   5,   5,   5,   5,   5,
+     0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+const KNIGHT_TABLE: [i32; 64] = [
+   -50, -40, -30, -30, -30, -30, -40, -50,
+   -40, -20,   0,   0,   0,   0, -20, -40,
+   -30,   0,  10,  15,  15,  10,   0, -30,
+   -30,   5,  15,  20,  20,  15,   5, -30,
+   -30,   0,  15,  20,  20,  15,   0, -30,
+   -30,   5,  10,  15,  15,  10,   5, -30,
+   -40, -20,   0,   5,   5,   0, -20, -40,
+   -50, -40, -30, -30, -30, -30, -40, -50,
+];
+
+const BISHOP_TABLE: [i32; 64] = [
+   -20, -10, -10, -10, -10, -10, -10, -20,
+   -10,   0,   0,   0,   0,   0,   0, -10,
+   -10,   0,   5,  10,  10,   5,   0, -10,
+   -10,   5,   5,  10,  10,   5,   5, -10,
+   -10,   0,  10,  10,  10,  10,   0, -10,
+   -10,  10,  10,  10,  10,  10,  10, -10,
+   -10,   5,   0,   0,   0,   0,   5, -10,
+   -20, -10, -10, -10, -10, -10, -10, -20,
+];
+
+const ROOK_TABLE: [i32; 64] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+     5,  10,  10,  10,  10,  10,  10,   5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+     0,   0,   0,   5,   5,   0,   0,   0,
+];
+
+const QUEEN_TABLE: [i32; 64] = [
+   -20, -10, -10,  -5,  -5, -10, -10, -20,
+   -10,   0,   0,   0,   0,   0,   0, -10,
+   -10,   0,   5,   5,   5,   5,   0, -10,
+    -5,   0,   5,   5,   5,   5,   0,  -5,
+    -5,   0,   5,   5,   5,   5,   0,  -5,
+   -10,   0,   5,   5,   5,   5,   0, -10,
+   -10,   0,   0,   0,   0,   0,   0, -10,
+   -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+
+const KING_MIDDLEGAME: [i32; 64] = [
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -20, -30, -30, -40, -40, -30, -30, -20,
+   -10, -20, -20, -20, -20, -20, -20, -10,
+    20,  20,   0,   0,   0,   0,  20,  20,
+    20,  30,  10,   0,   0,  10,  30,  20,
+];
+
+const KING_ENDGAME: [i32; 64] = [
+   -50, -40, -30, -20, -20, -30, -40, -50,
+   -30, -20, -10,   0,   0, -10, -20, -30,
+   -30, -10,  20,  30,  30,  20, -10, -30,
+   -30, -10,  30,  40,  40,  30, -10, -30,
+   -30, -10,  30,  40,  40,  30, -10, -30,
+   -30, -10,  20,  30,  30,  20, -10, -30,
+   -30, -30,   0,   0,   0,   0, -30, -30,
+   -50, -30, -30, -30, -30, -30, -30, -50,
+];
+
+// > give the middlegame and endgame piece-square values of a piece on a square
+#[cfg(any(test, not(target_arch = "x86_64")))]
+fn piece_square(piece: ChessPiece, row: usize, col: usize) -> Score {
+    // The tables start at White's far side, so White's rows count down and Black's count up.
+    let index = match piece.colour() {
+        Some(Colour::White) => (7 - row) * 8 + col,
+        _ => row * 8 + col,
+    };
+    match piece {
+        ChessPiece::Pawn(_) => (PAWN_MIDDLEGAME[index], PAWN_ENDGAME[index]),
+        ChessPiece::Knight(_) => (KNIGHT_TABLE[index], KNIGHT_TABLE[index]),
+        ChessPiece::Bishop(_) => (BISHOP_TABLE[index], BISHOP_TABLE[index]),
+        ChessPiece::Rook(_) => (ROOK_TABLE[index], ROOK_TABLE[index]),
+        ChessPiece::Queen(_) => (QUEEN_TABLE[index], QUEEN_TABLE[index]),
+        ChessPiece::King(_) => (KING_MIDDLEGAME[index], KING_ENDGAME[index]),
+        ChessPiece::Blank => (0, 0),
+    }
+}
+
+// > measure how far the game is from the endgame, from 0 with only kings and pawns up to 24 at the start
+fn phase(position: &Position) -> i32 {
+    let phase = position
+        .board
+        .iter()
+        .flatten()
+        .map(|piece| match piece {
+            ChessPiece::Knight(_) => KNIGHT_PHASE,
+            ChessPiece::Bishop(_) => BISHOP_PHASE,
+            ChessPiece::Rook(_) => ROOK_PHASE,
+            ChessPiece::Queen(_) => QUEEN_PHASE,
+            _ => 0,
+        })
+        .sum::<i32>();
+    // Promotions can take it past the starting count.
+    phase.min(MAX_PHASE)
+}
+
+// > score one side's pieces, pawn structure and king safety, as middlegame and endgame values
+fn score_side(position: &Position, colour: Colour, weights: &Weights) -> Score {
+    #[cfg(target_arch = "x86_64")]
+    return simd::score_side(position, colour, weights);
+    #[cfg(not(target_arch = "x86_64"))]
+    score_side_scalar(position, colour, weights)
+}
+
+// > score one side square by square: the fallback where there is no SIMD, and what the vectorised version is tested against
+#[cfg(any(test, not(target_arch = "x86_64")))]
+fn score_side_scalar(position: &Position, colour: Colour, weights: &Weights) -> Score {
+    let (mut middlegame, mut endgame) = (0, 0);
+    let mut add = |(mg, eg): Score, times: i32| {
+        middlegame += mg * times;
+        endgame += eg * times;
+    };
+
+    // This side's pawns on each file, and its king.
+    let mut pawns = [0; 8];
+    let mut king = None;
+    for (square, piece) in position.pieces_of(colour) {
+        let (row, col) = square.to_row_col();
+        match piece {
+            ChessPiece::Pawn(_) => pawns[col] += 1,
+            ChessPiece::King(_) => king = Some((row, col)),
+            _ => {}
+        }
+        add((piece_value(piece), piece_value(piece)), 1);
+        add(piece_square(piece, row, col), 1);
+    }
+
+    // Rows counted from this side's first rank.
+    let ahead = |row: usize| if colour == Colour::White { row } else { 7 - row };
+    for (row, rank) in position.board.iter().enumerate() {
+        for (col, &piece) in rank.iter().enumerate() {
+            if piece != ChessPiece::Pawn(colour) {
+                continue;
+            }
+            let passed = (col.saturating_sub(1)..=(col + 1).min(7)).all(|file| {
+                (0..8).all(|other| position.board[other][file] != ChessPiece::Pawn(colour.opponent()) || ahead(other) <= ahead(row))
+            });
+            if passed {
+                add(weights.passed_pawn[ahead(row)], 1);
+            }
+        }
+    }
+    for count in pawns {
+        add(weights.doubled_pawn, (count - 1).max(0));
+    }
+
+    if let Some((king_row, king_col)) = king {
+        for file in king_col.saturating_sub(1)..=(king_col + 1).min(7) {
+            let shield = (0..8)
+                .filter(|&row| position.board[row][file] == ChessPiece::Pawn(colour) && (1..=2).contains(&(ahead(row) as i32 - ahead(king_row) as i32)))
+                .count();
+            add(weights.king_shield, shield as i32);
+            if position.board.iter().all(|rank| rank[file] != ChessPiece::Pawn(colour)) {
+                add(weights.king_open_file, 1);
+            }
+        }
+    }
+    (middlegame, endgame)
+}
+
+// > score one side with SSE2, which every x86_64 processor has: the material and piece-square values of eight
+// > squares at a time in 16-bit lanes, and the pawn structure and king safety a whole board of pawns at a time
+#[cfg(target_arch = "x86_64")]
+mod simd {
+    use std::arch::x86_64::{__m128i, _mm_add_epi16, _mm_and_si128, _mm_cmpeq_epi16, _mm_loadu_si128, _mm_madd_epi16, _mm_set1_epi16, _mm_setzero_si128, _mm_storeu_si128};
+
+    use super::*;
+    use crate::bitboard::{self, Bitboard};
+
+    // For each kind of piece from pawn to king, its value plus its piece-square value on each square, middlegame
+    // then endgame. No lane can overflow: it adds up at most eight squares of a few hundred each.
+    static TABLES: [[[i16; 64]; 6]; 2] = tables();
+
+    const fn tables() -> [[[i16; 64]; 6]; 2] {
+        let values = [PAWN, KNIGHT, BISHOP, ROOK, QUEEN, 0];
+        let middlegame = [&PAWN_MIDDLEGAME, &KNIGHT_TABLE, &BISHOP_TABLE, &ROOK_TABLE, &QUEEN_TABLE, &KING_MIDDLEGAME];
+        let endgame = [&PAWN_ENDGAME, &KNIGHT_TABLE, &BISHOP_TABLE, &ROOK_TABLE, &QUEEN_TABLE, &KING_ENDGAME];
+        let mut tables = [[[0; 64]; 6]; 2];
+        let mut kind = 0;
+        while kind < 6 {
+            let mut square = 0;
+            while square < 64 {
+                tables[0][kind][square] = (values[kind] + middlegame[kind][square]) as i16;
+                tables[1][kind][square] = (values[kind] + endgame[kind][square]) as i16;
+                square += 1;
+            }
+            kind += 1;
+        }
+        tables
+    }
+
+    const FILE_A: Bitboard = 0x0101_0101_0101_0101;
+    const FILE_H: Bitboard = FILE_A << 7;
+
+    pub(super) fn score_side(position: &Position, colour: Colour, weights: &Weights) -> Score {
+        // The board as this side's tables see it, 1 for a pawn up to 6 for a king; and both sides' pawns and this
+        // side's king with the board turned round for Black, so that this side always plays up the board.
+        let mut kinds = [0; 64];
+        let (mut pawns, mut enemy_pawns, mut king) = (0, 0, None);
+        for (row, rank) in position.board.iter().enumerate() {
+            for (col, &piece) in rank.iter().enumerate() {
+                let (row, index) = match colour {
+                    Colour::White => (row, (7 - row) * 8 + col),
+                    Colour::Black => (7 - row, row * 8 + col),
+                };
+                match piece {
+                    ChessPiece::Pawn(owner) if owner != colour => enemy_pawns |= bitboard::bit(row, col),
+                    _ if piece.colour() != Some(colour) => {}
+                    ChessPiece::Pawn(_) => (kinds[index], pawns) = (1, pawns | bitboard::bit(row, col)),
+                    ChessPiece::Knight(_) => kinds[index] = 2,
+                    ChessPiece::Bishop(_) => kinds[index] = 3,
+                    ChessPiece::Rook(_) => kinds[index] = 4,
+                    ChessPiece::Queen(_) => kinds[index] = 5,
+                    ChessPiece::King(_) => (kinds[index], king) = (6, Some((row, col))),
+                    ChessPiece::Blank => {}
+                }
+            }
+        }
+        // SAFETY: SSE2 is part of x86_64, so every processor this is built for has it.
+        let (middlegame, endgame) = unsafe { piece_squares(&kinds) };
+        let (pawn_middlegame, pawn_endgame) = pawn_structure(pawns, enemy_pawns, king, weights);
+        (middlegame + pawn_middlegame, endgame + pawn_endgame)
+    }
+
+    // > add up the values of the pieces on their squares, comparing eight squares with each kind of piece at once
+    #[target_feature(enable = "sse2")]
+    fn piece_squares(kinds: &[i16; 64]) -> Score {
+        let (mut middlegame, mut endgame) = (_mm_setzero_si128(), _mm_setzero_si128());
+        for chunk in 0..8 {
+            let squares = load(&kinds[chunk * 8..]);
+            for (kind, (middlegame_table, endgame_table)) in (1..).zip(TABLES[0].iter().zip(&TABLES[1])) {
+                let here = _mm_cmpeq_epi16(squares, _mm_set1_epi16(kind));
+                middlegame = _mm_add_epi16(middlegame, _mm_and_si128(here, load(&middlegame_table[chunk * 8..])));
+                endgame = _mm_add_epi16(endgame, _mm_and_si128(here, load(&endgame_table[chunk * 8..])));
+            }
+        }
+        (sum(middlegame), sum(endgame))
+    }
+
+    #[target_feature(enable = "sse2")]
+    fn load(lanes: &[i16]) -> __m128i {
+        assert!(lanes.len() >= 8);
+        // SAFETY: there are at least eight lanes to read, and the load needs no alignment.
+        unsafe { _mm_loadu_si128(lanes.as_ptr().cast()) }
+    }
+
+    // > add up the eight lanes, widening to 32 bits first
+    #[target_feature(enable = "sse2")]
+    fn sum(lanes: __m128i) -> i32 {
+        let mut sums = [0i32; 4];
+        // SAFETY: the four 32-bit sums fill the array exactly, and the store needs no alignment.
+        unsafe { _mm_storeu_si128(sums.as_mut_ptr().cast(), _mm_madd_epi16(lanes, _mm_set1_epi16(1))) };
+        sums.iter().sum()
+    }
+
+    // > score doubled and passed pawns and the pawns around the king with bitboards, for a side playing up the board
+    fn pawn_structure(pawns: Bitboard, enemy_pawns: Bitboard, king: Option<(usize, usize)>, weights: &Weights) -> Score {
+        let (mut middlegame, mut endgame) = (0, 0);
+        let mut add = |(mg, eg): Score, times: u32| {
+            middlegame += mg * times as i32;
+            endgame += eg * times as i32;
+        };
+
+        // One bit for each file with a pawn on it: every pawn beyond the first on a file is doubled.
+        let files = fill_down(pawns) & 0xff;
+        add(weights.doubled_pawn, pawns.count_ones() - files.count_ones());
+
+        // Every square below an enemy pawn on its own and the neighbouring files; a pawn on none of them is passed.
+        let held = fill_down(enemy_pawns >> 8);
+        let held = held | (held << 1 & !FILE_A) | (held >> 1 & !FILE_H);
+        for square in bitboard::squares(pawns & !held) {
+            add(weights.passed_pawn[square / 8], 1);
+        }
+
+        if let Some((king_row, king_col)) = king {
+            // The king's file and those either side, on the first rank; and the two ranks in front of the king.
+            let beside: Bitboard = (0b111 << king_col >> 1) & 0xff;
+            let shield_rows: Bitboard = 0xffff << 8 << (king_row * 8);
+            add(weights.king_shield, (pawns & fill_up(beside) & shield_rows).count_ones());
+            add(weights.king_open_file, beside.count_ones() - (files & beside).count_ones());
+        }
+        (middlegame, endgame)
+    }
+
+    // > spread every bit to all the squares below it on its file
+    fn fill_down(mut bits: Bitboard) -> Bitboard {
+        bits |= bits >> 8;
+        bits |= bits >> 16;
+        bits | bits >> 32
+    }
+
+    // > spread every bit to all the squares above it on its file
+    fn fill_up(mut bits: Bitboard) -> Bitboard {
+        bits |= bits << 8;
+        bits |= bits << 16;
+        bits | bits << 32
+    }
+}
+
+// > add the ways a search can evaluate positions: the handcrafted terms with their weights, or an NNUE network
+#[derive(Clone)]
+pub enum Evaluator {
+    Classical(Weights),
+    Nnue(Arc<Network>),
+}
+
+impl Default for Evaluator {
+    fn default() -> Evaluator {
+        Evaluator::Classical(Weights::default())
+    }
+}
+
+// > evaluate the position in centipawns for the side to move
+pub fn evaluate(position: &Position) -> i32 {
+    evaluate_with(position, &Weights::default())
+}
+
+// > evaluate the position for the side to move with the given weights, blending middlegame and endgame by phase
+pub fn evaluate_with(position: &Position, weights: &Weights) -> i32 {
+    let player = position.current_player;
+    let (own_mg, own_eg) = score_side(position, player, weights);
+    let (their_mg, their_eg) = score_side(position, player.opponent(), weights);
+    let phase = phase(position);
+    ((own_mg - their_mg) * phase + (own_eg - their_eg) * (MAX_PHASE - phase)) / MAX_PHASE + TEMPO
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GameState;
+
+    #[test]
+    fn test_start_position() {
+        let position = GameState::new();
+        assert_eq!(material(&position, Colour::White), 8 * PAWN + 2 * (KNIGHT + BISHOP + ROOK) + QUEEN);
+        assert_eq!(material_balance(&position), 0);
+        assert_eq!(evaluate(&position), TEMPO);
+    }
+
+    #[test]
+    fn test_side_to_move_perspective() {
+        // White is a rook up.
+        let white_to_move = GameState::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        let black_to_move = GameState::from_fen("4k3/8/8/8/8/8/8/R3K3 b - - 0 1").unwrap();
+        assert_eq!(material_balance(&white_to_move), ROOK);
+        assert_eq!(evaluate(&white_to_move), ROOK + TEMPO);
+        assert_eq!(evaluate(&black_to_move), -ROOK + TEMPO);
+    }
+
+    #[test]
+    fn test_colours_are_symmetric() {
+        // The same position with the colours swapped and the board turned over.
+        let position = GameState::from_fen("r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP1B1PPP/R2QKB1R w KQ - 0 8").unwrap();
+        let mirrored = GameState::from_fen("r2qkb1r/pp1b1ppp/2n1pn2/2pp4/3P4/2N1PN2/PP2BPPP/R1BQ1RK1 b kq - 0 8").unwrap();
+        assert_eq!(evaluate(&position), evaluate(&mirrored));
+    }
+
+    #[test]
+    fn test_piece_squares_and_phase() {
+        // A knight is better in the centre than in the corner.
+        let centre = GameState::from_fen("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1").unwrap();
+        let corner = GameState::from_fen("4k3/8/8/8/8/8/8/N3K3 w - - 0 1").unwrap();
+        assert!(evaluate(&centre) > evaluate(&corner));
+        // With only kings and pawns left the king belongs in the centre...
+        let central_king = GameState::from_fen("4k3/pppp4/8/8/4K3/8/PPPP4/8 w - - 0 1").unwrap();
+        let castled_king = GameState::from_fen("4k3/pppp4/8/8/8/8/PPPP4/6K1 w - - 0 1").unwrap();
+        assert!(evaluate(&central_king) > evaluate(&castled_king));
+        // ...but with the queens and rooks on, it belongs behind its pawns.
+        let central_king = GameState::from_fen("r2qk2r/ppp2ppp/8/8/4K3/8/PPP2PPP/R2Q3R w - - 0 1").unwrap();
+        let castled_king = GameState::from_fen("r2qk2r/ppp2ppp/8/8/8/8/PPP2PPP/R2Q1RK1 w - - 0 1").unwrap();
+        assert!(evaluate(&central_king) < evaluate(&castled_king));
+        assert_eq!(phase(&GameState::new()), MAX_PHASE);
+        assert_eq!(phase(&central_king), 2 * (QUEEN_PHASE + 2 * ROOK_PHASE));
+    }
+
+    #[test]
+    fn test_pawn_structure() {
+        let weights = Weights::default();
+        // The same pawns, doubled on the e-file or side by side.
+        let doubled = GameState::from_fen("4k3/4p3/8/8/8/4P3/4P3/4K3 w - - 0 1").unwrap();
+        let side_by_side = GameState::from_fen("4k3/4p3/8/8/8/8/3PP3/4K3 w - - 0 1").unwrap();
+        assert!(evaluate(&doubled) < evaluate(&side_by_side));
+        let (_, doubled_eg) = score_side(&doubled, Colour::White, &weights);
+        let (_, without_eg) = score_side(&doubled, Colour::White, &Weights { doubled_pawn: (0, 0), ..weights });
+        assert_eq!(doubled_eg - without_eg, weights.doubled_pawn.1);
+
+        // The a-pawn is passed; the d-pawn is held by the pawn in front of it on the e-file.
+        let position = GameState::from_fen("4k3/8/4p3/P2P4/8/8/8/4K3 w - - 0 1").unwrap();
+        let (_, with_passed) = score_side(&position, Colour::White, &weights);
+        let (_, without_passed) = score_side(&position, Colour::White, &Weights { passed_pawn: [(0, 0); 8], ..weights });
+        assert_eq!(with_passed - without_passed, weights.passed_pawn[4].1);
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn test_simd_matches_scalar() {
+        let weights = [Weights::default(), Weights { doubled_pawn: (-7, -31), king_shield: (13, 3), king_open_file: (-17, -5), ..Weights::default() }];
+        let fens = crate::perft::SUITE.iter().map(|&(_, fen, _)| fen).chain([
+            "4k3/pppp4/8/8/4K3/8/PPPP4/8 w - - 0 1",
+            "4k3/8/4p3/P2P4/8/8/8/4K3 w - - 0 1",
+            "6k1/8/8/8/8/8/5P1P/6K1 w - - 0 1",
+            // Kings on the edge files and back ranks, doubled and passed pawns, and a promoted queen.
+            "K7/1P5p/8/p7/P6P/6pP/1Q4P1/7k b - - 0 1",
+        ]);
+        // Each position and every one a move away from it.
+        for fen in fens {
+            let position = GameState::from_fen(fen).unwrap();
+            let after = position.legal_moves().into_iter().map(|mv| {
+                let mut next = position.clone();
+                next.apply_move(mv);
+                next
+            });
+            for position in std::iter::once(position.clone()).chain(after) {
+                for (weights, colour) in weights.iter().flat_map(|weights| [(weights, Colour::White), (weights, Colour::Black)]) {
+                    assert_eq!(simd::score_side(&position, colour, weights), score_side_scalar(&position, colour, weights), "{:?} in {}", colour, position.to_fen());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_king_safety() {
+        let weights = Weights::default();
+        let unweighted = Weights { king_shield: (0, 0), king_open_file: (0, 0), ..weights };
+        let king_safety = |fen: &str| {
+            let position = GameState::from_fen(fen).unwrap();
+            score_side(&position, Colour::White, &weights).0 - score_side(&position, Colour::White, &unweighted).0
+        };
+        // Three pawns in front of the king, then one gone leaving an open file.
+        assert_eq!(king_safety("6k1/8/8/8/8/8/5PPP/6K1 w - - 0 1"), 3 * weights.king_shield.0);
+        assert_eq!(king_safety("6k1/8/8/8/8/8/5P1P/6K1 w - - 0 1"), 2 * weights.king_shield.0 + weights.king_open_file.0);
+        // Pawns too far up the board no longer shield the king.
+        assert_eq!(king_safety("6k1/8/8/8/5PPP/8/8/6K1 w - - 0 1"), 0);
+    }
+}
@@ -0,0 +1,103 @@
+//! # Game Events
+//!
+//! A [`Game`](crate::Game) tells whoever is watching it what happens, so a GUI, a
+//! logger or a network layer can react to moves instead of polling the game and
+//! working out what changed:
+//!
+//! ```
+//! use overload_vibe_coding::{Game, GameEvent, GameState, Move, Square};
+//!
+//! let mut game = Game::from_position(GameState::new());
+//! let events = game.events();
+//! game.play(Move::new(Square::E2, Square::E4)).unwrap();
+//! assert!(matches!(events.try_recv(), Ok(GameEvent::MovePlayed { ref san, .. }) if san == "e4"));
+//! ```
+//!
+//! Events are delivered as they happen, to callbacks given to
+//! [`Game::subscribe`](crate::Game::subscribe) or down channels from
+//! [`Game::events`](crate::Game::events). A move comes first as `MovePlayed`, then
+//! `Capture`, `Promotion` and `Check` as they apply, then `GameEnded` if it ended the
+//! game. A copy of a game starts with nobody watching it. Channels need the `std`
+//! feature; callbacks work without it.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::sync::mpsc::Sender;
+
+use crate::{ChessPiece, Colour, GameResult, Move};
+
+// > add an enum for the things that can happen in a game
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameEvent {
+    MovePlayed { mv: Move, san: String, by: Colour },
+    // The piece taken, which for en passant is not on the square moved to.
+    Capture { mv: Move, piece: ChessPiece },
+    Promotion { mv: Move, piece: ChessPiece },
+    // The side now in check.
+    Check(Colour),
+    GameEnded(GameResult),
+    MoveTakenBack(Move),
+}
+
+type Callback = Box<dyn FnMut(&GameEvent) + Send + Sync>;
+
+// > add the list of callbacks and channels watching a game
+#[derive(Default)]
+pub(crate) struct Observers {
+    callbacks: Vec<Callback>,
+    #[cfg(feature = "std")]
+    channels: Vec<Sender<GameEvent>>,
+}
+
+impl Observers {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.callbacks.is_empty() && self.channel_count() == 0
+    }
+
+    pub(crate) fn subscribe(&mut self, callback: impl FnMut(&GameEvent) + Send + Sync + 'static) {
+        self.callbacks.push(Box::new(callback));
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn add_channel(&mut self, sender: Sender<GameEvent>) {
+        self.channels.push(sender);
+    }
+
+    // > tell every observer about an event, forgetting channels whose receiver has gone
+    pub(crate) fn notify(&mut self, event: GameEvent) {
+        for callback in &mut self.callbacks {
+            callback(&event);
+        }
+        #[cfg(feature = "std")]
+        self.channels.retain(|sender| sender.send(event.clone()).is_ok());
+    }
+
+    fn channel_count(&self) -> usize {
+        #[cfg(feature = "std")]
+        return self.channels.len();
+        #[cfg(not(feature = "std"))]
+        0
+    }
+}
+
+impl fmt::Debug for Observers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Observers({} callbacks, {} channels)", self.callbacks.len(), self.channel_count())
+    }
+}
+
+// Observers belong to one game, so a copy is not watched and they never make two games differ.
+impl Clone for Observers {
+    fn clone(&self) -> Observers {
+        Observers::default()
+    }
+}
+
+impl PartialEq for Observers {
+    fn eq(&self, _: &Observers) -> bool {
+        true
+    }
+}
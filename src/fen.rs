@@ -0,0 +1,438 @@
+//! # FEN
+//!
+//! Reads and writes positions in Forsyth-Edwards Notation, for example the starting position:
+//!
+//! `rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1`
+//!
+//! The six fields are piece placement (from rank 8 down to rank 1), side to move,
+//! castling rights, en passant square, half-move clock and full-move number.
+//! The two clocks may be left off, as they are in EPD.
+//!
+//! Castling rights may also name the rook by its file, as in X-FEN (`K` means the
+//! outermost kingside rook, a letter such as `G` a rook that is not outermost) and
+//! Shredder-FEN (`HAha`, always by file).
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::error::FenError;
+use crate::{CastlingRights, ChessPiece, Colour, GameState, Position, Square};
+
+impl GameState {
+    // > build a GameState from a FEN string
+    pub fn from_fen(fen: &str) -> Result<GameState, FenError> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() != 4 && fields.len() != 6 {
+            return Err(FenError::WrongFieldCount(fields.len()));
+        }
+
+        let board = parse_placement(fields[0])?;
+        let current_player = match fields[1] {
+            "w" => Colour::White,
+            "b" => Colour::Black,
+            other => return Err(FenError::InvalidSideToMove(other.to_string())),
+        };
+        let castling = parse_castling(fields[2], &board)?;
+        let en_passant = parse_en_passant(fields[3], &board, current_player)?;
+        let (halfmove_clock, fullmove_number) = match fields.get(4..6) {
+            Some([halfmove, fullmove]) => (
+                halfmove.parse().map_err(|_| FenError::InvalidHalfmoveClock(halfmove.to_string()))?,
+                fullmove
+                    .parse()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| FenError::InvalidFullmoveNumber(fullmove.to_string()))?,
+            ),
+            _ => (0, 1),
+        };
+
+        let position = Position {
+            board,
+            current_player,
+            castling,
+            en_passant,
+            halfmove_clock,
+            fullmove_number,
+            ..Position::new()
+        };
+        position.finish_setup().map(GameState::from_position).map_err(FenError::IllegalPosition)
+    }
+}
+
+impl Position {
+    // > export the current position as a FEN string, naming castling rooks by file only where X-FEN needs it
+    pub fn to_fen(&self) -> String {
+        self.fen_with_castling(false)
+    }
+
+    // > export the current position as Shredder-FEN, which always names castling rooks by file
+    pub fn to_shredder_fen(&self) -> String {
+        self.fen_with_castling(true)
+    }
+
+    fn fen_with_castling(&self, shredder: bool) -> String {
+        let placement: Vec<String> = (0..8).rev().map(|row| rank_to_fen(&self.board[row])).collect();
+        let side = match self.current_player {
+            Colour::White => "w",
+            Colour::Black => "b",
+        };
+        let mut castling = String::new();
+        for (colour, kingside) in [(Colour::White, true), (Colour::White, false), (Colour::Black, true), (Colour::Black, false)] {
+            if let Some(rook_col) = self.castling.rook_col(colour, kingside) {
+                let row = if colour == Colour::White { 0 } else { 7 };
+                let rook = ChessPiece::Rook(colour);
+                let outermost = match kingside {
+                    true => !(rook_col + 1..8).any(|col| self.board[row][col] == rook),
+                    false => !(0..rook_col).any(|col| self.board[row][col] == rook),
+                };
+                let letter = match (shredder || !outermost, kingside) {
+                    (true, _) => (b'a' + rook_col as u8) as char,
+                    (false, true) => 'k',
+                    (false, false) => 'q',
+                };
+                castling.push(if colour == Colour::White { letter.to_ascii_uppercase() } else { letter });
+            }
+        }
+        if castling.is_empty() {
+            castling.push('-');
+        }
+        let en_passant = match self.en_passant {
+            Some(square) => square.to_string(),
+            None => "-".to_string(),
+        };
+        format!(
+            "{} {} {} {} {} {}",
+            placement.join("/"),
+            side,
+            castling,
+            en_passant,
+            self.halfmove_clock,
+            self.fullmove_number
+        )
+    }
+}
+
+// > write one rank of the board, using digits for runs of empty squares
+fn rank_to_fen(rank: &[ChessPiece; 8]) -> String {
+    let mut text = String::new();
+    let mut empty = 0;
+    for &piece in rank {
+        match piece_to_char(piece) {
+            Some(c) => {
+                if empty > 0 {
+                    text.push_str(&empty.to_string());
+                    empty = 0;
+                }
+                text.push(c);
+            }
+            None => empty += 1,
+        }
+    }
+    if empty > 0 {
+        text.push_str(&empty.to_string());
+    }
+    text
+}
+
+// > convert a ChessPiece into its FEN letter, uppercase for White
+pub fn piece_to_char(piece: ChessPiece) -> Option<char> {
+    let (c, colour) = match piece {
+        ChessPiece::Pawn(colour) => ('p', colour),
+        ChessPiece::Knight(colour) => ('n', colour),
+        ChessPiece::Bishop(colour) => ('b', colour),
+        ChessPiece::Rook(colour) => ('r', colour),
+        ChessPiece::Queen(colour) => ('q', colour),
+        ChessPiece::King(colour) => ('k', colour),
+        ChessPiece::Blank => return None,
+    };
+    match colour {
+        Colour::White => Some(c.to_ascii_uppercase()),
+        Colour::Black => Some(c),
+    }
+}
+
+// > convert a FEN letter such as 'N' or 'p' into a ChessPiece
+fn piece_from_char(c: char) -> Option<ChessPiece> {
+    let colour = if c.is_ascii_uppercase() { Colour::White } else { Colour::Black };
+    match c.to_ascii_lowercase() {
+        'p' => Some(ChessPiece::Pawn(colour)),
+        'n' => Some(ChessPiece::Knight(colour)),
+        'b' => Some(ChessPiece::Bishop(colour)),
+        'r' => Some(ChessPiece::Rook(colour)),
+        'q' => Some(ChessPiece::Queen(colour)),
+        'k' => Some(ChessPiece::King(colour)),
+        _ => None,
+    }
+}
+
+// > parse the piece placement field, which lists rank 8 first
+fn parse_placement(placement: &str) -> Result<[[ChessPiece; 8]; 8], FenError> {
+    let ranks: Vec<&str> = placement.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(FenError::WrongRankCount(ranks.len()));
+    }
+
+    let mut board = [[ChessPiece::Blank; 8]; 8];
+    for (i, rank) in ranks.iter().enumerate() {
+        let row = 7 - i;
+        let mut col = 0;
+        for c in rank.chars() {
+            if let Some(empty) = c.to_digit(10).filter(|n| (1..=8).contains(n)) {
+                col += empty as usize;
+            } else {
+                let piece = piece_from_char(c).ok_or(FenError::InvalidPiece(c))?;
+                if col < 8 {
+                    board[row][col] = piece;
+                }
+                col += 1;
+            }
+            if col > 8 {
+                break;
+            }
+        }
+        if col != 8 {
+            return Err(FenError::WrongRankLength(row + 1, rank.to_string()));
+        }
+    }
+    Ok(board)
+}
+
+// > parse the castling field in FEN, X-FEN or Shredder-FEN, checking that each right still has its king and rook at home
+fn parse_castling(field: &str, board: &[[ChessPiece; 8]; 8]) -> Result<CastlingRights, FenError> {
+    let mut castling = CastlingRights::NONE;
+    if field == "-" {
+        return Ok(castling);
+    }
+
+    let invalid = || FenError::InvalidCastling(field.to_string());
+    for c in field.chars() {
+        let (colour, row) = if c.is_ascii_uppercase() { (Colour::White, 0) } else { (Colour::Black, 7) };
+        let king_col = castling_king(board, colour).ok_or_else(invalid)?;
+        let rook_col = match c.to_ascii_lowercase() {
+            'k' => castling_rook(board, colour, true),
+            'q' => castling_rook(board, colour, false),
+            file @ 'a'..='h' => Some(file as usize - 'a' as usize).filter(|&col| col != king_col && board[row][col] == ChessPiece::Rook(colour)),
+            _ => None,
+        }
+        .ok_or_else(invalid)?;
+        let kingside = rook_col > king_col;
+        if castling.allows(colour, kingside) {
+            return Err(invalid());
+        }
+        castling.set(colour, kingside, Some(rook_col));
+        castling.set_king_col(colour, king_col);
+    }
+    Ok(castling)
+}
+
+// > parse the en passant field, which must be just behind a pawn that has made a double push
+fn parse_en_passant(
+    field: &str,
+    board: &[[ChessPiece; 8]; 8],
+    current_player: Colour,
+) -> Result<Option<Square>, FenError> {
+    if field == "-" {
+        return Ok(None);
+    }
+
+    let square: Square = field.parse().map_err(|_| FenError::InvalidEnPassant(field.to_string()))?;
+    if !is_en_passant_square(board, current_player, square) {
+        return Err(FenError::InvalidEnPassant(field.to_string()));
+    }
+    Ok(Some(square))
+}
+
+// > tell whether a square is empty and just behind an enemy pawn that could have made a double push
+pub(crate) fn is_en_passant_square(board: &[[ChessPiece; 8]; 8], current_player: Colour, square: Square) -> bool {
+    let (row, col) = square.to_row_col();
+    let (expected_row, pawn_row, pawn) = match current_player {
+        Colour::White => (5, 4, ChessPiece::Pawn(Colour::Black)),
+        Colour::Black => (2, 3, ChessPiece::Pawn(Colour::White)),
+    };
+    row == expected_row && board[pawn_row][col] == pawn && board[row][col] == ChessPiece::Blank
+}
+
+// > find the column of a king that could castle: on its back rank, between the b and g files as in Chess960
+pub(crate) fn castling_king(board: &[[ChessPiece; 8]; 8], colour: Colour) -> Option<usize> {
+    let row = match colour {
+        Colour::White => 0,
+        Colour::Black => 7,
+    };
+    (1..7).find(|&col| board[row][col] == ChessPiece::King(colour))
+}
+
+// > find the rook a castling right on one side would use: the outermost one on that side of a king on its
+// > back rank, as the K and Q of FEN and X-FEN mean
+pub(crate) fn castling_rook(board: &[[ChessPiece; 8]; 8], colour: Colour, kingside: bool) -> Option<usize> {
+    let row = match colour {
+        Colour::White => 0,
+        Colour::Black => 7,
+    };
+    let king_col = castling_king(board, colour)?;
+    let is_rook = |col: &usize| board[row][*col] == ChessPiece::Rook(colour);
+    if kingside { (king_col + 1..8).rev().find(is_rook) } else { (0..king_col).find(is_rook) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ChessError;
+    use crate::{DrawReason, GameResult, Move};
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    #[test]
+    fn test_start_position_matches_new() {
+        assert_eq!(GameState::from_fen(START).unwrap(), GameState::new());
+    }
+
+    #[test]
+    fn test_parse_fields() {
+        let fen = "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w Kq c6 3 7";
+        let game_state = GameState::from_fen(fen).unwrap();
+        assert_eq!(game_state.board[4][2], ChessPiece::Pawn(Colour::Black));
+        assert_eq!(game_state.board[3][4], ChessPiece::Pawn(Colour::White));
+        assert_eq!(game_state.current_player, Colour::White);
+        assert!(game_state.castling.allows(Colour::White, true) && !game_state.castling.allows(Colour::White, false));
+        assert!(!game_state.castling.allows(Colour::Black, true) && game_state.castling.allows(Colour::Black, false));
+        assert_eq!(game_state.en_passant, Some(Square::C6));
+        assert_eq!(game_state.halfmove_clock(), 3);
+        assert_eq!(game_state.fullmove_number(), 7);
+    }
+
+    #[test]
+    fn test_clocks_are_optional() {
+        let game_state = GameState::from_fen("8/8/8/4k3/8/8/8/4K2R b K -").unwrap();
+        assert_eq!((game_state.halfmove_clock(), game_state.fullmove_number()), (0, 1));
+        assert_eq!(game_state.current_player, Colour::Black);
+    }
+
+    #[test]
+    fn test_loaded_position_can_be_played() {
+        let mut game_state = GameState::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        assert!(game_state.make_move(Move::new(Square::E1, Square::G1)).is_ok());
+        assert_eq!(game_state.board[0][5], ChessPiece::Rook(Colour::White));
+    }
+
+    #[test]
+    fn test_loaded_position_result() {
+        let game_state = GameState::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(game_state.result, GameResult::Draw(DrawReason::Stalemate));
+        let game_state = GameState::from_fen("7k/6Q1/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(game_state.result, GameResult::WhiteWins);
+    }
+
+    #[test]
+    fn test_start_position_to_fen() {
+        assert_eq!(GameState::new().to_fen(), START);
+    }
+
+    #[test]
+    fn test_to_fen_after_moves() {
+        let mut game_state = GameState::new();
+        game_state.make_move(Move::new(Square::E2, Square::E4)).unwrap();
+        assert_eq!(game_state.to_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+        game_state.make_move(Move::new(Square::G8, Square::F6)).unwrap();
+        game_state.make_move(Move::new(Square::E1, Square::E2)).unwrap();
+        assert_eq!(game_state.to_fen(), "rnbqkb1r/pppppppp/5n2/8/4P3/8/PPPPKPPP/RNBQ1BNR b kq - 2 2");
+    }
+
+    #[test]
+    fn test_fen_round_trip() {
+        for fen in [
+            "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w Kq c6 3 7",
+            "r3k2r/8/8/8/8/8/8/R3K2R b Qk - 12 40",
+            "8/8/8/4k3/8/8/8/4K3 w - - 0 1",
+        ] {
+            assert_eq!(GameState::from_fen(fen).unwrap().to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn test_chess960_king_round_trip() {
+        // The king may start on any file from b to g, and castles from there whichever file it is.
+        for (fen, king_col) in [
+            ("rk5r/8/8/8/8/8/8/RK5R w KQkq - 0 1", 1),
+            ("r1k4r/8/8/8/8/8/8/R1K4R w KQkq - 0 1", 2),
+            ("r4k1r/8/8/8/8/8/8/R4K1R w KQkq - 0 1", 5),
+            ("r5kr/8/8/8/8/8/8/R5KR w KQkq - 0 1", 6),
+        ] {
+            let game_state = GameState::from_fen(fen).unwrap();
+            assert_eq!(game_state.castling.king_col(Colour::White), king_col, "{}", fen);
+            assert_eq!(game_state.castling.king_col(Colour::Black), king_col, "{}", fen);
+            assert_eq!(game_state.to_fen(), fen);
+            assert_eq!(GameState::from_fen(&game_state.to_shredder_fen()).unwrap(), game_state);
+            assert_eq!(game_state.legal_moves().iter().filter(|mv| mv.castling).count(), 2, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_x_fen_castling() {
+        // K names the outermost kingside rook; an inner rook needs its file.
+        let game_state = GameState::from_fen("r3k1r1/8/8/8/8/8/8/R3K1RR w KGq - 0 1");
+        assert_eq!(game_state, Err(FenError::InvalidCastling("KGq".to_string())));
+        let game_state = GameState::from_fen("r3k1r1/8/8/8/8/8/8/R3K1RR w Gq - 0 1").unwrap();
+        assert_eq!(game_state.castling.rook_col(Colour::White, true), Some(6));
+        assert_eq!(game_state.castling.rook_col(Colour::Black, true), None);
+        assert_eq!(game_state.castling.rook_col(Colour::Black, false), Some(0));
+        assert_eq!(game_state.to_fen(), "r3k1r1/8/8/8/8/8/8/R3K1RR w Gq - 0 1");
+
+        let game_state = GameState::from_fen("r3k1r1/8/8/8/8/8/8/R3K1RR w Kk - 0 1").unwrap();
+        assert_eq!(game_state.castling.rook_col(Colour::White, true), Some(7));
+        assert_eq!(game_state.castling.rook_col(Colour::Black, true), Some(6));
+    }
+
+    #[test]
+    fn test_shredder_fen() {
+        let game_state = GameState::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1").unwrap();
+        assert_eq!(game_state, GameState::new());
+        assert_eq!(game_state.to_fen(), START);
+        assert_eq!(game_state.to_shredder_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1");
+        assert_eq!(GameState::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap().to_shredder_fen(), "4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(
+            GameState::from_fen("4k3/8/8/8/8/8/8/4K2R w E - 0 1"),
+            Err(FenError::InvalidCastling("E".to_string()))
+        );
+        assert_eq!(
+            GameState::from_fen("4k3/8/8/8/8/8/8/4K2R w G - 0 1"),
+            Err(FenError::InvalidCastling("G".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = |fen: &str| GameState::from_fen(fen).unwrap_err();
+        assert_eq!(err("8/8/8 w - -"), FenError::WrongRankCount(3));
+        assert_eq!(err("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq"), FenError::WrongFieldCount(3));
+        assert_eq!(err("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNX w KQkq - 0 1"), FenError::InvalidPiece('X'));
+        assert_eq!(
+            err("rnbqkbnr/ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+            FenError::WrongRankLength(7, "ppppppp".to_string())
+        );
+        assert_eq!(
+            err("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1"),
+            FenError::InvalidSideToMove("x".to_string())
+        );
+        assert_eq!(
+            err("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN1 w KQkq - 0 1"),
+            FenError::InvalidCastling("KQkq".to_string())
+        );
+        assert_eq!(
+            err("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e3 0 1"),
+            FenError::InvalidEnPassant("e3".to_string())
+        );
+        assert_eq!(
+            err("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - x 1"),
+            FenError::InvalidHalfmoveClock("x".to_string())
+        );
+        assert_eq!(
+            err("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 0"),
+            FenError::InvalidFullmoveNumber("0".to_string())
+        );
+        assert_eq!(
+            err("8/8/8/8/8/8/8/4K3 w - - 0 1"),
+            FenError::IllegalPosition(ChessError::WrongKingCount(Colour::Black, 0))
+        );
+    }
+}
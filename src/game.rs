@@ -0,0 +1,420 @@
+//! # Game
+//!
+//! A `GameState` only knows the current position. A `Game` also remembers the
+//! position it started from, every move played since and the tags that describe
+//! it, which is what a PGN record needs.
+//!
+//! The moves form a tree: each move on the main line can carry comments, NAGs
+//! (numeric annotation glyphs such as `$1` for "good move") and variations that
+//! were considered instead of it.
+//!
+//! Each move played is also kept with what is needed to take it back, so moves can
+//! be undone and then redone, until a different move is played. Whoever wants to
+//! follow the game as it goes can subscribe to its [events](crate::event).
+
+#[cfg(feature = "std")]
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::sync::mpsc::{Receiver, channel};
+#[cfg(feature = "std")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::ChessError;
+use crate::event::{GameEvent, Observers};
+use crate::{ChessPiece, Colour, GameResult, GameState, Move, UndoInfo};
+
+// > add a node of the game tree: a move with its annotations and the alternatives to it
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct MoveNode {
+    pub(crate) mv: Move,
+    pub(crate) comment_before: Option<String>,
+    pub(crate) nags: Vec<u8>,
+    pub(crate) comment: Option<String>,
+    // Each variation replaces this move, so it starts from the position before it.
+    pub(crate) variations: Vec<Vec<MoveNode>>,
+}
+
+impl MoveNode {
+    pub(crate) fn new(mv: Move) -> MoveNode {
+        MoveNode {
+            mv,
+            comment_before: None,
+            nags: Vec::new(),
+            comment: None,
+            variations: Vec::new(),
+        }
+    }
+}
+
+// > add a record of a move played and how to take it back
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RecordedMove {
+    pub mv: Move,
+    pub undo: UndoInfo,
+}
+
+// > add a Game struct holding the starting position, the moves played and the PGN tags
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Game {
+    tags: Vec<(String, String)>,
+    start: GameState,
+    line: Vec<MoveNode>,
+    state: GameState,
+    // One entry for each move of the main line.
+    history: Vec<RecordedMove>,
+    // The moves taken back, the next one to redo last.
+    undone: Vec<MoveNode>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    observers: Observers,
+}
+
+impl Game {
+    // > start a game from the given position with the Seven Tag Roster filled in
+    pub fn from_position(state: GameState) -> Game {
+        let tags = [
+            ("Event", "Casual game".to_string()),
+            ("Site", "?".to_string()),
+            ("Date", today()),
+            ("Round", "-".to_string()),
+            ("White", "?".to_string()),
+            ("Black", "?".to_string()),
+            ("Result", "*".to_string()),
+        ];
+        Game {
+            tags: tags.into_iter().map(|(name, value)| (name.to_string(), value)).collect(),
+            start: state.clone(),
+            line: Vec::new(),
+            state,
+            history: Vec::new(),
+            undone: Vec::new(),
+            observers: Observers::default(),
+        }
+    }
+
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    pub fn start(&self) -> &GameState {
+        &self.start
+    }
+
+    // > give the moves of the main line
+    pub fn moves(&self) -> Vec<Move> {
+        self.history.iter().map(|recorded| recorded.mv).collect()
+    }
+
+    // > give the main line with its annotations and variations
+    #[cfg(feature = "pgn")]
+    pub(crate) fn line(&self) -> &[MoveNode] {
+        &self.line
+    }
+
+    // > give every move of the main line with what is needed to take it back
+    pub fn history(&self) -> &[RecordedMove] {
+        &self.history
+    }
+
+    pub fn tags(&self) -> &[(String, String)] {
+        &self.tags
+    }
+
+    // > replace the tags, for example with those read from a PGN file
+    pub fn set_tags(&mut self, tags: Vec<(String, String)>) {
+        self.tags = tags;
+    }
+
+    // > record a result decided off the board, such as a resignation or a loss on time read from PGN
+    pub fn set_result(&mut self, result: GameResult) {
+        if self.state.result == GameResult::Ongoing && result != GameResult::Ongoing {
+            self.state.result = result;
+            self.observers.notify(GameEvent::GameEnded(result));
+        }
+    }
+
+    // > go back to the position after the first `plies` moves, forgetting the rest
+    pub fn truncate(&mut self, plies: usize) {
+        while self.history.len() > plies {
+            self.undo();
+        }
+        self.undone.clear();
+    }
+
+    // > play a move and record it in the game
+    pub fn play(&mut self, mv: Move) -> Result<UndoInfo, ChessError> {
+        self.play_node(MoveNode::new(mv))
+    }
+
+    // > play the move of a node and record it along with its annotations; the moves taken back can no longer be redone
+    pub(crate) fn play_node(&mut self, node: MoveNode) -> Result<UndoInfo, ChessError> {
+        let undo = self.record(node)?;
+        self.undone.clear();
+        Ok(undo)
+    }
+
+    fn record(&mut self, node: MoveNode) -> Result<UndoInfo, ChessError> {
+        // Writing the move out takes a search of the legal moves, so it is only done for someone watching.
+        let san = if self.observers.is_empty() { String::new() } else { self.state.move_to_san(&node.mv) };
+        let by = self.state.current_player;
+        let undo = self.state.make_move(node.mv)?;
+        let mv = undo.position.mv;
+        self.line.push(MoveNode { mv, ..node });
+        self.history.push(RecordedMove { mv, undo });
+        if !self.observers.is_empty() {
+            self.observers.notify(GameEvent::MovePlayed { mv, san, by });
+            if undo.position.captured != ChessPiece::Blank {
+                self.observers.notify(GameEvent::Capture { mv, piece: undo.position.captured });
+            }
+            if let Some(piece) = mv.promotion {
+                self.observers.notify(GameEvent::Promotion { mv, piece });
+            }
+            if self.state.is_in_check(self.state.current_player) {
+                self.observers.notify(GameEvent::Check(self.state.current_player));
+            }
+            self.notify_if_ended(GameResult::Ongoing);
+        }
+        Ok(undo)
+    }
+
+    // > take back the last move, keeping it so it can be redone
+    pub fn undo(&mut self) -> Option<Move> {
+        let recorded = self.history.pop()?;
+        let node = self.line.pop().expect("every recorded move is on the main line");
+        self.state.unmake_move(recorded.undo);
+        self.undone.push(node);
+        self.observers.notify(GameEvent::MoveTakenBack(recorded.mv));
+        Some(recorded.mv)
+    }
+
+    // > play again the last move taken back, with its annotations
+    pub fn redo(&mut self) -> Option<Move> {
+        let node = self.undone.pop()?;
+        let undo = self.record(node).expect("moves taken back are legal where they were played");
+        Some(undo.position.mv)
+    }
+
+    pub fn resign(&mut self, colour: Colour) -> Result<(), ChessError> {
+        let before = self.state.result;
+        self.state.resign(colour)?;
+        self.notify_if_ended(before);
+        Ok(())
+    }
+
+    pub fn offer_draw(&mut self, colour: Colour) -> Result<(), ChessError> {
+        self.state.offer_draw(colour)
+    }
+
+    pub fn accept_draw(&mut self, colour: Colour) -> Result<(), ChessError> {
+        let before = self.state.result;
+        self.state.accept_draw(colour)?;
+        self.notify_if_ended(before);
+        Ok(())
+    }
+
+    pub fn claim_draw(&mut self) -> Result<(), ChessError> {
+        let before = self.state.result;
+        self.state.claim_draw()?;
+        self.notify_if_ended(before);
+        Ok(())
+    }
+
+    // > call back with every event from now on
+    pub fn subscribe(&mut self, callback: impl FnMut(&GameEvent) + Send + Sync + 'static) {
+        self.observers.subscribe(callback);
+    }
+
+    // > receive every event from now on down a channel
+    #[cfg(feature = "std")]
+    pub fn events(&mut self) -> Receiver<GameEvent> {
+        let (sender, receiver) = channel();
+        self.observers.add_channel(sender);
+        receiver
+    }
+
+    fn notify_if_ended(&mut self, before: GameResult) {
+        let result = self.state.result;
+        if before == GameResult::Ongoing && result != GameResult::Ongoing {
+            self.observers.notify(GameEvent::GameEnded(result));
+        }
+    }
+}
+
+// > give today's date in the PGN form YYYY.MM.DD
+#[cfg(feature = "std")]
+pub(crate) fn today() -> String {
+    pgn_date(SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()))
+}
+
+// Without std there is no clock, so the date is left unknown.
+#[cfg(not(feature = "std"))]
+pub(crate) fn today() -> String {
+    "????.??.??".to_string()
+}
+
+// > give the PGN date of a moment, in seconds since 1970-01-01 UTC
+#[cfg(feature = "std")]
+pub fn pgn_date(seconds: u64) -> String {
+    let (year, month, day) = civil_from_days(seconds / 86_400);
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+// > convert days since 1970-01-01 into a (year, month, day) date in the Gregorian calendar
+#[cfg(feature = "std")]
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    // Count from 0000-03-01 so the leap day falls at the end of each year.
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GameResult, Square};
+
+    #[test]
+    fn test_play_records_moves() {
+        let mut game = Game::from_position(GameState::new());
+        game.play(Move::new(Square::E2, Square::E4)).unwrap();
+        assert!(game.play(Move::new(Square::E2, Square::E4)).is_err());
+        game.play(Move::new(Square::E7, Square::E5)).unwrap();
+        assert_eq!(game.moves().len(), 2);
+        assert_eq!(game.moves()[0].from, Square::E2);
+        assert!(game.moves()[0].double_push);
+        assert_eq!(game.start(), &GameState::new());
+        assert_eq!(game.state().fullmove_number(), 2);
+    }
+
+    #[test]
+    fn test_resign_ends_game() {
+        let mut game = Game::from_position(GameState::new());
+        game.resign(Colour::White).unwrap();
+        assert_eq!(game.state().result, GameResult::BlackWins);
+        assert!(game.play(Move::new(Square::E2, Square::E4)).is_err());
+    }
+
+    #[test]
+    fn test_truncate_replays_from_start() {
+        let mut game = Game::from_position(GameState::new());
+        for (from, to) in [(Square::E2, Square::E4), (Square::E7, Square::E5), (Square::G1, Square::F3)] {
+            game.play(Move::new(from, to)).unwrap();
+        }
+        game.truncate(1);
+        assert_eq!(game.moves().len(), 1);
+        let mut expected = GameState::new();
+        expected.make_move(Move::new(Square::E2, Square::E4)).unwrap();
+        assert_eq!(game.state(), &expected);
+        game.truncate(5);
+        assert_eq!(game.moves().len(), 1);
+    }
+
+    #[test]
+    fn test_undo_and_redo() {
+        let mut game = Game::from_position(GameState::new());
+        assert_eq!(game.undo(), None);
+        for (from, to) in [(Square::E2, Square::E4), (Square::E7, Square::E5), (Square::G1, Square::F3)] {
+            game.play(Move::new(from, to)).unwrap();
+        }
+        let after_e5 = game.history()[1].undo;
+        assert_eq!(game.history().len(), 3);
+
+        assert_eq!(game.undo().map(|mv| mv.to), Some(Square::F3));
+        assert_eq!(game.undo().map(|mv| mv.to), Some(Square::E5));
+        assert_eq!(game.moves().len(), 1);
+        assert_eq!(game.history().len(), 1);
+        assert_eq!(game.state().current_player, Colour::Black);
+
+        assert_eq!(game.redo().map(|mv| mv.to), Some(Square::E5));
+        assert_eq!(game.history()[1].undo, after_e5);
+        assert_eq!(game.moves().len(), 2);
+
+        // A different move forgets the moves that were taken back.
+        game.play(Move::new(Square::B1, Square::C3)).unwrap();
+        assert_eq!(game.redo(), None);
+        game.truncate(1);
+        assert_eq!(game.history().len(), 1);
+        assert_eq!(game.redo(), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_events() {
+        use std::sync::{Arc, Mutex};
+        let mut game = Game::from_position(GameState::new());
+        for (from, to) in [(Square::F2, Square::F3), (Square::E7, Square::E5), (Square::G2, Square::G4)] {
+            game.play(Move::new(from, to)).unwrap();
+        }
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&seen);
+        game.subscribe(move |event| log.lock().unwrap().push(event.clone()));
+        let events = game.events();
+
+        let mate = Move::new(Square::D8, Square::H4);
+        game.play(mate).unwrap();
+        let mv = game.moves()[3];
+        let expected = vec![
+            GameEvent::MovePlayed { mv, san: "Qh4#".to_string(), by: Colour::Black },
+            GameEvent::Check(Colour::White),
+            GameEvent::GameEnded(GameResult::BlackWins),
+        ];
+        assert_eq!(events.try_iter().collect::<Vec<_>>(), expected);
+        game.undo();
+        assert_eq!(events.try_recv(), Ok(GameEvent::MoveTakenBack(mv)));
+        assert_eq!(seen.lock().unwrap().len(), 4);
+
+        // A dropped receiver is forgotten, and a copy of the game is not watched.
+        drop(events);
+        game.clone().resign(Colour::Black).unwrap();
+        game.resign(Colour::Black).unwrap();
+        assert_eq!(seen.lock().unwrap().last(), Some(&GameEvent::GameEnded(GameResult::WhiteWins)));
+        assert_eq!(seen.lock().unwrap().len(), 5);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_capture_and_promotion_events() {
+        let mut game = Game::from_position(GameState::from_fen("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap());
+        let events = game.events();
+        let promotion = Move::new(Square::A7, Square::B8).with_promotion(ChessPiece::Queen(Colour::White));
+        game.play(promotion).unwrap();
+        let mv = game.moves()[0];
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            vec![
+                GameEvent::MovePlayed { mv, san: "axb8=Q+".to_string(), by: Colour::White },
+                GameEvent::Capture { mv, piece: ChessPiece::Knight(Colour::Black) },
+                GameEvent::Promotion { mv, piece: ChessPiece::Queen(Colour::White) },
+                GameEvent::Check(Colour::Black),
+            ]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(20_742), (2026, 10, 16));
+    }
+
+    #[cfg(all(feature = "serde", feature = "pgn"))]
+    #[test]
+    fn test_serde_keeps_history() {
+        let mut game = Game::from_pgn("1. e4 {king's pawn} e5 (1... c5) 2. Nf3 *").unwrap();
+        game.set_tags(vec![("White".to_string(), "Me".to_string())]);
+        let json = serde_json::to_string(&game).unwrap();
+        assert_eq!(serde_json::from_str::<Game>(&json).unwrap(), game);
+    }
+}
@@ -0,0 +1,2744 @@
+//! # Two-Player Chess Program
+//!
+//! This module provides the foundation for implementing a two-player chess game in Rust.
+//! The program will include the following key components:
+//!
+//! ## Features
+//! - **Game Board Representation**: A data structure to represent an 8x8 chessboard, including pieces and their positions.
+//! - **Piece Movement Rules**: Logic to validate legal moves for each type of chess piece (pawn, knight, bishop, rook, queen, king).
+//! - **Turn-Based Gameplay**: Alternating turns between two players, enforcing the rules of chess.
+//! - **Check and Checkmate Detection**: Mechanisms to detect when a king is in check or checkmate.
+//! - **Stalemate and Draw Conditions**: Logic to handle stalemates, insufficient material, and other draw scenarios.
+//! - **Move History**: A record of all moves made during the game for undo functionality or analysis.
+//!
+//! ## Using the library
+//! The rules engine can be used by other programs through [`GameState`], [`Square`], [`Move`],
+//! [`Colour`], [`ChessPiece`] and [`PieceKind`]. A position also answers questions about itself
+//! ([`Position::attackers_of`], [`Position::checkers`], [`Position::pinned_pieces`],
+//! [`Position::validate_position`], [`GameState::classify_move`]), and a game can end off the board
+//! ([`GameState::resign`], [`GameState::offer_draw`], [`GameState::accept_draw`],
+//! [`GameState::claim_draw`]). The program itself, in `src/bin`, is built on the same public API.
+//!
+//! ```
+//! use overload_vibe_coding::{ChessPiece, Colour, GameResult, GameState, Move, MoveKind, Square};
+//!
+//! let mut position = GameState::new();
+//! assert_eq!(position.legal_moves().len(), 20);
+//! position.make_move(Move::new(Square::E2, Square::E4)).unwrap();
+//! assert_eq!(position.side_to_move(), Colour::Black);
+//! assert_eq!(position.piece_at(Square::E4), ChessPiece::Pawn(Colour::White));
+//! assert_eq!(position.to_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+//!
+//! let kind = position.classify_move(&Move::new(Square::D7, Square::D5)).unwrap();
+//! assert_eq!(kind, MoveKind::default());
+//! position.resign(Colour::Black).unwrap();
+//! assert_eq!(position.result(), GameResult::WhiteWins);
+//! ```
+//!
+//! The rest of the library is behind features. Only `std` is on by default; a program
+//! that only wants the rules can turn it off with `default-features = false`.
+//!
+//! - `std`: without it the rules engine is `no_std` and needs only `alloc`. It brings
+//!   [`Game::events`] channels, today's date in new games, [`perft::divide`] and
+//!   [`perft::parallel_perft`] on threads. Every feature below turns it on.
+//! - `engine`: [`evaluate`], the opening [`Book`], NNUE [`Network`]s, [`Skill`] levels and
+//!   the [`Engine`]s that search with them, and [`run_match`] to play them against each other.
+//!   [`search`] looks ahead for the best move and [`mate`] finds forced mates.
+//! - `uci`: [`UciSession`] to answer a chess GUI, and [`UciEngine`] to drive another engine.
+//! - `pgn`: [`Game::to_pgn`], [`Game::from_pgn`], [`PgnReader`] for PGN files, and the
+//!   binary and correspondence formats. With `engine` as well, [`annotate`] comments on games.
+//! - `network`: [`network::host`] and [`network::connect`] to play over TCP, and
+//!   [`Broadcast`] to push games to an [`Endpoint`].
+//! - `cli`: builds the command line program, `cargo run --features cli`, with all of the above.
+//!
+//! C and C++ programs use the rules engine through the `overload-vibe-coding-ffi` crate in
+//! `ffi/`, which builds it as a shared and a static library declared in `ffi/include/chess.h`.
+//!
+//! ## Plan
+//! 1. **Define Data Structures**:
+//!    - Create enums for `PieceType` (pawn, knight, etc.) and `Color` (white, black).
+//!    - Define a `Piece` struct to represent a chess piece with its type and color.
+//!    - Implement a `Board` struct to represent the chessboard as an 8x8 grid.
+//!
+//! 2. **Implement Movement Logic**:
+//!    - Write functions to validate moves for each piece type based on chess rules.
+//!    - Ensure moves respect the board boundaries and piece interactions (e.g., capturing, blocking).
+//!
+//! 3. **Game Flow Management**:
+//!    - Create a `Game` struct to manage the state of the game, including the board, turn order, and move history.
+//!    - Implement functions to switch turns and enforce valid moves.
+//!
+//! 4. **Special Rules**:
+//!    - Add support for castling, en passant, and pawn promotion.
+//!    - Handle check, checkmate, and stalemate conditions.
+//!
+//! 5. **User Interaction**:
+//!    - Provide a text-based interface for players to input moves (e.g., "e2 to e4").
+//!    - Display the board state after each move.
+//!
+//! 6. **Testing and Validation**:
+//!    - Write unit tests for movement rules, game state transitions, and special conditions.
+//!    - Ensure the program handles edge cases and invalid inputs gracefully.
+//!
+//! ## Future Enhancements
+//! - Add support for a graphical user interface (GUI).
+//! - Implement AI for single-player mode.
+//! - Include a timer for timed games (e.g., blitz chess).
+//! - Save and load game states for resuming later.
+
+// > the rules engine should only need core and alloc so it can run without std
+// Tests run under the test harness, which needs std either way.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+#[cfg(all(feature = "engine", feature = "pgn"))]
+pub mod annotate;
+#[cfg(feature = "pgn")]
+mod binary;
+mod bitboard;
+mod board;
+#[cfg(feature = "engine")]
+mod book;
+#[cfg(feature = "network")]
+mod broadcast;
+mod builder;
+#[cfg(feature = "pgn")]
+mod correspondence;
+#[cfg(feature = "engine")]
+mod epd;
+#[cfg(feature = "engine")]
+pub mod engine;
+#[cfg(feature = "engine")]
+mod engine_match;
+mod error;
+mod event;
+#[cfg(feature = "engine")]
+pub mod eval;
+mod fen;
+mod game;
+#[cfg(feature = "engine")]
+pub mod mate;
+#[cfg(feature = "engine")]
+mod mcts;
+#[cfg(feature = "network")]
+pub mod network;
+#[cfg(feature = "engine")]
+mod nnue;
+pub mod perft;
+#[cfg(feature = "pgn")]
+mod pgn;
+mod rules;
+mod san;
+#[cfg(feature = "engine")]
+pub mod search;
+#[cfg(feature = "engine")]
+pub mod skill;
+#[cfg(feature = "uci")]
+mod uci;
+mod zobrist;
+
+pub use bitboard::Bitboards;
+pub use board::BoardRepr;
+pub use builder::PositionBuilder;
+pub use error::{ChessError, FenError, SetupError};
+pub use event::GameEvent;
+pub use fen::piece_to_char;
+pub use game::{Game, RecordedMove};
+pub use rules::Pin;
+
+#[cfg(feature = "engine")]
+pub use book::{Book, BookChoice, BookOptions, PolyglotKeys};
+#[cfg(feature = "engine")]
+pub use engine::{BookEngine, Clocks, Engine, InternalEngine, MctsEngine, UciEngine};
+#[cfg(feature = "engine")]
+pub use engine_match::{MatchConfig, MatchScore, play_game, run_match};
+#[cfg(feature = "engine")]
+pub use epd::Epd;
+#[cfg(feature = "engine")]
+pub use error::{BookError, EngineError, EpdError, NnueError};
+#[cfg(feature = "engine")]
+pub use eval::{evaluate, material, material_balance};
+#[cfg(feature = "engine")]
+pub use nnue::Network;
+#[cfg(feature = "engine")]
+pub use skill::Skill;
+#[cfg(feature = "uci")]
+pub use uci::UciSession;
+#[cfg(feature = "pgn")]
+pub use error::{BinaryError, CorrespondenceError, PgnError};
+#[cfg(feature = "pgn")]
+pub use pgn::{PgnReader, PgnRecord};
+#[cfg(feature = "std")]
+pub use game::pgn_date;
+#[cfg(feature = "network")]
+pub use broadcast::{Broadcast, Endpoint};
+#[cfg(feature = "network")]
+pub use error::{BroadcastError, NetworkError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ChessPiece {
+    Pawn(Colour),
+    Knight(Colour),
+    Bishop(Colour),
+    Rook(Colour),
+    Queen(Colour),
+    King(Colour),
+    Blank,
+}
+
+// > add a method to get the colour of a ChessPiece
+impl ChessPiece {
+    pub fn colour(self) -> Option<Colour> {
+        match self {
+            ChessPiece::Pawn(colour)
+            | ChessPiece::Knight(colour)
+            | ChessPiece::Bishop(colour)
+            | ChessPiece::Rook(colour)
+            | ChessPiece::Queen(colour)
+            | ChessPiece::King(colour) => Some(colour),
+            ChessPiece::Blank => None,
+        }
+    }
+
+    // > add a method to get the kind of a ChessPiece, whatever its colour
+    pub fn kind(self) -> Option<PieceKind> {
+        match self {
+            ChessPiece::Pawn(_) => Some(PieceKind::Pawn),
+            ChessPiece::Knight(_) => Some(PieceKind::Knight),
+            ChessPiece::Bishop(_) => Some(PieceKind::Bishop),
+            ChessPiece::Rook(_) => Some(PieceKind::Rook),
+            ChessPiece::Queen(_) => Some(PieceKind::Queen),
+            ChessPiece::King(_) => Some(PieceKind::King),
+            ChessPiece::Blank => None,
+        }
+    }
+
+    // > make a piece from its kind and colour
+    pub fn new(kind: PieceKind, colour: Colour) -> ChessPiece {
+        match kind {
+            PieceKind::Pawn => ChessPiece::Pawn(colour),
+            PieceKind::Knight => ChessPiece::Knight(colour),
+            PieceKind::Bishop => ChessPiece::Bishop(colour),
+            PieceKind::Rook => ChessPiece::Rook(colour),
+            PieceKind::Queen => ChessPiece::Queen(colour),
+            PieceKind::King => ChessPiece::King(colour),
+        }
+    }
+
+    // > tell whether a piece belongs to the opponent of the given side; an empty square belongs to neither
+    pub fn is_enemy_of(self, colour: Colour) -> bool {
+        self.colour() == Some(colour.opponent())
+    }
+}
+
+// > add an enum for the kind of a piece without its colour, in order of value with the king last
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PieceKind {
+    Pawn,
+    Knight,
+    Bishop,
+    Rook,
+    Queen,
+    King,
+}
+
+impl PieceKind {
+    pub const ALL: [PieceKind; 6] = [PieceKind::Pawn, PieceKind::Knight, PieceKind::Bishop, PieceKind::Rook, PieceKind::Queen, PieceKind::King];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Colour {
+    White,
+    Black,
+}
+
+// > add a method to get the opposing colour
+impl Colour {
+    pub fn opponent(self) -> Colour {
+        match self {
+            Colour::White => Colour::Black,
+            Colour::Black => Colour::White,
+        }
+    }
+}
+
+// > split the board, castling rights, en passant square and clocks, which a search copies about freely,
+// > from the game they belong to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Position {
+    board: [[ChessPiece; 8]; 8],
+    current_player: Colour,
+    // The square a pawn skipped over with a double push on the previous move.
+    en_passant: Option<Square>,
+    castling: CastlingRights,
+    // The Zobrist key of the board, castling rights and side to move, updated by each move.
+    hash: u64,
+    // Half-moves since the last capture or pawn move.
+    halfmove_clock: u32,
+    // Starts at 1 and goes up after each of Black's moves.
+    fullmove_number: u32,
+    // Where each king stood, White's first, as apply_move and undo_move last left it. Boards set up square
+    // by square leave it behind, so king_square checks it against the board before trusting it.
+    #[cfg_attr(feature = "serde", serde(default))]
+    kings: [Option<Square>; 2],
+    // The pieces giving check to the side to move, worked out by apply_move from the move alone and put back by
+    // undo_move, so asking whether the side to move is in check costs nothing.
+    #[cfg_attr(feature = "serde", serde(default))]
+    checkers: bitboard::Bitboard,
+}
+
+// A position together with what the rules need to know about how the game reached it.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GameState {
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    position: Position,
+    // The key of every position reached so far, including the current one.
+    position_history: Vec<u64>,
+    result: GameResult,
+    // The side that has offered a draw which the other side has not yet answered.
+    draw_offer: Option<Colour>,
+}
+
+// > let a GameState be used wherever a Position is wanted
+impl core::ops::Deref for GameState {
+    type Target = Position;
+
+    fn deref(&self) -> &Position {
+        &self.position
+    }
+}
+
+impl core::ops::DerefMut for GameState {
+    fn deref_mut(&mut self) -> &mut Position {
+        &mut self.position
+    }
+}
+
+// > add a struct describing what kind of move a candidate move is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MoveKind {
+    pub capture: bool,
+    pub check: bool,
+    pub checkmate: bool,
+    pub castle: bool,
+    pub promotion: bool,
+    pub en_passant: bool,
+}
+
+impl fmt::Display for MoveKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let labels = [
+            (self.castle, "castle"),
+            (self.capture, "capture"),
+            (self.en_passant, "en passant"),
+            (self.promotion, "promotion"),
+            (self.checkmate, "checkmate"),
+            (self.check && !self.checkmate, "check"),
+        ];
+        let names: Vec<&str> = labels.iter().filter(|(set, _)| *set).map(|(_, name)| *name).collect();
+        write!(f, "{}", names.join(", "))
+    }
+}
+
+// > add a struct holding everything needed to take a move back on the board
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PositionUndo {
+    mv: Move,
+    captured: ChessPiece,
+    castling: CastlingRights,
+    en_passant: Option<Square>,
+    halfmove_clock: u32,
+    hash: u64,
+    checkers: bitboard::Bitboard,
+}
+
+// > add a struct holding everything needed to take a move back
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UndoInfo {
+    position: PositionUndo,
+    result: GameResult,
+    draw_offer: Option<Colour>,
+}
+
+// > add an enum for the result of the game
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw(DrawReason),
+    Ongoing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DrawReason {
+    Stalemate,
+    InsufficientMaterial,
+    ThreefoldRepetition,
+    Agreement,
+    FiftyMoveRule,
+    FivefoldRepetition,
+    SeventyFiveMoveRule,
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameResult::WhiteWins => write!(f, "White wins"),
+            GameResult::BlackWins => write!(f, "Black wins"),
+            GameResult::Draw(reason) => write!(f, "Draw by {}", reason),
+            GameResult::Ongoing => write!(f, "Game in progress"),
+        }
+    }
+}
+
+impl fmt::Display for DrawReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DrawReason::Stalemate => write!(f, "stalemate"),
+            DrawReason::InsufficientMaterial => write!(f, "insufficient material"),
+            DrawReason::ThreefoldRepetition => write!(f, "threefold repetition"),
+            DrawReason::Agreement => write!(f, "agreement"),
+            DrawReason::FiftyMoveRule => write!(f, "the fifty-move rule"),
+            DrawReason::FivefoldRepetition => write!(f, "fivefold repetition"),
+            DrawReason::SeventyFiveMoveRule => write!(f, "the seventy-five-move rule"),
+        }
+    }
+}
+
+// > add a struct to track which castling moves are still available
+// Each right holds the column of the rook it castles with, so that FEN can name
+// the rook by its file (X-FEN and Shredder-FEN) as well as by side. The column each
+// king castles from is kept beside them, as in Chess960 it need not be the e-file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct CastlingRights {
+    white_kingside: Option<usize>,
+    white_queenside: Option<usize>,
+    black_kingside: Option<usize>,
+    black_queenside: Option<usize>,
+    #[cfg_attr(feature = "serde", serde(default = "king_col_default"))]
+    white_king: usize,
+    #[cfg_attr(feature = "serde", serde(default = "king_col_default"))]
+    black_king: usize,
+}
+
+#[cfg(feature = "serde")]
+fn king_col_default() -> usize {
+    rules::KING_COL
+}
+
+impl CastlingRights {
+    const ALL: CastlingRights = CastlingRights {
+        white_kingside: Some(7),
+        white_queenside: Some(0),
+        black_kingside: Some(7),
+        black_queenside: Some(0),
+        white_king: rules::KING_COL,
+        black_king: rules::KING_COL,
+    };
+
+    const NONE: CastlingRights = CastlingRights {
+        white_kingside: None,
+        white_queenside: None,
+        black_kingside: None,
+        black_queenside: None,
+        white_king: rules::KING_COL,
+        black_king: rules::KING_COL,
+    };
+
+    // > give the column the king of a side castles from
+    fn king_col(self, colour: Colour) -> usize {
+        match colour {
+            Colour::White => self.white_king,
+            Colour::Black => self.black_king,
+        }
+    }
+
+    fn set_king_col(&mut self, colour: Colour, col: usize) {
+        match colour {
+            Colour::White => self.white_king = col,
+            Colour::Black => self.black_king = col,
+        }
+    }
+
+    // > give the column of the rook a castling right uses, if the right remains
+    fn rook_col(self, colour: Colour, kingside: bool) -> Option<usize> {
+        match (colour, kingside) {
+            (Colour::White, true) => self.white_kingside,
+            (Colour::White, false) => self.white_queenside,
+            (Colour::Black, true) => self.black_kingside,
+            (Colour::Black, false) => self.black_queenside,
+        }
+    }
+
+    fn allows(self, colour: Colour, kingside: bool) -> bool {
+        self.rook_col(colour, kingside).is_some()
+    }
+
+    fn set(&mut self, colour: Colour, kingside: bool, rook_col: Option<usize>) {
+        match (colour, kingside) {
+            (Colour::White, true) => self.white_kingside = rook_col,
+            (Colour::White, false) => self.white_queenside = rook_col,
+            (Colour::Black, true) => self.black_kingside = rook_col,
+            (Colour::Black, false) => self.black_queenside = rook_col,
+        }
+        // Once a side cannot castle at all, where its king started no longer matters, and forgetting it lets
+        // the position compare equal to the same one read from FEN.
+        if !self.allows(colour, true) && !self.allows(colour, false) {
+            self.set_king_col(colour, rules::KING_COL);
+        }
+    }
+
+    // Any move from or to a king or castling rook home square loses the rights that depend on it.
+    fn update(&mut self, from: Square, to: Square) {
+        for square in [from, to] {
+            let (row, col) = square.to_row_col();
+            for (colour, back_row) in [(Colour::White, 0), (Colour::Black, 7)] {
+                if row != back_row {
+                    continue;
+                }
+                let king_col = self.king_col(colour);
+                for kingside in [true, false] {
+                    if col == king_col || self.rook_col(colour, kingside) == Some(col) {
+                        self.set(colour, kingside, None);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// > add a struct to represent a move, including the promotion piece and flags for the special moves
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Move {
+    pub from: Square,
+    pub to: Square,
+    // The piece a pawn becomes when it reaches the far rank.
+    pub promotion: Option<ChessPiece>,
+    castling: bool,
+    en_passant: bool,
+    double_push: bool,
+}
+
+impl Move {
+    // > create a plain move; GameState::complete_move fills in the flags for a given position
+    pub fn new(from: Square, to: Square) -> Move {
+        Move {
+            from,
+            to,
+            promotion: None,
+            castling: false,
+            en_passant: false,
+            double_push: false,
+        }
+    }
+
+    pub fn with_promotion(self, piece: ChessPiece) -> Move {
+        Move {
+            promotion: Some(piece),
+            ..self
+        }
+    }
+
+    // > parse a move in UCI long algebraic notation, such as "e2e4" or "e7e8q"
+    pub fn from_uci(text: &str) -> Result<Move, ChessError> {
+        let invalid = || ChessError::InvalidNotation(text.to_string());
+        if !text.is_ascii() || (text.len() != 4 && text.len() != 5) {
+            return Err(invalid());
+        }
+        let from: Square = text[0..2].parse().map_err(|_| invalid())?;
+        let to: Square = text[2..4].parse().map_err(|_| invalid())?;
+        let mv = Move::new(from, to);
+        if text.len() == 4 {
+            return Ok(mv);
+        }
+        // The letter carries no colour, but only a pawn reaching its own far rank can promote.
+        let colour = match to.to_row_col().0 {
+            7 => Colour::White,
+            0 => Colour::Black,
+            _ => return Err(invalid()),
+        };
+        let piece = promotion_piece(&text[4..], colour).ok_or_else(invalid)?;
+        Ok(mv.with_promotion(piece))
+    }
+
+    // > give the square the moving piece ends on: for castling written as the king taking its own rook, as
+    // > Chess960 needs when the king is beside the square it castles to, that is still the king's square
+    fn landing(self) -> Square {
+        match self.castling {
+            true => Square::from_row_col(self.from.to_row_col().0, rules::castling_king_target(self.to.file() > self.from.file())),
+            false => self.to,
+        }
+    }
+
+    // > write a move in UCI long algebraic notation
+    pub fn to_uci(self) -> String {
+        let mut text = format!("{}{}", self.from, self.to);
+        match self.promotion {
+            Some(ChessPiece::Queen(_)) => text.push('q'),
+            Some(ChessPiece::Rook(_)) => text.push('r'),
+            Some(ChessPiece::Bishop(_)) => text.push('b'),
+            Some(ChessPiece::Knight(_)) => text.push('n'),
+            _ => {}
+        }
+        text
+    }
+}
+
+// > add a compact 16-bit encoding of a move, for the search's tables: the from square in the low six bits,
+// > the to square in the next six, and the kind of move in the top four
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct PackedMove(u16);
+
+impl PackedMove {
+    const DOUBLE_PUSH: u16 = 1;
+    const CASTLING: u16 = 2;
+    const EN_PASSANT: u16 = 3;
+    // Promotions to a knight, bishop, rook and queen are this plus 0 to 3.
+    const PROMOTION: u16 = 4;
+}
+
+impl From<Move> for PackedMove {
+    fn from(mv: Move) -> PackedMove {
+        let kind = match mv.promotion {
+            Some(ChessPiece::Knight(_)) => PackedMove::PROMOTION,
+            Some(ChessPiece::Bishop(_)) => PackedMove::PROMOTION + 1,
+            Some(ChessPiece::Rook(_)) => PackedMove::PROMOTION + 2,
+            Some(_) => PackedMove::PROMOTION + 3,
+            None if mv.double_push => PackedMove::DOUBLE_PUSH,
+            None if mv.castling => PackedMove::CASTLING,
+            None if mv.en_passant => PackedMove::EN_PASSANT,
+            None => 0,
+        };
+        PackedMove(kind << 12 | (mv.to as u16) << 6 | mv.from as u16)
+    }
+}
+
+impl From<PackedMove> for Move {
+    fn from(PackedMove(code): PackedMove) -> Move {
+        let square = |index: u16| Square::from_row_col(usize::from(index % 8), usize::from(index / 8));
+        let mv = Move::new(square(code & 63), square(code >> 6 & 63));
+        // As in UCI, the colour of the promotion piece follows from the rank it is made on.
+        let colour = if mv.to.to_row_col().0 == 7 { Colour::White } else { Colour::Black };
+        match code >> 12 {
+            PackedMove::DOUBLE_PUSH => Move { double_push: true, ..mv },
+            PackedMove::CASTLING => Move { castling: true, ..mv },
+            PackedMove::EN_PASSANT => Move { en_passant: true, ..mv },
+            0 => mv,
+            kind => mv.with_promotion(match kind - PackedMove::PROMOTION {
+                0 => ChessPiece::Knight(colour),
+                1 => ChessPiece::Bishop(colour),
+                2 => ChessPiece::Rook(colour),
+                _ => ChessPiece::Queen(colour),
+            }),
+        }
+    }
+}
+
+// > add a list of moves with a fixed capacity, kept on the stack, for move generation to write into
+// > without allocating at every node of a search
+/// A list of moves kept on the stack, which [`Position::generate_moves`] fills in.
+///
+/// Generating into the same list again clears it first, so a search can make one list per ply
+/// and reuse it at every node it visits at that ply:
+///
+/// ```
+/// use overload_vibe_coding::{GameState, Move, MoveList, Square};
+///
+/// let mut moves = MoveList::new();
+/// let mut game = GameState::new();
+/// game.generate_moves(&mut moves);
+/// assert_eq!(moves.len(), 20);
+/// game.make_move(Move::new(Square::E2, Square::E4)).unwrap();
+/// game.generate_moves(&mut moves);
+/// assert!(moves.iter().all(|mv| mv.from.rank() >= 6));
+/// ```
+#[derive(Clone)]
+pub struct MoveList {
+    moves: [Move; MoveList::CAPACITY],
+    len: usize,
+}
+
+impl MoveList {
+    // No position has more than 218 legal moves.
+    pub const CAPACITY: usize = 256;
+
+    pub fn new() -> MoveList {
+        MoveList { moves: [Move::new(Square::A1, Square::A1); MoveList::CAPACITY], len: 0 }
+    }
+
+    pub fn push(&mut self, mv: Move) {
+        self.moves[self.len] = mv;
+        self.len += 1;
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    // > keep only the moves for which the test is true, in the same order
+    pub fn retain(&mut self, mut keep: impl FnMut(Move) -> bool) {
+        let mut kept = 0;
+        for index in 0..self.len {
+            if keep(self.moves[index]) {
+                self.moves[kept] = self.moves[index];
+                kept += 1;
+            }
+        }
+        self.len = kept;
+    }
+}
+
+impl Default for MoveList {
+    fn default() -> MoveList {
+        MoveList::new()
+    }
+}
+
+impl core::ops::Deref for MoveList {
+    type Target = [Move];
+
+    fn deref(&self) -> &[Move] {
+        &self.moves[..self.len]
+    }
+}
+
+impl core::ops::DerefMut for MoveList {
+    fn deref_mut(&mut self) -> &mut [Move] {
+        &mut self.moves[..self.len]
+    }
+}
+
+impl fmt::Debug for MoveList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+// > add an enum to represent the name of a square on the board
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Square {
+    A1, A2, A3, A4, A5, A6, A7, A8,
+    B1, B2, B3, B4, B5, B6, B7, B8,
+    C1, C2, C3, C4, C5, C6, C7, C8,
+    D1, D2, D3, D4, D5, D6, D7, D8,
+    E1, E2, E3, E4, E5, E6, E7, E8,
+    F1, F2, F3, F4, F5, F6, F7, F8,
+    G1, G2, G3, G4, G5, G6, G7, G8,
+    H1, H2, H3, H4, H5, H6, H7, H8,
+}
+
+// > implement a method to convert Square to a row and column
+// The squares are listed file by file, A1 to A8 and then B1, so the index of a square is
+// eight times its file plus its rank: the row (rank) is the remainder and the column (file) the quotient.
+impl Square {
+    // > list every square in order, A1 to A8, B1 to B8 and so on to H8
+    pub const ALL: [Square; 64] = {
+        use Square::*;
+        [
+            A1, A2, A3, A4, A5, A6, A7, A8,
+            B1, B2, B3, B4, B5, B6, B7, B8,
+            C1, C2, C3, C4, C5, C6, C7, C8,
+            D1, D2, D3, D4, D5, D6, D7, D8,
+            E1, E2, E3, E4, E5, E6, E7, E8,
+            F1, F2, F3, F4, F5, F6, F7, F8,
+            G1, G2, G3, G4, G5, G6, G7, G8,
+            H1, H2, H3, H4, H5, H6, H7, H8,
+        ]
+    };
+
+    pub fn to_row_col(self) -> (usize, usize) {
+        let index = self as usize;
+        let row = index % 8;
+        let col = index / 8;
+        (row, col)
+    }
+
+    // > implement the inverse of to_row_col
+    pub fn from_row_col(row: usize, col: usize) -> Square {
+        Square::ALL[col * 8 + row]
+    }
+
+    // > give the file of a square from 0 for the a-file to 7 for the h-file
+    pub fn file(self) -> usize {
+        self as usize / 8
+    }
+
+    // > give the rank of a square from 0 for the first rank to 7 for the eighth
+    pub fn rank(self) -> usize {
+        self as usize % 8
+    }
+
+    // > make a square from its file and rank, or nothing if either is off the board
+    pub fn from_file_rank(file: usize, rank: usize) -> Option<Square> {
+        (file < 8 && rank < 8).then(|| Square::ALL[file * 8 + rank])
+    }
+
+    // > move a number of files to the right and ranks up, or nothing if that leaves the board
+    pub fn offset(self, files: i32, ranks: i32) -> Option<Square> {
+        let file = usize::try_from(self.file() as i32 + files).ok()?;
+        let rank = usize::try_from(self.rank() as i32 + ranks).ok()?;
+        Square::from_file_rank(file, rank)
+    }
+
+    // > count the king moves between two squares
+    pub fn distance(self, other: Square) -> usize {
+        self.file().abs_diff(other.file()).max(self.rank().abs_diff(other.rank()))
+    }
+}
+
+// > write a square as it is named in algebraic notation, such as "e4"
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", (b'a' + self.file() as u8) as char, self.rank() + 1)
+    }
+}
+
+// > implement FromStr for square
+impl FromStr for Square {
+    type Err = ChessError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "A1" => Ok(Square::A1), "A2" => Ok(Square::A2), "A3" => Ok(Square::A3), "A4" => Ok(Square::A4),
+            "A5" => Ok(Square::A5), "A6" => Ok(Square::A6), "A7" => Ok(Square::A7), "A8" => Ok(Square::A8),
+            "B1" => Ok(Square::B1), "B2" => Ok(Square::B2), "B3" => Ok(Square::B3), "B4" => Ok(Square::B4),
+            "B5" => Ok(Square::B5), "B6" => Ok(Square::B6), "B7" => Ok(Square::B7), "B8" => Ok(Square::B8),
+            "C1" => Ok(Square::C1), "C2" => Ok(Square::C2), "C3" => Ok(Square::C3), "C4" => Ok(Square::C4),
+            "C5" => Ok(Square::C5), "C6" => Ok(Square::C6), "C7" => Ok(Square::C7), "C8" => Ok(Square::C8),
+            "D1" => Ok(Square::D1), "D2" => Ok(Square::D2), "D3" => Ok(Square::D3), "D4" => Ok(Square::D4),
+            "D5" => Ok(Square::D5), "D6" => Ok(Square::D6), "D7" => Ok(Square::D7), "D8" => Ok(Square::D8),
+            "E1" => Ok(Square::E1), "E2" => Ok(Square::E2), "E3" => Ok(Square::E3), "E4" => Ok(Square::E4),
+            "E5" => Ok(Square::E5), "E6" => Ok(Square::E6), "E7" => Ok(Square::E7), "E8" => Ok(Square::E8),
+            "F1" => Ok(Square::F1), "F2" => Ok(Square::F2), "F3" => Ok(Square::F3), "F4" => Ok(Square::F4),
+            "F5" => Ok(Square::F5), "F6" => Ok(Square::F6), "F7" => Ok(Square::F7), "F8" => Ok(Square::F8),
+            "G1" => Ok(Square::G1), "G2" => Ok(Square::G2), "G3" => Ok(Square::G3), "G4" => Ok(Square::G4),
+            "G5" => Ok(Square::G5), "G6" => Ok(Square::G6), "G7" => Ok(Square::G7), "G8" => Ok(Square::G8),
+            "H1" => Ok(Square::H1), "H2" => Ok(Square::H2), "H3" => Ok(Square::H3), "H4" => Ok(Square::H4),
+            "H5" => Ok(Square::H5), "H6" => Ok(Square::H6), "H7" => Ok(Square::H7), "H8" => Ok(Square::H8),
+            _ => Err(ChessError::InvalidSquare(s.to_string())),
+        }
+    }
+}
+
+impl Default for Position {
+    fn default() -> Position {
+        Position::new()
+    }
+}
+
+impl Default for GameState {
+    fn default() -> GameState {
+        GameState::new()
+    }
+}
+
+impl fmt::Display for GameState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.position.fmt(f)
+    }
+}
+
+// > implement Display for GameState with labeled rows and columns
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // let white_bg = "\x1b[47m"; // Escape code for white background
+        // let blue_bg = "\x1b[44m"; // Escape code for grey background
+        let cancel = "\x1b[0m"; // Escape code to cancel color
+        // let white_fg = "\x1b[37m"; // Escape code for white foreground
+        // let black_fg = "\x1b[30m"; // Escape code for black foreground
+        writeln!(f, "  A B C D E F G H")?;
+        for (i, row) in self.board.iter().rev().enumerate() {
+            write!(f, "{} ", 8 - i)?; // Row labels (8 to 1)
+            for piece in row.iter() {
+                use ChessPiece::*; // These were manual edits to make the code shorter.
+                use Colour::*;
+                let symbol = match piece {
+                    Pawn(White) => "♟",
+                    Knight(White) => "♞",
+                    Bishop(White) => "♝",
+                    Rook(White) => "♜",
+                    Queen(White) => "♛",
+                    King(White) => "♚",
+                    Pawn(Black) => "♙",
+                    Knight(Black) => "♘",
+                    Bishop(Black) => "♗",
+                    Rook(Black) => "♖",
+                    Queen(Black) => "♕",
+                    King(Black) => "♔",
+                    Blank => ".",
+                };
+                write!(f, "{} {}", symbol, cancel)?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f, "  A B C D E F G H")?;
+        writeln!(f, "Move {}, Current Player: {:?}", self.fullmove_number(), self.current_player)
+    }
+}
+
+impl Position {
+    // > add a constructor for the starting position using an 8x8 const array
+    pub fn new() -> Self {
+        use ChessPiece::*; // These were manual edits to make the code shorter.
+        use Colour::*;
+        const INITIAL_BOARD: [[ChessPiece; 8]; 8] = [
+            [Rook(White), Knight(White), Bishop(White), Queen(White),
+                King(White), Bishop(White), Knight(White), Rook(White)],
+            [Pawn(White); 8],
+            [Blank; 8],
+            [Blank; 8],
+            [Blank; 8],
+            [Blank; 8],
+            [Pawn(Black); 8],
+            [Rook(Black), Knight(Black), Bishop(Black), Queen(Black),
+                King(Black), Bishop(Black), Knight(Black), Rook(Black)],
+        ];
+
+        let mut position = Position {
+            board: INITIAL_BOARD,
+            current_player: Colour::White,
+            en_passant: None,
+            castling: CastlingRights::ALL,
+            hash: 0,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            kings: [Some(Square::E1), Some(Square::E8)],
+            checkers: 0,
+        };
+        position.hash = zobrist::hash(&zobrist::KEYS, &position);
+        position
+    }
+
+    // > add accessors for the move counters
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    pub fn fullmove_number(&self) -> u32 {
+        self.fullmove_number
+    }
+
+    // > add accessors for the rest of the position, for programs using the library that can't see the fields
+    pub fn side_to_move(&self) -> Colour {
+        self.current_player
+    }
+
+    pub fn board(&self) -> &[[ChessPiece; 8]; 8] {
+        &self.board
+    }
+
+    pub fn piece_at(&self, square: Square) -> ChessPiece {
+        let (row, col) = square.to_row_col();
+        self.board[row][col]
+    }
+
+    // > iterate over the pieces on the board with their squares, rank by rank from a1, skipping empty squares
+    pub fn pieces(&self) -> impl Iterator<Item = (Square, ChessPiece)> {
+        all_squares().map(|square| (square, self.piece_at(square))).filter(|&(_, piece)| piece != ChessPiece::Blank)
+    }
+
+    // > iterate over the pieces of one side with their squares, in the same order as pieces
+    pub fn pieces_of(&self, colour: Colour) -> impl Iterator<Item = (Square, ChessPiece)> {
+        self.pieces().filter(move |&(_, piece)| piece.colour() == Some(colour))
+    }
+
+    // > give the key of the parts of the position that matter for repetition: board, side to move, castling and en passant
+    pub fn position_key(&self) -> u64 {
+        // The en passant square only makes a position different if a capture onto it is actually possible.
+        let mut scratch = *self;
+        let en_passant = self.en_passant.filter(|&square| {
+            let (row, col) = square.to_row_col();
+            let pawn_row = match self.current_player {
+                Colour::White => row - 1,
+                Colour::Black => row + 1,
+            };
+            [col.wrapping_sub(1), col + 1].into_iter().any(|pawn_col| {
+                pawn_col < 8
+                    && self.board[pawn_row][pawn_col] == ChessPiece::Pawn(self.current_player)
+                    && scratch.keeps_king_safe(self.complete_move(Move::new(Square::from_row_col(pawn_row, pawn_col), square)))
+            })
+        });
+
+        match en_passant {
+            Some(square) => self.hash ^ zobrist::KEYS[zobrist::EN_PASSANT_OFFSET + square.to_row_col().1],
+            None => self.hash,
+        }
+    }
+
+    // > list the squares of all pieces of the given colour attacking a square
+    pub fn attackers_of(&self, square: Square, colour: Colour) -> Vec<Square> {
+        rules::attackers(&self.board, square.to_row_col(), colour)
+    }
+
+    // > list the pieces of the given colour that are pinned to their king
+    pub fn pinned_pieces(&self, colour: Colour) -> Vec<Pin> {
+        rules::find_pins(&self.board, colour)
+    }
+
+    // > check that the position is one that could arise in a real game
+    pub fn validate_position(&self) -> Result<(), ChessError> {
+        rules::validate_position(&self.board, self.current_player)
+    }
+
+    // > add a method that tells whether the game is drawn because neither side can checkmate
+    fn is_insufficient_material(&self) -> bool {
+        rules::is_insufficient_material(&self.board)
+    }
+
+    // > add a method that tells whether the given side's king is attacked
+    pub fn is_in_check(&self, colour: Colour) -> bool {
+        if colour == self.current_player {
+            return self.checkers != 0;
+        }
+        match self.king_square(colour) {
+            Some((row, col)) => rules::is_square_attacked(&self.board, (row, col), colour.opponent()),
+            None => false,
+        }
+    }
+
+    // > list the enemy pieces giving check to the side to move; two of them is a double check
+    pub fn checkers(&self) -> Vec<Square> {
+        bitboard::squares(self.checkers).map(|square| Square::from_row_col(square / 8, square % 8)).collect()
+    }
+
+    // > find the pieces checking the side to move by looking at every enemy piece, for a position that has been set up
+    fn find_checkers(&self) -> bitboard::Bitboard {
+        match self.king_square(self.current_player) {
+            Some((row, col)) => self.attackers_of(Square::from_row_col(row, col), self.current_player.opponent()).iter().fold(0, |checkers, square| {
+                let (row, col) = square.to_row_col();
+                checkers | bitboard::bit(row, col)
+            }),
+            None => 0,
+        }
+    }
+
+    // > find the pieces checking the side to move after a move, from the move alone: the piece that moved, attacking
+    // > from where it landed, and the rooks, bishops and queens behind the squares the move emptied
+    // Before the move the mover gave no check, as the other side would otherwise have been able to take the king.
+    fn checks_after(&self, mv: Move, castling: CastlingRights) -> bitboard::Bitboard {
+        let mover = self.current_player.opponent();
+        let Some(king) = self.king_square(self.current_player) else {
+            return 0;
+        };
+        let (from_row, from_col) = mv.from.to_row_col();
+        let (landing_row, landing_col) = mv.landing().to_row_col();
+        let landing = landing_row * 8 + landing_col;
+        let king_bit = bitboard::bit(king.0, king.1);
+        let mut checkers = match self.board[landing_row][landing_col] {
+            ChessPiece::Pawn(_) if bitboard::pawn_attacks(mover, landing) & king_bit != 0 => bitboard::bit(landing_row, landing_col),
+            ChessPiece::Knight(_) if bitboard::KNIGHT_ATTACKS[landing] & king_bit != 0 => bitboard::bit(landing_row, landing_col),
+            _ => self.slider_check(king, (landing_row, landing_col), mover),
+        };
+        checkers |= self.slider_check(king, (from_row, from_col), mover);
+        if mv.en_passant {
+            checkers |= self.slider_check(king, (from_row, mv.to.file()), mover);
+        }
+        if mv.castling {
+            let kingside = mv.to.file() > from_col;
+            checkers |= self.slider_check(king, (from_row, rules::castling_rook_target(kingside)), mover);
+            if let Some(rook_from) = castling.rook_col(mover, kingside) {
+                checkers |= self.slider_check(king, (from_row, rook_from), mover);
+            }
+        }
+        checkers
+    }
+
+    // > give the rook, bishop or queen of the given colour, if any, that attacks the king along the line from it
+    // > through a square, looking past empty squares to the first piece
+    fn slider_check(&self, king: (usize, usize), through: (usize, usize), by: Colour) -> bitboard::Bitboard {
+        let aligned = king.0 == through.0 || king.1 == through.1 || king.0.abs_diff(through.0) == king.1.abs_diff(through.1);
+        if !aligned || king == through {
+            return 0;
+        }
+        let step = |from: usize, to: usize| to.cmp(&from) as isize;
+        let (row_step, col_step) = (step(king.0, through.0), step(king.1, through.1));
+        let straight = row_step == 0 || col_step == 0;
+        let (mut row, mut col) = (king.0.wrapping_add_signed(row_step), king.1.wrapping_add_signed(col_step));
+        while row < 8 && col < 8 {
+            let slides = match self.board[row][col] {
+                ChessPiece::Blank => {
+                    (row, col) = (row.wrapping_add_signed(row_step), col.wrapping_add_signed(col_step));
+                    continue;
+                }
+                ChessPiece::Queen(colour) => colour == by,
+                ChessPiece::Rook(colour) => colour == by && straight,
+                ChessPiece::Bishop(colour) => colour == by && !straight,
+                _ => false,
+            };
+            return if slides { bitboard::bit(row, col) } else { 0 };
+        }
+        0
+    }
+
+    // > find the king of the given colour from where the last move left it, searching the board only if it is not there
+    fn king_square(&self, colour: Colour) -> Option<(usize, usize)> {
+        match self.kings[colour as usize].map(Square::to_row_col) {
+            Some((row, col)) if self.board[row][col] == ChessPiece::King(colour) => Some((row, col)),
+            _ => rules::find_king(&self.board, colour),
+        }
+    }
+
+    // > note where both kings stand, for a board that has been set up rather than played to
+    fn locate_kings(&mut self) {
+        for colour in [Colour::White, Colour::Black] {
+            self.kings[colour as usize] = rules::find_king(&self.board, colour).map(|(row, col)| Square::from_row_col(row, col));
+        }
+    }
+
+    // > finish a position that has been set up rather than played to: find the kings, check that it could
+    // > arise in a game, and work out its key
+    fn finish_setup(mut self) -> Result<Position, ChessError> {
+        self.locate_kings();
+        self.validate_position()?;
+        self.hash = zobrist::hash(&zobrist::KEYS, &self);
+        self.checkers = self.find_checkers();
+        Ok(self)
+    }
+
+    // > add a method that tells whether the side to move has been checkmated
+    pub fn is_checkmate(&self) -> bool {
+        self.is_in_check(self.current_player) && !self.has_legal_move()
+    }
+
+    // > add a method that tells whether the side to move has any legal move at all
+    fn has_legal_move(&self) -> bool {
+        !self.legal_moves().is_empty()
+    }
+
+    // > list every legal move for the side to move, including castling, en passant and all four promotions
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let mut moves = MoveList::new();
+        self.generate_moves(&mut moves);
+        moves.to_vec()
+    }
+
+    // > write the legal moves into a list the caller provides, in the same order as legal_moves, so a search
+    // > need not allocate at every node
+    /// Writes the legal moves into `moves`, replacing whatever it held, in the same order as
+    /// [`legal_moves`](Position::legal_moves). Keep the list and pass it in again for the next
+    /// position rather than making a new one: nothing is allocated either way.
+    pub fn generate_moves(&self, moves: &mut MoveList) {
+        let mut scratch = *self;
+        scratch.write_moves(moves);
+    }
+
+    fn write_moves(&mut self, moves: &mut MoveList) {
+        moves.clear();
+        self.visit_moves(|mv| moves.push(mv));
+    }
+
+    // > call visit with each legal move, in the order of legal_moves, one for each promotion piece
+    // Moves are tried on this board and taken back, leaving it as it was.
+    fn visit_moves(&mut self, mut visit: impl FnMut(Move)) {
+        let colour = self.current_player;
+        let king = self.king_square(colour).map_or(0, |(row, col)| row * 8 + col);
+        // Any move but the king's must block or capture a single checker, and none will do against two.
+        let targets = match self.checkers.count_ones() {
+            0 => !0,
+            1 => bitboard::BETWEEN[king][self.checkers.trailing_zeros() as usize] | self.checkers,
+            _ => 0,
+        };
+        let pinned = self.pinned_pieces(colour).iter().fold(0, |pinned, pin| {
+            let (row, col) = pin.pinned.to_row_col();
+            pinned | bitboard::bit(row, col)
+        });
+        let pieces = bitboard::Pieces::new(&self.board, colour);
+        for square in bitboard::squares(pieces.own()) {
+            let (row, col) = (square / 8, square % 8);
+            let piece = self.board[row][col];
+            if targets == 0 && piece != ChessPiece::King(colour) {
+                continue;
+            }
+            // A pinned piece may only move along the line through its king and the piece pinning it.
+            let allowed = match pinned & bitboard::bit(row, col) {
+                0 => targets,
+                _ => targets & bitboard::LINE[king][square],
+            };
+            let from = Square::from_row_col(row, col);
+            for to in bitboard::squares(self.destinations(square, piece, &pieces)) {
+                let mv = self.complete_move(Move::new(from, Square::from_row_col(to / 8, to % 8)));
+                // The king's own moves, and en passant, where two pawns leave the rank at once and can uncover
+                // the king, are played out to test them.
+                let legal = match piece != ChessPiece::King(colour) && !mv.en_passant {
+                    true => allowed & bitboard::bit(to / 8, to % 8) != 0,
+                    false => self.keeps_king_safe(mv),
+                };
+                if !legal {
+                    continue;
+                }
+                match mv.promotion {
+                    Some(_) => {
+                        for piece in [
+                            ChessPiece::Queen(colour),
+                            ChessPiece::Rook(colour),
+                            ChessPiece::Bishop(colour),
+                            ChessPiece::Knight(colour),
+                        ] {
+                            visit(mv.with_promotion(piece));
+                        }
+                    }
+                    None => visit(mv),
+                }
+            }
+        }
+    }
+
+    // > give the squares the piece on a square can move to by the way it moves, whether or not that leaves its
+    // > king in check: the knight and king tables and the magic lookups, less the mover's own pieces, then pawn
+    // > pushes, captures and en passant, and castling
+    fn destinations(&self, square: usize, piece: ChessPiece, pieces: &bitboard::Pieces) -> bitboard::Bitboard {
+        let (row, col) = (square / 8, square % 8);
+        let not_own = !pieces.own();
+        match piece {
+            ChessPiece::Knight(_) => bitboard::KNIGHT_ATTACKS[square] & not_own,
+            ChessPiece::Bishop(_) => bitboard::bishop_attacks(square, pieces.occupied) & not_own,
+            ChessPiece::Rook(_) => bitboard::rook_attacks(square, pieces.occupied) & not_own,
+            ChessPiece::Queen(_) => (bitboard::bishop_attacks(square, pieces.occupied) | bitboard::rook_attacks(square, pieces.occupied)) & not_own,
+            ChessPiece::Pawn(colour) => {
+                let (forward, start_row): (isize, usize) = if colour == Colour::White { (1, 1) } else { (-1, 6) };
+                let empty = |row: usize| self.board[row][col] == ChessPiece::Blank;
+                let one = row.wrapping_add_signed(forward);
+                let mut targets = 0;
+                if one < 8 && empty(one) {
+                    targets |= bitboard::bit(one, col);
+                    let two = one.wrapping_add_signed(forward);
+                    if row == start_row && empty(two) {
+                        targets |= bitboard::bit(two, col);
+                    }
+                }
+                let en_passant = self.en_passant.map_or(0, |square| {
+                    let (row, col) = square.to_row_col();
+                    bitboard::bit(row, col)
+                });
+                targets | (bitboard::pawn_attacks(colour, square) & (pieces.occupied & not_own | en_passant))
+            }
+            ChessPiece::King(colour) => {
+                let mut targets = bitboard::KING_ATTACKS[square] & not_own;
+                let king_col = self.castling.king_col(colour);
+                for kingside in [false, true] {
+                    let rook_col = self.castling.rook_col(colour, kingside);
+                    if rules::validate_castling(&self.board, colour, (row, col), kingside, king_col, rook_col).is_err() {
+                        continue;
+                    }
+                    // Castling is written as the king's move to where it castles to, or onto its rook when that is
+                    // less than two squares away, as is_castling reads it.
+                    let king_to = rules::castling_king_target(kingside);
+                    let to_col = match col.abs_diff(king_to) {
+                        0 | 1 => rook_col.expect("castling was validated"),
+                        _ => king_to,
+                    };
+                    targets |= bitboard::bit(row, to_col);
+                }
+                targets
+            }
+            ChessPiece::Blank => 0,
+        }
+    }
+
+    // > count the legal moves for either side without building a move list
+    pub fn mobility(&self, colour: Colour) -> usize {
+        let mut scratch = *self;
+        if colour != self.current_player {
+            // Count as if it were the other side's turn; an en passant chance only ever belongs to the side to move.
+            scratch.current_player = colour;
+            scratch.en_passant = None;
+            scratch.checkers = scratch.find_checkers();
+        }
+
+        let mut count = 0;
+        scratch.visit_moves(|_| count += 1);
+        count
+    }
+
+    // > list the squares the piece on `from` can legally move to, so a UI can highlight them
+    pub fn legal_moves_from(&self, from: Square) -> Vec<Square> {
+        let mut position = *self;
+        position.legal_destinations(from)
+    }
+
+    fn legal_destinations(&mut self, from: Square) -> Vec<Square> {
+        let mut destinations = Vec::new();
+        self.visit_moves(|mv| {
+            // The four promotions of a pawn all go to the same square.
+            if mv.from == from && destinations.last() != Some(&mv.to) {
+                destinations.push(mv.to);
+            }
+        });
+        destinations
+    }
+
+    // > tell whether a move that follows the rules of movement leaves the mover's king out of check
+    fn keeps_king_safe(&mut self, mv: Move) -> bool {
+        let mover = self.current_player;
+        let undo = self.apply_move(mv);
+        let safe = !self.is_in_check(mover);
+        self.undo_move(&undo);
+        safe
+    }
+
+    // > fill in the promotion and special move flags of a move from the current position
+    pub fn complete_move(&self, mv: Move) -> Move {
+        let (from_row, from_col) = mv.from.to_row_col();
+        let (to_row, _) = mv.to.to_row_col();
+        let piece = self.board[from_row][from_col];
+        let is_pawn = piece.kind() == Some(PieceKind::Pawn);
+        let promotion = match piece {
+            // A pawn that reaches the far rank without a promotion piece becomes a queen.
+            ChessPiece::Pawn(colour) if to_row == 0 || to_row == 7 => {
+                mv.promotion.or(Some(ChessPiece::Queen(colour)))
+            }
+            _ => mv.promotion,
+        };
+        Move {
+            promotion,
+            castling: self.is_castling(piece, mv),
+            en_passant: is_pawn && Some(mv.to) == self.en_passant,
+            double_push: is_pawn && from_row.abs_diff(to_row) == 2,
+            ..mv
+        }
+    }
+
+    // > tell whether a king move is castling: the king goes from where it castles from to the square it castles
+    // > to, or, when that is less than two squares away, onto the rook it castles with
+    fn is_castling(&self, piece: ChessPiece, mv: Move) -> bool {
+        let ChessPiece::King(colour) = piece else {
+            return false;
+        };
+        let (from_row, from_col) = mv.from.to_row_col();
+        let (to_row, to_col) = mv.to.to_row_col();
+        let back_row = if colour == Colour::White { 0 } else { 7 };
+        let kingside = to_col > from_col;
+        let king_to = rules::castling_king_target(kingside);
+        if from_row == back_row && to_row == back_row && from_col == self.castling.king_col(colour) && from_col != to_col {
+            return match from_col.abs_diff(king_to) {
+                0 | 1 => self.castling.rook_col(colour, kingside) == Some(to_col),
+                _ => to_col == king_to,
+            };
+        }
+        // Any other king move of two squares along a rank is taken for castling, so it is turned away as castling
+        // rather than for its shape.
+        from_row == to_row && from_col.abs_diff(to_col) == 2
+    }
+
+    // > check that a move obeys the movement rules and return it with its flags filled in
+    fn validate_move(&self, mv: Move) -> Result<Move, ChessError> {
+        let (from_row, from_col) = mv.from.to_row_col();
+        let (to_row, to_col) = mv.to.to_row_col();
+
+        let piece = self.board[from_row][from_col];
+        if piece == ChessPiece::Blank {
+            return Err(ChessError::NoPieceAtSource);
+        }
+
+        if piece.colour() != Some(self.current_player) {
+            return Err(ChessError::WrongTurn);
+        }
+
+        let mv = self.complete_move(mv);
+        if let Some(promotion) = mv.promotion {
+            if piece.kind() != Some(PieceKind::Pawn) || (to_row != 0 && to_row != 7) {
+                return Err(ChessError::InvalidPromotion);
+            }
+            let kind = promotion.kind();
+            if promotion.colour() != Some(self.current_player) || matches!(kind, Some(PieceKind::Pawn | PieceKind::King) | None) {
+                return Err(ChessError::InvalidPromotion);
+            }
+        }
+
+        if mv.castling {
+            let kingside = to_col > from_col;
+            rules::validate_castling(
+                &self.board,
+                self.current_player,
+                (from_row, from_col),
+                kingside,
+                self.castling.king_col(self.current_player),
+                self.castling.rook_col(self.current_player, kingside),
+            )?;
+            return Ok(mv);
+        }
+
+        let en_passant = self.en_passant.map(Square::to_row_col);
+        rules::validate_geometry(piece, (from_row, from_col), (to_row, to_col))?;
+        rules::validate_path(&self.board, piece, (from_row, from_col), (to_row, to_col))?;
+        rules::validate_destination(&self.board, piece, (to_row, to_col))?;
+        rules::validate_pawn(&self.board, piece, (from_row, from_col), (to_row, to_col), en_passant)?;
+        Ok(mv)
+    }
+
+    // > play a move on the position alone, so a search can copy positions about rather than whole games;
+    // > the move is checked as GameState::make_move checks it
+    /// Plays a legal move, returning what [`unmake_move`](Position::unmake_move) needs to take it back.
+    /// Unlike [`GameState::make_move`] it keeps no history, so it does not notice repetitions or the
+    /// end of the game.
+    ///
+    /// ```
+    /// use overload_vibe_coding::Position;
+    ///
+    /// // Count the positions three plies from the start, the first steps of a search.
+    /// fn count(position: &mut Position, depth: u32) -> usize {
+    ///     if depth == 0 {
+    ///         return 1;
+    ///     }
+    ///     let mut total = 0;
+    ///     for mv in position.legal_moves() {
+    ///         let undo = position.make_move(mv).unwrap();
+    ///         total += count(position, depth - 1);
+    ///         position.unmake_move(undo);
+    ///     }
+    ///     total
+    /// }
+    ///
+    /// let mut position = Position::new();
+    /// assert_eq!(count(&mut position, 3), 8902);
+    /// assert_eq!(position, Position::new());
+    /// ```
+    pub fn make_move(&mut self, mv: Move) -> Result<PositionUndo, ChessError> {
+        let mv = self.validate_move(mv)?;
+        let mover = self.current_player;
+        let undo = self.apply_move(mv);
+        if self.is_in_check(mover) {
+            self.undo_move(&undo);
+            return Err(ChessError::LeavesKingInCheck);
+        }
+        Ok(undo)
+    }
+
+    // > take back the last move made with make_move
+    pub fn unmake_move(&mut self, undo: PositionUndo) {
+        self.undo_move(&undo);
+    }
+
+    // > update the board for a move that has already been validated, returning what is needed to undo it
+    fn apply_move(&mut self, mv: Move) -> PositionUndo {
+        let (from_row, from_col) = mv.from.to_row_col();
+        let (to_row, to_col) = mv.to.to_row_col();
+        let piece = self.board[from_row][from_col];
+        let hash = self.hash;
+
+        // An en passant capture removes the pawn beside the mover rather than the one on the target square.
+        let captured = if mv.en_passant {
+            self.put(from_row, to_col, ChessPiece::Blank)
+        } else if mv.castling {
+            // The king may land on its own rook's square; that is not a capture.
+            ChessPiece::Blank
+        } else {
+            self.board[to_row][to_col]
+        };
+        let undo = PositionUndo {
+            mv,
+            captured,
+            castling: self.castling,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            hash,
+            checkers: self.checkers,
+        };
+
+        // A double push leaves the skipped square open to en passant for one move only.
+        self.en_passant = if mv.double_push {
+            Some(Square::from_row_col((from_row + to_row) / 2, from_col))
+        } else {
+            None
+        };
+
+        if captured != ChessPiece::Blank || piece.kind() == Some(PieceKind::Pawn) {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+
+        // The rook is lifted first, as the king may land on the square it started from.
+        let castling_rook = if mv.castling {
+            let kingside = to_col > from_col;
+            let rook_from = self.castling.rook_col(self.current_player, kingside).expect("castling was validated");
+            let rook = self.put(from_row, rook_from, ChessPiece::Blank);
+            Some((rook, rules::castling_rook_target(kingside)))
+        } else {
+            None
+        };
+
+        // A castling king may be written as taking its own rook, so it goes where castling puts it instead.
+        let (landing_row, landing_col) = mv.landing().to_row_col();
+        self.put(from_row, from_col, ChessPiece::Blank);
+        self.put(landing_row, landing_col, mv.promotion.unwrap_or(piece));
+        if piece == ChessPiece::King(self.current_player) {
+            self.kings[self.current_player as usize] = Some(mv.landing());
+        }
+        if let Some((rook, rook_to)) = castling_rook {
+            self.put(from_row, rook_to, rook);
+        }
+        self.hash ^= zobrist::castling_key(&zobrist::KEYS, &self.castling);
+        self.castling.update(mv.from, mv.to);
+        self.hash ^= zobrist::castling_key(&zobrist::KEYS, &self.castling);
+
+        if self.current_player == Colour::Black {
+            self.fullmove_number += 1;
+        }
+
+        // Switch the current player
+        self.current_player = self.current_player.opponent();
+        self.hash ^= zobrist::turn_key(&zobrist::KEYS, Colour::White);
+        self.checkers = self.checks_after(mv, undo.castling);
+        undo
+    }
+
+    // > put a piece (or nothing) on a square, keeping the key up to date, and give back what was there
+    fn put(&mut self, row: usize, col: usize, piece: ChessPiece) -> ChessPiece {
+        let old = core::mem::replace(&mut self.board[row][col], piece);
+        self.hash ^= zobrist::piece_key(&zobrist::KEYS, old, row, col) ^ zobrist::piece_key(&zobrist::KEYS, piece, row, col);
+        old
+    }
+
+    // > restore the board exactly as it was before apply_move
+    fn undo_move(&mut self, undo: &PositionUndo) {
+        let mv = undo.mv;
+        let (from_row, from_col) = mv.from.to_row_col();
+        let (to_row, to_col) = mv.to.to_row_col();
+
+        self.current_player = self.current_player.opponent();
+        if self.current_player == Colour::Black {
+            self.fullmove_number -= 1;
+        }
+
+        if mv.castling {
+            // Both pieces are lifted before either is put back, as in Chess960 each may return to where the other stood.
+            let kingside = to_col > from_col;
+            let rook_from = undo.castling.rook_col(self.current_player, kingside).expect("castling was validated");
+            self.board[from_row][rules::castling_king_target(kingside)] = ChessPiece::Blank;
+            self.board[from_row][rules::castling_rook_target(kingside)] = ChessPiece::Blank;
+            self.board[from_row][from_col] = ChessPiece::King(self.current_player);
+            self.board[from_row][rook_from] = ChessPiece::Rook(self.current_player);
+            self.kings[self.current_player as usize] = Some(mv.from);
+        } else {
+            let piece = match mv.promotion {
+                Some(_) => ChessPiece::Pawn(self.current_player),
+                None => self.board[to_row][to_col],
+            };
+            self.board[from_row][from_col] = piece;
+            if piece == ChessPiece::King(self.current_player) {
+                self.kings[self.current_player as usize] = Some(mv.from);
+            }
+            if mv.en_passant {
+                self.board[to_row][to_col] = ChessPiece::Blank;
+                self.board[from_row][to_col] = undo.captured;
+            } else {
+                self.board[to_row][to_col] = undo.captured;
+            }
+        }
+
+        self.castling = undo.castling;
+        self.en_passant = undo.en_passant;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.hash = undo.hash;
+        self.checkers = undo.checkers;
+    }
+}
+
+impl GameState {
+    // > start a new game from the starting position
+    pub fn new() -> Self {
+        GameState::from_position(Position::new())
+    }
+
+    // > start a game from a position, with no moves played yet
+    pub fn from_position(position: Position) -> GameState {
+        let mut game_state = GameState {
+            position,
+            position_history: Vec::new(),
+            result: GameResult::Ongoing,
+            draw_offer: None,
+        };
+        game_state.position_history.push(game_state.position_key());
+        game_state.update_result();
+        game_state
+    }
+
+    // > give the position on its own, without the history and result of the game
+    pub fn position(&self) -> &Position {
+        &self.position
+    }
+
+    pub fn result(&self) -> GameResult {
+        self.result
+    }
+
+    // > give the player whose draw offer is waiting for an answer, if any
+    pub fn draw_offer(&self) -> Option<Colour> {
+        self.draw_offer
+    }
+
+    // > count how many times the current position has occurred in the game
+    fn repetition_count(&self) -> usize {
+        let key = self.position_key();
+        self.position_history.iter().filter(|&&k| k == key).count()
+    }
+
+    // > add a method that tells whether a draw can be claimed by threefold repetition
+    pub fn is_threefold_repetition(&self) -> bool {
+        self.repetition_count() >= 3
+    }
+
+    // > list every legal move for the side to move; once the game is over there are none
+    pub fn legal_moves(&self) -> Vec<Move> {
+        match self.result {
+            GameResult::Ongoing => self.position.legal_moves(),
+            _ => Vec::new(),
+        }
+    }
+
+    // > write the legal moves into a list the caller provides, leaving it empty once the game is over
+    pub fn generate_moves(&self, moves: &mut MoveList) {
+        match self.result {
+            GameResult::Ongoing => self.position.generate_moves(moves),
+            _ => moves.clear(),
+        }
+    }
+
+    // > list the squares the piece on `from` can legally move to; once the game is over there are none
+    pub fn legal_moves_from(&self, from: Square) -> Vec<Square> {
+        match self.result {
+            GameResult::Ongoing => self.position.legal_moves_from(from),
+            _ => Vec::new(),
+        }
+    }
+
+    // > implement make_move, returning the information needed to take the move back
+    pub fn make_move(&mut self, mv: Move) -> Result<UndoInfo, ChessError> {
+        if self.result != GameResult::Ongoing {
+            return Err(ChessError::GameOver);
+        }
+        let mover = self.current_player;
+        let undo = UndoInfo {
+            position: self.position.make_move(mv)?,
+            result: self.result,
+            draw_offer: self.draw_offer,
+        };
+
+        // Replying with a move declines the opponent's draw offer.
+        if self.draw_offer == Some(mover.opponent()) {
+            self.draw_offer = None;
+        }
+
+        let key = self.position_key();
+        self.position_history.push(key);
+        self.update_result();
+        Ok(undo)
+    }
+
+    // > take back the last move made with make_move
+    pub fn unmake_move(&mut self, undo: UndoInfo) {
+        self.position_history.pop();
+        self.position.unmake_move(undo.position);
+        self.result = undo.result;
+        self.draw_offer = undo.draw_offer;
+    }
+
+    // > decide whether the game has ended after the last move
+    fn update_result(&mut self) {
+        self.result = if self.is_checkmate() {
+            match self.current_player {
+                Colour::White => GameResult::BlackWins,
+                Colour::Black => GameResult::WhiteWins,
+            }
+        } else if !self.has_legal_move() {
+            GameResult::Draw(DrawReason::Stalemate)
+        } else if self.is_insufficient_material() {
+            GameResult::Draw(DrawReason::InsufficientMaterial)
+        } else if self.repetition_count() >= 5 {
+            // FIDE ends the game automatically on the fifth repetition or after 75 moves by each
+            // side without a capture or pawn move, whether or not anyone claims it.
+            GameResult::Draw(DrawReason::FivefoldRepetition)
+        } else if self.halfmove_clock >= 150 {
+            GameResult::Draw(DrawReason::SeventyFiveMoveRule)
+        } else {
+            GameResult::Ongoing
+        };
+    }
+
+    // > let the side to move claim a draw by threefold repetition or the fifty-move rule
+    pub fn claim_draw(&mut self) -> Result<(), ChessError> {
+        if self.result != GameResult::Ongoing {
+            return Err(ChessError::GameOver);
+        }
+        let reason = if self.is_threefold_repetition() {
+            DrawReason::ThreefoldRepetition
+        } else if self.halfmove_clock >= 100 {
+            DrawReason::FiftyMoveRule
+        } else {
+            return Err(ChessError::NoDrawToClaim);
+        };
+        self.result = GameResult::Draw(reason);
+        Ok(())
+    }
+
+    // > describe a legal move (capture, check, castle, promotion, en passant) without playing it
+    pub fn classify_move(&self, mv: &Move) -> Result<MoveKind, ChessError> {
+        let mv = self.validate_move(*mv)?;
+        let (to_row, to_col) = mv.to.to_row_col();
+        let capture = mv.en_passant || self.board[to_row][to_col] != ChessPiece::Blank;
+
+        let mut after = self.clone();
+        after.make_move(mv)?;
+        let check = after.is_in_check(after.current_player);
+        Ok(MoveKind {
+            capture,
+            check,
+            checkmate: check && !after.has_legal_move(),
+            castle: mv.castling,
+            promotion: mv.promotion.is_some(),
+            en_passant: mv.en_passant,
+        })
+    }
+
+    // > let a player resign, giving the game to the opponent
+    pub fn resign(&mut self, colour: Colour) -> Result<(), ChessError> {
+        if self.result != GameResult::Ongoing {
+            return Err(ChessError::GameOver);
+        }
+        self.result = match colour {
+            Colour::White => GameResult::BlackWins,
+            Colour::Black => GameResult::WhiteWins,
+        };
+        Ok(())
+    }
+
+    // > let a player offer a draw, which stands until the opponent accepts it or replies with a move
+    pub fn offer_draw(&mut self, colour: Colour) -> Result<(), ChessError> {
+        if self.result != GameResult::Ongoing {
+            return Err(ChessError::GameOver);
+        }
+        self.draw_offer = Some(colour);
+        Ok(())
+    }
+
+    // > let a player accept the opponent's draw offer
+    pub fn accept_draw(&mut self, colour: Colour) -> Result<(), ChessError> {
+        if self.result != GameResult::Ongoing {
+            return Err(ChessError::GameOver);
+        }
+        if self.draw_offer != Some(colour.opponent()) {
+            return Err(ChessError::NoDrawOffer);
+        }
+        self.result = GameResult::Draw(DrawReason::Agreement);
+        self.draw_offer = None;
+        Ok(())
+    }
+
+    // > check that a move can be played in the game: the game is not over and the move obeys the movement rules
+    fn validate_move(&self, mv: Move) -> Result<Move, ChessError> {
+        if self.result != GameResult::Ongoing {
+            return Err(ChessError::GameOver);
+        }
+        self.position.validate_move(mv)
+    }
+}
+
+// > iterate over every square on the board
+fn all_squares() -> impl Iterator<Item = Square> {
+    (0..8).flat_map(|row| (0..8).map(move |col| Square::from_row_col(row, col)))
+}
+
+// > convert a letter such as "q" or "N" into the promotion piece for the given colour
+pub fn promotion_piece(letter: &str, colour: Colour) -> Option<ChessPiece> {
+    match letter.to_ascii_lowercase().as_str() {
+        "q" => Some(ChessPiece::Queen(colour)),
+        "r" => Some(ChessPiece::Rook(colour)),
+        "b" => Some(ChessPiece::Bishop(colour)),
+        "n" => Some(ChessPiece::Knight(colour)),
+        _ => None,
+    }
+}
+
+// > test that to_row_col returns (0,0) for A1
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_row_col_a1() {
+        let square = Square::A1;
+        let (row, col) = square.to_row_col();
+        assert_eq!((row, col), (0, 0));
+    }
+
+    #[test]
+    fn test_to_row_col_h8() {
+        let square = Square::H8;
+        let (row, col) = square.to_row_col();
+        assert_eq!((row, col), (7, 7));
+    }
+
+    #[test]
+    fn test_to_row_col_e4() {
+        let square = Square::E4;
+        let (row, col) = square.to_row_col();
+        assert_eq!((row, col), (3, 4));
+    }
+
+    #[test]
+    fn test_from_str_valid_square() {
+        let square: Square = "A1".parse().unwrap();
+        assert_eq!(square, Square::A1);
+    }
+
+    #[test]
+    fn test_from_str_invalid_square() {
+        let square: Result<Square, _> = "Z9".parse();
+        assert!(square.is_err());
+    }
+
+    #[test]
+    fn test_game_state_initialization() {
+        let game_state = GameState::new();
+        assert_eq!(game_state.board[0][0], ChessPiece::Rook(Colour::White));
+        assert_eq!(game_state.board[7][7], ChessPiece::Rook(Colour::Black));
+        assert_eq!(game_state.current_player, Colour::White);
+    }
+
+    #[test]
+    fn test_make_move_rejects_illegal_geometry() {
+        let mut game_state = GameState::new();
+        assert!(game_state.make_move(Move::new(Square::G1, Square::G3)).is_err());
+        assert_eq!(game_state.current_player, Colour::White);
+        assert!(game_state.make_move(Move::new(Square::G1, Square::F3)).is_ok());
+        assert_eq!(game_state.current_player, Colour::Black);
+    }
+
+    #[test]
+    fn test_errors_identify_the_rule_broken() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        assert_eq!(game_state.make_move(Move::new(E4, E5)).unwrap_err(), ChessError::NoPieceAtSource);
+        assert_eq!(game_state.make_move(Move::new(E7, E5)), Err(ChessError::WrongTurn));
+        assert_eq!(
+            game_state.make_move(Move::new(G1, G3)),
+            Err(ChessError::IllegalGeometry(ChessPiece::Knight(Colour::White)))
+        );
+        assert_eq!(
+            game_state.make_move(Move::new(A1, A3)),
+            Err(ChessError::PathBlocked(ChessPiece::Rook(Colour::White)))
+        );
+        assert_eq!(game_state.make_move(Move::new(D1, D2)), Err(ChessError::CaptureOwnPiece));
+        assert_eq!(game_state.make_move(Move::new(E1, G1)), Err(ChessError::CastlingPathBlocked));
+        assert_eq!("Z9".parse::<Square>(), Err(ChessError::InvalidSquare("Z9".to_string())));
+    }
+
+    #[test]
+    fn test_make_move_rejects_jumping_over_pieces() {
+        let mut game_state = GameState::new();
+        assert!(game_state.make_move(Move::new(Square::A1, Square::A3)).is_err());
+        assert!(game_state.make_move(Move::new(Square::F1, Square::C4)).is_err());
+    }
+
+    #[test]
+    fn test_make_move_rejects_capturing_own_piece() {
+        let mut game_state = GameState::new();
+        assert!(game_state.make_move(Move::new(Square::D1, Square::D2)).is_err());
+        assert_eq!(game_state.board[1][3], ChessPiece::Pawn(Colour::White));
+    }
+
+    #[test]
+    fn test_pawn_promotes_to_queen() {
+        let mut game_state = GameState::new();
+        game_state.board[6][0] = ChessPiece::Pawn(Colour::White);
+        assert!(game_state.make_move(Move::new(Square::A7, Square::B8)).is_ok());
+        assert_eq!(game_state.board[7][1], ChessPiece::Queen(Colour::White));
+    }
+
+    #[test]
+    fn test_pawn_under_promotion() {
+        let mut game_state = GameState::new();
+        game_state.board[6][0] = ChessPiece::Pawn(Colour::White);
+        let mv = Move::new(Square::A7, Square::B8);
+        assert!(game_state.make_move(mv.with_promotion(ChessPiece::King(Colour::White))).is_err());
+        assert!(game_state.make_move(mv.with_promotion(ChessPiece::Knight(Colour::Black))).is_err());
+        assert!(game_state.make_move(mv.with_promotion(ChessPiece::Knight(Colour::White))).is_ok());
+        assert_eq!(game_state.board[7][1], ChessPiece::Knight(Colour::White));
+    }
+
+    #[test]
+    fn test_only_pawns_on_last_rank_promote() {
+        let mut game_state = GameState::new();
+        let mv = Move::new(Square::E2, Square::E4).with_promotion(ChessPiece::Queen(Colour::White));
+        assert!(game_state.make_move(mv).is_err());
+    }
+
+    #[test]
+    fn test_legal_moves_include_every_promotion() {
+        let mut game_state = GameState::new();
+        game_state.board[6][0] = ChessPiece::Pawn(Colour::White);
+        let promotions: Vec<Move> = game_state
+            .legal_moves()
+            .into_iter()
+            .filter(|mv| mv.from == Square::A7 && mv.to == Square::B8)
+            .collect();
+        assert_eq!(promotions.len(), 4);
+    }
+
+    #[test]
+    fn test_complete_move_sets_flags() {
+        use Square::*;
+        let game_state = GameState::new();
+        assert!(game_state.complete_move(Move::new(E2, E4)).double_push);
+        assert!(!game_state.complete_move(Move::new(E2, E3)).double_push);
+        assert!(game_state.complete_move(Move::new(E1, G1)).castling);
+    }
+
+    #[test]
+    fn test_from_row_col_round_trips() {
+        assert_eq!(Square::from_row_col(3, 4), Square::E4);
+        assert_eq!(Square::from_row_col(7, 0), Square::A8);
+        assert_eq!(Square::from_row_col(0, 7), Square::H1);
+    }
+
+    #[test]
+    fn test_square_file_and_rank() {
+        assert_eq!((Square::E4.file(), Square::E4.rank()), (4, 3));
+        assert_eq!((Square::A8.file(), Square::A8.rank()), (0, 7));
+        assert_eq!((Square::H1.file(), Square::H1.rank()), (7, 0));
+        for (index, square) in Square::ALL.into_iter().enumerate() {
+            assert_eq!(square as usize, index);
+            // The row is the rank and the column the file.
+            assert_eq!(square.to_row_col(), (square.rank(), square.file()));
+            assert_eq!(Square::from_file_rank(square.file(), square.rank()), Some(square));
+            assert_eq!(square.to_string().parse::<Square>().unwrap(), square);
+        }
+        assert_eq!(Square::from_file_rank(8, 0), None);
+        assert_eq!(Square::from_file_rank(0, 8), None);
+    }
+
+    #[test]
+    fn test_pieces() {
+        let game_state = GameState::new();
+        assert_eq!(game_state.pieces().count(), 32);
+        assert_eq!(game_state.pieces().next(), Some((Square::A1, ChessPiece::Rook(Colour::White))));
+        assert_eq!(game_state.pieces().last(), Some((Square::H8, ChessPiece::Rook(Colour::Black))));
+        assert!(game_state.pieces_of(Colour::Black).all(|(square, piece)| square.rank() >= 6 && piece.colour() == Some(Colour::Black)));
+
+        let game_state = GameState::from_fen("8/8/4k3/8/8/3QK3/8/8 w - - 0 1").unwrap();
+        let white: Vec<_> = game_state.pieces_of(Colour::White).collect();
+        assert_eq!(white, vec![(Square::D3, ChessPiece::Queen(Colour::White)), (Square::E3, ChessPiece::King(Colour::White))]);
+        assert_eq!(game_state.pieces_of(Colour::Black).collect::<Vec<_>>(), vec![(Square::E6, ChessPiece::King(Colour::Black))]);
+    }
+
+    #[test]
+    fn test_square_display() {
+        assert_eq!(Square::E4.to_string(), "e4");
+        assert_eq!(Square::A1.to_string(), "a1");
+        assert_eq!(Square::H8.to_string(), "h8");
+    }
+
+    #[test]
+    fn test_square_offset_and_distance() {
+        assert_eq!(Square::E4.offset(1, 2), Some(Square::F6));
+        assert_eq!(Square::E4.offset(-4, -3), Some(Square::A1));
+        assert_eq!(Square::A1.offset(-1, 0), None);
+        assert_eq!(Square::H8.offset(0, 1), None);
+        assert_eq!(Square::E4.distance(Square::E4), 0);
+        assert_eq!(Square::E4.distance(Square::F6), 2);
+        assert_eq!(Square::A1.distance(Square::H8), 7);
+        assert_eq!(Square::B1.distance(Square::H2), 6);
+    }
+
+    #[test]
+    fn test_piece_kind_and_colour() {
+        for kind in [PieceKind::Pawn, PieceKind::Knight, PieceKind::Bishop, PieceKind::Rook, PieceKind::Queen, PieceKind::King] {
+            for colour in [Colour::White, Colour::Black] {
+                let piece = ChessPiece::new(kind, colour);
+                assert_eq!((piece.kind(), piece.colour()), (Some(kind), Some(colour)));
+                assert!(piece.is_enemy_of(colour.opponent()) && !piece.is_enemy_of(colour));
+            }
+        }
+        assert_eq!(ChessPiece::new(PieceKind::Knight, Colour::Black), ChessPiece::Knight(Colour::Black));
+        assert_eq!((ChessPiece::Blank.kind(), ChessPiece::Blank.colour()), (None, None));
+        assert!(!ChessPiece::Blank.is_enemy_of(Colour::White) && !ChessPiece::Blank.is_enemy_of(Colour::Black));
+    }
+
+    fn play(game_state: &mut GameState, moves: &[(Square, Square)]) {
+        for &(from, to) in moves {
+            game_state.make_move(Move::new(from, to)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_en_passant_capture() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        play(&mut game_state, &[(E2, E4), (A7, A6), (E4, E5), (D7, D5)]);
+        assert_eq!(game_state.en_passant, Some(D6));
+        assert!(game_state.make_move(Move::new(E5, D6)).is_ok());
+        assert_eq!(game_state.board[5][3], ChessPiece::Pawn(Colour::White));
+        assert_eq!(game_state.board[4][3], ChessPiece::Blank);
+        assert_eq!(game_state.en_passant, None);
+    }
+
+    #[test]
+    fn test_en_passant_expires_after_one_move() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        play(&mut game_state, &[(E2, E4), (A7, A6), (E4, E5), (D7, D5), (H2, H3), (H7, H6)]);
+        assert!(game_state.make_move(Move::new(E5, D6)).is_err());
+    }
+
+    #[test]
+    fn test_castling_kingside() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        play(&mut game_state, &[(E2, E4), (E7, E5), (G1, F3), (B8, C6), (F1, C4), (G8, F6)]);
+        assert!(game_state.make_move(Move::new(E1, G1)).is_ok());
+        assert_eq!(game_state.board[0][6], ChessPiece::King(Colour::White));
+        assert_eq!(game_state.board[0][5], ChessPiece::Rook(Colour::White));
+        assert_eq!(game_state.board[0][7], ChessPiece::Blank);
+        assert!(!game_state.castling.allows(Colour::White, false));
+    }
+
+    #[test]
+    fn test_castling_queenside() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        play(&mut game_state, &[(D2, D4), (D7, D5), (B1, C3), (B8, C6), (C1, F4), (C8, F5), (D1, D2), (D8, D7)]);
+        assert!(game_state.make_move(Move::new(E1, C1)).is_ok());
+        assert!(game_state.make_move(Move::new(E8, C8)).is_ok());
+        assert_eq!(game_state.board[7][2], ChessPiece::King(Colour::Black));
+        assert_eq!(game_state.board[7][3], ChessPiece::Rook(Colour::Black));
+    }
+
+    #[test]
+    fn test_castling_with_inner_rook() {
+        use Square::*;
+        // The kingside right belongs to the rook on g1, so the king lands where the rook started.
+        let mut game_state = GameState::from_fen("4k3/8/8/8/8/8/8/1R2K1R1 w GB - 0 1").unwrap();
+        assert!(game_state.legal_moves().iter().any(|mv| mv.castling && mv.to == G1));
+        assert!(game_state.make_move(Move::new(E1, G1)).is_ok());
+        assert_eq!(game_state.board[0][6], ChessPiece::King(Colour::White));
+        assert_eq!(game_state.board[0][5], ChessPiece::Rook(Colour::White));
+        assert_eq!(game_state.to_fen(), "4k3/8/8/8/8/8/8/1R3RK1 b - - 1 1");
+
+        let mut game_state = GameState::from_fen("4k3/8/8/8/8/8/8/1R2K1R1 w GB - 0 1").unwrap();
+        assert!(game_state.make_move(Move::new(E1, C1)).is_ok());
+        assert_eq!(game_state.to_fen(), "4k3/8/8/8/8/8/8/2KR2R1 b - - 1 1");
+    }
+
+    #[test]
+    fn test_chess960_castling() {
+        use Square::*;
+        // Beside the square it castles to, the king is moved onto its own rook.
+        let mut game_state = GameState::from_fen("5k1r/8/8/8/8/8/8/RK5R w KQk - 0 1").unwrap();
+        assert!(game_state.make_move(Move::new(B1, A1)).is_ok());
+        assert_eq!(game_state.to_fen(), "5k1r/8/8/8/8/8/8/2KR3R b k - 1 1");
+        assert!(game_state.make_move(Move::new(F8, H8)).is_ok());
+        assert_eq!(game_state.to_fen(), "5rk1/8/8/8/8/8/8/2KR3R w - - 2 2");
+
+        // A king on g1 castling kingside stays where it is, and one on f1 takes its rook as well; a king
+        // stepping to g1 is an ordinary move.
+        let mut game_state = GameState::from_fen("4k3/8/8/8/8/8/8/R5KR w K - 0 1").unwrap();
+        assert!(game_state.make_move(Move::new(G1, H1)).is_ok());
+        assert_eq!(game_state.to_fen(), "4k3/8/8/8/8/8/8/R4RK1 b - - 1 1");
+        let mut game_state = GameState::from_fen("4k3/8/8/8/8/8/8/R4K1R w KQ - 0 1").unwrap();
+        let before = game_state.clone();
+        assert!(game_state.make_move(Move::new(F1, H1)).is_ok());
+        assert_eq!(game_state.to_fen(), "4k3/8/8/8/8/8/8/R4RK1 b - - 1 1");
+        game_state = before.clone();
+        assert!(game_state.make_move(Move::new(F1, G1)).is_ok());
+        assert_eq!(game_state.to_fen(), "4k3/8/8/8/8/8/8/R5KR b - - 1 1");
+        game_state = before;
+        assert!(game_state.make_move(Move::new(F1, C1)).is_ok());
+        assert_eq!(game_state.to_fen(), "4k3/8/8/8/8/8/8/2KR3R b - - 1 1");
+    }
+
+    #[test]
+    fn test_castling_requires_empty_squares() {
+        let mut game_state = GameState::new();
+        assert!(game_state.make_move(Move::new(Square::E1, Square::G1)).is_err());
+    }
+
+    #[test]
+    fn test_castling_forbidden_after_king_moves() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        play(&mut game_state, &[(E2, E4), (E7, E5), (G1, F3), (B8, C6), (F1, C4), (G8, F6), (E1, E2), (A7, A6), (E2, E1), (A6, A5)]);
+        assert!(game_state.make_move(Move::new(E1, G1)).is_err());
+    }
+
+    #[test]
+    fn test_castling_forbidden_through_attacked_square() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        // The bishop on a6 covers f1, which the king would have to cross.
+        play(&mut game_state, &[(E2, E4), (B7, B6), (G1, F3), (C8, A6), (G2, G3), (E7, E6)]);
+        game_state.board[0][5] = ChessPiece::Blank;
+        assert!(game_state.make_move(Move::new(E1, G1)).is_err());
+    }
+
+    #[test]
+    fn test_is_in_check() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        assert!(!game_state.is_in_check(Colour::White));
+        play(&mut game_state, &[(E2, E4), (F7, F6), (D2, D4), (G7, G5), (D1, H5)]);
+        assert!(game_state.is_in_check(Colour::Black));
+        assert!(!game_state.is_in_check(Colour::White));
+        assert_eq!(game_state.attackers_of(E8, Colour::White), vec![H5]);
+    }
+
+    #[test]
+    fn test_cannot_move_pinned_piece() {
+        use ChessPiece::*;
+        use Colour::*;
+        let mut game_state = GameState::new();
+        game_state.board = [[Blank; 8]; 8];
+        game_state.board[0][0] = King(White);
+        game_state.board[1][4] = Rook(White);
+        game_state.board[6][4] = Knight(Black);
+        game_state.board[7][4] = King(Black);
+        game_state.current_player = Black;
+        assert!(game_state.make_move(Move::new(Square::E7, Square::C6)).is_err());
+        assert!(game_state.make_move(Move::new(Square::E8, Square::D8)).is_ok());
+    }
+
+    #[test]
+    fn test_cannot_ignore_check() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        play(&mut game_state, &[(E2, E4), (F7, F5), (D1, H5)]);
+        let err = game_state.make_move(Move::new(A7, A6)).unwrap_err();
+        assert_eq!(err, ChessError::LeavesKingInCheck);
+        assert!(game_state.make_move(Move::new(E8, F7)).is_err());
+        assert!(game_state.make_move(Move::new(G7, G6)).is_ok());
+    }
+
+    #[test]
+    fn test_king_cannot_move_into_check() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        play(&mut game_state, &[(E2, E4), (E7, E5), (E1, E2), (D8, H4)]);
+        assert!(game_state.make_move(Move::new(E2, F3)).is_ok());
+        assert!(game_state.make_move(Move::new(A7, A6)).is_ok());
+        assert!(game_state.make_move(Move::new(F3, G4)).is_err());
+    }
+
+    #[test]
+    fn test_fools_mate_is_checkmate() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        play(&mut game_state, &[(F2, F3), (E7, E5), (G2, G4)]);
+        assert!(!game_state.is_checkmate());
+        play(&mut game_state, &[(D8, H4)]);
+        assert!(game_state.is_checkmate());
+    }
+
+    #[test]
+    fn test_check_that_can_be_blocked_is_not_checkmate() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        play(&mut game_state, &[(E2, E4), (F7, F5), (D1, H5)]);
+        assert!(game_state.is_in_check(Colour::Black));
+        assert!(!game_state.is_checkmate());
+    }
+
+    #[test]
+    fn test_threefold_repetition() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        let shuffle = [(G1, F3), (G8, F6), (F3, G1), (F6, G8)];
+        play(&mut game_state, &shuffle);
+        assert_eq!(game_state.repetition_count(), 2);
+        assert!(!game_state.is_threefold_repetition());
+        play(&mut game_state, &shuffle);
+        assert!(game_state.is_threefold_repetition());
+    }
+
+    #[test]
+    fn test_repetition_depends_on_castling_rights() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        play(&mut game_state, &[(E2, E4), (E7, E5)]);
+        // The king's walk loses castling rights, so the first position never recurs.
+        let shuffle = [(E1, E2), (E8, E7), (E2, E1), (E7, E8)];
+        play(&mut game_state, &shuffle);
+        play(&mut game_state, &shuffle);
+        assert_eq!(game_state.repetition_count(), 2);
+    }
+
+    #[test]
+    fn test_uncapturable_en_passant_square_is_ignored() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        // After 1. e4 the en passant square e3 cannot be captured on, so knight moves that return
+        // to the same placement repeat the position reached straight after the double push.
+        play(&mut game_state, &[(E2, E4), (G8, F6), (G1, F3), (F6, G8), (F3, G1)]);
+        assert_eq!(game_state.repetition_count(), 2);
+    }
+
+    #[test]
+    fn test_legal_moves_from_start() {
+        let mut game_state = GameState::new();
+        assert_eq!(game_state.legal_moves().len(), 20);
+        game_state.make_move(Move::new(Square::E2, Square::E4)).unwrap();
+        assert_eq!(game_state.legal_moves().len(), 20);
+    }
+
+    #[test]
+    fn test_legal_moves_after_checkmate_is_empty() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        play(&mut game_state, &[(F2, F3), (E7, E5), (G2, G4), (D8, H4)]);
+        assert!(game_state.legal_moves().is_empty());
+    }
+
+    #[test]
+    fn test_legal_moves_only_evade_check() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        play(&mut game_state, &[(E2, E4), (F7, F5), (D1, H5)]);
+        let moves = game_state.legal_moves();
+        assert_eq!(moves, vec![Move::new(G7, G6)]);
+    }
+
+    #[test]
+    fn test_legal_moves_include_special_moves() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        play(&mut game_state, &[(E2, E4), (A7, A6), (E4, E5), (D7, D5), (G1, F3), (A6, A5), (F1, E2), (A5, A4)]);
+        let moves = game_state.legal_moves();
+        assert!(moves.iter().any(|mv| mv.from == E1 && mv.to == G1 && mv.castling));
+        assert!(!moves.iter().any(|mv| mv.from == E5 && mv.to == D6));
+
+        let mut game_state = GameState::new();
+        play(&mut game_state, &[(E2, E4), (A7, A6), (E4, E5), (D7, D5)]);
+        assert!(game_state.legal_moves().iter().any(|mv| mv.from == E5 && mv.to == D6 && mv.en_passant));
+    }
+
+    #[test]
+    fn test_legal_moves_from_square() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        assert_eq!(game_state.legal_moves_from(G1), vec![F3, H3]);
+        assert_eq!(game_state.legal_moves_from(E2), vec![E3, E4]);
+        assert!(game_state.legal_moves_from(E7).is_empty());
+        assert!(game_state.legal_moves_from(E4).is_empty());
+
+        play(&mut game_state, &[(E2, E4), (F7, F5), (D1, H5)]);
+        assert_eq!(game_state.legal_moves_from(G7), vec![G6]);
+        assert!(game_state.legal_moves_from(E8).is_empty());
+    }
+
+    #[test]
+    fn test_legal_moves_from_includes_castling() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        play(&mut game_state, &[(E2, E4), (E7, E5), (G1, F3), (B8, C6), (F1, C4), (G8, F6)]);
+        assert_eq!(game_state.legal_moves_from(E1), vec![F1, G1, E2]);
+    }
+
+    #[test]
+    fn test_result_after_checkmate() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        assert_eq!(game_state.result, GameResult::Ongoing);
+        play(&mut game_state, &[(F2, F3), (E7, E5), (G2, G4), (D8, H4)]);
+        assert_eq!(game_state.result, GameResult::BlackWins);
+        assert!(game_state.make_move(Move::new(A2, A3)).is_err());
+    }
+
+    #[test]
+    fn test_result_after_stalemate() {
+        use ChessPiece::*;
+        use Colour::*;
+        let mut game_state = GameState::new();
+        game_state.board = [[Blank; 8]; 8];
+        game_state.board[7][0] = King(Black);
+        game_state.board[5][1] = King(White);
+        game_state.board[5][3] = Queen(White);
+        game_state.make_move(Move::new(Square::D6, Square::C7)).unwrap();
+        assert_eq!(game_state.result, GameResult::Draw(DrawReason::Stalemate));
+    }
+
+    #[test]
+    fn test_result_after_insufficient_material() {
+        use ChessPiece::*;
+        use Colour::*;
+        let mut game_state = GameState::new();
+        game_state.board = [[Blank; 8]; 8];
+        game_state.board[0][0] = King(White);
+        game_state.board[2][2] = Knight(White);
+        game_state.board[7][7] = King(Black);
+        game_state.board[4][3] = Rook(Black);
+        game_state.make_move(Move::new(Square::C3, Square::D5)).unwrap();
+        assert_eq!(game_state.result, GameResult::Draw(DrawReason::InsufficientMaterial));
+    }
+
+    #[test]
+    fn test_claim_draw() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        assert!(game_state.claim_draw().is_err());
+        let shuffle = [(G1, F3), (G8, F6), (F3, G1), (F6, G8)];
+        play(&mut game_state, &shuffle);
+        play(&mut game_state, &shuffle);
+        assert!(game_state.claim_draw().is_ok());
+        assert_eq!(game_state.result, GameResult::Draw(DrawReason::ThreefoldRepetition));
+        assert!(game_state.make_move(Move::new(E2, E4)).is_err());
+    }
+
+    #[test]
+    fn test_fivefold_repetition_ends_game() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        let shuffle = [(G1, F3), (G8, F6), (F3, G1), (F6, G8)];
+        for _ in 0..3 {
+            play(&mut game_state, &shuffle);
+        }
+        assert_eq!(game_state.result, GameResult::Ongoing);
+        play(&mut game_state, &shuffle);
+        assert_eq!(game_state.result, GameResult::Draw(DrawReason::FivefoldRepetition));
+    }
+
+    #[test]
+    fn test_halfmove_clock_resets_on_pawn_moves_and_captures() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        play(&mut game_state, &[(G1, F3), (G8, F6)]);
+        assert_eq!(game_state.halfmove_clock, 2);
+        play(&mut game_state, &[(E2, E4)]);
+        assert_eq!(game_state.halfmove_clock, 0);
+        play(&mut game_state, &[(B8, C6), (B1, C3), (F6, E4)]);
+        assert_eq!(game_state.halfmove_clock, 0);
+    }
+
+    #[test]
+    fn test_fullmove_number() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        assert_eq!(game_state.fullmove_number(), 1);
+        play(&mut game_state, &[(E2, E4)]);
+        assert_eq!(game_state.fullmove_number(), 1);
+        play(&mut game_state, &[(E7, E5)]);
+        assert_eq!(game_state.fullmove_number(), 2);
+        play(&mut game_state, &[(G1, F3)]);
+        assert_eq!((game_state.fullmove_number(), game_state.halfmove_clock()), (2, 1));
+    }
+
+    #[test]
+    fn test_fifty_move_claim_and_seventy_five_move_draw() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        game_state.halfmove_clock = 99;
+        play(&mut game_state, &[(G1, F3)]);
+        assert!(game_state.claim_draw().is_ok());
+        assert_eq!(game_state.result, GameResult::Draw(DrawReason::FiftyMoveRule));
+
+        let mut game_state = GameState::new();
+        game_state.halfmove_clock = 149;
+        play(&mut game_state, &[(G1, F3)]);
+        assert_eq!(game_state.result, GameResult::Draw(DrawReason::SeventyFiveMoveRule));
+    }
+
+    fn assert_unmake_restores(game_state: &mut GameState, mv: Move) {
+        let before = game_state.clone();
+        let undo = game_state.make_move(mv).unwrap();
+        assert_ne!(*game_state, before);
+        game_state.unmake_move(undo);
+        assert_eq!(*game_state, before);
+    }
+
+    #[test]
+    fn test_unmake_quiet_move_and_capture() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        assert_unmake_restores(&mut game_state, Move::new(G1, F3));
+        play(&mut game_state, &[(E2, E4), (D7, D5)]);
+        assert_unmake_restores(&mut game_state, Move::new(E4, D5));
+    }
+
+    #[test]
+    fn test_unmake_en_passant() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        play(&mut game_state, &[(E2, E4), (A7, A6), (E4, E5), (D7, D5)]);
+        assert_unmake_restores(&mut game_state, Move::new(E5, D6));
+    }
+
+    #[test]
+    fn test_unmake_castling() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        play(&mut game_state, &[(E2, E4), (E7, E5), (G1, F3), (B8, C6), (F1, C4), (G8, F6)]);
+        assert_unmake_restores(&mut game_state, Move::new(E1, G1));
+    }
+
+    #[test]
+    fn test_unmake_promotion_and_checkmate() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        game_state.board[6][0] = ChessPiece::Pawn(Colour::White);
+        assert_unmake_restores(&mut game_state, Move::new(A7, B8).with_promotion(ChessPiece::Knight(Colour::White)));
+
+        let mut game_state = GameState::new();
+        play(&mut game_state, &[(F2, F3), (E7, E5), (G2, G4)]);
+        assert_unmake_restores(&mut game_state, Move::new(D8, H4));
+    }
+
+    #[test]
+    fn test_illegal_move_leaves_state_unchanged() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        play(&mut game_state, &[(E2, E4), (F7, F5), (D1, H5)]);
+        let before = game_state.clone();
+        assert!(game_state.make_move(Move::new(A7, A6)).is_err());
+        assert_eq!(game_state, before);
+    }
+
+    #[test]
+    fn test_king_cannot_be_captured() {
+        use ChessPiece::*;
+        use Colour::*;
+        // An illegal set-up where Black is to move but White is already in check.
+        let mut game_state = GameState::new();
+        game_state.board = [[Blank; 8]; 8];
+        game_state.board[0][4] = King(White);
+        game_state.board[7][4] = King(Black);
+        game_state.board[4][4] = Rook(Black);
+        game_state.current_player = Black;
+        assert_eq!(game_state.validate_position(), Err(ChessError::SideNotToMoveInCheck));
+        assert_eq!(game_state.make_move(Move::new(Square::E5, Square::E1)).unwrap_err(), ChessError::CaptureKing);
+    }
+
+    fn discovered_double_check() -> GameState {
+        use ChessPiece::*;
+        use Colour::*;
+        let mut game_state = GameState::new();
+        game_state.board = [[Blank; 8]; 8];
+        game_state.board[0][7] = King(White);
+        game_state.board[0][4] = Rook(White);
+        game_state.board[3][4] = Knight(White);
+        game_state.board[7][4] = King(Black);
+        game_state.board[4][2] = Bishop(Black);
+        game_state.board[6][0] = Rook(Black);
+        game_state
+    }
+
+    #[test]
+    fn test_discovered_check() {
+        use Square::*;
+        let mut game_state = discovered_double_check();
+        // Moving the knight off the e-file uncovers the rook.
+        play(&mut game_state, &[(E4, C3)]);
+        assert!(game_state.is_in_check(Colour::Black));
+        assert_eq!(game_state.checkers(), vec![E1]);
+        let moves = game_state.legal_moves();
+        assert!(moves.contains(&Move::new(A7, E7)));
+        assert!(moves.contains(&Move::new(C5, E3)));
+        assert!(moves.iter().all(|mv| mv.from == E8 || mv.to.to_row_col().1 == 4));
+    }
+
+    #[test]
+    fn test_double_check_allows_only_king_moves() {
+        use Square::*;
+        let mut game_state = discovered_double_check();
+        play(&mut game_state, &[(E4, D6)]);
+        assert_eq!(game_state.checkers(), vec![E1, D6]);
+        let mut destinations: Vec<Square> = game_state.legal_moves().iter().map(|mv| mv.to).collect();
+        destinations.sort_by_key(|square| *square as usize);
+        assert!(game_state.legal_moves().iter().all(|mv| mv.from == E8));
+        assert_eq!(destinations, vec![D7, D8, F8]);
+        // Neither capturing the knight nor blocking the rook deals with both checks.
+        assert_eq!(game_state.make_move(Move::new(C5, D6)).unwrap_err(), ChessError::LeavesKingInCheck);
+        assert_eq!(game_state.make_move(Move::new(A7, E7)).unwrap_err(), ChessError::LeavesKingInCheck);
+    }
+
+    fn en_passant_along_rank() -> GameState {
+        use ChessPiece::*;
+        use Colour::*;
+        let mut game_state = GameState::new();
+        game_state.board = [[Blank; 8]; 8];
+        game_state.board[4][0] = King(White);
+        game_state.board[4][1] = Pawn(White);
+        game_state.board[6][2] = Pawn(Black);
+        game_state.board[4][7] = Rook(Black);
+        game_state.board[7][4] = King(Black);
+        game_state.current_player = Black;
+        game_state
+    }
+
+    #[test]
+    fn test_en_passant_cannot_expose_king_along_rank() {
+        use Square::*;
+        let mut game_state = en_passant_along_rank();
+        play(&mut game_state, &[(C7, C5)]);
+        assert_eq!(game_state.en_passant, Some(C6));
+        // Taking en passant would remove both pawns from the fifth rank and open it to the rook.
+        assert!(!game_state.legal_moves().iter().any(|mv| mv.en_passant));
+        assert_eq!(game_state.make_move(Move::new(B5, C6)).unwrap_err(), ChessError::LeavesKingInCheck);
+        assert!(game_state.make_move(Move::new(B5, B6)).is_ok());
+    }
+
+    #[test]
+    fn test_en_passant_allowed_when_rank_stays_blocked() {
+        use Square::*;
+        let mut game_state = en_passant_along_rank();
+        game_state.board[4][6] = ChessPiece::Knight(Colour::White);
+        play(&mut game_state, &[(C7, C5)]);
+        assert!(game_state.legal_moves().iter().any(|mv| mv.en_passant));
+        assert!(game_state.make_move(Move::new(B5, C6)).is_ok());
+        assert_eq!(game_state.board[4][2], ChessPiece::Blank);
+    }
+
+    #[test]
+    fn test_pinned_en_passant_square_does_not_affect_repetition() {
+        use Square::*;
+        let mut game_state = en_passant_along_rank();
+        play(&mut game_state, &[(C7, C5)]);
+        let with_square = game_state.position_key();
+        game_state.en_passant = None;
+        assert_eq!(game_state.position_key(), with_square);
+    }
+
+    #[test]
+    fn test_mobility_matches_legal_moves() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        assert_eq!(game_state.mobility(Colour::White), 20);
+        assert_eq!(game_state.mobility(Colour::Black), 20);
+        play(&mut game_state, &[(E2, E4), (D7, D5)]);
+        assert_eq!(game_state.mobility(Colour::White), game_state.legal_moves().len());
+        assert_eq!(game_state.mobility(Colour::White), 31);
+        assert_eq!(game_state.mobility(Colour::Black), 29);
+    }
+
+    #[test]
+    fn test_mobility_counts_each_promotion() {
+        let mut game_state = GameState::new();
+        game_state.board[6][0] = ChessPiece::Pawn(Colour::White);
+        assert_eq!(game_state.mobility(Colour::White), game_state.legal_moves().len());
+    }
+
+    #[test]
+    fn test_resign() {
+        let mut game_state = GameState::new();
+        assert!(game_state.resign(Colour::White).is_ok());
+        assert_eq!(game_state.result, GameResult::BlackWins);
+        assert_eq!(game_state.resign(Colour::Black), Err(ChessError::GameOver));
+        assert_eq!(game_state.make_move(Move::new(Square::E2, Square::E4)).unwrap_err(), ChessError::GameOver);
+    }
+
+    #[test]
+    fn test_draw_offer_accepted() {
+        let mut game_state = GameState::new();
+        game_state.offer_draw(Colour::White).unwrap();
+        assert_eq!(game_state.accept_draw(Colour::White), Err(ChessError::NoDrawOffer));
+        play(&mut game_state, &[(Square::E2, Square::E4)]);
+        assert!(game_state.accept_draw(Colour::Black).is_ok());
+        assert_eq!(game_state.result, GameResult::Draw(DrawReason::Agreement));
+    }
+
+    #[test]
+    fn test_draw_offer_declined_by_moving() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        game_state.offer_draw(Colour::White).unwrap();
+        play(&mut game_state, &[(E2, E4), (E7, E5)]);
+        assert_eq!(game_state.draw_offer, None);
+        assert_eq!(game_state.accept_draw(Colour::Black), Err(ChessError::NoDrawOffer));
+    }
+
+    #[test]
+    fn test_classify_move() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        assert_eq!(game_state.classify_move(&Move::new(E2, E4)).unwrap(), MoveKind::default());
+        play(&mut game_state, &[(E2, E4), (F7, F5)]);
+        let before = game_state.clone();
+        let kind = game_state.classify_move(&Move::new(E4, F5)).unwrap();
+        assert_eq!(kind, MoveKind { capture: true, ..MoveKind::default() });
+        let kind = game_state.classify_move(&Move::new(D1, H5)).unwrap();
+        assert_eq!(kind, MoveKind { check: true, ..MoveKind::default() });
+        assert_eq!(kind.to_string(), "check");
+        assert_eq!(game_state, before);
+        assert!(game_state.classify_move(&Move::new(E1, G1)).is_err());
+    }
+
+    #[test]
+    fn test_classify_special_moves() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        play(&mut game_state, &[(F2, F3), (E7, E5), (G2, G4)]);
+        let kind = game_state.classify_move(&Move::new(D8, H4)).unwrap();
+        assert!(kind.check && kind.checkmate);
+        assert_eq!(kind.to_string(), "checkmate");
+
+        let mut game_state = GameState::new();
+        play(&mut game_state, &[(E2, E4), (A7, A6), (E4, E5), (D7, D5)]);
+        let kind = game_state.classify_move(&Move::new(E5, D6)).unwrap();
+        assert_eq!(kind, MoveKind { capture: true, en_passant: true, ..MoveKind::default() });
+
+        let mut game_state = GameState::new();
+        game_state.board[6][0] = ChessPiece::Pawn(Colour::White);
+        let kind = game_state.classify_move(&Move::new(A7, B8)).unwrap();
+        assert_eq!(kind.to_string(), "capture, promotion");
+
+        let mut game_state = GameState::new();
+        play(&mut game_state, &[(E2, E4), (E7, E5), (G1, F3), (B8, C6), (F1, C4), (G8, F6)]);
+        assert!(game_state.classify_move(&Move::new(E1, G1)).unwrap().castle);
+    }
+
+    #[test]
+    fn test_uci_round_trip() {
+        let mv = Move::from_uci("e2e4").unwrap();
+        assert_eq!(mv, Move::new(Square::E2, Square::E4));
+        assert_eq!(mv.to_uci(), "e2e4");
+
+        let mv = Move::from_uci("e7e8q").unwrap();
+        assert_eq!(mv.promotion, Some(ChessPiece::Queen(Colour::White)));
+        assert_eq!(mv.to_uci(), "e7e8q");
+        let mv = Move::from_uci("b2a1n").unwrap();
+        assert_eq!(mv.promotion, Some(ChessPiece::Knight(Colour::Black)));
+        assert_eq!(mv.to_uci(), "b2a1n");
+    }
+
+    #[test]
+    fn test_uci_rejects_bad_input() {
+        for text in ["e2", "e2e9", "e2e4x", "e6e7q", "e7e8k", "e2e4e5"] {
+            assert_eq!(Move::from_uci(text), Err(ChessError::InvalidNotation(text.to_string())));
+        }
+    }
+
+    #[test]
+    fn test_uci_moves_can_be_played() {
+        let mut game_state = GameState::new();
+        for text in ["e2e4", "e7e5", "e1e2"] {
+            game_state.make_move(Move::from_uci(text).unwrap()).unwrap();
+        }
+        assert_eq!(game_state.board[1][4], ChessPiece::King(Colour::White));
+    }
+
+    #[test]
+    fn test_packed_move_round_trip() {
+        assert_eq!(std::mem::size_of::<PackedMove>(), 2);
+        // Between them these have castling, en passant, double pushes and promotions for both sides.
+        let fens = perft::SUITE.iter().map(|&(_, fen, _)| fen).chain(["r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1", "8/8/8/8/8/8/p5k1/4K3 b - - 0 1"]);
+        for fen in fens {
+            for mv in GameState::from_fen(fen).unwrap().legal_moves() {
+                assert_eq!(Move::from(PackedMove::from(mv)), mv, "{} in {}", mv.to_uci(), fen);
+            }
+        }
+        let packed = PackedMove::from(Move::from_uci("b2a1n").unwrap());
+        assert_eq!(packed, PackedMove(4 << 12 | (Square::A1 as u16) << 6 | Square::B2 as u16));
+    }
+
+    #[test]
+    fn test_generate_moves() {
+        // The position with the most legal moves known fits in a move list.
+        let position = GameState::from_fen("R6R/3Q4/1Q4Q1/4Q3/2Q4Q/Q4Q2/pp1Q4/kBNN1KB1 w - - 0 1").unwrap();
+        let before = position.clone();
+        let mut moves = MoveList::new();
+        position.generate_moves(&mut moves);
+        assert_eq!(moves.len(), 218);
+        assert_eq!(position, before);
+        // The list is cleared first, and matches legal_moves in order.
+        let position = GameState::new();
+        position.generate_moves(&mut moves);
+        assert_eq!(moves[..], position.legal_moves()[..]);
+        moves.retain(|mv| mv.double_push);
+        assert_eq!(moves.len(), 8);
+    }
+
+    #[test]
+    fn test_king_square_follows_moves() {
+        use Square::*;
+        let mut game_state = GameState::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let castle = game_state.complete_move(Move::new(E1, G1));
+        let undo = game_state.apply_move(castle);
+        assert_eq!(game_state.kings, [Some(G1), Some(E8)]);
+        game_state.undo_move(&undo);
+        assert_eq!(game_state.kings, [Some(E1), Some(E8)]);
+        // A board set up square by square leaves the noted square behind; the king is found all the same.
+        game_state.board[0][4] = ChessPiece::Blank;
+        game_state.board[1][4] = ChessPiece::King(Colour::White);
+        game_state.board[7][4] = ChessPiece::Rook(Colour::Black);
+        assert_eq!(game_state.king_square(Colour::White), Some((1, 4)));
+        assert_eq!(game_state.find_checkers(), bitboard::bit(7, 4));
+    }
+
+    #[test]
+    fn test_checkers_follow_moves() {
+        use Square::*;
+        // Check given directly by each kind of piece, by discovery, by en passant, by castling and by promotion.
+        let cases = [
+            ("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", Move::new(A1, A8), vec![A8]),
+            ("4k3/8/8/8/8/8/8/4KB2 w - - 0 1", Move::new(F1, B5), vec![B5]),
+            ("4k3/8/8/8/4N3/8/8/4K3 w - - 0 1", Move::new(E4, F6), vec![F6]),
+            ("4k3/8/5P2/8/8/8/8/4K3 w - - 0 1", Move::new(F6, F7), vec![F7]),
+            ("4k3/8/8/8/8/8/5P2/4K3 w - - 0 1", Move::new(F2, F4), vec![]),
+            ("4k3/8/8/8/8/8/4N3/4R1K1 w - - 0 1", Move::new(E2, D4), vec![E1]),
+            ("4k3/8/8/8/4N3/8/8/4R1K1 w - - 0 1", Move::new(E4, F6), vec![E1, F6]),
+            ("8/8/8/1k1pP2R/8/8/8/4K3 w - d6 0 1", Move::new(E5, D6), vec![H5]),
+            ("5k2/8/8/8/8/8/8/4K2R w K - 0 1", Move::new(E1, G1), vec![F1]),
+            ("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", Move::new(B7, B8).with_promotion(ChessPiece::Queen(Colour::White)), vec![B8]),
+            ("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", Move::new(B7, B8).with_promotion(ChessPiece::Knight(Colour::White)), vec![]),
+        ];
+        for (fen, mv, checkers) in cases {
+            let mut position = *GameState::from_fen(fen).expect(fen).position();
+            let before = position;
+            let undo = position.make_move(mv).unwrap();
+            assert_eq!(position.checkers(), checkers, "{}", fen);
+            assert_eq!(position.checkers, position.find_checkers(), "{}", fen);
+            assert_eq!(position.is_in_check(position.current_player), !checkers.is_empty());
+            position.unmake_move(undo);
+            assert_eq!(position, before);
+        }
+    }
+
+    #[test]
+    fn test_position_is_separate_from_game() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        play(&mut game_state, &[(G1, F3), (G8, F6), (F3, G1), (F6, G8)]);
+        let before = *game_state.position();
+        assert_eq!(before.board, Position::new().board);
+        assert_eq!((before.halfmove_clock(), before.fullmove_number()), (4, 3));
+
+        // Moving a copy of the position leaves the game and its history alone.
+        let mut position = before;
+        let mv = position.complete_move(Move::new(E2, E4));
+        let undo = position.apply_move(mv);
+        assert_eq!(position.side_to_move(), Colour::Black);
+        assert_eq!(game_state.position(), &before);
+        assert_eq!(game_state.repetition_count(), 2);
+        position.undo_move(&undo);
+        assert_eq!(position, before);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        use Square::*;
+        let mut game_state = GameState::new();
+        play(&mut game_state, &[(E2, E4), (D7, D5), (E4, D5)]);
+        let json = serde_json::to_string(&game_state).unwrap();
+        assert!(json.contains("\"current_player\":\"Black\""));
+        assert_eq!(serde_json::from_str::<GameState>(&json).unwrap(), game_state);
+
+        let mv = Move::new(E7, E8).with_promotion(ChessPiece::Queen(Colour::White));
+        assert_eq!(serde_json::from_str::<Move>(&serde_json::to_string(&mv).unwrap()).unwrap(), mv);
+    }
+}
@@ -0,0 +1,101 @@
+//! # Mate
+//!
+//! Solves mate-in-N problems by proof rather than by score: a move forces mate in N
+//! when it mates at once, or when every reply leaves a forced mate in N - 1. Every
+//! line is followed to the end, so the answer is exact, and every key move is found,
+//! not just the best one, which is what a composer needs to check that a problem has
+//! one solution and no cooks.
+//!
+//! Each key is given with the fewest moves it forces mate in, so a problem that also
+//! has a quicker mate shows it.
+//!
+//! ```text
+//! matein 2                     # at the prompt: list the moves that force mate in two
+//! ```
+//!
+//! The tree grows with the number of moves to the power of 2N - 1, so beyond three or
+//! four moves the search can take a long time.
+
+use crate::{GameResult, GameState, Move, MoveList};
+
+// The most moves "matein" will look for a mate in.
+pub const MAX_MOVES: u32 = 6;
+
+// > find every move that forces mate in at most n moves, with the fewest moves each needs
+pub fn key_moves(position: &GameState, n: u32) -> Vec<(Move, u32)> {
+    let mut position = position.clone();
+    if position.result != GameResult::Ongoing {
+        return Vec::new();
+    }
+    position
+        .legal_moves()
+        .into_iter()
+        .filter_map(|mv| (1..=n).find(|&moves| forces_mate(&mut position, mv, moves)).map(|moves| (mv, moves)))
+        .collect()
+}
+
+// > tell whether the side to move has a move that forces mate in n moves
+fn has_mate(position: &mut GameState, n: u32) -> bool {
+    let mut moves = MoveList::new();
+    position.generate_moves(&mut moves);
+    moves.iter().any(|&mv| forces_mate(position, mv, n))
+}
+
+// > tell whether a move mates at once, or leaves the opponent no reply that escapes a mate in n - 1 more
+fn forces_mate(position: &mut GameState, mv: Move, n: u32) -> bool {
+    let undo = position.make_move(mv).expect("legal moves can be played");
+    let forced = match position.result {
+        GameResult::WhiteWins | GameResult::BlackWins => true,
+        GameResult::Ongoing if n > 1 => {
+            let mut replies = MoveList::new();
+            position.generate_moves(&mut replies);
+            replies.iter().all(|&reply| {
+                let undo = position.make_move(reply).expect("legal moves can be played");
+                // A reply that draws, by stalemate or otherwise, escapes the mate.
+                let mated = position.result == GameResult::Ongoing && has_mate(position, n - 1);
+                position.unmake_move(undo);
+                mated
+            })
+        }
+        _ => false,
+    };
+    position.unmake_move(undo);
+    forced
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Square;
+
+    fn keys(fen: &str, n: u32) -> Vec<(Square, Square, u32)> {
+        key_moves(&GameState::from_fen(fen).unwrap(), n).into_iter().map(|(mv, moves)| (mv.from, mv.to, moves)).collect()
+    }
+
+    #[test]
+    fn test_mate_in_one() {
+        assert_eq!(keys("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1", 1), vec![(Square::A1, Square::A8, 1)]);
+        // Black has a way out of the back rank, so there is no mate.
+        assert!(keys("6k1/5pp1/7p/8/8/8/5PPP/R5K1 w - - 0 1", 1).is_empty());
+        assert!(keys("6k1/5ppp/8/8/8/8/5PPP/R5K1 b - - 0 1", 3).is_empty());
+    }
+
+    #[test]
+    fn test_mate_in_two() {
+        // The king and rook mate a lone king on the edge; with the king on f6 no check mates at once.
+        let fen = "7k/8/5K2/8/8/8/8/R7 w - - 0 1";
+        assert!(keys(fen, 1).is_empty());
+        let keys = keys(fen, 2);
+        assert!(keys.contains(&(Square::F6, Square::F7, 2)) && keys.contains(&(Square::F6, Square::G6, 2)), "{:?}", keys);
+        // Giving check at once lets the king out.
+        assert!(!keys.iter().any(|&(from, to, _)| (from, to) == (Square::A1, Square::A8)));
+    }
+
+    #[test]
+    fn test_stalemate_is_not_mate() {
+        // Qg6 leaves Black no reply at all, but stalemate is a draw, so it is no key.
+        let keys = keys("7k/5K2/8/6Q1/8/8/8/8 w - - 0 1", 2);
+        assert!(keys.contains(&(Square::G5, Square::G7, 1)));
+        assert!(!keys.iter().any(|&(from, to, _)| (from, to) == (Square::G5, Square::G6)));
+    }
+}
@@ -0,0 +1,192 @@
+//! # Monte Carlo Tree Search
+//!
+//! An alternative to the alpha-beta search, mainly for experimenting and teaching.
+//! Instead of looking at every move to a fixed depth, it grows a tree one position at
+//! a time, spending more visits on the moves that have done well so far while still
+//! trying the others now and then (UCT: the upper confidence bound applied to trees).
+//! Chosen with `--engine-type mcts`:
+//!
+//! ```text
+//! overload-vibe-coding --engine internal --engine-type mcts
+//! ```
+//!
+//! Each iteration walks down the tree to a position with a move not yet tried, plays
+//! it, and finishes with a short rollout of random moves. Random play all the way to
+//! the end of a chess game says little about the position, so the rollout stops after a
+//! few moves and the evaluation, turned into a chance of winning, scores what is left.
+//! The move played is the one visited most.
+
+use std::time::{Duration, Instant};
+
+use crate::{GameResult, GameState, Move, eval};
+
+// How strongly to favour moves that have been tried less; the square root of 2 in theory.
+const EXPLORATION: f64 = 1.4;
+// How many random moves a rollout plays before the evaluation takes over.
+const ROLLOUT_PLIES: u32 = 4;
+// Centipawns at which the chance of winning reaches about 73%.
+const WIN_SCALE: f64 = 400.0;
+
+// > add how long a tree search may run: a number of iterations or a time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Limit {
+    // For searches that come out the same on any machine.
+    #[cfg(test)]
+    Iterations(u32),
+    Time(Duration),
+}
+
+// > add what a tree search found: the most visited move and how well it did
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct MctsResult {
+    pub(crate) best_move: Option<Move>,
+    pub(crate) iterations: u32,
+    pub(crate) visits: u32,
+    // The share of the best move's rollouts won by the side to move, draws counting half.
+    pub(crate) win_rate: f64,
+}
+
+// > add a node of the tree: a position reached by a move, with the moves from it not yet tried
+struct Node {
+    mv: Option<Move>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    untried: Vec<Move>,
+    visits: u32,
+    // The sum of the results for the side that played mv.
+    wins: f64,
+}
+
+impl Node {
+    fn new(mv: Option<Move>, parent: Option<usize>, position: &GameState) -> Node {
+        let untried = if position.result == GameResult::Ongoing { position.legal_moves() } else { Vec::new() };
+        Node { mv, parent, children: Vec::new(), untried, visits: 0, wins: 0.0 }
+    }
+}
+
+// > grow a tree from the position until the limit is reached, and give the most visited move
+pub(crate) fn search(position: &GameState, limit: Limit, seed: u64) -> MctsResult {
+    let start = Instant::now();
+    let mut random = Random(seed | 1);
+    let mut tree = vec![Node::new(None, None, position)];
+    let mut iterations = 0;
+    while tree[0].visits == 0
+        || match limit {
+            #[cfg(test)]
+            Limit::Iterations(count) => iterations < count,
+            Limit::Time(time) => start.elapsed() < time,
+        }
+    {
+        iterations += 1;
+        let mut current = position.clone();
+
+        // Walk down through positions whose moves have all been tried.
+        let mut node = 0;
+        while tree[node].untried.is_empty() && !tree[node].children.is_empty() {
+            node = select(&tree, node);
+            current.make_move(tree[node].mv.expect("only the root has no move")).expect("tree moves are legal");
+        }
+
+        // Try one new move.
+        if !tree[node].untried.is_empty() {
+            let index = random.below(tree[node].untried.len());
+            let mv = tree[node].untried.swap_remove(index);
+            current.make_move(mv).expect("legal moves can be played");
+            tree.push(Node::new(Some(mv), Some(node), &current));
+            let child = tree.len() - 1;
+            tree[node].children.push(child);
+            node = child;
+        }
+
+        // The result for the side to move at the new node counts against the side that moved into it.
+        let mut result = 1.0 - rollout(&mut current, &mut random);
+        let mut next = Some(node);
+        while let Some(index) = next {
+            tree[index].visits += 1;
+            tree[index].wins += result;
+            result = 1.0 - result;
+            next = tree[index].parent;
+        }
+    }
+
+    let best = tree[0].children.iter().copied().max_by_key(|&child| tree[child].visits);
+    MctsResult {
+        best_move: best.and_then(|child| tree[child].mv),
+        iterations,
+        visits: best.map_or(0, |child| tree[child].visits),
+        win_rate: best.map_or(0.0, |child| tree[child].wins / f64::from(tree[child].visits)),
+    }
+}
+
+// > choose the child with the highest upper confidence bound
+fn select(tree: &[Node], node: usize) -> usize {
+    let log_visits = f64::from(tree[node].visits).ln();
+    let bound = |child: usize| {
+        let visits = f64::from(tree[child].visits);
+        tree[child].wins / visits + EXPLORATION * (log_visits / visits).sqrt()
+    };
+    tree[node].children.iter().copied().max_by(|&a, &b| bound(a).total_cmp(&bound(b))).expect("the node has children")
+}
+
+// > play a few random moves, then give the chance that the side to move at the start wins
+fn rollout(position: &mut GameState, random: &mut Random) -> f64 {
+    let mover = position.current_player;
+    for _ in 0..ROLLOUT_PLIES {
+        if position.result != GameResult::Ongoing {
+            break;
+        }
+        let moves = position.legal_moves();
+        position.make_move(moves[random.below(moves.len())]).expect("legal moves can be played");
+    }
+    let chance = match position.result {
+        GameResult::WhiteWins | GameResult::BlackWins => 0.0,
+        GameResult::Draw(_) => 0.5,
+        GameResult::Ongoing => 1.0 / (1.0 + (-f64::from(eval::evaluate(position)) / WIN_SCALE).exp()),
+    };
+    // A finished game is lost by the side to move, so this also holds after a mate.
+    if position.current_player == mover { chance } else { 1.0 - chance }
+}
+
+// A xorshift generator; rollouts need speed, not quality.
+struct Random(u64);
+
+impl Random {
+    fn below(&mut self, bound: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % bound as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Square;
+
+    #[test]
+    fn test_finds_mate_in_one() {
+        let position = GameState::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let result = search(&position, Limit::Iterations(200), 1);
+        let mv = result.best_move.unwrap();
+        assert_eq!((mv.from, mv.to), (Square::A1, Square::A8));
+        assert_eq!(result.iterations, 200);
+        assert_eq!(result.win_rate, 1.0);
+    }
+
+    #[test]
+    fn test_takes_a_hanging_queen() {
+        let position = GameState::from_fen("4k3/8/8/3q4/8/8/8/3QK3 w - - 0 1").unwrap();
+        let mv = search(&position, Limit::Iterations(300), 7).best_move.unwrap();
+        assert_eq!((mv.from, mv.to), (Square::D1, Square::D5));
+    }
+
+    #[test]
+    fn test_game_over_and_time_limit() {
+        let mated = GameState::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        let result = search(&mated, Limit::Iterations(10), 1);
+        assert_eq!(result.best_move, None);
+        let result = search(&GameState::new(), Limit::Time(Duration::from_millis(50)), 1);
+        assert!(result.best_move.is_some() && result.iterations >= 1);
+    }
+}
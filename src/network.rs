@@ -0,0 +1,253 @@
+//! # Network Play
+//!
+//! Two copies of the program playing each other directly over TCP, without a server:
+//!
+//! ```text
+//! overload-vibe-coding --host 7878             # plays White and waits for a connection
+//! overload-vibe-coding --connect myhost:7878   # plays Black
+//! ```
+//!
+//! Each side checks every move it receives with its own rules engine, so a modified
+//! or buggy opponent cannot make an illegal move. Messages are framed as a type byte,
+//! a big-endian 16-bit length and that many bytes of UTF-8:
+//!
+//! | Type | Frame       | Payload                                 |
+//! |------|-------------|-----------------------------------------|
+//! | 1    | hello       | protocol version byte, then starting FEN |
+//! | 2    | move        | the move in UCI                         |
+//! | 3    | resign      |                                         |
+//! | 4    | offer draw  |                                         |
+//! | 5    | accept draw |                                         |
+//! | 6    | goodbye     |                                         |
+//!
+//! The host sends hello as soon as the connection is made.
+
+use std::io::{BufRead, Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+use crate::error::NetworkError;
+use crate::game::Game;
+use crate::{Colour, GameResult, GameState, Move};
+
+const VERSION: u8 = 1;
+
+// > add the messages two players exchange over TCP
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Frame {
+    Hello { version: u8, start: String },
+    Move(String),
+    Resign,
+    OfferDraw,
+    AcceptDraw,
+    Goodbye,
+}
+
+impl Frame {
+    fn write_to(&self, writer: &mut impl Write) -> Result<(), NetworkError> {
+        let (kind, payload) = match self {
+            Frame::Hello { version, start } => (1, [&[*version], start.as_bytes()].concat()),
+            Frame::Move(uci) => (2, uci.as_bytes().to_vec()),
+            Frame::Resign => (3, Vec::new()),
+            Frame::OfferDraw => (4, Vec::new()),
+            Frame::AcceptDraw => (5, Vec::new()),
+            Frame::Goodbye => (6, Vec::new()),
+        };
+        let len = u16::try_from(payload.len()).map_err(|_| NetworkError::InvalidFrame)?;
+        let mut bytes = vec![kind];
+        bytes.extend(len.to_be_bytes());
+        bytes.extend(payload);
+        writer.write_all(&bytes)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn read_from(reader: &mut impl Read) -> Result<Frame, NetworkError> {
+        let mut header = [0; 3];
+        reader.read_exact(&mut header)?;
+        let mut payload = vec![0; usize::from(u16::from_be_bytes([header[1], header[2]]))];
+        reader.read_exact(&mut payload)?;
+        let text = |bytes: &[u8]| String::from_utf8(bytes.to_vec()).map_err(|_| NetworkError::InvalidFrame);
+        match (header[0], payload.as_slice()) {
+            (1, [version, start @ ..]) => Ok(Frame::Hello { version: *version, start: text(start)? }),
+            (2, uci) => Ok(Frame::Move(text(uci)?)),
+            (3, []) => Ok(Frame::Resign),
+            (4, []) => Ok(Frame::OfferDraw),
+            (5, []) => Ok(Frame::AcceptDraw),
+            (6, []) => Ok(Frame::Goodbye),
+            _ => Err(NetworkError::InvalidFrame),
+        }
+    }
+}
+
+// > wait for one opponent on the given port and play the game as White
+pub fn host(port: u16, game: &mut Game) -> Result<(), NetworkError> {
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    println!("Waiting for an opponent on port {}...", port);
+    let (mut stream, address) = listener.accept()?;
+    println!("{} connected.", address);
+    Frame::Hello { version: VERSION, start: game.state().to_fen() }.write_to(&mut stream)?;
+    *game = Game::from_position(game.state().clone());
+    play(stream, Colour::White, game, std::io::stdin().lock(), &mut std::io::stdout())
+}
+
+// > connect to a hosting player and play the game as Black
+pub fn connect(address: &str, game: &mut Game) -> Result<(), NetworkError> {
+    let mut stream = TcpStream::connect(address)?;
+    *game = start_game(&mut stream)?;
+    play(stream, Colour::Black, game, std::io::stdin().lock(), &mut std::io::stdout())
+}
+
+// > read the host's hello and set up the game it describes
+fn start_game(stream: &mut impl Read) -> Result<Game, NetworkError> {
+    match Frame::read_from(stream)? {
+        Frame::Hello { version: VERSION, start } => {
+            Ok(Game::from_position(GameState::from_fen(&start).map_err(NetworkError::InvalidPosition)?))
+        }
+        Frame::Hello { version, .. } => Err(NetworkError::UnsupportedVersion(version)),
+        _ => Err(NetworkError::InvalidFrame),
+    }
+}
+
+// > take turns: read our moves from the input and send them, then receive and check the opponent's
+fn play(
+    mut stream: impl Read + Write,
+    me: Colour,
+    game: &mut Game,
+    mut input: impl BufRead,
+    output: &mut impl Write,
+) -> Result<(), NetworkError> {
+    let opponent = me.opponent();
+    loop {
+        let state = game.state();
+        writeln!(output, "{}", state)?;
+        if state.result != GameResult::Ongoing {
+            writeln!(output, "Game over: {}.", state.result)?;
+            return Ok(());
+        }
+
+        if state.current_player != me {
+            writeln!(output, "Waiting for {:?}...", opponent)?;
+            match Frame::read_from(&mut stream)? {
+                Frame::Move(uci) => {
+                    let illegal = |err| NetworkError::IllegalMove(uci.clone(), err);
+                    let mv = Move::from_uci(&uci).map_err(illegal)?;
+                    let san = state.move_to_san(&mv);
+                    game.play(mv).map_err(illegal)?;
+                    writeln!(output, "{:?} played {}", opponent, san)?;
+                }
+                Frame::Resign => game.resign(opponent).map_err(|_| NetworkError::InvalidFrame)?,
+                Frame::OfferDraw => {
+                    game.offer_draw(opponent).map_err(|_| NetworkError::InvalidFrame)?;
+                    writeln!(output, "{:?} offers a draw. Type 'draw' to accept, or make your move.", opponent)?;
+                }
+                Frame::AcceptDraw => game.accept_draw(opponent).map_err(|_| NetworkError::InvalidFrame)?,
+                Frame::Goodbye => {
+                    writeln!(output, "{:?} has left the game.", opponent)?;
+                    return Ok(());
+                }
+                Frame::Hello { .. } => return Err(NetworkError::InvalidFrame),
+            }
+            continue;
+        }
+
+        writeln!(output, "Enter your move, 'draw', 'resign' or 'quit':")?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 || line.trim().eq_ignore_ascii_case("quit") {
+            Frame::Goodbye.write_to(&mut stream)?;
+            return Ok(());
+        }
+        let line = line.trim();
+        if line.eq_ignore_ascii_case("resign") {
+            game.resign(me).expect("the game is in progress");
+            Frame::Resign.write_to(&mut stream)?;
+        } else if line.eq_ignore_ascii_case("draw") {
+            if state.draw_offer == Some(opponent) {
+                game.accept_draw(me).expect("the opponent offered a draw");
+                Frame::AcceptDraw.write_to(&mut stream)?;
+            } else {
+                match game.offer_draw(me) {
+                    Ok(()) => Frame::OfferDraw.write_to(&mut stream)?,
+                    Err(err) => writeln!(output, "{}", err)?,
+                }
+            }
+        } else {
+            match Move::from_uci(line).or_else(|_| state.parse_san(line)).and_then(|mv| game.play(mv).map(|_| mv)) {
+                Ok(mv) => Frame::Move(mv.to_uci()).write_to(&mut stream)?,
+                Err(err) => writeln!(output, "Invalid move: {}", err)?,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_frame_round_trip() {
+        let frames = [
+            Frame::Hello { version: VERSION, start: GameState::new().to_fen() },
+            Frame::Move("e7e8q".to_string()),
+            Frame::Resign,
+            Frame::OfferDraw,
+            Frame::AcceptDraw,
+            Frame::Goodbye,
+        ];
+        let mut bytes = Vec::new();
+        for frame in &frames {
+            frame.write_to(&mut bytes).unwrap();
+        }
+        assert_eq!(&bytes[bytes.len() - 3..], [6, 0, 0]);
+        let mut reader = Cursor::new(bytes);
+        for frame in &frames {
+            assert_eq!(&Frame::read_from(&mut reader).unwrap(), frame);
+        }
+        assert_eq!(Frame::read_from(&mut reader), Err(NetworkError::Closed));
+        assert_eq!(Frame::read_from(&mut Cursor::new([3, 0, 1, 0])), Err(NetworkError::InvalidFrame));
+        assert_eq!(Frame::read_from(&mut Cursor::new([9, 0, 0])), Err(NetworkError::InvalidFrame));
+    }
+
+    #[test]
+    fn test_start_game() {
+        let mut bytes = Vec::new();
+        Frame::Hello { version: 2, start: String::new() }.write_to(&mut bytes).unwrap();
+        assert_eq!(start_game(&mut Cursor::new(bytes)).map(|_| ()), Err(NetworkError::UnsupportedVersion(2)));
+        let mut bytes = Vec::new();
+        Frame::Hello { version: VERSION, start: "4k3/8/8/8/8/8/8/4K3 b - - 0 1".to_string() }.write_to(&mut bytes).unwrap();
+        assert_eq!(start_game(&mut Cursor::new(bytes)).unwrap().state().current_player, Colour::Black);
+    }
+
+    #[test]
+    fn test_game_over_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let host = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            Frame::Hello { version: VERSION, start: GameState::new().to_fen() }.write_to(&mut stream).unwrap();
+            let mut game = Game::from_position(GameState::new());
+            let input = Cursor::new("f3\ng4\n");
+            play(stream, Colour::White, &mut game, input, &mut Vec::new()).map(|_| game)
+        });
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        let mut game = start_game(&mut stream).unwrap();
+        let mut output = Vec::new();
+        play(stream, Colour::Black, &mut game, Cursor::new("e5\nd4\nQh4\n"), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("Invalid move"), "d4 is not legal for Black");
+        assert!(output.contains("White played g4"));
+        assert!(output.ends_with("Game over: Black wins.\n"));
+        assert_eq!(host.join().unwrap().unwrap().state().result, GameResult::BlackWins);
+    }
+
+    #[test]
+    fn test_illegal_move_is_rejected() {
+        let mut bytes = Vec::new();
+        Frame::Move("e2e5".to_string()).write_to(&mut bytes).unwrap();
+        let mut stream = Cursor::new(bytes);
+        let mut game = Game::from_position(GameState::new());
+        let result = play(&mut stream, Colour::Black, &mut game, Cursor::new(""), &mut Vec::new());
+        assert!(matches!(result, Err(NetworkError::IllegalMove(uci, _)) if uci == "e2e5"));
+    }
+}
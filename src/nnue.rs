@@ -0,0 +1,343 @@
+//! # NNUE
+//!
+//! An efficiently updatable neural network, as an alternative to the handcrafted
+//! evaluation. Networks are read from the `.nnue` files Stockfish 12 introduced, with
+//! the HalfKP 256x2-32-32 architecture:
+//!
+//! ```text
+//! overload-vibe-coding --nnue nn-62ef826d1a6d.nnue     # then "go" at the prompt
+//! setoption name EvalFile value nn-62ef826d1a6d.nnue   # or over UCI
+//! ```
+//!
+//! The first layer has an input for every (king square, piece, square) combination, so
+//! its output, the accumulator, is the sum of the weights of the pieces on the board as
+//! seen from each side's king. A move only changes a few of them, so the search updates
+//! the accumulator by subtracting the weights of the pieces that left their squares and
+//! adding those that arrived, instead of adding up the whole board again. Only when a
+//! king moves does its side's half have to be rebuilt. The small layers after it are
+//! computed in full for each evaluation.
+
+use crate::error::NnueError;
+use crate::{ChessPiece, Colour, GameState, Move, PieceKind};
+
+const VERSION: u32 = 0x7AF3_2F16;
+// The accumulator has this many values for each side.
+const HALF_DIMENSIONS: usize = 256;
+// For each king square: one input for no piece, then 10 kinds of piece on 64 squares.
+const FEATURES_PER_KING: usize = 1 + 10 * 64;
+const FEATURES: usize = 64 * FEATURES_PER_KING;
+const HIDDEN: usize = 32;
+// The hidden layers work in fixed point with 6 fractional bits.
+const WEIGHT_SHIFT: i32 = 6;
+// The output is 16 times Stockfish's internal units, which count 208 to a pawn.
+const OUTPUT_SCALE: i32 = 16;
+const PAWN_VALUE: i32 = 208;
+
+// > add a network read from a .nnue file
+pub struct Network {
+    description: String,
+    feature_biases: Vec<i16>,
+    // HALF_DIMENSIONS weights for each feature, one feature after another.
+    feature_weights: Vec<i16>,
+    hidden1_biases: Vec<i32>,
+    // A row of 2 * HALF_DIMENSIONS inputs for each output.
+    hidden1_weights: Vec<i8>,
+    hidden2_biases: Vec<i32>,
+    hidden2_weights: Vec<i8>,
+    output_bias: i32,
+    output_weights: Vec<i8>,
+}
+
+// > add the first layer's output for each side, kept up to date as moves are made
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Accumulator {
+    // Indexed by colour: White's view, then Black's.
+    values: [Vec<i16>; 2],
+}
+
+impl Network {
+    // > read a network from a file
+    pub fn load(path: &str) -> Result<Network, NnueError> {
+        let bytes = std::fs::read(path).map_err(|err| NnueError::Io(err.to_string()))?;
+        Network::from_bytes(&bytes)
+    }
+
+    // > read a network in the HalfKP 256x2-32-32 format, rejecting any other architecture
+    pub fn from_bytes(bytes: &[u8]) -> Result<Network, NnueError> {
+        let mut reader = Reader { bytes, at: 0 };
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(NnueError::UnsupportedVersion(version));
+        }
+        let _hash = reader.u32()?;
+        let length = reader.u32()? as usize;
+        let description = String::from_utf8_lossy(reader.take(length)?).into_owned();
+
+        let _feature_hash = reader.u32()?;
+        let feature_biases = reader.i16s(HALF_DIMENSIONS)?;
+        let feature_weights = reader.i16s(HALF_DIMENSIONS * FEATURES)?;
+        let _network_hash = reader.u32()?;
+        let hidden1_biases = reader.i32s(HIDDEN)?;
+        let hidden1_weights = reader.i8s(HIDDEN * 2 * HALF_DIMENSIONS)?;
+        let hidden2_biases = reader.i32s(HIDDEN)?;
+        let hidden2_weights = reader.i8s(HIDDEN * HIDDEN)?;
+        let output_bias = reader.u32()? as i32;
+        let output_weights = reader.i8s(HIDDEN)?;
+        if reader.at != bytes.len() {
+            return Err(NnueError::UnsupportedArchitecture(description));
+        }
+        Ok(Network {
+            description,
+            feature_biases,
+            feature_weights,
+            hidden1_biases,
+            hidden1_weights,
+            hidden2_biases,
+            hidden2_weights,
+            output_bias,
+            output_weights,
+        })
+    }
+
+    // > give the description the trainer stored in the file
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    // > build the accumulator for a position from scratch
+    pub(crate) fn accumulator(&self, position: &GameState) -> Accumulator {
+        Accumulator { values: [self.refresh(&position.board, Colour::White), self.refresh(&position.board, Colour::Black)] }
+    }
+
+    // > update the accumulator for a move, given the board before it and the position after it
+    pub(crate) fn update(&self, accumulator: &Accumulator, before: &[[ChessPiece; 8]; 8], after: &GameState, mv: Move) -> Accumulator {
+        let (from_row, from_col) = mv.from.to_row_col();
+        let (to_row, to_col) = mv.to.to_row_col();
+        // The squares a move can change: castling moves a rook along the back rank, en passant takes a pawn beside the target.
+        let mut squares = vec![(from_row, from_col), (to_row, to_col), (from_row, to_col)];
+        if mv.castling {
+            squares.extend((0..8).map(|col| (from_row, col)));
+        }
+        squares.sort_unstable();
+        squares.dedup();
+
+        let mut accumulator = accumulator.clone();
+        for perspective in [Colour::White, Colour::Black] {
+            let values = &mut accumulator.values[perspective as usize];
+            if before[from_row][from_col] == ChessPiece::King(perspective) {
+                *values = self.refresh(&after.board, perspective);
+                continue;
+            }
+            let king = king_square(&after.board, perspective);
+            for &(row, col) in &squares {
+                let (old, new) = (before[row][col], after.board[row][col]);
+                if old == new {
+                    continue;
+                }
+                if let Some(feature) = feature(perspective, king, old, row, col) {
+                    for (value, weight) in values.iter_mut().zip(self.weights(feature)) {
+                        *value = value.wrapping_sub(*weight);
+                    }
+                }
+                if let Some(feature) = feature(perspective, king, new, row, col) {
+                    for (value, weight) in values.iter_mut().zip(self.weights(feature)) {
+                        *value = value.wrapping_add(*weight);
+                    }
+                }
+            }
+        }
+        accumulator
+    }
+
+    // > run the rest of the network on the accumulator, giving centipawns for the side to move
+    pub(crate) fn evaluate(&self, accumulator: &Accumulator, side_to_move: Colour) -> i32 {
+        // The side to move's half comes first, so the network knows whose turn it is.
+        let input: Vec<i32> = [side_to_move, side_to_move.opponent()]
+            .iter()
+            .flat_map(|&colour| accumulator.values[colour as usize].iter().map(|&value| i32::from(value).clamp(0, 127)))
+            .collect();
+        let hidden1 = layer(&input, &self.hidden1_biases, &self.hidden1_weights);
+        let hidden2 = layer(&hidden1, &self.hidden2_biases, &self.hidden2_weights);
+        let output = self.output_bias + hidden2.iter().zip(&self.output_weights).map(|(&x, &w)| x * i32::from(w)).sum::<i32>();
+        output / OUTPUT_SCALE * 100 / PAWN_VALUE
+    }
+
+    // The accumulator half for one side, from the biases and every piece but the kings.
+    fn refresh(&self, board: &[[ChessPiece; 8]; 8], perspective: Colour) -> Vec<i16> {
+        let king = king_square(board, perspective);
+        let mut values = self.feature_biases.clone();
+        for (row, rank) in board.iter().enumerate() {
+            for (col, &piece) in rank.iter().enumerate() {
+                if let Some(feature) = feature(perspective, king, piece, row, col) {
+                    for (value, weight) in values.iter_mut().zip(self.weights(feature)) {
+                        *value = value.wrapping_add(*weight);
+                    }
+                }
+            }
+        }
+        values
+    }
+
+    fn weights(&self, feature: usize) -> &[i16] {
+        &self.feature_weights[feature * HALF_DIMENSIONS..(feature + 1) * HALF_DIMENSIONS]
+    }
+}
+
+// A fully connected layer followed by a clipped ReLU.
+fn layer(input: &[i32], biases: &[i32], weights: &[i8]) -> Vec<i32> {
+    biases
+        .iter()
+        .zip(weights.chunks(input.len()))
+        .map(|(&bias, row)| {
+            let sum = bias + input.iter().zip(row).map(|(&x, &w)| x * i32::from(w)).sum::<i32>();
+            (sum >> WEIGHT_SHIFT).clamp(0, 127)
+        })
+        .collect()
+}
+
+// Squares count from a1 along the ranks to h8. Black sees the board turned round.
+fn oriented(perspective: Colour, row: usize, col: usize) -> usize {
+    let square = row * 8 + col;
+    if perspective == Colour::White { square } else { square ^ 63 }
+}
+
+fn king_square(board: &[[ChessPiece; 8]; 8], colour: Colour) -> usize {
+    (0..64).find(|&square| board[square / 8][square % 8] == ChessPiece::King(colour)).map_or(0, |square| oriented(colour, square / 8, square % 8))
+}
+
+// > give the input for a piece on a square as seen from one side's king; kings and empty squares have none
+fn feature(perspective: Colour, king: usize, piece: ChessPiece, row: usize, col: usize) -> Option<usize> {
+    let kind = match piece.kind() {
+        Some(PieceKind::King) | None => return None,
+        Some(kind) => kind as usize,
+    };
+    // The side's own pieces come before the other side's of the same kind.
+    let theirs = usize::from(piece.colour() != Some(perspective));
+    Some(king * FEATURES_PER_KING + 1 + (2 * kind + theirs) * 64 + oriented(perspective, row, col))
+}
+
+// Reads the little-endian numbers of a network file.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], NnueError> {
+        let bytes = self.bytes.get(self.at..self.at + count).ok_or(NnueError::Truncated)?;
+        self.at += count;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, NnueError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().expect("four bytes")))
+    }
+
+    fn i8s(&mut self, count: usize) -> Result<Vec<i8>, NnueError> {
+        Ok(self.take(count)?.iter().map(|&byte| byte as i8).collect())
+    }
+
+    fn i16s(&mut self, count: usize) -> Result<Vec<i16>, NnueError> {
+        Ok(self.take(2 * count)?.chunks(2).map(|pair| i16::from_le_bytes([pair[0], pair[1]])).collect())
+    }
+
+    fn i32s(&mut self, count: usize) -> Result<Vec<i32>, NnueError> {
+        Ok(self.take(4 * count)?.chunks(4).map(|bytes| i32::from_le_bytes(bytes.try_into().expect("four bytes"))).collect())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::Square;
+
+    // A network file with small pseudo-random weights, laid out as a real one is; built once, as it is large.
+    pub(crate) fn network_bytes() -> &'static [u8] {
+        static BYTES: std::sync::OnceLock<Vec<u8>> = std::sync::OnceLock::new();
+        BYTES.get_or_init(build_network)
+    }
+
+    fn build_network() -> Vec<u8> {
+        let mut seed = 0x2545_f491_4f6c_dd1du64;
+        let mut next = |range: i64| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % (2 * range as u64 + 1)) as i64 - range
+        };
+        let description = b"Features=HalfKP(Friend)[41024->256x2],Network=AffineTransform[1<-32](ClippedReLU[32](AffineTransform[32<-32](ClippedReLU[32](AffineTransform[32<-512](InputSlice[512(0:512)])))))";
+        let mut bytes = Vec::new();
+        bytes.extend(VERSION.to_le_bytes());
+        bytes.extend(0x3e5a_a6eeu32.to_le_bytes());
+        bytes.extend((description.len() as u32).to_le_bytes());
+        bytes.extend(description);
+        bytes.extend(0x5d69_d7b8u32.to_le_bytes());
+        for _ in 0..HALF_DIMENSIONS {
+            bytes.extend((next(40) as i16 + 40).to_le_bytes());
+        }
+        for _ in 0..HALF_DIMENSIONS * FEATURES {
+            bytes.extend((next(8) as i16).to_le_bytes());
+        }
+        bytes.extend(0x6333_7156u32.to_le_bytes());
+        for (outputs, inputs) in [(HIDDEN, 2 * HALF_DIMENSIONS), (HIDDEN, HIDDEN), (1, HIDDEN)] {
+            for _ in 0..outputs {
+                bytes.extend((next(1000) as i32).to_le_bytes());
+            }
+            bytes.extend((0..outputs * inputs).map(|_| next(20) as u8));
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_reading_networks() {
+        let bytes = network_bytes();
+        let network = Network::from_bytes(bytes).unwrap();
+        assert!(network.description().starts_with("Features=HalfKP"));
+        assert_eq!(Network::from_bytes(&bytes[..bytes.len() - 1]).err(), Some(NnueError::Truncated));
+        let mut longer = bytes.to_vec();
+        longer.push(0);
+        assert!(matches!(Network::from_bytes(&longer), Err(NnueError::UnsupportedArchitecture(_))));
+        assert_eq!(Network::from_bytes(&[1, 0, 0, 0]).err(), Some(NnueError::UnsupportedVersion(1)));
+    }
+
+    #[test]
+    fn test_incremental_updates_match_a_refresh() {
+        let network = Network::from_bytes(network_bytes()).unwrap();
+        let mut position = GameState::from_fen("r3k2r/pppq1ppp/8/3Pp3/8/8/PpP2PPP/R3K2R w KQkq e6 0 1").unwrap();
+        let mut accumulator = network.accumulator(&position);
+        // En passant, castling both ways, a king move and a promotion with capture.
+        let moves = [(Square::D5, Square::E6), (Square::E8, Square::C8), (Square::E1, Square::G1), (Square::C8, Square::B8), (Square::H2, Square::H3), (Square::B2, Square::A1)];
+        for (from, to) in moves {
+            let mv = position.legal_moves().into_iter().find(|mv| mv.from == from && mv.to == to).unwrap();
+            let before = position.board;
+            position.make_move(mv).unwrap();
+            accumulator = network.update(&accumulator, &before, &position, mv);
+            assert_eq!(accumulator, network.accumulator(&position), "after {:?}", mv);
+        }
+    }
+
+    #[test]
+    fn test_colours_are_symmetric() {
+        let network = Network::from_bytes(network_bytes()).unwrap();
+        let position = GameState::from_fen("r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP1B1PPP/R2QKB1R w KQ - 0 8").unwrap();
+        // The same position with the colours swapped and the board turned round.
+        let mut rotated = position.clone();
+        for (row, rank) in position.board.iter().enumerate() {
+            for (col, &piece) in rank.iter().enumerate() {
+                rotated.board[7 - row][7 - col] = match piece {
+                    ChessPiece::Pawn(colour) => ChessPiece::Pawn(colour.opponent()),
+                    ChessPiece::Knight(colour) => ChessPiece::Knight(colour.opponent()),
+                    ChessPiece::Bishop(colour) => ChessPiece::Bishop(colour.opponent()),
+                    ChessPiece::Rook(colour) => ChessPiece::Rook(colour.opponent()),
+                    ChessPiece::Queen(colour) => ChessPiece::Queen(colour.opponent()),
+                    ChessPiece::King(colour) => ChessPiece::King(colour.opponent()),
+                    ChessPiece::Blank => ChessPiece::Blank,
+                };
+            }
+        }
+        assert_eq!(
+            network.evaluate(&network.accumulator(&position), Colour::White),
+            network.evaluate(&network.accumulator(&rotated), Colour::Black)
+        );
+    }
+}
@@ -0,0 +1,183 @@
+//! # Perft
+//!
+//! Counts the leaves of the tree of legal moves to a fixed depth. The counts for
+//! well-known positions have been worked out by many programs and agree, so a perft
+//! that matches them is strong evidence that move generation, castling, en passant
+//! and promotion are all correct; one that does not points to a bug.
+//!
+//! ```text
+//! perft 4                      # at the prompt: count the positions four plies ahead
+//! ```
+//!
+//! From the starting position the counts are 20, 400, 8902, 197281 and 4865609.
+//! Perft works on a bare `Position`, playing moves with `apply_move` rather than
+//! `make_move`, since the moves are known to be legal and perft has no use for the
+//! game result or repetition history; each thread takes its own copy of the position.
+//!
+//! When a count is wrong, `perft divide` splits it by the first move, in the format
+//! most engines use, so the counts can be compared with another engine's move by move
+//! and the faulty line followed down:
+//!
+//! ```text
+//! perft divide 3               # a2a3: 380, b2b3: 420, ... and the total
+//! ```
+//!
+//! Deep counts are split across threads by the first move: each thread takes the next
+//! move not yet counted, so a thread that draws a quick move goes on to another. With
+//! `--threads` the prompt command uses that many, and so does the `perft` subcommand,
+//! which exits with an error when a count in the suite is wrong:
+//!
+//! ```text
+//! overload-vibe-coding perft suite 5 --threads 8
+//! overload-vibe-coding perft 6 --fen "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1"
+//! ```
+//!
+//! [`SUITE`] holds the standard test positions from the Chess Programming Wiki, which
+//! between them exercise castling, en passant, promotion, pins and checks.
+//! [`divide`] and [`parallel_perft`] start threads, so they need the `std` feature.
+
+#[cfg(feature = "std")]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[cfg(feature = "std")]
+use crate::Move;
+use crate::{MoveList, Position};
+
+// > add the standard perft positions with their known counts, one ply deeper for each count
+pub const SUITE: [(&str, &str, &[u64]); 6] = [
+    ("start", "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", &[20, 400, 8902, 197_281, 4_865_609]),
+    ("Kiwipete", "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", &[48, 2039, 97_862, 4_085_603]),
+    ("position 3", "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", &[14, 191, 2812, 43_238, 674_624]),
+    ("position 4", "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", &[6, 264, 9467, 422_333]),
+    ("position 5", "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", &[44, 1486, 62_379, 2_103_487]),
+    ("position 6", "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10", &[46, 2079, 89_890, 3_894_594]),
+];
+
+// > count the leaf positions of the legal move tree the given number of plies deep
+pub fn perft(position: &mut Position, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let mut moves = MoveList::new();
+    position.generate_moves(&mut moves);
+    // The last ply only needs the number of moves, not the positions they lead to.
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+    moves
+        .iter()
+        .map(|&mv| {
+            let undo = position.apply_move(mv);
+            let nodes = perft(position, depth - 1);
+            position.undo_move(&undo);
+            nodes
+        })
+        .sum()
+}
+
+// > count the leaves below each legal move separately, for tracking down a wrong count,
+// > sharing the moves out among the given number of threads
+#[cfg(feature = "std")]
+pub fn divide(position: &Position, depth: u32, threads: usize) -> Vec<(Move, u64)> {
+    let moves = position.legal_moves();
+    let next = AtomicUsize::new(0);
+    let mut counts: Vec<(usize, u64)> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads.clamp(1, moves.len().max(1)))
+            .map(|_| {
+                let (moves, next) = (&moves, &next);
+                scope.spawn(move || {
+                    let mut position = *position;
+                    let mut counts = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(&mv) = moves.get(index) else { break };
+                        let undo = position.apply_move(mv);
+                        counts.push((index, perft(&mut position, depth.saturating_sub(1))));
+                        position.undo_move(&undo);
+                    }
+                    counts
+                })
+            })
+            .collect();
+        workers.into_iter().flat_map(|worker| worker.join().expect("a perft thread panicked")).collect()
+    });
+    counts.sort_unstable();
+    counts.into_iter().map(|(index, nodes)| (moves[index], nodes)).collect()
+}
+
+// > count the leaves of the legal move tree with the first moves shared out among threads
+#[cfg(feature = "std")]
+pub fn parallel_perft(position: &Position, depth: u32, threads: usize) -> u64 {
+    if depth <= 1 {
+        let mut position = *position;
+        return perft(&mut position, depth);
+    }
+    divide(position, depth, threads).iter().map(|&(_, nodes)| nodes).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GameState;
+
+    #[test]
+    fn test_start_position() {
+        let mut position = GameState::new();
+        let counts: Vec<u64> = (0..=3).map(|depth| perft(&mut position, depth)).collect();
+        assert_eq!(counts, vec![1, 20, 400, 8902]);
+        assert_eq!(position, GameState::new());
+    }
+
+    #[test]
+    fn test_suite() {
+        // Deeper counts take too long without optimisations; "perft" at the prompt checks them.
+        const MAX_NODES: u64 = 10_000;
+        for (name, fen, counts) in SUITE {
+            let mut position = GameState::from_fen(fen).unwrap();
+            for (depth, &expected) in (1..).zip(counts.iter().take_while(|&&count| count <= MAX_NODES)) {
+                assert_eq!(perft(&mut position, depth), expected, "{} at depth {}", name, depth);
+            }
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_divide() {
+        let position = GameState::from_fen(SUITE[1].1).unwrap();
+        let divided = divide(&position, 2, 1);
+        assert_eq!(divided.len(), 48);
+        assert_eq!(divided.iter().map(|&(_, nodes)| nodes).sum::<u64>(), 2039);
+        let castles = divided.iter().find(|(mv, _)| mv.to_uci() == "e1g1").unwrap();
+        assert_eq!(castles.1, 43);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_threads_agree() {
+        // However the first moves are shared out, the counts and their order are the same.
+        let position = GameState::from_fen(SUITE[1].1).unwrap();
+        assert_eq!(divide(&position, 2, 4), divide(&position, 2, 1));
+        assert_eq!(parallel_perft(&position, 2, 3), 2039);
+        assert_eq!(parallel_perft(&position, 1, 3), 48);
+        // A position with no moves needs no threads.
+        let mated = GameState::from_fen("R5k1/5ppp/8/8/8/8/5PPP/6K1 b - - 0 1").unwrap();
+        assert_eq!(parallel_perft(&mated, 3, 4), 0);
+    }
+
+    #[test]
+    fn test_special_moves() {
+        // En passant is possible on d6, and both sides may still castle.
+        let mut position = GameState::from_fen("r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1").unwrap();
+        // Rooks 10 and 9, king 5, castling 2, the pawn push and the capture en passant.
+        assert_eq!(perft(&mut position, 1), 10 + 9 + 5 + 2 + 1 + 1);
+        // Each promotion counts four times, once for each piece.
+        let mut position = GameState::from_fen("8/P6k/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        assert_eq!(perft(&mut position, 1), 4 + 3);
+        // A Chess960 start, with the kings on the g-file.
+        let mut position = GameState::from_fen("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9").unwrap();
+        let counts: Vec<u64> = (1..=3).map(|depth| perft(&mut position, depth)).collect();
+        assert_eq!(counts, vec![21, 528, 12189]);
+    }
+}
@@ -0,0 +1,684 @@
+//! # PGN
+//!
+//! Portable Game Notation, the standard text format for recording games:
+//!
+//! ```text
+//! [Event "Casual game"]
+//! [Site "?"]
+//! ...
+//! [Result "1-0"]
+//!
+//! 1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0
+//! ```
+//!
+//! Games that do not start from the initial position also carry `SetUp` and `FEN` tags.
+//!
+//! Reading a game replays every move through the rules engine, variations included,
+//! so a loaded game is known to be legal. Comments, variations and NAGs are kept in
+//! the game tree and written back out on export.
+//!
+//! A database of many games can be read one game at a time with [`PgnReader`],
+//! which never holds more than the current game in memory:
+//!
+//! ```text
+//! for record in PgnReader::new(BufReader::new(File::open("games.pgn")?)) {
+//!     let record = record?;
+//!     println!("{:?}", record.tags()?);
+//!     let game = record.game()?;
+//! }
+//! ```
+
+use std::io::{self, BufRead};
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::error::PgnError;
+use crate::game::{Game, MoveNode};
+use crate::{Colour, DrawReason, GameResult, GameState};
+
+// Export lines are kept below this length, as the PGN standard asks.
+const MAX_LINE: usize = 79;
+
+impl Game {
+    // > export the game as PGN with the Seven Tag Roster, numbered SAN moves and the result
+    pub fn to_pgn(&self) -> String {
+        let result = result_token(self.state().result);
+        let mut pgn = String::new();
+        for (name, value) in self.tags() {
+            let value = if name == "Result" { result } else { value.as_str() };
+            pgn.push_str(&format!("[{} \"{}\"]\n", name, escape(value)));
+        }
+        if *self.start() != GameState::new() {
+            pgn.push_str("[SetUp \"1\"]\n");
+            pgn.push_str(&format!("[FEN \"{}\"]\n", self.start().to_fen()));
+        }
+        pgn.push('\n');
+
+        let mut tokens = Vec::new();
+        write_line(self.start(), self.line(), &mut tokens);
+        tokens.push(result.to_string());
+
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > MAX_LINE {
+                pgn.push_str(&line);
+                pgn.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        pgn.push_str(&line);
+        pgn.push('\n');
+        pgn
+    }
+}
+
+impl Game {
+    // > read the first game in PGN text, replaying its moves through the rules engine
+    pub fn from_pgn(text: &str) -> Result<Game, PgnError> {
+        let tokens = tokenize(text)?;
+        let mut tags = Vec::new();
+        for token in &tokens {
+            if let Token::Tag(name, value) = token {
+                tags.push((name.clone(), value.clone()));
+            }
+        }
+        if tokens.is_empty() {
+            return Err(PgnError::NoGame);
+        }
+
+        let start = match tags.iter().find(|(name, _)| name == "FEN") {
+            Some((_, fen)) => GameState::from_fen(fen).map_err(PgnError::InvalidFen)?,
+            None => GameState::new(),
+        };
+        let mut game = Game::from_position(start);
+        // SetUp and FEN are written from the starting position on export.
+        tags.retain(|(name, _)| name != "SetUp" && name != "FEN");
+        if !tags.is_empty() {
+            game.set_tags(tags);
+        }
+
+        let (line, result) = read_line(&mut tokens.into_iter(), game.start(), false)?;
+        for node in line {
+            game.play_node(node).expect("moves were replayed while reading");
+        }
+        game.set_result(result);
+        Ok(game)
+    }
+}
+
+// > stream the games of a PGN database one at a time from any buffered reader
+pub struct PgnReader<R> {
+    input: R,
+    // The first line of the next game, read while looking for the end of the last one.
+    pending: Option<String>,
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(input: R) -> PgnReader<R> {
+        PgnReader { input, pending: None }
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = io::Result<PgnRecord>;
+
+    // A game ends at its result, or failing that where the next game's tags begin.
+    fn next(&mut self) -> Option<io::Result<PgnRecord>> {
+        let mut text = self.pending.take().unwrap_or_default();
+        let mut scan = Scan::default();
+        scan.line(&text);
+        loop {
+            let mut bytes = Vec::new();
+            match self.input.read_until(b'\n', &mut bytes) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(err) => return Some(Err(err)),
+            }
+            // Old databases are often in Latin-1; stray bytes should not lose the whole game.
+            let line = String::from_utf8_lossy(&bytes).into_owned();
+            if scan.starts_next_game(&line) {
+                self.pending = Some(line);
+                break;
+            }
+            scan.line(&line);
+            text.push_str(&line);
+        }
+        if text.trim().is_empty() { None } else { Some(Ok(PgnRecord { text })) }
+    }
+}
+
+// > track enough of the PGN syntax, line by line, to see where one game stops
+#[derive(Default)]
+struct Scan {
+    in_comment: bool,
+    in_movetext: bool,
+    depth: usize,
+    finished: bool,
+}
+
+impl Scan {
+    fn starts_next_game(&self, line: &str) -> bool {
+        let line = line.trim_start();
+        !self.in_comment && !line.is_empty() && (self.finished || (self.in_movetext && line.starts_with('[')))
+    }
+
+    fn line(&mut self, line: &str) {
+        let trimmed = line.trim_start();
+        if !self.in_comment && (trimmed.starts_with('[') || trimmed.starts_with('%')) {
+            return;
+        }
+        let mut symbol = String::new();
+        for c in line.chars().chain(Some('\n')) {
+            if self.in_comment {
+                self.in_comment = c != '}';
+                continue;
+            }
+            if !c.is_whitespace() && !"{}();".contains(c) {
+                symbol.push(c);
+                continue;
+            }
+            if matches!(symbol.as_str(), "1-0" | "0-1" | "1/2-1/2" | "*") && self.depth == 0 {
+                self.finished = true;
+            }
+            if !symbol.is_empty() {
+                self.in_movetext = true;
+            }
+            symbol.clear();
+            match c {
+                '{' => self.in_comment = true,
+                '(' => self.depth += 1,
+                ')' => self.depth = self.depth.saturating_sub(1),
+                ';' => break,
+                _ => {}
+            }
+            if !c.is_whitespace() {
+                self.in_movetext = true;
+            }
+        }
+    }
+}
+
+// > hold the text of one game from a database, parsed only when asked
+#[derive(Debug, Clone, PartialEq)]
+pub struct PgnRecord {
+    text: String,
+}
+
+impl PgnRecord {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    // > read just the tag pairs, without replaying the moves
+    pub fn tags(&self) -> Result<Vec<(String, String)>, PgnError> {
+        let mut chars = self.text.chars().peekable();
+        let mut tags = Vec::new();
+        while let Some(&c) = chars.peek() {
+            match c {
+                _ if c.is_whitespace() => {
+                    chars.next();
+                }
+                '[' => {
+                    chars.next();
+                    if let Token::Tag(name, value) = read_tag(&mut chars)? {
+                        tags.push((name, value));
+                    }
+                }
+                ';' | '%' => chars.by_ref().take_while(|&c| c != '\n').for_each(drop),
+                _ => break,
+            }
+        }
+        Ok(tags)
+    }
+
+    pub fn game(&self) -> Result<Game, PgnError> {
+        Game::from_pgn(&self.text)
+    }
+}
+
+// > write the moves of a line, with their annotations and variations, as PGN tokens
+fn write_line(start: &GameState, line: &[MoveNode], tokens: &mut Vec<String>) {
+    let mut position = start.clone();
+    // Black's moves get a number at the start of a line and after a comment or variation.
+    let mut interrupted = true;
+    for node in line {
+        if let Some(comment) = &node.comment_before {
+            push_comment(comment, tokens);
+            interrupted = true;
+        }
+        match position.current_player {
+            Colour::White => tokens.push(format!("{}.", position.fullmove_number())),
+            Colour::Black if interrupted => tokens.push(format!("{}...", position.fullmove_number())),
+            Colour::Black => {}
+        }
+        tokens.push(position.move_to_san(&node.mv));
+        interrupted = false;
+        for nag in &node.nags {
+            tokens.push(format!("${}", nag));
+        }
+        if let Some(comment) = &node.comment {
+            push_comment(comment, tokens);
+            interrupted = true;
+        }
+        for variation in node.variations.iter().filter(|variation| !variation.is_empty()) {
+            let first = tokens.len();
+            write_line(&position, variation, tokens);
+            tokens[first].insert(0, '(');
+            if let Some(last) = tokens.last_mut() {
+                last.push(')');
+            }
+            interrupted = true;
+        }
+        position.make_move(node.mv).expect("recorded moves are legal");
+    }
+}
+
+// > add a comment as one token per word, so long comments can be wrapped
+fn push_comment(comment: &str, tokens: &mut Vec<String>) {
+    let first = tokens.len();
+    tokens.extend(comment.split_whitespace().map(str::to_string));
+    if tokens.len() == first {
+        tokens.push(String::new());
+    }
+    tokens[first].insert(0, '{');
+    if let Some(last) = tokens.last_mut() {
+        last.push('}');
+    }
+}
+
+// > read the moves of a line from PGN tokens, replaying them from the start position
+fn read_line(
+    tokens: &mut std::vec::IntoIter<Token>,
+    start: &GameState,
+    nested: bool,
+) -> Result<(Vec<MoveNode>, GameResult), PgnError> {
+    let mut position = start.clone();
+    // Variations replace the last move, so they start from the position before it.
+    let mut before = start.clone();
+    let mut line: Vec<MoveNode> = Vec::new();
+    let mut comment_before = None;
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::Move(san) => {
+                let number = match position.current_player {
+                    Colour::White => format!("{}.", position.fullmove_number()),
+                    Colour::Black => format!("{}...", position.fullmove_number()),
+                };
+                let illegal = |err| PgnError::IllegalMove(format!("{} {}", number, san), err);
+                let mv = position.parse_san(&san).map_err(illegal)?;
+                before = position.clone();
+                position.make_move(mv).map_err(illegal)?;
+                line.push(MoveNode {
+                    comment_before: comment_before.take(),
+                    ..MoveNode::new(mv)
+                });
+            }
+            Token::Nag(nag) => {
+                if let Some(node) = line.last_mut() {
+                    node.nags.push(nag);
+                }
+            }
+            Token::Comment(comment) => match line.last_mut() {
+                Some(node) => append_comment(&mut node.comment, comment),
+                None => append_comment(&mut comment_before, comment),
+            },
+            Token::VariationStart => {
+                let (variation, _) = read_line(tokens, &before, true)?;
+                line.last_mut().ok_or(PgnError::MisplacedVariation)?.variations.push(variation);
+            }
+            Token::VariationEnd if nested => return Ok((line, GameResult::Ongoing)),
+            Token::Result(result) if !nested => return Ok((line, result)),
+            Token::VariationEnd | Token::Result(_) => return Err(PgnError::UnbalancedVariation),
+            // Tags are read before the moves.
+            Token::Tag(..) => {}
+        }
+    }
+    if nested {
+        return Err(PgnError::UnbalancedVariation);
+    }
+    Ok((line, GameResult::Ongoing))
+}
+
+// > join comments that follow one another into one
+fn append_comment(existing: &mut Option<String>, comment: String) {
+    *existing = match existing.take() {
+        Some(previous) => Some(format!("{} {}", previous, comment)),
+        None => Some(comment),
+    };
+}
+
+// > add a token type for the parts of a PGN game
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Tag(String, String),
+    Move(String),
+    Comment(String),
+    Nag(u8),
+    VariationStart,
+    VariationEnd,
+    Result(GameResult),
+}
+
+// > split PGN text into tokens, up to and including the result of the first game
+fn tokenize(text: &str) -> Result<Vec<Token>, PgnError> {
+    let mut chars = text.chars().peekable();
+    let mut tokens = Vec::new();
+    let mut depth = 0;
+    while let Some(&c) = chars.peek() {
+        match c {
+            _ if c.is_whitespace() => {
+                chars.next();
+            }
+            '[' => {
+                chars.next();
+                tokens.push(read_tag(&mut chars)?);
+            }
+            '{' => {
+                chars.next();
+                let mut comment = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => comment.push(c),
+                        None => return Err(PgnError::UnterminatedComment),
+                    }
+                }
+                tokens.push(Token::Comment(comment.trim().to_string()));
+            }
+            // A comment to the end of the line, or an escaped line starting with '%'.
+            ';' | '%' => {
+                chars.by_ref().take_while(|&c| c != '\n').for_each(drop);
+            }
+            '(' => {
+                chars.next();
+                depth += 1;
+                tokens.push(Token::VariationStart);
+            }
+            ')' => {
+                chars.next();
+                if depth == 0 {
+                    return Err(PgnError::UnbalancedVariation);
+                }
+                depth -= 1;
+                tokens.push(Token::VariationEnd);
+            }
+            _ => {
+                let mut symbol = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "[]{}();".contains(c) {
+                        break;
+                    }
+                    symbol.push(c);
+                    chars.next();
+                }
+                let symbol = symbol.as_str();
+                match symbol {
+                    "1-0" | "0-1" | "1/2-1/2" | "*" if depth == 0 => {
+                        tokens.push(Token::Result(result_from_token(symbol)));
+                        return Ok(tokens);
+                    }
+                    "1-0" | "0-1" | "1/2-1/2" | "*" => {}
+                    _ if symbol.starts_with('$') => match symbol[1..].parse() {
+                        Ok(nag) => tokens.push(Token::Nag(nag)),
+                        Err(_) => tokens.push(Token::Move(symbol.to_string())),
+                    },
+                    _ => {
+                        // Move numbers such as "12." or "12..." may be written against the move.
+                        let san = match symbol.find(|c: char| !c.is_ascii_digit()) {
+                            Some(i) if i > 0 && symbol[i..].starts_with('.') => symbol[i..].trim_start_matches('.'),
+                            _ => symbol,
+                        };
+                        if !san.is_empty() {
+                            tokens.push(Token::Move(san.to_string()));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    if depth != 0 {
+        return Err(PgnError::UnbalancedVariation);
+    }
+    Ok(tokens)
+}
+
+// > read a tag pair such as [White "Kasparov, Garry"] after its opening bracket
+fn read_tag(chars: &mut Peekable<Chars>) -> Result<Token, PgnError> {
+    let mut raw = String::new();
+    let mut value = String::new();
+    let mut in_value = false;
+    loop {
+        let c = chars.next().ok_or_else(|| PgnError::InvalidTag(format!("[{}", raw)))?;
+        match c {
+            ']' if !in_value => break,
+            '"' => in_value = !in_value,
+            '\\' if in_value => {
+                if let Some(escaped) = chars.next() {
+                    value.push(escaped);
+                    raw.push(escaped);
+                }
+                continue;
+            }
+            _ if in_value => value.push(c),
+            _ => {}
+        }
+        raw.push(c);
+    }
+    match raw.split_once('"') {
+        Some((name, _)) if !name.trim().is_empty() && !name.trim().contains(char::is_whitespace) => {
+            Ok(Token::Tag(name.trim().to_string(), value))
+        }
+        _ => Err(PgnError::InvalidTag(format!("[{}]", raw))),
+    }
+}
+
+// > convert a PGN result token into a game result
+fn result_from_token(token: &str) -> GameResult {
+    match token {
+        "1-0" => GameResult::WhiteWins,
+        "0-1" => GameResult::BlackWins,
+        // PGN does not say why a game was drawn; a draw not reached on the board must have been agreed.
+        "1/2-1/2" => GameResult::Draw(DrawReason::Agreement),
+        _ => GameResult::Ongoing,
+    }
+}
+
+// > give the PGN result token for a game result
+fn result_token(result: GameResult) -> &'static str {
+    match result {
+        GameResult::WhiteWins => "1-0",
+        GameResult::BlackWins => "0-1",
+        GameResult::Draw(_) => "1/2-1/2",
+        GameResult::Ongoing => "*",
+    }
+}
+
+// > escape quotes and backslashes in a tag value
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Move, Square};
+
+    fn play(game: &mut Game, moves: &[(Square, Square)]) {
+        for &(from, to) in moves {
+            game.play(Move::new(from, to)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_scholars_mate() {
+        let mut game = Game::from_position(GameState::new());
+        play(
+            &mut game,
+            &[
+                (Square::E2, Square::E4),
+                (Square::E7, Square::E5),
+                (Square::D1, Square::H5),
+                (Square::B8, Square::C6),
+                (Square::F1, Square::C4),
+                (Square::G8, Square::F6),
+                (Square::H5, Square::F7),
+            ],
+        );
+        let pgn = game.to_pgn();
+        assert!(pgn.starts_with("[Event \"Casual game\"]\n[Site \"?\"]\n[Date \""));
+        assert!(pgn.contains("[Round \"-\"]\n[White \"?\"]\n[Black \"?\"]\n[Result \"1-0\"]\n\n"));
+        assert!(pgn.ends_with("\n1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0\n"));
+        assert!(!pgn.contains("FEN"));
+    }
+
+    #[test]
+    fn test_unfinished_game_from_position() {
+        let start = GameState::from_fen("4k3/8/8/8/8/8/8/R3K3 b Q - 0 30").unwrap();
+        let mut game = Game::from_position(start);
+        play(&mut game, &[(Square::E8, Square::D7), (Square::E1, Square::C1)]);
+        let pgn = game.to_pgn();
+        assert!(pgn.contains("[Result \"*\"]\n[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/R3K3 b Q - 0 30\"]\n"));
+        assert!(pgn.ends_with("\n30... Kd7 31. O-O-O+ *\n"));
+    }
+
+    #[test]
+    fn test_long_movetext_is_wrapped() {
+        let mut game = Game::from_position(GameState::new());
+        for _ in 0..10 {
+            play(
+                &mut game,
+                &[
+                    (Square::G1, Square::F3),
+                    (Square::G8, Square::F6),
+                    (Square::F3, Square::G1),
+                    (Square::F6, Square::G8),
+                ],
+            );
+            if game.state().result != GameResult::Ongoing {
+                break;
+            }
+        }
+        let pgn = game.to_pgn();
+        let movetext = pgn.split("\n\n").nth(1).unwrap();
+        assert!(movetext.lines().count() > 1);
+        assert!(movetext.lines().all(|line| line.len() <= MAX_LINE));
+        assert!(movetext.trim_end().ends_with("1/2-1/2"));
+    }
+
+    const SCHOLARS_MATE: &str = "[Event \"Casual game\"]\n[White \"Me\"]\n[Result \"1-0\"]\n\n1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0\n";
+
+    #[test]
+    fn test_read_game() {
+        let game = Game::from_pgn(SCHOLARS_MATE).unwrap();
+        assert_eq!(game.moves().len(), 7);
+        assert_eq!(game.state().result, GameResult::WhiteWins);
+        assert_eq!(
+            game.tags(),
+            &[
+                ("Event".to_string(), "Casual game".to_string()),
+                ("White".to_string(), "Me".to_string()),
+                ("Result".to_string(), "1-0".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_export_and_read_back() {
+        let start = GameState::from_fen("4k3/8/8/8/8/8/8/R3K3 b Q - 0 30").unwrap();
+        let mut game = Game::from_position(start);
+        play(&mut game, &[(Square::E8, Square::D7), (Square::E1, Square::C1)]);
+        let read = Game::from_pgn(&game.to_pgn()).unwrap();
+        assert_eq!(read.start(), game.start());
+        assert_eq!(read.moves(), game.moves());
+        assert_eq!(read.to_pgn(), game.to_pgn());
+    }
+
+    #[test]
+    fn test_reads_comments_variations_and_nags() {
+        let pgn = "1.e4 {best by test} e5 $1 (1... c5 2. Nf3 (2. c3) d6) 2.Nf3 ; the main line\n2...Nc6 % escaped\n3. Bb5 *";
+        let game = Game::from_pgn(pgn).unwrap();
+        assert_eq!(game.moves().len(), 5);
+        assert_eq!(game.moves()[4].to, Square::B5);
+        assert_eq!(game.state().result, GameResult::Ongoing);
+
+        let line = game.line();
+        assert_eq!(line[0].comment.as_deref(), Some("best by test"));
+        assert_eq!(line[1].nags, vec![1]);
+        let sicilian = &line[1].variations[0];
+        assert_eq!(sicilian.len(), 3);
+        assert_eq!(sicilian[0].mv.to, Square::C5);
+        assert_eq!(sicilian[1].variations[0][0].mv.to, Square::C3);
+        assert!(line[2].variations.is_empty() && line[2].comment.is_none());
+    }
+
+    #[test]
+    fn test_annotated_game_round_trips() {
+        let movetext = "{The Ruy Lopez} 1. e4 e5 2. Nf3 $1 {develops} (2. f4 exf4 (2... d5) 3. Nf3 $2\n$14) 2... Nc6 3. Bb5 *";
+        let game = Game::from_pgn(movetext).unwrap();
+        let pgn = game.to_pgn();
+        assert!(pgn.ends_with(
+            "\n{The Ruy Lopez} 1. e4 e5 2. Nf3 $1 {develops} (2. f4 exf4 (2... d5) 3. Nf3 $2\n$14) 2... Nc6 3. Bb5 *\n"
+        ));
+        assert_eq!(Game::from_pgn(&pgn).unwrap(), game);
+    }
+
+    #[test]
+    fn test_result_off_the_board() {
+        let game = Game::from_pgn("1. e4 e5 0-1").unwrap();
+        assert_eq!(game.state().result, GameResult::BlackWins);
+        let game = Game::from_pgn("1. e4 e5 1/2-1/2").unwrap();
+        assert_eq!(game.state().result, GameResult::Draw(DrawReason::Agreement));
+    }
+
+    #[test]
+    fn test_read_errors() {
+        assert_eq!(Game::from_pgn("  \n"), Err(PgnError::NoGame));
+        assert!(matches!(Game::from_pgn("[White Me] 1. e4 *"), Err(PgnError::InvalidTag(_))));
+        assert_eq!(Game::from_pgn("1. e4 {unfinished"), Err(PgnError::UnterminatedComment));
+        assert_eq!(Game::from_pgn("1. e4 (1. d4 *"), Err(PgnError::UnbalancedVariation));
+        assert_eq!(Game::from_pgn("1. e4 ) e5 *"), Err(PgnError::UnbalancedVariation));
+        assert_eq!(Game::from_pgn("(1. d4) 1. e4 *"), Err(PgnError::MisplacedVariation));
+        assert!(matches!(Game::from_pgn("1. e4 (1. Ke2) *"), Err(PgnError::IllegalMove(..))));
+        assert!(matches!(Game::from_pgn("[FEN \"8/8 w - -\"] *"), Err(PgnError::InvalidFen(_))));
+        assert_eq!(
+            Game::from_pgn("1. e4 e5 2. Ke3 *"),
+            Err(PgnError::IllegalMove(
+                "2. Ke3".to_string(),
+                crate::error::ChessError::NoMatchingMove("Ke3".to_string())
+            ))
+        );
+    }
+
+    #[test]
+    fn test_reader_splits_games() {
+        let database = b"[Event \"One\"]\n[White \"Anna\"]\n\n1. e4 e5 {a comment\n[that looks like a tag]} 2. Nf3 1-0\n\n\
+[Event \"Two\"]\n[White \"Ben\"]\n\n1. d4 (1. c4\n) d5\n[Event \"Three\"]\n\n1. f3 e5 2. g4 Qh4# 0-1\n\
+1. Nf3 *\n";
+        let records: Vec<PgnRecord> = PgnReader::new(&database[..]).map(Result::unwrap).collect();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0].tags().unwrap(), vec![("Event".to_string(), "One".to_string()), ("White".to_string(), "Anna".to_string())]);
+        assert_eq!(records[0].game().unwrap().moves().len(), 3);
+        assert_eq!(records[0].game().unwrap().line()[1].comment.as_deref(), Some("a comment\n[that looks like a tag]"));
+        // The second game has no result, so it runs until the next game's tags.
+        assert_eq!(records[1].tags().unwrap()[0].1, "Two");
+        assert_eq!(records[1].game().unwrap().moves().len(), 2);
+        assert_eq!(records[2].game().unwrap().state().result, GameResult::BlackWins);
+        // A game without tags is found after the previous game's result.
+        assert_eq!(records[3].tags().unwrap(), vec![]);
+        assert_eq!(records[3].text(), "1. Nf3 *\n");
+    }
+
+    #[test]
+    fn test_reader_tags_without_replaying() {
+        let database = b"[White \"Caf\xe9\"]\n\n1. e4 e5 2. Ke3 *\n";
+        let records: Vec<PgnRecord> = PgnReader::new(&database[..]).map(Result::unwrap).collect();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].tags().unwrap(), vec![("White".to_string(), "Caf\u{fffd}".to_string())]);
+        assert!(matches!(records[0].game(), Err(PgnError::IllegalMove(..))));
+        assert_eq!(PgnReader::new(&b"\n\n"[..]).count(), 0);
+    }
+}
@@ -0,0 +1,578 @@
+//! # Movement Rules
+//!
+//! Checks that a move has the right shape for the piece being moved:
+//! knights jump in an L, bishops move diagonally, rooks along ranks and files,
+//! queens either way, kings a single step and pawns forward (or diagonally when capturing).
+//!
+//! Squares are given as `(row, col)` pairs as returned by `Square::to_row_col`,
+//! where row 0 is White's back rank.
+
+use alloc::vec::Vec;
+
+use crate::bitboard;
+use crate::error::ChessError;
+use crate::{ChessPiece, Colour, Square};
+
+// > write a function that checks the geometry of a move for each ChessPiece variant
+pub(crate) fn validate_geometry(
+    piece: ChessPiece,
+    from: (usize, usize),
+    to: (usize, usize),
+) -> Result<(), ChessError> {
+    if from == to {
+        return Err(ChessError::SameSquare);
+    }
+
+    let d_row = to.0 as i32 - from.0 as i32;
+    let d_col = to.1 as i32 - from.1 as i32;
+
+    let legal = match piece {
+        ChessPiece::Pawn(colour) => pawn_geometry(colour, from.0, d_row, d_col),
+        ChessPiece::Knight(_) => {
+            (d_row.abs() == 1 && d_col.abs() == 2) || (d_row.abs() == 2 && d_col.abs() == 1)
+        }
+        ChessPiece::Bishop(_) => d_row.abs() == d_col.abs(),
+        ChessPiece::Rook(_) => d_row == 0 || d_col == 0,
+        ChessPiece::Queen(_) => d_row == 0 || d_col == 0 || d_row.abs() == d_col.abs(),
+        ChessPiece::King(_) => d_row.abs() <= 1 && d_col.abs() <= 1,
+        ChessPiece::Blank => return Err(ChessError::NoPieceAtSource),
+    };
+
+    if legal {
+        Ok(())
+    } else {
+        Err(ChessError::IllegalGeometry(piece))
+    }
+}
+
+// > a pawn moves one square forward, two from its starting row, or one diagonally forward
+fn pawn_geometry(colour: Colour, from_row: usize, d_row: i32, d_col: i32) -> bool {
+    let (forward, start_row) = match colour {
+        Colour::White => (1, 1),
+        Colour::Black => (-1, 6),
+    };
+    match d_col.abs() {
+        0 => d_row == forward || (d_row == 2 * forward && from_row == start_row),
+        1 => d_row == forward,
+        _ => false,
+    }
+}
+
+// > write a helper that walks the squares strictly between from and to along a rank, file or diagonal
+fn squares_between(from: (usize, usize), to: (usize, usize)) -> Vec<(usize, usize)> {
+    let d_row = (to.0 as i32 - from.0 as i32).signum();
+    let d_col = (to.1 as i32 - from.1 as i32).signum();
+    let mut squares = Vec::new();
+    let (mut row, mut col) = (from.0 as i32 + d_row, from.1 as i32 + d_col);
+    while (row, col) != (to.0 as i32, to.1 as i32) {
+        squares.push((row as usize, col as usize));
+        row += d_row;
+        col += d_col;
+    }
+    squares
+}
+
+// > check that sliding pieces do not jump over other pieces
+pub(crate) fn validate_path(
+    board: &[[ChessPiece; 8]; 8],
+    piece: ChessPiece,
+    from: (usize, usize),
+    to: (usize, usize),
+) -> Result<(), ChessError> {
+    // Knights jump, and every other move is a straight line once the geometry has been checked.
+    if let ChessPiece::Knight(_) = piece {
+        return Ok(());
+    }
+    for (row, col) in squares_between(from, to) {
+        if board[row][col] != ChessPiece::Blank {
+            return Err(ChessError::PathBlocked(piece));
+        }
+    }
+    Ok(())
+}
+
+// > check that the destination square is empty or holds an enemy piece
+pub(crate) fn validate_destination(
+    board: &[[ChessPiece; 8]; 8],
+    piece: ChessPiece,
+    to: (usize, usize),
+) -> Result<(), ChessError> {
+    let target = board[to.0][to.1];
+    if target != ChessPiece::Blank && target.colour() == piece.colour() {
+        return Err(ChessError::CaptureOwnPiece);
+    }
+    if let ChessPiece::King(_) = target {
+        return Err(ChessError::CaptureKing);
+    }
+    Ok(())
+}
+
+// > pawns may only push onto empty squares and may only move diagonally when capturing
+pub(crate) fn validate_pawn(
+    board: &[[ChessPiece; 8]; 8],
+    piece: ChessPiece,
+    from: (usize, usize),
+    to: (usize, usize),
+    en_passant: Option<(usize, usize)>,
+) -> Result<(), ChessError> {
+    let ChessPiece::Pawn(colour) = piece else {
+        return Ok(());
+    };
+    let target = board[to.0][to.1];
+    if from.1 == to.1 {
+        if target != ChessPiece::Blank {
+            return Err(ChessError::PawnBlocked);
+        }
+    } else if !target.is_enemy_of(colour) && Some(to) != en_passant {
+        return Err(ChessError::PawnMustCaptureDiagonally);
+    }
+    Ok(())
+}
+
+// > write a function that yields the positions of all pieces of the given colour attacking a square
+// The attackers are looked up with bitboards rather than by trying every piece, lowest square first.
+fn attacking_positions(board: &[[ChessPiece; 8]; 8], square: (usize, usize), by: Colour) -> impl Iterator<Item = (usize, usize)> {
+    let attackers = bitboard::Pieces::new(board, by).attackers(square.0 * 8 + square.1, by);
+    bitboard::squares(attackers).map(|square| (square / 8, square % 8))
+}
+
+// > write a function that tells whether a square is attacked by any piece of the given colour
+pub(crate) fn is_square_attacked(board: &[[ChessPiece; 8]; 8], square: (usize, usize), by: Colour) -> bool {
+    attacking_positions(board, square, by).next().is_some()
+}
+
+// > list the squares of all pieces of the given colour attacking a square
+pub(crate) fn attackers(board: &[[ChessPiece; 8]; 8], square: (usize, usize), by: Colour) -> Vec<Square> {
+    attacking_positions(board, square, by)
+        .map(|(row, col)| Square::from_row_col(row, col))
+        .collect()
+}
+
+// > write a function that finds the square of the king of the given colour
+pub(crate) fn find_king(board: &[[ChessPiece; 8]; 8], colour: Colour) -> Option<(usize, usize)> {
+    (0..8)
+        .flat_map(|row| (0..8).map(move |col| (row, col)))
+        .find(|&(row, col)| board[row][col] == ChessPiece::King(colour))
+}
+
+// > add a struct describing a piece that is pinned to its own king
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pin {
+    pub pinned: Square,
+    pub pinner: Square,
+    // The (row, col) step from the king towards the pinning piece.
+    pub direction: (i32, i32),
+}
+
+// > find every piece of the given colour that is absolutely pinned to its king
+pub(crate) fn find_pins(board: &[[ChessPiece; 8]; 8], colour: Colour) -> Vec<Pin> {
+    let Some(king) = find_king(board, colour) else {
+        return Vec::new();
+    };
+
+    const DIRECTIONS: [(i32, i32); 8] = [(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (1, -1), (-1, 1), (-1, -1)];
+    let mut pins = Vec::new();
+    for (d_row, d_col) in DIRECTIONS {
+        let diagonal = d_row != 0 && d_col != 0;
+        let mut candidate = None;
+        let (mut row, mut col) = (king.0 as i32 + d_row, king.1 as i32 + d_col);
+        while (0..8).contains(&row) && (0..8).contains(&col) {
+            let piece = board[row as usize][col as usize];
+            if piece.colour() == Some(colour) {
+                if candidate.is_some() {
+                    break;
+                }
+                candidate = Some((row as usize, col as usize));
+            } else if piece != ChessPiece::Blank {
+                let slides_this_way = match piece {
+                    ChessPiece::Queen(_) => true,
+                    ChessPiece::Bishop(_) => diagonal,
+                    ChessPiece::Rook(_) => !diagonal,
+                    _ => false,
+                };
+                if let (Some((pinned_row, pinned_col)), true) = (candidate, slides_this_way) {
+                    pins.push(Pin {
+                        pinned: Square::from_row_col(pinned_row, pinned_col),
+                        pinner: Square::from_row_col(row as usize, col as usize),
+                        direction: (d_row, d_col),
+                    });
+                }
+                break;
+            }
+            row += d_row;
+            col += d_col;
+        }
+    }
+    pins
+}
+
+// > check that a position could arise in a game: one king each, no pawns on the end ranks,
+// > and the side that has just moved not left in check
+pub(crate) fn validate_position(board: &[[ChessPiece; 8]; 8], side_to_move: Colour) -> Result<(), ChessError> {
+    for colour in [Colour::White, Colour::Black] {
+        let kings = board.iter().flatten().filter(|&&piece| piece == ChessPiece::King(colour)).count();
+        if kings != 1 {
+            return Err(ChessError::WrongKingCount(colour, kings));
+        }
+    }
+
+    for row in [0, 7] {
+        if let Some(col) = board[row].iter().position(|piece| matches!(piece, ChessPiece::Pawn(_))) {
+            return Err(ChessError::PawnOnBackRank(Square::from_row_col(row, col)));
+        }
+    }
+
+    let waiting = side_to_move.opponent();
+    match find_king(board, waiting) {
+        Some(king) if is_square_attacked(board, king, side_to_move) => Err(ChessError::SideNotToMoveInCheck),
+        _ => Ok(()),
+    }
+}
+
+// > tell whether neither side has enough material left to ever deliver checkmate
+pub(crate) fn is_insufficient_material(board: &[[ChessPiece; 8]; 8]) -> bool {
+    let mut knights = 0;
+    let mut bishop_square_colours = Vec::new();
+    for (row, pieces) in board.iter().enumerate() {
+        for (col, piece) in pieces.iter().enumerate() {
+            match piece {
+                ChessPiece::King(_) | ChessPiece::Blank => {}
+                ChessPiece::Knight(_) => knights += 1,
+                ChessPiece::Bishop(_) => bishop_square_colours.push((row + col) % 2),
+                _ => return false,
+            }
+        }
+    }
+
+    // K v K, K+N v K, or any number of bishops that all travel on the same colour of square.
+    match (knights, bishop_square_colours.as_slice()) {
+        (0, []) | (1, []) => true,
+        (0, [first, rest @ ..]) => rest.iter().all(|colour| colour == first),
+        _ => false,
+    }
+}
+
+// The column the king starts on in standard chess; in Chess960 it may start on any file from b to g.
+pub(crate) const KING_COL: usize = 4;
+
+// > return the column the king lands on when castling on the given side, wherever it started
+pub(crate) fn castling_king_target(kingside: bool) -> usize {
+    if kingside { 6 } else { 2 }
+}
+
+// > return the column the rook lands on when castling on the given side
+pub(crate) fn castling_rook_target(kingside: bool) -> usize {
+    if kingside { 5 } else { 3 }
+}
+
+// > check that a castling move is allowed for a king that castles from king_col with the rook on rook_col
+pub(crate) fn validate_castling(
+    board: &[[ChessPiece; 8]; 8],
+    colour: Colour,
+    from: (usize, usize),
+    kingside: bool,
+    king_col: usize,
+    rook_col: Option<usize>,
+) -> Result<(), ChessError> {
+    let back_row = match colour {
+        Colour::White => 0,
+        Colour::Black => 7,
+    };
+    let rook_col = match rook_col {
+        Some(rook_col) if from == (back_row, king_col) => rook_col,
+        _ => return Err(ChessError::CastlingNotAllowed),
+    };
+    if board[back_row][rook_col] != ChessPiece::Rook(colour) {
+        return Err(ChessError::NoRookToCastle);
+    }
+
+    // Every square the king or rook passes over or lands on must be empty, apart from the two of them.
+    let king_to = castling_king_target(kingside);
+    let cols = [from.1, king_to, rook_col, castling_rook_target(kingside)];
+    let (first, last) = (*cols.iter().min().expect("four columns"), *cols.iter().max().expect("four columns"));
+    if (first..=last).any(|col| col != from.1 && col != rook_col && board[back_row][col] != ChessPiece::Blank) {
+        return Err(ChessError::CastlingPathBlocked);
+    }
+
+    // The king may not castle out of, through or into check.
+    let king_path = from.1.min(king_to)..=from.1.max(king_to);
+    if king_path.into_iter().any(|col| is_square_attacked(board, (back_row, col), colour.opponent())) {
+        return Err(ChessError::CastlingThroughCheck);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Colour::*;
+
+    fn check(piece: ChessPiece, from: Square, to: Square) -> Result<(), ChessError> {
+        validate_geometry(piece, from.to_row_col(), to.to_row_col())
+    }
+
+    #[test]
+    fn test_knight_moves_in_l_shape() {
+        assert!(check(ChessPiece::Knight(White), Square::G1, Square::F3).is_ok());
+        assert!(check(ChessPiece::Knight(White), Square::B1, Square::D2).is_ok());
+        assert!(check(ChessPiece::Knight(White), Square::G1, Square::G3).is_err());
+    }
+
+    #[test]
+    fn test_bishop_moves_diagonally() {
+        assert!(check(ChessPiece::Bishop(White), Square::C1, Square::H6).is_ok());
+        assert!(check(ChessPiece::Bishop(White), Square::C1, Square::C4).is_err());
+    }
+
+    #[test]
+    fn test_rook_moves_along_ranks_and_files() {
+        assert!(check(ChessPiece::Rook(Black), Square::A8, Square::A3).is_ok());
+        assert!(check(ChessPiece::Rook(Black), Square::A8, Square::H8).is_ok());
+        assert!(check(ChessPiece::Rook(Black), Square::A8, Square::B7).is_err());
+    }
+
+    #[test]
+    fn test_queen_moves_like_rook_or_bishop() {
+        assert!(check(ChessPiece::Queen(White), Square::D1, Square::D7).is_ok());
+        assert!(check(ChessPiece::Queen(White), Square::D1, Square::H5).is_ok());
+        assert!(check(ChessPiece::Queen(White), Square::D1, Square::E3).is_err());
+    }
+
+    #[test]
+    fn test_king_moves_one_square() {
+        assert!(check(ChessPiece::King(White), Square::E1, Square::E2).is_ok());
+        assert!(check(ChessPiece::King(White), Square::E1, Square::F2).is_ok());
+        assert!(check(ChessPiece::King(White), Square::E1, Square::E3).is_err());
+    }
+
+    #[test]
+    fn test_pawn_pushes_and_captures() {
+        assert!(check(ChessPiece::Pawn(White), Square::E2, Square::E4).is_ok());
+        assert!(check(ChessPiece::Pawn(White), Square::E2, Square::D3).is_ok());
+        assert!(check(ChessPiece::Pawn(White), Square::E3, Square::E5).is_err());
+        assert!(check(ChessPiece::Pawn(Black), Square::E7, Square::E5).is_ok());
+        assert!(check(ChessPiece::Pawn(Black), Square::E7, Square::E8).is_err());
+    }
+
+    #[test]
+    fn test_sliding_pieces_cannot_jump() {
+        let board = crate::GameState::new().board;
+        let path = |piece, from: Square, to: Square| {
+            validate_path(&board, piece, from.to_row_col(), to.to_row_col())
+        };
+        assert!(path(ChessPiece::Rook(White), Square::A1, Square::A5).is_err());
+        assert!(path(ChessPiece::Bishop(White), Square::C1, Square::G5).is_err());
+        assert!(path(ChessPiece::Queen(Black), Square::D8, Square::D4).is_err());
+        assert!(path(ChessPiece::Pawn(White), Square::E2, Square::E4).is_ok());
+        assert!(path(ChessPiece::Knight(White), Square::B1, Square::C3).is_ok());
+    }
+
+    #[test]
+    fn test_cannot_capture_own_piece() {
+        let board = crate::GameState::new().board;
+        let rook = ChessPiece::Rook(White);
+        assert!(validate_destination(&board, rook, Square::A2.to_row_col()).is_err());
+        assert!(validate_destination(&board, rook, Square::A3.to_row_col()).is_ok());
+        assert!(validate_destination(&board, rook, Square::A7.to_row_col()).is_ok());
+    }
+
+    fn pawn_board() -> [[ChessPiece; 8]; 8] {
+        let mut board = [[ChessPiece::Blank; 8]; 8];
+        let mut put = |square: Square, piece| {
+            let (row, col) = square.to_row_col();
+            board[row][col] = piece;
+        };
+        put(Square::E4, ChessPiece::Pawn(White));
+        put(Square::E5, ChessPiece::Pawn(Black));
+        put(Square::D5, ChessPiece::Knight(Black));
+        put(Square::F5, ChessPiece::Knight(White));
+        put(Square::C2, ChessPiece::Pawn(White));
+        board
+    }
+
+    fn pawn_move(from: Square, to: Square) -> Result<(), ChessError> {
+        let board = pawn_board();
+        let (row, col) = from.to_row_col();
+        let piece = board[row][col];
+        validate_geometry(piece, from.to_row_col(), to.to_row_col())?;
+        validate_path(&board, piece, from.to_row_col(), to.to_row_col())?;
+        validate_destination(&board, piece, to.to_row_col())?;
+        validate_pawn(&board, piece, from.to_row_col(), to.to_row_col(), None)
+    }
+
+    #[test]
+    fn test_pawn_single_push() {
+        assert!(pawn_move(Square::C2, Square::C3).is_ok());
+    }
+
+    #[test]
+    fn test_pawn_double_push_only_from_start() {
+        assert!(pawn_move(Square::C2, Square::C4).is_ok());
+        assert!(pawn_move(Square::E4, Square::E6).is_err());
+    }
+
+    #[test]
+    fn test_pawn_cannot_push_into_piece() {
+        assert!(pawn_move(Square::E4, Square::E5).is_err());
+        assert!(pawn_move(Square::E5, Square::E4).is_err());
+    }
+
+    #[test]
+    fn test_pawn_captures_diagonally() {
+        assert!(pawn_move(Square::E4, Square::D5).is_ok());
+    }
+
+    #[test]
+    fn test_pawn_cannot_capture_own_piece() {
+        assert!(pawn_move(Square::E4, Square::F5).is_err());
+    }
+
+    #[test]
+    fn test_pawn_cannot_move_diagonally_without_capture() {
+        assert!(pawn_move(Square::C2, Square::D3).is_err());
+        assert!(pawn_move(Square::C2, Square::B3).is_err());
+    }
+
+    #[test]
+    fn test_pawn_may_capture_en_passant_square() {
+        let board = pawn_board();
+        let pawn = ChessPiece::Pawn(White);
+        let (from, to) = (Square::C2.to_row_col(), Square::D3.to_row_col());
+        assert!(validate_pawn(&board, pawn, from, to, None).is_err());
+        assert!(validate_pawn(&board, pawn, from, to, Some(to)).is_ok());
+    }
+
+    #[test]
+    fn test_pawn_cannot_move_backwards() {
+        assert!(pawn_move(Square::E4, Square::E3).is_err());
+        assert!(pawn_move(Square::E4, Square::D3).is_err());
+    }
+
+    #[test]
+    fn test_is_square_attacked() {
+        let board = pawn_board();
+        assert!(is_square_attacked(&board, Square::D5.to_row_col(), White));
+        assert!(is_square_attacked(&board, Square::D4.to_row_col(), Black));
+        assert!(!is_square_attacked(&board, Square::E5.to_row_col(), White));
+        assert!(is_square_attacked(&board, Square::E7.to_row_col(), White));
+    }
+
+    fn board_with(pieces: &[(Square, ChessPiece)]) -> [[ChessPiece; 8]; 8] {
+        let mut board = [[ChessPiece::Blank; 8]; 8];
+        board[0][4] = ChessPiece::King(White);
+        board[7][4] = ChessPiece::King(Black);
+        for &(square, piece) in pieces {
+            let (row, col) = square.to_row_col();
+            board[row][col] = piece;
+        }
+        board
+    }
+
+    #[test]
+    fn test_insufficient_material() {
+        assert!(is_insufficient_material(&board_with(&[])));
+        assert!(is_insufficient_material(&board_with(&[(Square::C1, ChessPiece::Bishop(White))])));
+        assert!(is_insufficient_material(&board_with(&[(Square::G8, ChessPiece::Knight(Black))])));
+        assert!(is_insufficient_material(&board_with(&[
+            (Square::C1, ChessPiece::Bishop(White)),
+            (Square::F8, ChessPiece::Bishop(Black)),
+        ])));
+    }
+
+    #[test]
+    fn test_sufficient_material() {
+        assert!(!is_insufficient_material(&crate::GameState::new().board));
+        assert!(!is_insufficient_material(&board_with(&[(Square::A2, ChessPiece::Pawn(White))])));
+        assert!(!is_insufficient_material(&board_with(&[(Square::A1, ChessPiece::Rook(White))])));
+        assert!(!is_insufficient_material(&board_with(&[
+            (Square::C1, ChessPiece::Bishop(White)),
+            (Square::C8, ChessPiece::Bishop(Black)),
+        ])));
+        assert!(!is_insufficient_material(&board_with(&[
+            (Square::B1, ChessPiece::Knight(White)),
+            (Square::G8, ChessPiece::Knight(Black)),
+        ])));
+    }
+
+    #[test]
+    fn test_find_pins() {
+        let board = board_with(&[
+            (Square::E4, ChessPiece::Knight(White)),
+            (Square::E6, ChessPiece::Rook(Black)),
+            (Square::D2, ChessPiece::Pawn(White)),
+            (Square::B4, ChessPiece::Bishop(Black)),
+            (Square::F2, ChessPiece::Pawn(White)),
+            (Square::H4, ChessPiece::Rook(Black)),
+        ]);
+        let pins = find_pins(&board, White);
+        assert_eq!(pins.len(), 2);
+        assert!(pins.contains(&Pin { pinned: Square::E4, pinner: Square::E6, direction: (1, 0) }));
+        assert!(pins.contains(&Pin { pinned: Square::D2, pinner: Square::B4, direction: (1, -1) }));
+    }
+
+    #[test]
+    fn test_two_pieces_in_the_way_are_not_pinned() {
+        let board = board_with(&[
+            (Square::E2, ChessPiece::Knight(White)),
+            (Square::E3, ChessPiece::Bishop(White)),
+            (Square::E6, ChessPiece::Rook(Black)),
+        ]);
+        assert!(find_pins(&board, White).is_empty());
+    }
+
+    #[test]
+    fn test_attackers() {
+        let board = pawn_board();
+        assert_eq!(attackers(&board, Square::D5.to_row_col(), White), vec![Square::E4]);
+        assert_eq!(attackers(&board, Square::F4.to_row_col(), Black), vec![Square::D5, Square::E5]);
+        assert_eq!(attackers(&board, Square::F6.to_row_col(), Black), vec![Square::D5]);
+
+        let board = board_with(&[
+            (Square::D4, ChessPiece::Queen(Black)),
+            (Square::C3, ChessPiece::Knight(Black)),
+            (Square::H4, ChessPiece::Rook(Black)),
+        ]);
+        assert_eq!(attackers(&board, Square::E2.to_row_col(), Black), vec![Square::C3]);
+        assert_eq!(attackers(&board, Square::F4.to_row_col(), Black), vec![Square::D4, Square::H4]);
+    }
+
+    #[test]
+    fn test_cannot_capture_king() {
+        let board = board_with(&[(Square::E2, ChessPiece::Queen(Black))]);
+        let queen = ChessPiece::Queen(Black);
+        assert_eq!(validate_destination(&board, queen, Square::E1.to_row_col()), Err(ChessError::CaptureKing));
+    }
+
+    #[test]
+    fn test_validate_position() {
+        assert!(validate_position(&crate::GameState::new().board, White).is_ok());
+        assert!(validate_position(&board_with(&[]), Black).is_ok());
+
+        let mut board = board_with(&[]);
+        board[7][4] = ChessPiece::Blank;
+        assert_eq!(validate_position(&board, White), Err(ChessError::WrongKingCount(Black, 0)));
+
+        let board = board_with(&[(Square::A1, ChessPiece::King(White))]);
+        assert_eq!(validate_position(&board, White), Err(ChessError::WrongKingCount(White, 2)));
+
+        let board = board_with(&[(Square::C8, ChessPiece::Pawn(White))]);
+        assert_eq!(validate_position(&board, White), Err(ChessError::PawnOnBackRank(Square::C8)));
+
+        let board = board_with(&[(Square::E4, ChessPiece::Rook(White))]);
+        assert_eq!(validate_position(&board, White), Err(ChessError::SideNotToMoveInCheck));
+        assert!(validate_position(&board, Black).is_ok());
+    }
+
+    #[test]
+    fn test_squares_between() {
+        assert_eq!(squares_between((0, 0), (3, 3)), vec![(1, 1), (2, 2)]);
+        assert_eq!(squares_between((4, 7), (4, 5)), vec![(4, 6)]);
+        assert!(squares_between((0, 0), (1, 0)).is_empty());
+    }
+
+    #[test]
+    fn test_piece_cannot_stay_put() {
+        assert!(check(ChessPiece::Queen(White), Square::D1, Square::D1).is_err());
+    }
+}
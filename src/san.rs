@@ -0,0 +1,299 @@
+//! # SAN
+//!
+//! Standard Algebraic Notation, the way moves are written in books and PGN files:
+//! `e4`, `Nf3`, `exd5`, `O-O`, `e8=Q+`, with a file or rank added (`Rad1`, `N5f3`)
+//! when two pieces of the same kind could reach the square.
+//!
+//! A move is resolved against the legal moves of the position, so anything that
+//! parses here is known to be playable. Writing a move uses the same legal moves
+//! to decide how much disambiguation it needs.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::error::ChessError;
+use crate::{ChessPiece, Colour, GameState, Move, Square, promotion_piece};
+
+impl GameState {
+    // > parse a move in Standard Algebraic Notation for the side to move
+    pub fn parse_san(&self, san: &str) -> Result<Move, ChessError> {
+        let text = san.trim().trim_end_matches(['+', '#', '!', '?']);
+        let invalid = || ChessError::InvalidNotation(san.to_string());
+
+        let colour = self.current_player;
+        let castle = match text {
+            "O-O" | "0-0" => Some(6),
+            "O-O-O" | "0-0-0" => Some(2),
+            _ => None,
+        };
+        if let Some(king_col) = castle {
+            return self.resolve_san(san, |mv| mv.castling && mv.landing().file() == king_col);
+        }
+
+        let mut rest = text;
+        let piece = match rest.chars().next().and_then(|c| san_piece(c, colour)) {
+            Some(piece) => {
+                rest = &rest[1..];
+                piece
+            }
+            None => ChessPiece::Pawn(colour),
+        };
+
+        // The promotion piece follows the destination, usually after '='.
+        let mut promotion = None;
+        if let ChessPiece::Pawn(_) = piece {
+            let (head, letter) = match rest.split_once('=') {
+                Some((head, letter)) => (head, letter),
+                None if rest.ends_with(['Q', 'R', 'B', 'N']) => rest.split_at(rest.len() - 1),
+                None => (rest, ""),
+            };
+            if !letter.is_empty() {
+                promotion = Some(promotion_piece(letter, colour).ok_or_else(invalid)?);
+            }
+            rest = head;
+        }
+
+        if rest.len() < 2 || !rest.is_ascii() {
+            return Err(invalid());
+        }
+        let (prefix, destination) = rest.split_at(rest.len() - 2);
+        let to: Square = destination.parse().map_err(|_| invalid())?;
+        let prefix = prefix.strip_suffix('x').unwrap_or(prefix);
+
+        let mut from_col = None;
+        let mut from_row = None;
+        for c in prefix.chars() {
+            match c {
+                'a'..='h' if from_col.is_none() && from_row.is_none() => from_col = Some(c as usize - 'a' as usize),
+                '1'..='8' if from_row.is_none() => from_row = Some(c as usize - '1' as usize),
+                _ => return Err(invalid()),
+            }
+        }
+        // Without a file, a pawn moves straight ahead.
+        if let (ChessPiece::Pawn(_), None) = (piece, from_col) {
+            from_col = Some(to.to_row_col().1);
+        }
+
+        self.resolve_san(san, |mv| {
+            let (row, col) = mv.from.to_row_col();
+            self.board[row][col] == piece
+                && mv.to == to
+                && from_col.is_none_or(|c| c == col)
+                && from_row.is_none_or(|r| r == row)
+                && mv.promotion == promotion.or(mv.promotion.map(|_| ChessPiece::Queen(colour)))
+        })
+    }
+
+    // > write a legal move in Standard Algebraic Notation, with disambiguation and x, + and # markers
+    pub fn move_to_san(&self, mv: &Move) -> String {
+        let mv = self.complete_move(*mv);
+        let kind = self.classify_move(&mv).unwrap_or_default();
+        let suffix = if kind.checkmate {
+            "#"
+        } else if kind.check {
+            "+"
+        } else {
+            ""
+        };
+        let (from_row, from_col) = mv.from.to_row_col();
+        let (to_row, to_col) = mv.to.to_row_col();
+        if mv.castling {
+            let castle = if to_col > from_col { "O-O" } else { "O-O-O" };
+            return format!("{}{}", castle, suffix);
+        }
+
+        let piece = self.board[from_row][from_col];
+        let mut san = String::new();
+        match piece_letter(piece) {
+            Some(letter) => {
+                san.push(letter);
+                // Name the starting file, rank or both if another piece of the same kind could also go there.
+                let rivals: Vec<Move> = self
+                    .legal_moves()
+                    .into_iter()
+                    .filter(|other| other.to == mv.to && other.from != mv.from)
+                    .filter(|other| {
+                        let (row, col) = other.from.to_row_col();
+                        self.board[row][col] == piece
+                    })
+                    .collect();
+                if !rivals.is_empty() {
+                    let same_col = rivals.iter().any(|other| other.from.to_row_col().1 == from_col);
+                    let same_row = rivals.iter().any(|other| other.from.to_row_col().0 == from_row);
+                    if !same_col {
+                        san.push(file_char(from_col));
+                    } else if !same_row {
+                        san.push(rank_char(from_row));
+                    } else {
+                        san.push(file_char(from_col));
+                        san.push(rank_char(from_row));
+                    }
+                }
+            }
+            None if kind.capture => san.push(file_char(from_col)),
+            None => {}
+        }
+        if kind.capture {
+            san.push('x');
+        }
+        san.push(file_char(to_col));
+        san.push(rank_char(to_row));
+        if let Some(letter) = mv.promotion.and_then(piece_letter) {
+            san.push('=');
+            san.push(letter);
+        }
+        san.push_str(suffix);
+        san
+    }
+
+    // > find the single legal move matching a SAN description
+    fn resolve_san(&self, san: &str, matches: impl Fn(&Move) -> bool) -> Result<Move, ChessError> {
+        let candidates: Vec<Move> = self.legal_moves().into_iter().filter(|mv| matches(mv)).collect();
+        match candidates.as_slice() {
+            [mv] => Ok(*mv),
+            [] => Err(ChessError::NoMatchingMove(san.to_string())),
+            _ => Err(ChessError::AmbiguousMove(san.to_string())),
+        }
+    }
+}
+
+// > convert a SAN piece letter into a piece of the given colour; pawns have no letter
+fn san_piece(letter: char, colour: Colour) -> Option<ChessPiece> {
+    match letter {
+        'K' => Some(ChessPiece::King(colour)),
+        'Q' => Some(ChessPiece::Queen(colour)),
+        'R' => Some(ChessPiece::Rook(colour)),
+        'B' => Some(ChessPiece::Bishop(colour)),
+        'N' => Some(ChessPiece::Knight(colour)),
+        _ => None,
+    }
+}
+
+// > give the SAN letter of a piece; pawns have none
+fn piece_letter(piece: ChessPiece) -> Option<char> {
+    match piece {
+        ChessPiece::King(_) => Some('K'),
+        ChessPiece::Queen(_) => Some('Q'),
+        ChessPiece::Rook(_) => Some('R'),
+        ChessPiece::Bishop(_) => Some('B'),
+        ChessPiece::Knight(_) => Some('N'),
+        ChessPiece::Pawn(_) | ChessPiece::Blank => None,
+    }
+}
+
+fn file_char(col: usize) -> char {
+    (b'a' + col as u8) as char
+}
+
+fn rank_char(row: usize) -> char {
+    (b'1' + row as u8) as char
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn san(fen: &str, text: &str) -> Result<Move, ChessError> {
+        GameState::from_fen(fen).unwrap().parse_san(text)
+    }
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    #[test]
+    fn test_pawn_and_piece_moves() {
+        let mv = san(START, "e4").unwrap();
+        assert_eq!((mv.from, mv.to), (Square::E2, Square::E4));
+        assert_eq!(san(START, "Nf3").unwrap(), Move::new(Square::G1, Square::F3));
+        assert_eq!(san(START, "Nf3+!?").unwrap(), Move::new(Square::G1, Square::F3));
+        assert_eq!(san(START, "e5"), Err(ChessError::NoMatchingMove("e5".to_string())));
+        assert_eq!(san(START, "Zf3"), Err(ChessError::InvalidNotation("Zf3".to_string())));
+        assert_eq!(san(START, "N"), Err(ChessError::InvalidNotation("N".to_string())));
+    }
+
+    #[test]
+    fn test_pawn_captures() {
+        let fen = "rnbqkbnr/ppp1pppp/8/3p4/2P1P3/8/PP1P1PPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(san(fen, "exd5").unwrap().from, Square::E4);
+        assert_eq!(san(fen, "cxd5").unwrap().from, Square::C4);
+        assert_eq!(san(fen, "d5"), Err(ChessError::NoMatchingMove("d5".to_string())));
+    }
+
+    #[test]
+    fn test_disambiguation() {
+        let fen = "4k3/8/8/8/8/8/4K3/R6R w - - 0 1";
+        assert_eq!(san(fen, "Rd1"), Err(ChessError::AmbiguousMove("Rd1".to_string())));
+        assert_eq!(san(fen, "Rad1").unwrap().from, Square::A1);
+        assert_eq!(san(fen, "Rhd1").unwrap().from, Square::H1);
+        let fen = "4k3/8/8/N7/8/8/8/N3K3 w - - 0 1";
+        assert_eq!(san(fen, "Nb3"), Err(ChessError::AmbiguousMove("Nb3".to_string())));
+        assert_eq!(san(fen, "N5b3").unwrap().from, Square::A5);
+        assert_eq!(san(fen, "Na1b3").unwrap().from, Square::A1);
+    }
+
+    #[test]
+    fn test_castling() {
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        assert_eq!(san(fen, "O-O").unwrap().to, Square::G1);
+        assert_eq!(san(fen, "0-0-0").unwrap().to, Square::C1);
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1";
+        assert_eq!(san(fen, "O-O-O").unwrap().to, Square::C8);
+    }
+
+    #[test]
+    fn test_promotion() {
+        let fen = "4k3/P7/8/8/8/8/8/4K3 w - - 0 1";
+        let queen = ChessPiece::Queen(Colour::White);
+        assert_eq!(san(fen, "a8=Q+").unwrap().promotion, Some(queen));
+        assert_eq!(san(fen, "a8N").unwrap().promotion, Some(ChessPiece::Knight(Colour::White)));
+        assert_eq!(san(fen, "a8").unwrap().promotion, Some(queen));
+        assert_eq!(san(fen, "a8=K"), Err(ChessError::InvalidNotation("a8=K".to_string())));
+    }
+
+    fn to_san(fen: &str, from: Square, to: Square) -> String {
+        GameState::from_fen(fen).unwrap().move_to_san(&Move::new(from, to))
+    }
+
+    #[test]
+    fn test_format_simple_moves() {
+        assert_eq!(to_san(START, Square::E2, Square::E4), "e4");
+        assert_eq!(to_san(START, Square::G1, Square::F3), "Nf3");
+        let fen = "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(to_san(fen, Square::E4, Square::D5), "exd5");
+        let fen = "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1";
+        assert_eq!(to_san(fen, Square::E5, Square::D6), "exd6");
+    }
+
+    #[test]
+    fn test_format_disambiguation() {
+        let fen = "4k3/8/8/8/8/8/4K3/R6R w - - 0 1";
+        assert_eq!(to_san(fen, Square::A1, Square::D1), "Rad1");
+        let fen = "4k3/8/8/N7/8/8/8/N3K3 w - - 0 1";
+        assert_eq!(to_san(fen, Square::A5, Square::B3), "N5b3");
+        let fen = "4k3/8/8/8/8/Q1Q5/8/Q3K3 w - - 0 1";
+        assert_eq!(to_san(fen, Square::A3, Square::B2), "Qa3b2");
+    }
+
+    #[test]
+    fn test_format_castling_promotion_and_checks() {
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        assert_eq!(to_san(fen, Square::E1, Square::G1), "O-O");
+        assert_eq!(to_san(fen, Square::E1, Square::C1), "O-O-O");
+        let fen = "4k3/P7/8/8/8/8/8/4K3 w - - 0 1";
+        assert_eq!(to_san(fen, Square::A7, Square::A8), "a8=Q+");
+        let game_state = GameState::from_fen(fen).unwrap();
+        let knight = Move::new(Square::A7, Square::A8).with_promotion(ChessPiece::Knight(Colour::White));
+        assert_eq!(game_state.move_to_san(&knight), "a8=N");
+        let fen = "7k/8/6K1/8/8/8/8/1Q6 w - - 0 1";
+        assert_eq!(to_san(fen, Square::B1, Square::B8), "Qb8#");
+    }
+
+    #[test]
+    fn test_format_round_trips_through_parse() {
+        let game_state = GameState::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        for mv in game_state.legal_moves() {
+            let san = game_state.move_to_san(&mv);
+            assert_eq!(game_state.parse_san(&san), Ok(mv), "{}", san);
+        }
+    }
+}
@@ -0,0 +1,1080 @@
+//! # Search
+//!
+//! Looks ahead to choose a move. Negamax tries every legal move to a fixed depth and
+//! scores the positions at the end with [`eval::evaluate`], taking the best score for
+//! the side to move at each ply. Because evaluations are from the side to move's point
+//! of view, the score of a move is the negated score of the position it leads to.
+//!
+//! Alpha-beta gives the same answer while skipping most of the tree: once a move is
+//! found to be too good for the opponent to allow, the rest of the moves at that ply
+//! cannot matter. The bounds are fail-soft, so a score outside the window still says
+//! how far outside it is. The plain negamax is kept in the tests to check against.
+//!
+//! At the end of each line the quiescence search keeps playing captures and promotions
+//! until the position is quiet, so the search never stops in the middle of an exchange
+//! and thinks it has won a piece that is about to be taken back. The side to move may
+//! "stand pat" on the static score instead of capturing, unless it is in check, when
+//! every way out of check is searched.
+//!
+//! Alpha-beta cuts off soonest when the best move is tried first, so moves are
+//! ordered: the move that was best the last time the position was searched (the hash
+//! move, which is how one depth passes its principal variation on to the next), then
+//! captures with the most valuable victim and least valuable attacker first (MVV-LVA),
+//! then the killer moves, quiet moves that caused a cutoff at the same ply elsewhere in
+//! the tree, and last the other quiet moves by how often they have caused cutoffs
+//! anywhere (the history heuristic).
+//!
+//! The search is selective, giving up exact scores to look deeper in the same time.
+//! Null-move pruning lets the side to move pass: if a shallower search still reaches
+//! beta, a real move surely would, and the node is cut off. Late move reductions search
+//! the quiet moves at the end of the order a ply less, and again in full only if they
+//! turn out better than expected. Futility pruning skips quiet moves one ply from the
+//! end when the position is too far behind for them to catch up. Check extensions
+//! search a ply deeper when in check. Each can be turned off in [`Pruning`].
+//!
+//! Iterative deepening searches to depth 1, then 2, and so on, until a depth, node or
+//! time limit is reached, reporting the score, the positions searched and the principal
+//! variation after each depth. A search that runs out of time is abandoned and the
+//! last finished depth gives the move.
+//!
+//! With a clock, a move is planned to take a thirtieth of the time left plus half the
+//! increment. That is a soft limit: no new depth starts after it, and it is stretched
+//! while the best move keeps changing from one depth to the next. The hard limit, four
+//! times the plan but never more than half the time left, abandons the search outright,
+//! so the engine cannot lose on time.
+//!
+//! ```text
+//! go depth 3                   # at the prompt: search three plies and play the best move
+//! go movetime 500              # or search for half a second
+//! go wtime 60000 btime 60000   # or play as if a minute were left on each clock
+//! ```
+//!
+//! Positions are scored by an [`eval::Evaluator`]. With an NNUE network the searcher
+//! keeps a stack of accumulators alongside the position, updating it as each move is
+//! made and popping it as the move is taken back.
+//!
+//! With more than one thread (`--threads` at the prompt, `Threads` over UCI) the search
+//! is Lazy SMP: helper threads search the same position at the same time, sharing the
+//! table of best moves, and the main thread's result is played. The helpers' entries in
+//! the table send the main thread down the likely lines sooner.
+//!
+//! `bench` searches [`BENCH_POSITIONS`] to a fixed depth and prints the positions
+//! searched and how fast, to measure a change to move generation or the search:
+//!
+//! ```text
+//! overload-vibe-coding bench            # every position to depth 5 with one thread
+//! overload-vibe-coding bench 8 --threads 4
+//! ```
+//!
+//! Mates are scored as [`MATE`] less the number of plies to reach them, so the search
+//! prefers the quickest mate and the slowest defeat.
+
+use std::sync::{Arc, OnceLock};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::eval::{self, Evaluator};
+use crate::nnue::Accumulator;
+use crate::skill::Skill;
+use crate::{ChessPiece, Colour, GameResult, GameState, Move, MoveList, PackedMove, Square, UndoInfo, zobrist};
+
+// Far above any material count.
+pub const MATE: i32 = 1_000_000;
+// Beyond any score, for an open window.
+const INFINITY: i32 = MATE + 1;
+// How deep to go when there is neither a depth nor a time limit.
+const DEFAULT_DEPTH: u32 = 4;
+// How deep a search with only a time limit may go.
+const MAX_DEPTH: u32 = 64;
+// The share of the remaining clock time to spend on one move.
+const MOVES_TO_GO: u32 = 30;
+// How many times the planned time a move may take before the search is abandoned.
+const HARD_FACTOR: u32 = 4;
+// Time kept back on the clock for sending the move and anything else that is not the search.
+const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+// The number of positions whose best move is remembered; a power of two.
+const HASH_MOVES: usize = 1 << 16;
+// The most threads a search may use.
+pub const MAX_THREADS: usize = 64;
+// How deep "bench" searches each of its positions unless told otherwise.
+pub const BENCH_DEPTH: u32 = 5;
+
+// The positions "bench" searches: openings and middlegames full of tactics, and endgames with few pieces.
+pub const BENCH_POSITIONS: [&str; 12] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP1B1PPP/R2QKB1R w KQ - 0 8",
+    "r2q1rk1/ppp2ppp/2np1n2/2b1p1B1/2B1P1b1/2NP1N2/PPP2PPP/R2Q1RK1 w - - 0 8",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    "r3k2r/1b4bq/8/8/8/8/7B/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "8/5pk1/6p1/8/3R4/6P1/5PK1/3r4 w - - 0 1",
+    "8/8/4k3/8/2p5/8/B2K4/8 w - - 0 1",
+    "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",
+];
+
+// Null-move pruning: how many plies shallower the search after a pass goes, and how deep a node must be to try it.
+const NULL_MOVE_REDUCTION: u32 = 2;
+const NULL_MOVE_DEPTH: u32 = 3;
+// Late move reductions: how many moves are searched in full first, and how deep a node must be.
+const LATE_MOVE: usize = 3;
+const LATE_MOVE_DEPTH: u32 = 3;
+// Futility pruning: how far below alpha the static score must be for a quiet move to be skipped.
+const FUTILITY_MARGIN: i32 = 2 * eval::PAWN;
+
+// Move ordering scores: the hash move, then captures, then killers, then history.
+const HASH_MOVE: i32 = 3_000_000;
+const CAPTURE: i32 = 2_000_000;
+const KILLER: i32 = 1_000_000;
+
+// > add the limits on a search: how deep to go, how many positions to look at and how long to take
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Limits {
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    // After this long no new depth is started: the time the move is meant to take.
+    pub soft_time: Option<Duration>,
+    // After this long the search is abandoned, whatever depth it is on.
+    pub hard_time: Option<Duration>,
+    // Search until told to stop, however long that is.
+    pub infinite: bool,
+    // Search on the opponent's time until a ponderhit, and only then start the clock.
+    pub ponder: bool,
+    // How well to play, for opponents who want a chance of winning.
+    pub skill: Skill,
+    pub pruning: Pruning,
+}
+
+// > add the selective search techniques, each of which can be turned off to compare
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pruning {
+    // Let the side to move pass; if it still reaches beta with a shallower search, cut the node off.
+    pub null_move: bool,
+    // Search quiet moves late in the order a ply less, and again in full only if they beat alpha.
+    pub late_move_reductions: bool,
+    // One ply from the end, skip quiet moves when the static score is too far below alpha for them to matter.
+    pub futility: bool,
+    // Search a ply deeper when in check, so the search does not stop in the middle of an attack on the king.
+    pub check_extensions: bool,
+}
+
+impl Default for Pruning {
+    fn default() -> Pruning {
+        Pruning { null_move: true, late_move_reductions: true, futility: true, check_extensions: true }
+    }
+}
+
+impl Pruning {
+    // A full-width search, which gives the same scores as plain negamax.
+    pub const NONE: Pruning = Pruning { null_move: false, late_move_reductions: false, futility: false, check_extensions: false };
+}
+
+impl Limits {
+    pub fn depth(depth: u32) -> Limits {
+        Limits { depth: Some(depth), ..Limits::default() }
+    }
+
+    pub fn movetime(time: Duration) -> Limits {
+        Limits { soft_time: Some(time), hard_time: Some(time), ..Limits::default() }
+    }
+
+    // > share out the time left on the clock: aim for a fair share of it plus half the increment,
+    // > and never go past half of what is left, so the flag cannot fall
+    pub fn clock(remaining: Duration, increment: Duration, moves_to_go: Option<u32>) -> Limits {
+        let most = remaining.saturating_sub(MOVE_OVERHEAD) / 2;
+        let soft = (remaining / moves_to_go.unwrap_or(MOVES_TO_GO).max(1) + increment / 2).min(most);
+        Limits { soft_time: Some(soft), hard_time: Some((soft * HARD_FACTOR).min(most)), ..Limits::default() }
+    }
+
+    // > read the limits from the words after "go": depth, nodes, movetime, infinite, ponder,
+    // > or the clock times wtime, btime, winc, binc and movestogo
+    #[cfg(feature = "uci")]
+    pub fn from_go(words: &[&str], side: Colour) -> Result<Limits, String> {
+        let mut limits = Limits::default();
+        let (mut remaining, mut increment, mut moves_to_go) = (None, Duration::ZERO, None);
+        let mut words = words.iter();
+        while let Some(&word) = words.next() {
+            if matches!(word, "infinite" | "ponder") {
+                limits.infinite |= word == "infinite";
+                limits.ponder |= word == "ponder";
+                continue;
+            }
+            if !matches!(word, "depth" | "nodes" | "movetime" | "wtime" | "btime" | "winc" | "binc" | "movestogo") {
+                continue;
+            }
+            let value: u64 = words.next().and_then(|value| value.parse().ok()).ok_or_else(|| format!("'{}' needs a number", word))?;
+            let millis = Duration::from_millis(value);
+            match (word, side) {
+                ("depth", _) => limits.depth = Some(value.clamp(1, u64::from(MAX_DEPTH)) as u32),
+                ("nodes", _) => limits.nodes = Some(value),
+                ("movetime", _) => (limits.soft_time, limits.hard_time) = (Some(millis), Some(millis)),
+                ("movestogo", _) => moves_to_go = Some(value.min(u64::from(u32::MAX)) as u32),
+                ("wtime", Colour::White) | ("btime", Colour::Black) => remaining = Some(millis),
+                ("winc", Colour::White) | ("binc", Colour::Black) => increment = millis,
+                _ => {}
+            }
+        }
+        if let (None, Some(remaining)) = (limits.soft_time, remaining) {
+            let clock = Limits::clock(remaining, increment, moves_to_go);
+            (limits.soft_time, limits.hard_time) = (clock.soft_time, clock.hard_time);
+        }
+        // An infinite search ignores the clock and waits for "stop".
+        if limits.infinite {
+            (limits.soft_time, limits.hard_time) = (None, None);
+        }
+        Ok(limits)
+    }
+}
+
+// > add what a search found: the move to play, its score for the side to move and the line it expects
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    pub score: i32,
+    // The depth of the last iteration that finished.
+    pub depth: u32,
+    pub nodes: u64,
+    pub time: Duration,
+    // The principal variation: the moves both sides are expected to play.
+    pub pv: Vec<Move>,
+}
+
+impl SearchResult {
+    // > give the number of moves to a forced mate, negative when the side to move is being mated
+    pub fn mate_in(&self) -> Option<i32> {
+        let plies = MATE - self.score.abs();
+        (plies <= 1000).then(|| if self.score > 0 { (plies + 1) / 2 } else { -(plies / 2) })
+    }
+}
+
+// > add what a running search listens for from outside: stop, and ponderhit while it is pondering
+#[derive(Clone, Copy)]
+pub struct Signals<'a> {
+    pub stop: &'a dyn Fn() -> bool,
+    pub ponderhit: &'a dyn Fn() -> bool,
+}
+
+impl Signals<'_> {
+    // For a search that only its limits end.
+    pub const NONE: Signals<'static> = Signals { stop: &|| false, ponderhit: &|| false };
+}
+
+// > search every line to the given depth in plies and give the best move for the side to move
+pub fn search(position: &GameState, depth: u32) -> SearchResult {
+    iterative_deepening(position, Limits::depth(depth), &Evaluator::default(), 1, Signals::NONE, |_| {})
+}
+
+// > search each position, such as the bench positions, to a fixed depth, calling report after each, and give the
+// > positions searched in all; with one thread the count is the same on every run, so a change in it means the
+// > search itself has changed
+pub fn bench(positions: &[&str], depth: u32, evaluator: &Evaluator, threads: usize, mut report: impl FnMut(&str, &SearchResult)) -> u64 {
+    positions
+        .iter()
+        .map(|fen| {
+            let position = GameState::from_fen(fen).expect("the bench positions are valid");
+            let result = iterative_deepening(&position, Limits::depth(depth), evaluator, threads, Signals::NONE, |_| {});
+            report(fen, &result);
+            result.nodes
+        })
+        .sum()
+}
+
+// > search one ply deeper at a time until a limit is reached or stop says so, reporting each depth as it finishes,
+// > with helper threads searching the same position alongside (Lazy SMP)
+// The first depth always finishes, so there is a move to play however short the time. No new depth
+// is started after the soft time, which is stretched by half while the best move keeps changing.
+// A pondering search starts its clock at the ponderhit.
+// The helpers share the table of best moves, so each finds the others' work and they spread out over
+// the tree; only the main thread's results count. With one thread the search is the same every time.
+pub fn iterative_deepening(
+    position: &GameState,
+    limits: Limits,
+    evaluator: &Evaluator,
+    threads: usize,
+    signals: Signals,
+    mut report: impl FnMut(&SearchResult),
+) -> SearchResult {
+    let start = Instant::now();
+    // When the clock started: with the search, or at the ponderhit when pondering.
+    let clock = OnceLock::new();
+    if !limits.ponder {
+        let _ = clock.set(start);
+    }
+    let elapsed = || {
+        if clock.get().is_none() && (signals.ponderhit)() {
+            let _ = clock.set(Instant::now());
+        }
+        clock.get().map(Instant::elapsed)
+    };
+    let out_of_time = || (signals.stop)() || elapsed().zip(limits.hard_time).is_some_and(|(elapsed, hard)| elapsed >= hard);
+    let table = Arc::new(HashTable::new(HASH_MOVES));
+    let finished = AtomicBool::new(false);
+    std::thread::scope(|scope| {
+        let helpers: Vec<_> = (1..threads.clamp(1, MAX_THREADS))
+            .map(|helper| {
+                let table = table.clone();
+                let finished = &finished;
+                scope.spawn(move || help(position, evaluator, table, helper, &|| finished.load(Ordering::Relaxed)))
+            })
+            .collect();
+
+        let mut searcher = Searcher::new(evaluator.clone());
+        searcher.table = table.clone();
+        searcher.skill = limits.skill;
+        searcher.pruning = limits.pruning;
+        searcher.start(position);
+        let mut position = position.clone();
+        let mut best = SearchResult { best_move: None, score: 0, depth: 0, nodes: 0, time: Duration::ZERO, pv: Vec::new() };
+        let unlimited = limits.infinite || limits.ponder || limits.nodes.is_some() || limits.soft_time.is_some();
+        let max_depth = limits.depth.unwrap_or(if unlimited { MAX_DEPTH } else { DEFAULT_DEPTH });
+        let max_depth = limits.skill.max_depth().map_or(max_depth, |skill_depth| max_depth.min(skill_depth));
+        let mut soft_time = limits.soft_time;
+        for depth in 1..=max_depth {
+            let mut pv = Vec::new();
+            let score = searcher.alpha_beta(&mut position, depth, 0, -INFINITY, INFINITY, &mut pv);
+            if searcher.stopped {
+                break;
+            }
+            let changed = depth > 1 && pv.first() != best.best_move.as_ref();
+            best = SearchResult { best_move: pv.first().copied(), score, depth, nodes: searcher.nodes, time: start.elapsed(), pv };
+            report(&best);
+            // A full-width search finds the quickest mate first, and a finished game has no moves to look at.
+            if best.mate_in().is_some() || best.best_move.is_none() {
+                break;
+            }
+            if changed && let (Some(soft), Some(planned), Some(hard)) = (soft_time, limits.soft_time, limits.hard_time) {
+                soft_time = Some((soft + planned / 2).min(hard));
+            }
+            if elapsed().zip(soft_time).is_some_and(|(elapsed, soft)| elapsed >= soft) {
+                break;
+            }
+            searcher.max_nodes = limits.nodes;
+            searcher.stop = &out_of_time;
+        }
+
+        if limits.skill.is_limited() && best.best_move.is_some() {
+            best = searcher.weaken(&mut position, best);
+        }
+        finished.store(true, Ordering::Relaxed);
+        best.nodes = searcher.nodes + helpers.into_iter().map(|helper| helper.join().expect("a search thread panicked")).sum::<u64>();
+        best.time = start.elapsed();
+        best
+    })
+}
+
+// > search as a helper thread until the main thread has finished, giving the number of positions searched
+// Odd helpers start a depth ahead, so the threads are not all at the same depth at once.
+fn help(position: &GameState, evaluator: &Evaluator, table: Arc<HashTable>, helper: usize, finished: &dyn Fn() -> bool) -> u64 {
+    let mut searcher = Searcher::new(evaluator.clone());
+    searcher.table = table;
+    searcher.stop = finished;
+    searcher.start(position);
+    let mut position = position.clone();
+    for depth in (1 + helper as u32 % 2)..=MAX_DEPTH {
+        searcher.alpha_beta(&mut position, depth, 0, -INFINITY, INFINITY, &mut Vec::new());
+        if searcher.stopped {
+            break;
+        }
+    }
+    searcher.nodes
+}
+
+// > add the table of best moves by Zobrist key that every thread of a search shares, overwritten on collision
+// Each entry is one word: the top 48 bits of the key, to check that the entry is for the position, and
+// the packed move in the low 16. A thread reads or writes a whole entry at once, so none is ever torn.
+// With HASH_MOVES entries the index is the low 16 bits, so between them the index and check use the whole key.
+pub(crate) struct HashTable {
+    entries: Vec<AtomicU64>,
+}
+
+impl HashTable {
+    const MOVE_BITS: u64 = 0xffff;
+
+    pub(crate) fn new(size: usize) -> HashTable {
+        HashTable { entries: (0..size).map(|_| AtomicU64::new(0)).collect() }
+    }
+
+    // > give the move stored for a position
+    fn get(&self, key: u64) -> Option<PackedMove> {
+        let entry = self.entries[key as usize % self.entries.len()].load(Ordering::Relaxed);
+        // A packed move is never zero, since no move goes from a square to itself.
+        let code = entry & HashTable::MOVE_BITS;
+        (code != 0 && (entry ^ key) & !HashTable::MOVE_BITS == 0).then_some(PackedMove(code as u16))
+    }
+
+    fn store(&self, key: u64, mv: Move) {
+        let entry = key & !HashTable::MOVE_BITS | u64::from(PackedMove::from(mv).0);
+        self.entries[key as usize % self.entries.len()].store(entry, Ordering::Relaxed);
+    }
+}
+
+// > add the state of a search in progress
+struct Searcher<'a> {
+    nodes: u64,
+    max_nodes: Option<u64>,
+    // Asked at every position whether to give up, for running out of time or a "stop" from outside;
+    // not until the first depth is done.
+    stop: &'a dyn Fn() -> bool,
+    // Set when the nodes run out or stop says so; the scores from then on mean nothing.
+    stopped: bool,
+    // The best move found in each position searched, shared with any other threads.
+    table: Arc<HashTable>,
+    // Two quiet moves per ply that caused a cutoff, the most recent first.
+    killers: [[Option<PackedMove>; 2]; MAX_DEPTH as usize],
+    // How much each quiet move, by from and to square, has caused cutoffs; deeper cutoffs count more.
+    history: [[i32; 64]; 64],
+    evaluator: Evaluator,
+    // Below full strength, evaluations are nudged at random.
+    skill: Skill,
+    pruning: Pruning,
+    // Set while searching the reply to a null move, so that a line never has two.
+    in_null: bool,
+    // With an NNUE network, the accumulator for each ply from the root to the current position.
+    accumulators: Vec<Accumulator>,
+}
+
+impl<'a> Searcher<'a> {
+    fn new(evaluator: Evaluator) -> Searcher<'a> {
+        Searcher {
+            nodes: 0,
+            max_nodes: None,
+            stop: &|| false,
+            stopped: false,
+            table: Arc::new(HashTable::new(HASH_MOVES)),
+            killers: [[None; 2]; MAX_DEPTH as usize],
+            history: [[0; 64]; 64],
+            evaluator,
+            skill: Skill::default(),
+            pruning: Pruning::default(),
+            in_null: false,
+            accumulators: Vec::new(),
+        }
+    }
+
+    // > set up the accumulator for the position at the root
+    fn start(&mut self, position: &GameState) {
+        self.accumulators.clear();
+        if let Evaluator::Nnue(network) = &self.evaluator {
+            self.accumulators.push(network.accumulator(position));
+        }
+    }
+
+    // > make a legal move, updating the accumulator
+    fn make(&mut self, position: &mut GameState, mv: Move) -> UndoInfo {
+        let before = position.board;
+        let undo = position.make_move(mv).expect("legal moves can be played");
+        if let Evaluator::Nnue(network) = &self.evaluator {
+            let accumulator = network.update(self.accumulator(), &before, position, mv);
+            self.accumulators.push(accumulator);
+        }
+        undo
+    }
+
+    // > take a move back, and its accumulator with it
+    fn unmake(&mut self, position: &mut GameState, undo: UndoInfo) {
+        position.unmake_move(undo);
+        self.accumulators.pop();
+    }
+
+    // > pass the move to the other side for null-move pruning, giving back what unmake_null needs;
+    // > only the side to move and the en passant square change
+    // Passing is never tried in check, and the side that passes gives none, so neither side is in check after it.
+    fn make_null(&mut self, position: &mut GameState) -> (Option<Square>, u64) {
+        let undo = (position.en_passant, position.hash);
+        position.en_passant = None;
+        position.hash ^= zobrist::turn_key(&zobrist::KEYS, Colour::White);
+        position.current_player = position.current_player.opponent();
+        position.checkers = 0;
+        if let Some(accumulator) = self.accumulators.last().cloned() {
+            self.accumulators.push(accumulator);
+        }
+        undo
+    }
+
+    fn unmake_null(&mut self, position: &mut GameState, (en_passant, hash): (Option<Square>, u64)) {
+        position.current_player = position.current_player.opponent();
+        position.checkers = 0;
+        (position.en_passant, position.hash) = (en_passant, hash);
+        self.accumulators.pop();
+    }
+
+    // > score the position for the side to move with the chosen evaluator
+    fn evaluate(&self, position: &GameState) -> i32 {
+        let score = match &self.evaluator {
+            Evaluator::Classical(weights) => eval::evaluate_with(position, weights),
+            Evaluator::Nnue(network) => network.evaluate(self.accumulator(), position.current_player),
+        };
+        score + self.skill.noise(position.hash)
+    }
+
+    // > below full strength, score every move at the root to the depth reached and let the skill choose one
+    // Each move gets a full window, so the scores can be compared; the depth is small at these levels.
+    fn weaken(&mut self, position: &mut GameState, best: SearchResult) -> SearchResult {
+        (self.stop, self.stopped, self.max_nodes) = (&|| false, false, None);
+        let mut scored = Vec::new();
+        for mv in position.legal_moves() {
+            let undo = self.make(position, mv);
+            scored.push((mv, -self.alpha_beta(position, best.depth - 1, 1, -INFINITY, INFINITY, &mut Vec::new())));
+            self.unmake(position, undo);
+        }
+        match self.skill.choose(&scored) {
+            Some(mv) if Some(mv) != best.best_move => {
+                let score = scored.iter().find(|&&(scored, _)| scored == mv).map_or(best.score, |&(_, score)| score);
+                SearchResult { best_move: Some(mv), score, nodes: self.nodes, pv: vec![mv], ..best }
+            }
+            _ => SearchResult { nodes: self.nodes, ..best },
+        }
+    }
+
+    // > count a position, and check whether the search has to give up
+    fn out_of_time(&mut self) -> bool {
+        self.nodes += 1;
+        if self.max_nodes.is_some_and(|nodes| self.nodes > nodes) || (self.stop)() {
+            self.stopped = true;
+        }
+        self.stopped
+    }
+
+    fn accumulator(&self) -> &Accumulator {
+        self.accumulators.last().expect("start sets up the accumulator at the root")
+    }
+
+    // > score a position to the given depth, stopping early at a ply once a move reaches beta,
+    // > and leave the best line found in pv
+    fn alpha_beta(&mut self, position: &mut GameState, depth: u32, ply: i32, mut alpha: i32, beta: i32, pv: &mut Vec<Move>) -> i32 {
+        pv.clear();
+        if self.out_of_time() {
+            return 0;
+        }
+        if position.result != GameResult::Ongoing {
+            return terminal_score(position, ply);
+        }
+        let in_check = position.is_in_check(position.current_player);
+        let depth = if in_check && self.pruning.check_extensions { depth + 1 } else { depth };
+        if depth == 0 {
+            return self.quiesce(position, ply, alpha, beta);
+        }
+        let key = position.hash;
+        let mut line = Vec::new();
+        // Neither pruning is safe in check, at the root, or where a mate score is at stake.
+        let selective = !in_check && ply > 0 && alpha.abs() < MATE - 1000 && beta.abs() < MATE - 1000;
+        let static_score = if selective && (self.pruning.null_move || self.pruning.futility) { self.evaluate(position) } else { 0 };
+
+        // Without pieces to move, passing can be better than any move (zugzwang), so pawn endings are left alone.
+        if selective && self.pruning.null_move && !self.in_null && depth >= NULL_MOVE_DEPTH && static_score >= beta && has_pieces(position) {
+            let undo = self.make_null(position);
+            self.in_null = true;
+            let score = -self.alpha_beta(position, depth - 1 - NULL_MOVE_REDUCTION, ply + 1, -beta, -beta + 1, &mut line);
+            self.in_null = false;
+            self.unmake_null(position, undo);
+            if self.stopped {
+                return 0;
+            }
+            if score >= beta {
+                return beta;
+            }
+        }
+        let futile = selective && self.pruning.futility && depth == 1 && static_score + FUTILITY_MARGIN <= alpha;
+
+        let mut best: Option<(i32, Move)> = None;
+        let mut moves = MoveList::new();
+        position.generate_moves(&mut moves);
+        self.order_moves(position, &mut moves, ply);
+        for (index, &mv) in moves.iter().enumerate() {
+            let quiet = !is_tactical(position, mv) && !self.killers(ply).contains(&Some(PackedMove::from(mv)));
+            let undo = self.make(position, mv);
+            let quiet = quiet && !position.is_in_check(position.current_player);
+            // The first move is always searched, so there is a score to return.
+            if futile && quiet && best.is_some() {
+                self.unmake(position, undo);
+                continue;
+            }
+            let reduce = self.pruning.late_move_reductions && quiet && !in_check && depth >= LATE_MOVE_DEPTH && index >= LATE_MOVE;
+            let reduced = reduce.then(|| -self.alpha_beta(position, depth - 2, ply + 1, -alpha - 1, -alpha, &mut line));
+            let score = match reduced {
+                Some(score) if score <= alpha => score,
+                _ => -self.alpha_beta(position, depth - 1, ply + 1, -beta, -alpha, &mut line),
+            };
+            self.unmake(position, undo);
+            if self.stopped {
+                return 0;
+            }
+            if best.is_none_or(|(best, _)| score > best) {
+                best = Some((score, mv));
+            }
+            if score > alpha {
+                alpha = score;
+                pv.clear();
+                pv.push(mv);
+                pv.extend_from_slice(&line);
+                if alpha >= beta {
+                    if !is_tactical(position, mv) {
+                        self.add_killer(mv, ply);
+                        self.history[mv.from as usize][mv.to as usize] += (depth * depth) as i32;
+                    }
+                    break;
+                }
+            }
+        }
+        match best {
+            Some((score, mv)) => {
+                self.table.store(key, mv);
+                score
+            }
+            None => terminal_score(position, ply),
+        }
+    }
+
+    // > put the moves in the order most likely to cause a cutoff
+    fn order_moves(&self, position: &GameState, moves: &mut [Move], ply: i32) {
+        let hash_move = self.table.get(position.hash);
+        let killers = self.killers(ply);
+        let mut scores = [0; MoveList::CAPACITY];
+        for (score, &mv) in scores.iter_mut().zip(moves.iter()) {
+            let packed = Some(PackedMove::from(mv));
+            *score = if packed == hash_move {
+                HASH_MOVE
+            } else if is_tactical(position, mv) {
+                CAPTURE + mvv_lva(position, mv)
+            } else if let Some(slot) = killers.iter().position(|&killer| killer == packed) {
+                KILLER - slot as i32
+            } else {
+                self.history[mv.from as usize][mv.to as usize].min(KILLER - 2)
+            };
+        }
+        // An insertion sort needs no allocation, and keeps moves that score the same in the order generated.
+        for index in 1..moves.len() {
+            let mut at = index;
+            while at > 0 && scores[at - 1] < scores[at] {
+                scores.swap(at - 1, at);
+                moves.swap(at - 1, at);
+                at -= 1;
+            }
+        }
+    }
+
+    fn killers(&self, ply: i32) -> [Option<PackedMove>; 2] {
+        self.killers.get(ply as usize).copied().unwrap_or_default()
+    }
+
+    fn add_killer(&mut self, mv: Move, ply: i32) {
+        let mv = PackedMove::from(mv);
+        if let Some(killers) = self.killers.get_mut(ply as usize)
+            && killers[0] != Some(mv)
+        {
+            killers[1] = killers[0];
+            killers[0] = Some(mv);
+        }
+    }
+}
+
+impl Searcher<'_> {
+    // > search captures and promotions until the position is quiet, so no exchange is cut off halfway
+    fn quiesce(&mut self, position: &mut GameState, ply: i32, mut alpha: i32, beta: i32) -> i32 {
+        if self.out_of_time() {
+            return 0;
+        }
+        if position.result != GameResult::Ongoing {
+            return terminal_score(position, ply);
+        }
+        let in_check = position.is_in_check(position.current_player);
+        let mut best = None;
+        if !in_check {
+            let stand_pat = self.evaluate(position);
+            if stand_pat >= beta {
+                return stand_pat;
+            }
+            alpha = alpha.max(stand_pat);
+            best = Some(stand_pat);
+        }
+        let mut moves = MoveList::new();
+        position.generate_moves(&mut moves);
+        if !in_check {
+            moves.retain(|mv| is_tactical(position, mv));
+        }
+        self.order_moves(position, &mut moves, ply);
+        for &mv in moves.iter() {
+            let undo = self.make(position, mv);
+            let score = -self.quiesce(position, ply + 1, -beta, -alpha);
+            self.unmake(position, undo);
+            if self.stopped {
+                return 0;
+            }
+            best = best.max(Some(score));
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+        best.unwrap_or_else(|| terminal_score(position, ply))
+    }
+}
+
+// > check whether the side to move has a piece other than pawns and the king
+fn has_pieces(position: &GameState) -> bool {
+    position.pieces_of(position.current_player).any(|(_, piece)| !matches!(piece, ChessPiece::Pawn(_) | ChessPiece::King(_)))
+}
+
+// Captures and promotions change the material count, so they are searched until none are left.
+fn is_tactical(position: &GameState, mv: Move) -> bool {
+    let (row, col) = mv.to.to_row_col();
+    mv.en_passant || mv.promotion.is_some() || position.board[row][col] != ChessPiece::Blank
+}
+
+// > score a capture by the value of what it takes less the value of what takes it, counting what a pawn promotes to
+fn mvv_lva(position: &GameState, mv: Move) -> i32 {
+    let (from_row, from_col) = mv.from.to_row_col();
+    let (to_row, to_col) = mv.to.to_row_col();
+    let victim = if mv.en_passant { eval::PAWN } else { eval::piece_value(position.board[to_row][to_col]) };
+    let promotion = mv.promotion.map_or(0, eval::piece_value);
+    // Ten times the victim, so taking a queen with anything comes before taking a rook with a pawn.
+    10 * (victim + promotion) - eval::piece_value(position.board[from_row][from_col])
+}
+
+// > score a position by trying every move to the given depth, to check alpha-beta against
+#[cfg(test)]
+fn negamax(position: &mut GameState, depth: u32, ply: i32, nodes: &mut u64) -> i32 {
+    *nodes += 1;
+    if position.result != GameResult::Ongoing {
+        return terminal_score(position, ply);
+    }
+    if depth == 0 {
+        // The quiescence score with a full window is exact, so it is the same leaf score alpha-beta sees.
+        let mut searcher = Searcher::new(Evaluator::default());
+        let score = searcher.quiesce(position, ply, -INFINITY, INFINITY);
+        *nodes += searcher.nodes;
+        return score;
+    }
+    let mut best = None;
+    for mv in position.legal_moves() {
+        let undo = position.make_move(mv).expect("legal moves can be played");
+        let score = -negamax(position, depth - 1, ply + 1, nodes);
+        position.unmake_move(undo);
+        best = best.max(Some(score));
+    }
+    best.unwrap_or_else(|| terminal_score(position, ply))
+}
+
+// The score when the game is over, or would be if there are no legal moves:
+// the side to move has been mated, or it is a draw.
+pub fn terminal_score(position: &GameState, ply: i32) -> i32 {
+    match position.result {
+        GameResult::WhiteWins | GameResult::BlackWins => -(MATE - ply),
+        GameResult::Ongoing if position.is_in_check(position.current_player) => -(MATE - ply),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Square;
+
+    #[test]
+    fn test_finds_mate_in_one() {
+        // Back-rank mate with Ra8.
+        let position = GameState::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let result = search(&position, 1);
+        let mv = result.best_move.unwrap();
+        assert_eq!((mv.from, mv.to), (Square::A1, Square::A8));
+        assert_eq!(result.score, MATE - 1);
+        assert_eq!(result.mate_in(), Some(1));
+    }
+
+    #[test]
+    fn test_wins_material() {
+        // The knight on d5 is hanging.
+        let position = GameState::from_fen("4k3/8/8/3n4/8/8/8/3QK3 w - - 0 1").unwrap();
+        let result = search(&position, 2);
+        let mv = result.best_move.unwrap();
+        assert_eq!((mv.from, mv.to), (Square::D1, Square::D5));
+        assert_eq!(result.mate_in(), None);
+        assert!(result.score > 0);
+    }
+
+    #[test]
+    fn test_sees_the_reply() {
+        // Taking the pawn on d5 loses the queen to the pawn on e6; quiescence sees the recapture even at depth 1.
+        let position = GameState::from_fen("4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1").unwrap();
+        let careful = search(&position, 1).best_move.unwrap();
+        assert_ne!((careful.from, careful.to), (Square::D1, Square::D5));
+        let mut searcher = Searcher::new(Evaluator::default());
+        let mut after = position.clone();
+        after.make_move(Move::new(Square::D1, Square::D5)).unwrap();
+        let mut recaptured = after.clone();
+        recaptured.make_move(Move::new(Square::E6, Square::D5)).unwrap();
+        assert_eq!(-searcher.quiesce(&mut after, 1, -INFINITY, INFINITY), eval::evaluate(&recaptured));
+    }
+
+    #[test]
+    fn test_quiescence_escapes_check() {
+        // Black is in check after Qxf7+ and must take the queen rather than stand pat.
+        let mut position = GameState::from_fen("4k2r/5p2/8/8/8/8/8/R3KQ2 w - - 0 1").unwrap();
+        position.make_move(Move::new(Square::F1, Square::F7)).unwrap();
+        let mut searcher = Searcher::new(Evaluator::default());
+        let score = searcher.quiesce(&mut position, 1, -INFINITY, INFINITY);
+        position.make_move(Move::new(Square::E8, Square::F7)).unwrap();
+        assert_eq!(score, -eval::evaluate(&position));
+    }
+
+    #[test]
+    fn test_game_over() {
+        let mated = GameState::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        let result = search(&mated, 3);
+        assert_eq!(result.best_move, None);
+        assert_eq!(result.mate_in(), Some(0));
+        let stalemate = GameState::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(search(&stalemate, 3).score, 0);
+    }
+
+    #[test]
+    fn test_alpha_beta_matches_negamax() {
+        let positions = [
+            ("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3", 1),
+            ("4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1", 3),
+            ("6k1/5ppp/8/8/8/8/5PPP/R5K1 b - - 0 1", 3),
+            ("8/2k5/3p4/p2P1p2/P2P1P2/8/8/4K2R w K - 0 1", 2),
+        ];
+        for (fen, depth) in positions {
+            let mut position = GameState::from_fen(fen).unwrap();
+            let mut full = 0;
+            let expected = negamax(&mut position, depth, 0, &mut full);
+            // The selective techniques give up exact scores for speed.
+            let limits = Limits { pruning: Pruning::NONE, ..Limits::depth(depth) };
+            let result = iterative_deepening(&position, limits, &Evaluator::default(), 1, Signals::NONE, |_| {});
+            assert_eq!(result.score, expected, "{}", fen);
+            assert!(result.nodes < full, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_pruning_suite() {
+        // The selective search must still find the best move in each tactic, with fewer nodes over
+        // the whole suite, which ends in a quiet opening where there is no one best move.
+        let suite = [
+            ("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1", Some((Square::A1, Square::A8))),
+            ("4k3/8/8/3q4/4P3/8/8/3QK3 w - - 0 1", Some((Square::E4, Square::D5))),
+            ("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4", Some((Square::H5, Square::F7))),
+            ("2r3k1/5ppp/8/8/8/8/1q3PPP/1R1Q2K1 w - - 0 1", Some((Square::B1, Square::B2))),
+            ("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4", None),
+        ];
+        let (mut selective, mut full) = (0, 0);
+        for (fen, best) in suite {
+            let position = GameState::from_fen(fen).unwrap();
+            for (pruning, nodes) in [(Pruning::default(), &mut selective), (Pruning::NONE, &mut full)] {
+                let result = iterative_deepening(&position, Limits { pruning, ..Limits::depth(3) }, &Evaluator::default(), 1, Signals::NONE, |_| {});
+                if best.is_some() {
+                    assert_eq!(result.best_move.map(|mv| (mv.from, mv.to)), best, "{} {:?}", fen, pruning);
+                }
+                *nodes += result.nodes;
+            }
+        }
+        assert!(selective < full, "{} {}", selective, full);
+    }
+
+    #[test]
+    fn test_null_move() {
+        let mut position = GameState::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1").unwrap();
+        let before = position.clone();
+        let mut searcher = Searcher::new(Evaluator::default());
+        let undo = searcher.make_null(&mut position);
+        assert_eq!((position.current_player, position.en_passant), (Colour::White, None));
+        assert_eq!(position.hash, zobrist::hash(&zobrist::KEYS, &position));
+        searcher.unmake_null(&mut position, undo);
+        assert_eq!(position, before);
+        assert!(!has_pieces(&position));
+    }
+
+    #[test]
+    fn test_move_ordering() {
+        let position = GameState::from_fen("4k3/8/8/3q4/4P3/8/8/3QK3 w - - 0 1").unwrap();
+        let mut searcher = Searcher::new(Evaluator::default());
+        let ordered = |searcher: &Searcher, ply| {
+            let mut moves = position.legal_moves();
+            searcher.order_moves(&position, &mut moves, ply);
+            moves.into_iter().map(|mv| (mv.from, mv.to)).collect::<Vec<_>>()
+        };
+        // The pawn takes the queen before the queen does.
+        assert_eq!(ordered(&searcher, 0)[..2], [(Square::E4, Square::D5), (Square::D1, Square::D5)]);
+
+        let quiet = |from, to| position.legal_moves().into_iter().find(|mv| (mv.from, mv.to) == (from, to)).unwrap();
+        searcher.add_killer(quiet(Square::E1, Square::F2), 0);
+        searcher.history[Square::D1 as usize][Square::A4 as usize] = 1;
+        searcher.table.store(position.hash, quiet(Square::D1, Square::H5));
+        assert_eq!(
+            ordered(&searcher, 0)[..5],
+            [(Square::D1, Square::H5), (Square::E4, Square::D5), (Square::D1, Square::D5), (Square::E1, Square::F2), (Square::D1, Square::A4)]
+        );
+        // Killers belong to their ply.
+        assert_eq!(ordered(&searcher, 1)[3], (Square::D1, Square::A4));
+    }
+
+    #[test]
+    fn test_nnue_search() {
+        let network = std::sync::Arc::new(crate::nnue::Network::from_bytes(crate::nnue::tests::network_bytes()).unwrap());
+        let mut position = GameState::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let mut searcher = Searcher::new(Evaluator::Nnue(network.clone()));
+        searcher.start(&position);
+        let mut pv = Vec::new();
+        assert_eq!(searcher.alpha_beta(&mut position, 2, 0, -INFINITY, INFINITY, &mut pv), MATE - 1);
+        assert_eq!((pv[0].from, pv[0].to), (Square::A1, Square::A8));
+        // Every accumulator pushed on the way down was popped on the way back.
+        assert_eq!(searcher.accumulators, [network.accumulator(&position)]);
+    }
+
+    #[test]
+    fn test_reports_each_depth_with_a_pv() {
+        let position = GameState::from_fen("4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1").unwrap();
+        let mut depths = Vec::new();
+        let result = iterative_deepening(&position, Limits::depth(3), &Evaluator::default(), 1, Signals::NONE, |iteration| {
+            depths.push(iteration.depth);
+            assert_eq!(iteration.pv.len(), iteration.depth as usize);
+            assert_eq!(iteration.pv.first(), iteration.best_move.as_ref());
+        });
+        assert_eq!(depths, [1, 2, 3]);
+        // The line can be played out from the position.
+        let mut after = position.clone();
+        for mv in &result.pv {
+            after.make_move(*mv).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_time_limit() {
+        let limits = Limits { soft_time: Some(Duration::from_millis(50)), hard_time: Some(Duration::from_millis(50)), ..Limits::default() };
+        let result = iterative_deepening(&GameState::new(), limits, &Evaluator::default(), 1, Signals::NONE, |_| {});
+        assert!(result.best_move.is_some());
+        assert!(result.depth >= 1 && result.depth < MAX_DEPTH);
+        assert!(result.time < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_node_limit_and_stop() {
+        let limits = Limits { nodes: Some(2000), ..Limits::default() };
+        let result = iterative_deepening(&GameState::new(), limits, &Evaluator::default(), 1, Signals::NONE, |_| {});
+        assert!(result.best_move.is_some());
+        // The search gives up one position past the limit, once the first depth is done.
+        assert!(result.nodes <= 2001);
+        // Told to stop straight away, the first depth still finishes.
+        let infinite = Limits { infinite: true, ..Limits::default() };
+        let result = iterative_deepening(&GameState::new(), infinite, &Evaluator::default(), 1, Signals { stop: &|| true, ..Signals::NONE }, |_| {});
+        assert_eq!((result.depth, result.best_move.is_some()), (1, true));
+    }
+
+    #[test]
+    fn test_single_thread_is_deterministic() {
+        let position = GameState::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4").unwrap();
+        let run = || {
+            let result = iterative_deepening(&position, Limits::depth(2), &Evaluator::default(), 1, Signals::NONE, |_| {});
+            (result.best_move, result.score, result.nodes, result.pv)
+        };
+        assert_eq!(run(), run());
+    }
+
+    #[test]
+    fn test_many_threads() {
+        for fen in ["r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4", "4k3/8/8/3q4/8/8/8/3QK3 w - - 0 1", "8/8/8/8/8/5k2/4p3/4K3 w - - 0 1"] {
+            let position = GameState::from_fen(fen).unwrap();
+            let result = iterative_deepening(&position, Limits::depth(2), &Evaluator::default(), 8, Signals::NONE, |_| {});
+            assert!(position.legal_moves().contains(&result.best_move.unwrap()), "{}", fen);
+        }
+        let position = GameState::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let result = iterative_deepening(&position, Limits::depth(2), &Evaluator::default(), 8, Signals::NONE, |_| {});
+        assert_eq!(result.mate_in(), Some(1));
+    }
+
+    #[test]
+    fn test_hash_table() {
+        let position = GameState::new();
+        let mv = position.legal_moves()[0];
+        let table = HashTable::new(16);
+        assert_eq!(table.get(position.hash), None);
+        table.store(position.hash, mv);
+        assert_eq!(table.get(position.hash).map(Move::from), Some(mv));
+        // Another position in the same entry does not see the move.
+        assert_eq!(table.get(position.hash ^ 16 << 16), None);
+        // Each entry is a single word.
+        assert_eq!(std::mem::size_of_val(&table.entries[..]), 16 * 8);
+    }
+
+    #[test]
+    fn test_bench_is_reproducible() {
+        for fen in BENCH_POSITIONS {
+            assert!(GameState::from_fen(fen).is_ok_and(|position| position.result == GameResult::Ongoing), "{}", fen);
+        }
+        // The endgames are quick enough to search without optimisations.
+        let endgames = &BENCH_POSITIONS[8..];
+        let mut searched = Vec::new();
+        let nodes = bench(endgames, 3, &Evaluator::default(), 1, |fen, result| searched.push((fen.to_string(), result.best_move)));
+        assert_eq!(searched.len(), endgames.len());
+        assert!(searched.iter().all(|(_, best_move)| best_move.is_some()));
+        assert_eq!(bench(endgames, 3, &Evaluator::default(), 1, |_, _| {}), nodes);
+    }
+
+    #[test]
+    fn test_ponder() {
+        let limits = Limits { ponder: true, soft_time: Some(Duration::ZERO), hard_time: Some(Duration::ZERO), ..Limits::default() };
+        // Before the ponderhit the clock does not run, so only the stop ends the search.
+        let calls = std::cell::Cell::new(0);
+        let stop = || {
+            calls.set(calls.get() + 1);
+            calls.get() > 2000
+        };
+        let result = iterative_deepening(&GameState::new(), limits, &Evaluator::default(), 1, Signals { stop: &stop, ..Signals::NONE }, |_| {});
+        assert!(result.depth >= 2 && calls.get() > 2000);
+        // After it, the clock has already run out.
+        let signals = Signals { ponderhit: &|| true, ..Signals::NONE };
+        assert_eq!(iterative_deepening(&GameState::new(), limits, &Evaluator::default(), 1, signals, |_| {}).depth, 1);
+    }
+
+    #[test]
+    fn test_skill() {
+        let weak = |level, seed| Limits { skill: Skill { level, seed }, ..Limits::default() };
+        // Even at level 0 a hanging queen is taken; only moves close to the best are played.
+        let position = GameState::from_fen("4k3/8/8/3q4/8/8/8/3QK3 w - - 0 1").unwrap();
+        for seed in 0..5 {
+            let result = iterative_deepening(&position, weak(0, seed), &Evaluator::default(), 1, Signals::NONE, |_| {});
+            assert_eq!(result.depth, 1);
+            assert_eq!(result.best_move.map(|mv| (mv.from, mv.to)), Some((Square::D1, Square::D5)));
+        }
+        // From the start, where many moves are about as good, the moves vary.
+        let moves: std::collections::HashSet<_> = (0..8)
+            .filter_map(|seed| iterative_deepening(&GameState::new(), weak(4, seed), &Evaluator::default(), 1, Signals::NONE, |_| {}).best_move)
+            .collect();
+        assert!(moves.len() > 1);
+    }
+
+    #[cfg(feature = "uci")]
+    #[test]
+    fn test_limits_from_go() {
+        assert_eq!(Limits::from_go(&["depth", "5"], Colour::White), Ok(Limits::depth(5)));
+        let movetime = Limits::from_go(&["movetime", "250"], Colour::Black).unwrap();
+        assert_eq!((movetime.soft_time, movetime.hard_time), (Some(Duration::from_millis(250)), Some(Duration::from_millis(250))));
+        let clock = Limits::from_go(&["wtime", "60000", "btime", "3000", "winc", "0", "binc", "1000"], Colour::Black).unwrap();
+        assert_eq!(clock.soft_time, Some(Duration::from_millis(3000 / 30 + 500)));
+        assert_eq!(clock.hard_time, Some(Duration::from_millis((3000 - 50) / 2)));
+        let moves_to_go = Limits::from_go(&["wtime", "60000", "movestogo", "10"], Colour::White).unwrap();
+        assert_eq!(moves_to_go.soft_time, Some(Duration::from_secs(6)));
+        assert_eq!(moves_to_go.hard_time, Some(Duration::from_secs(24)));
+        let nodes = Limits::from_go(&["nodes", "5000", "wtime", "1000"], Colour::White).unwrap();
+        assert_eq!(nodes.nodes, Some(5000));
+        let infinite = Limits::from_go(&["infinite", "wtime", "1000"], Colour::White).unwrap();
+        assert_eq!(infinite, Limits { infinite: true, ..Limits::default() });
+        let ponder = Limits::from_go(&["ponder", "wtime", "1000"], Colour::White).unwrap();
+        assert_eq!(ponder, Limits { ponder: true, ..Limits::clock(Duration::from_secs(1), Duration::ZERO, None) });
+        assert_eq!(Limits::from_go(&[], Colour::White), Ok(Limits::default()));
+        assert!(Limits::from_go(&["depth", "deep"], Colour::White).is_err());
+    }
+
+    #[test]
+    fn test_clock_never_flags() {
+        // With little time left and a big increment, the move still takes less than half the clock.
+        let limits = Limits::clock(Duration::from_millis(1000), Duration::from_secs(5), None);
+        assert_eq!(limits.soft_time, Some(Duration::from_millis(475)));
+        assert_eq!(limits.hard_time, Some(Duration::from_millis(475)));
+        let limits = Limits::clock(Duration::from_millis(20), Duration::ZERO, None);
+        assert_eq!(limits.hard_time, Some(Duration::ZERO));
+    }
+}
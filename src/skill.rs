@@ -0,0 +1,124 @@
+//! # Skill
+//!
+//! Lets the engine play below its full strength, so that beginners can win games
+//! against it. A skill level from 0 to 20, or an Elo rating from 800 to 2400, weakens
+//! the search in three ways:
+//!
+//! - it looks fewer plies ahead, from one ply at level 0 to five at level 19;
+//! - it sees positions through a haze: every evaluation is nudged up or down by a
+//!   random amount, up to two pawns at level 0;
+//! - it does not always play its best move, but picks at random among the moves that
+//!   score close to it, up to three pawns worse at level 0.
+//!
+//! Level 20, the default, is full strength. Over UCI the options are `Skill Level`, or
+//! `UCI_LimitStrength` with `UCI_Elo`; at the prompt they are `--skill` and `--elo`:
+//!
+//! ```text
+//! overload-vibe-coding --engine internal --skill 3
+//! ```
+
+use crate::{zobrist, Move};
+
+pub const MAX_LEVEL: u32 = 20;
+pub const MIN_ELO: u32 = 800;
+pub const MAX_ELO: u32 = 2400;
+// The most an evaluation is nudged, in centipawns, for each level below the top.
+const NOISE_PER_LEVEL: i32 = 10;
+// How much worse than the best move a chosen move may score, in centipawns, for each level below the top.
+const MARGIN_PER_LEVEL: i32 = 15;
+
+// > add a playing strength: a skill level, and a seed for the random choices it makes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Skill {
+    pub level: u32,
+    pub seed: u64,
+}
+
+impl Default for Skill {
+    fn default() -> Skill {
+        Skill { level: MAX_LEVEL, seed: 0 }
+    }
+}
+
+impl Skill {
+    pub fn level(level: u32) -> Skill {
+        Skill { level: level.min(MAX_LEVEL), ..Skill::default() }
+    }
+
+    // > map an Elo rating onto the skill levels, a level for every 80 points from 800
+    pub fn from_elo(elo: u32) -> Skill {
+        Skill::level((elo.clamp(MIN_ELO, MAX_ELO) - MIN_ELO) * MAX_LEVEL / (MAX_ELO - MIN_ELO))
+    }
+
+    pub(crate) fn is_limited(&self) -> bool {
+        self.level < MAX_LEVEL
+    }
+
+    // > give the deepest the search may go, if the level limits it
+    pub(crate) fn max_depth(&self) -> Option<u32> {
+        self.is_limited().then_some(1 + self.level / 4)
+    }
+
+    // > give the amount to nudge the evaluation of a position by, the same each time the position is seen
+    pub(crate) fn noise(&self, key: u64) -> i32 {
+        let most = (MAX_LEVEL - self.level) as i32 * NOISE_PER_LEVEL;
+        if most == 0 {
+            return 0;
+        }
+        (mix(key ^ self.seed) % (2 * most as u64 + 1)) as i32 - most
+    }
+
+    // > choose among scored moves: the best at full strength, else any close enough to it
+    pub(crate) fn choose(&self, scored: &[(Move, i32)]) -> Option<Move> {
+        let best = scored.iter().map(|&(_, score)| score).max()?;
+        let margin = (MAX_LEVEL - self.level) as i32 * MARGIN_PER_LEVEL;
+        let close: Vec<Move> = scored.iter().filter(|&&(_, score)| score >= best - margin).map(|&(mv, _)| mv).collect();
+        Some(close[(mix(self.seed) % close.len() as u64) as usize])
+    }
+}
+
+// One step of SplitMix64 from the key or seed, to spread its bits.
+fn mix(mut z: u64) -> u64 {
+    zobrist::split_mix64(&mut z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GameState;
+
+    #[test]
+    fn test_levels_and_elo() {
+        assert!(!Skill::default().is_limited());
+        assert_eq!(Skill::default().max_depth(), None);
+        assert_eq!(Skill::level(0).max_depth(), Some(1));
+        assert_eq!(Skill::level(19).max_depth(), Some(5));
+        assert_eq!(Skill::level(99), Skill::default());
+        assert_eq!(Skill::from_elo(800), Skill::level(0));
+        assert_eq!(Skill::from_elo(1600), Skill::level(10));
+        assert_eq!(Skill::from_elo(3000), Skill::default());
+    }
+
+    #[test]
+    fn test_noise() {
+        assert_eq!(Skill::default().noise(12345), 0);
+        let skill = Skill { level: 0, seed: 7 };
+        let noise: Vec<i32> = (0..1000).map(|key| skill.noise(key)).collect();
+        assert!(noise.iter().all(|noise| noise.abs() <= 200));
+        assert!(noise.iter().any(|&noise| noise > 100) && noise.iter().any(|&noise| noise < -100));
+        // The same position is always seen the same way.
+        assert_eq!(skill.noise(42), skill.noise(42));
+    }
+
+    #[test]
+    fn test_choose() {
+        let moves = GameState::new().legal_moves();
+        let scored = [(moves[0], -100), (moves[1], 100), (moves[2], 90), (moves[3], -300)];
+        assert_eq!(Skill::default().choose(&scored), Some(moves[1]));
+        // A weak player picks moves close to the best but never the blunder.
+        let chosen: Vec<Move> = (0..50).filter_map(|seed| Skill { level: 10, seed }.choose(&scored)).collect();
+        assert!(chosen.contains(&moves[1]) && chosen.contains(&moves[2]));
+        assert!(!chosen.contains(&moves[0]) && !chosen.contains(&moves[3]));
+        assert_eq!(Skill::level(0).choose(&[]), None);
+    }
+}
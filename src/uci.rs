@@ -0,0 +1,357 @@
+//! # UCI
+//!
+//! The Universal Chess Interface, so that any chess GUI can use the program as an
+//! engine. Started with `--uci`, it reads commands on stdin and answers on stdout:
+//!
+//! ```text
+//! > uci
+//! < id name overload-vibe-coding 0.1.0
+//! < uciok
+//! > position startpos moves e2e4 e7e5
+//! > go movetime 1000
+//! < bestmove g1f3
+//! ```
+//!
+//! `go` searches with iterative deepening, sending an `info` line with the depth,
+//! score, nodes, time and principal variation as each depth finishes. It accepts
+//! `depth`, `nodes`, `movetime`, `infinite` and the clock times `wtime`, `btime`,
+//! `winc`, `binc` and `movestogo`. Commands are read on a thread of their own, so a
+//! `stop` ends the search while it runs; after `go infinite` the best move is only
+//! sent once `stop` arrives.
+//!
+//! `bestmove` names the reply the engine expects with `ponder`. The GUI can then send
+//! `go ponder` with that reply played, to think on the opponent's time: the search runs
+//! without a clock until `ponderhit` says the opponent played the expected move, and
+//! then goes on with the time from the `go` command, or until `stop` says they did not.
+//!
+//! The `EvalFile` option loads an NNUE network to evaluate with; setting it to
+//! `<empty>` goes back to the handcrafted evaluation. `Threads` sets how many threads
+//! search together. `Skill Level`, or `UCI_LimitStrength` with `UCI_Elo`, makes the
+//! engine play below its full strength.
+
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, mpsc};
+use std::time::Duration;
+
+use crate::eval::Evaluator;
+use crate::search::{self, Limits, SearchResult, Signals};
+use crate::skill::{self, Skill};
+use crate::nnue::Network;
+use crate::{GameState, Move, engine};
+
+// > add the state of a UCI session: the position to search and the options set by the GUI
+pub struct UciSession {
+    position: GameState,
+    options: Vec<(String, String)>,
+    evaluator: Evaluator,
+    // Set once the input has run out, which ends an infinite search that nobody is left to stop.
+    input_ended: Arc<AtomicBool>,
+}
+
+impl UciSession {
+    pub fn new() -> UciSession {
+        UciSession { position: GameState::new(), options: Vec::new(), evaluator: Evaluator::default(), input_ended: Arc::default() }
+    }
+
+}
+
+impl Default for UciSession {
+    fn default() -> UciSession {
+        UciSession::new()
+    }
+}
+
+impl UciSession {
+    // > read UCI commands until "quit" or the end of the input, reading on a second thread
+    // > so that "stop" and "ponderhit" can be seen while a search is running
+    pub fn run(&mut self, input: impl BufRead + Send, output: &mut impl Write) -> io::Result<()> {
+        // How many "stop" and "quit" commands, and how many "ponderhit" commands, have been read.
+        let stops = &AtomicUsize::new(0);
+        let ponderhits = &AtomicUsize::new(0);
+        let input_ended = self.input_ended.clone();
+        let (sender, receiver) = mpsc::channel();
+        std::thread::scope(|scope| {
+            scope.spawn(move || {
+                for line in input.lines() {
+                    let command = line.as_ref().map_or("", |line| line.trim());
+                    let quit = command == "quit";
+                    if quit || command == "stop" {
+                        stops.fetch_add(1, Ordering::SeqCst);
+                    }
+                    if command == "ponderhit" {
+                        ponderhits.fetch_add(1, Ordering::SeqCst);
+                    }
+                    if sender.send(line).is_err() || quit {
+                        return;
+                    }
+                }
+                input_ended.store(true, Ordering::SeqCst);
+            });
+            // A search stops once more stops have been read than handled, so a "stop" that
+            // comes before a "go" cannot end its search; the same goes for a "ponderhit".
+            let (mut handled_stops, mut handled_ponderhits) = (0, 0);
+            for line in receiver {
+                let line = line?;
+                match line.trim() {
+                    "stop" | "quit" => handled_stops += 1,
+                    "ponderhit" => handled_ponderhits += 1,
+                    _ => {}
+                }
+                let signals = Signals {
+                    stop: &|| stops.load(Ordering::SeqCst) > handled_stops,
+                    ponderhit: &|| ponderhits.load(Ordering::SeqCst) > handled_ponderhits,
+                };
+                if !self.handle(&line, signals, output)? {
+                    break;
+                }
+                output.flush()?;
+            }
+            Ok(())
+        })
+    }
+
+    // > handle one UCI command, returning false once the GUI has asked to quit;
+    // > a search runs until its limits are reached or the signals say so
+    fn handle(&mut self, line: &str, signals: Signals, output: &mut impl Write) -> io::Result<bool> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.first().copied() {
+            Some("uci") => {
+                writeln!(output, "id name {} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))?;
+                writeln!(output, "id author the {} authors", env!("CARGO_PKG_NAME"))?;
+                writeln!(output, "option name EvalFile type string default <empty>")?;
+                writeln!(output, "option name Threads type spin default 1 min 1 max {}", search::MAX_THREADS)?;
+                writeln!(output, "option name Ponder type check default false")?;
+                writeln!(output, "option name Skill Level type spin default {0} min 0 max {0}", skill::MAX_LEVEL)?;
+                writeln!(output, "option name UCI_LimitStrength type check default false")?;
+                writeln!(output, "option name UCI_Elo type spin default {1} min {0} max {1}", skill::MIN_ELO, skill::MAX_ELO)?;
+                writeln!(output, "uciok")?;
+            }
+            Some("isready") => writeln!(output, "readyok")?,
+            Some("ucinewgame") => self.position = GameState::new(),
+            Some("position") => {
+                if let Err(message) = self.set_position(&words[1..]) {
+                    writeln!(output, "info string {}", message)?;
+                }
+            }
+            Some("setoption") => {
+                if let Err(message) = self.set_option(&words[1..]) {
+                    writeln!(output, "info string {}", message)?;
+                }
+            }
+            Some("go") => {
+                let mut limits = match Limits::from_go(&words[1..], self.position.current_player) {
+                    Ok(limits) => limits,
+                    Err(message) => {
+                        writeln!(output, "info string {}", message)?;
+                        Limits::default()
+                    }
+                };
+                limits.skill = self.skill();
+                // Nobody is left to stop a search that only a stop or a ponderhit can end once the input has run out.
+                let stop = || (signals.stop)() || ((limits.infinite || limits.ponder) && self.input_ended.load(Ordering::SeqCst));
+                let signals = Signals { stop: &stop, ..signals };
+                let mut written = Ok(());
+                let threads = self.option("Threads").and_then(|threads| threads.parse().ok()).unwrap_or(1);
+                let result = search::iterative_deepening(&self.position, limits, &self.evaluator, threads, signals, |iteration| {
+                    if written.is_ok() {
+                        written = writeln!(output, "{}", info_line(iteration));
+                    }
+                });
+                written?;
+                // An infinite search that found a mate, or ran out of depths, still waits to be stopped,
+                // and a pondering one for the ponderhit.
+                while (limits.infinite || (limits.ponder && !(signals.ponderhit)())) && !stop() {
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+                match (result.best_move, result.pv.get(1)) {
+                    (Some(mv), Some(reply)) => writeln!(output, "bestmove {} ponder {}", mv.to_uci(), reply.to_uci())?,
+                    (best_move, _) => writeln!(output, "bestmove {}", best_move.map_or("0000".to_string(), Move::to_uci))?,
+                }
+            }
+            Some("quit") => return Ok(false),
+            // A "stop" or "ponderhit" with no search running has nothing to do.
+            // Unknown commands are ignored, as the protocol asks.
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    // > handle "position [startpos | fen <fen>] [moves <move>...]"
+    fn set_position(&mut self, words: &[&str]) -> Result<(), String> {
+        let moves_at = words.iter().position(|&word| word == "moves").unwrap_or(words.len());
+        let mut position = match words.first().copied() {
+            Some("startpos") => GameState::new(),
+            Some("fen") => GameState::from_fen(&words[1..moves_at].join(" ")).map_err(|err| err.to_string())?,
+            _ => return Err(format!("Unknown position: '{}'", words.join(" "))),
+        };
+        for &uci in words.iter().skip(moves_at + 1) {
+            let mv = Move::from_uci(uci).map_err(|err| err.to_string())?;
+            position.make_move(mv).map_err(|err| format!("{} cannot be played: {}", uci, err))?;
+        }
+        self.position = position;
+        Ok(())
+    }
+
+    // > handle "setoption name <name> [value <value>]", keeping the value for later,
+    // > and load the network when the option is EvalFile or check the number when it is Threads
+    fn set_option(&mut self, words: &[&str]) -> Result<(), String> {
+        let value_at = words.iter().position(|&word| word == "value").unwrap_or(words.len());
+        let name = words.get(1..value_at).unwrap_or_default().join(" ");
+        let value = words.get(value_at + 1..).unwrap_or_default().join(" ");
+        if name.eq_ignore_ascii_case("EvalFile") {
+            self.evaluator = match value.as_str() {
+                "" | "<empty>" => Evaluator::default(),
+                path => Evaluator::Nnue(Arc::new(Network::load(path).map_err(|err| err.to_string())?)),
+            };
+        }
+        if name.eq_ignore_ascii_case("Threads") && !value.parse().is_ok_and(|threads| (1..=search::MAX_THREADS).contains(&threads)) {
+            return Err(format!("Threads must be from 1 to {}", search::MAX_THREADS));
+        }
+        if name.eq_ignore_ascii_case("Skill Level") && !value.parse::<u32>().is_ok_and(|level| level <= skill::MAX_LEVEL) {
+            return Err(format!("Skill Level must be from 0 to {}", skill::MAX_LEVEL));
+        }
+        if name.eq_ignore_ascii_case("UCI_Elo") && !value.parse().is_ok_and(|elo| (skill::MIN_ELO..=skill::MAX_ELO).contains(&elo)) {
+            return Err(format!("UCI_Elo must be from {} to {}", skill::MIN_ELO, skill::MAX_ELO));
+        }
+        self.options.retain(|(existing, _)| !existing.eq_ignore_ascii_case(&name));
+        self.options.push((name, value));
+        Ok(())
+    }
+
+    pub(crate) fn option(&self, name: &str) -> Option<&str> {
+        self.options.iter().find(|(existing, _)| existing.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str())
+    }
+
+    // > give the strength to play at: the Elo rating when UCI_LimitStrength is on, else the skill level
+    fn skill(&self) -> Skill {
+        let number = |name| self.option(name).and_then(|value| value.parse().ok());
+        let skill = match self.option("UCI_LimitStrength").is_some_and(|value| value.eq_ignore_ascii_case("true")) {
+            true => number("UCI_Elo").map_or(Skill::default(), Skill::from_elo),
+            false => number("Skill Level").map_or(Skill::default(), Skill::level),
+        };
+        Skill { seed: engine::seed(), ..skill }
+    }
+}
+
+// > describe a finished depth as an "info" line
+fn info_line(result: &SearchResult) -> String {
+    let score = match result.mate_in() {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", result.score),
+    };
+    let mut line = format!("info depth {} score {} nodes {} time {}", result.depth, score, result.nodes, result.time.as_millis());
+    if !result.pv.is_empty() {
+        line.push_str(" pv");
+        for mv in &result.pv {
+            line.push(' ');
+            line.push_str(&mv.to_uci());
+        }
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(commands: &str) -> String {
+        let mut output = Vec::new();
+        UciSession::new().run(commands.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_handshake() {
+        let output = run("uci\nisready\nquit\nisready\n");
+        assert!(output.starts_with("id name overload-vibe-coding"));
+        assert!(output.ends_with("uciok\nreadyok\n"));
+    }
+
+    #[test]
+    fn test_position_and_go() {
+        let output = run("position fen 4k3/8/8/3n4/8/8/8/3QK3 w - - 0 1\ngo depth 1\n");
+        // Up a knight, give or take where the pieces stand.
+        assert!(output.starts_with("info depth 1 score cp 8"), "{}", output);
+        assert!(output.ends_with(" pv d1d5\nbestmove d1d5\n"), "{}", output);
+        let output = run("position fen 7k/8/6K1/8/8/8/8/1Q6 w - - 0 1\ngo depth 3\n");
+        assert!(output.starts_with("info depth 1 score mate 1 "), "{}", output);
+        assert!(output.ends_with("bestmove b1b8\n"), "{}", output);
+        let output = run("position fen 7k/5Q2/6K1/8/8/8/8/8 b - - 0 1\ngo wtime 1000 btime 1000\n");
+        assert!(output.starts_with("info depth 1 score cp 0 nodes 1 time "), "{}", output);
+        assert!(output.ends_with("\nbestmove 0000\n"), "{}", output);
+        let output = run("go depth many\n");
+        assert!(output.starts_with("info string 'depth' needs a number\n"), "{}", output);
+    }
+
+    #[test]
+    fn test_infinite_and_stop() {
+        let output = run("go infinite\nstop\nisready\n");
+        assert!(output.contains("\nbestmove ") && output.ends_with("readyok\n"), "{}", output);
+        // The mate is found at once, but the move waits for the end of the input.
+        let output = run("position fen 7k/8/6K1/8/8/8/8/1Q6 w - - 0 1\ngo infinite\n");
+        assert!(output.ends_with("bestmove b1b8\n"), "{}", output);
+        // Pondering goes on until the ponderhit, or until the stop when the reply was not the expected one.
+        let output = run("position startpos moves e2e4\ngo ponder wtime 1000 btime 1000\nponderhit\n");
+        assert!(output.contains("\nbestmove "), "{}", output);
+        let output = run("go ponder wtime 1000 btime 1000\nstop\n");
+        assert!(output.contains("\nbestmove "), "{}", output);
+        // The best move comes with the reply the engine expects.
+        let output = run("position fen 4k3/8/8/3q4/8/8/8/3QK3 w - - 0 1\ngo depth 2\n");
+        assert!(output.contains("\nbestmove d1d5 ponder "), "{}", output);
+        // A "stop" with no search running does not cut short the next one.
+        let output = run("stop\ngo depth 2\n");
+        assert!(output.contains("info depth 2 "), "{}", output);
+    }
+
+    #[test]
+    fn test_bad_position() {
+        let mut session = UciSession::new();
+        let mut output = Vec::new();
+        session.handle("position startpos moves e2e4", Signals::NONE, &mut output).unwrap();
+        session.handle("position startpos moves d2d4 e2e5", Signals::NONE, &mut output).unwrap();
+        session.handle("position fen 8/8 w", Signals::NONE, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("info string e2e5 cannot be played"), "{}", output);
+        assert_eq!(output.lines().count(), 2);
+        // The position is left as it was.
+        assert_eq!(session.position.to_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+    }
+
+    #[test]
+    fn test_setoption() {
+        let mut session = UciSession::new();
+        let mut output = Vec::new();
+        session.handle("setoption name Skill Level value 3", Signals::NONE, &mut output).unwrap();
+        session.handle("setoption name skill level value 5", Signals::NONE, &mut output).unwrap();
+        session.handle("setoption name Clear Hash", Signals::NONE, &mut output).unwrap();
+        assert_eq!(session.option("Skill Level"), Some("5"));
+        assert_eq!(session.option("Clear Hash"), Some(""));
+        assert!(output.is_empty());
+
+        session.handle("setoption name Threads value 4", Signals::NONE, &mut output).unwrap();
+        assert_eq!(session.option("Threads"), Some("4"));
+        assert!(output.is_empty());
+        session.handle("go depth 2", Signals::NONE, &mut output).unwrap();
+        assert!(String::from_utf8_lossy(&output).contains("bestmove "));
+        output.clear();
+        session.handle("setoption name Threads value 0", Signals::NONE, &mut output).unwrap();
+        assert_eq!(session.option("Threads"), Some("4"));
+        assert_eq!(String::from_utf8_lossy(&output), "info string Threads must be from 1 to 64\n");
+        output.clear();
+
+        session.handle("setoption name Skill Level value 3", Signals::NONE, &mut output).unwrap();
+        assert_eq!(session.skill().level, 3);
+        session.handle("setoption name UCI_Elo value 1200", Signals::NONE, &mut output).unwrap();
+        session.handle("setoption name UCI_LimitStrength value true", Signals::NONE, &mut output).unwrap();
+        assert_eq!(session.skill().level, 5);
+        session.handle("setoption name UCI_Elo value 100", Signals::NONE, &mut output).unwrap();
+        assert_eq!(String::from_utf8_lossy(&output), "info string UCI_Elo must be from 800 to 2400\n");
+        output.clear();
+
+        session.handle("setoption name EvalFile value missing.nnue", Signals::NONE, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("info string The network cannot be read"), "{}", output);
+        assert!(matches!(session.evaluator, Evaluator::Classical(_)));
+    }
+}
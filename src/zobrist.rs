@@ -0,0 +1,132 @@
+//! # Zobrist Hashing
+//!
+//! A 64-bit key for a position, built by XORing together one random number for each
+//! piece on each square, one for each castling right still held, one for the en
+//! passant file and one for White to move. Since XOR undoes itself, a move changes
+//! the key by XORing out what left a square and XORing in what arrived, so the key is
+//! kept up to date inside `make_move` instead of being rebuilt from the whole board.
+//!
+//! The numbers are laid out as in Polyglot (see [`crate::book`]): 768 for pieces on
+//! squares, then 4 for castling, 8 for en passant files and 1 for the side to move.
+//! They are generated at compile time with SplitMix64 from a fixed seed, so keys are
+//! the same on every run and every machine.
+
+use crate::{CastlingRights, ChessPiece, Colour, Position};
+
+pub(crate) const RANDOM_COUNT: usize = 781;
+pub(crate) const CASTLE_OFFSET: usize = 768;
+pub(crate) const EN_PASSANT_OFFSET: usize = 772;
+pub(crate) const TURN_OFFSET: usize = 780;
+
+pub(crate) static KEYS: [u64; RANDOM_COUNT] = generate(0x5eed_c0de_cafe_f00d);
+
+const fn generate(seed: u64) -> [u64; RANDOM_COUNT] {
+    let mut keys = [0; RANDOM_COUNT];
+    let mut state = seed;
+    let mut i = 0;
+    while i < RANDOM_COUNT {
+        keys[i] = split_mix64(&mut state);
+        i += 1;
+    }
+    keys
+}
+
+// > step SplitMix64 on, giving its next number; the keys, the skill levels and the tests all draw from it
+pub(crate) const fn split_mix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+// > give the piece number: black pawn 0, white pawn 1, black knight 2 and so on up to white king 11
+pub(crate) fn piece_index(piece: ChessPiece) -> Option<usize> {
+    let (kind, colour) = match piece {
+        ChessPiece::Pawn(colour) => (0, colour),
+        ChessPiece::Knight(colour) => (1, colour),
+        ChessPiece::Bishop(colour) => (2, colour),
+        ChessPiece::Rook(colour) => (3, colour),
+        ChessPiece::Queen(colour) => (4, colour),
+        ChessPiece::King(colour) => (5, colour),
+        ChessPiece::Blank => return None,
+    };
+    Some(2 * kind + usize::from(colour == Colour::White))
+}
+
+// > give the key of a piece on a square, or 0 for an empty square so it can be XORed in regardless
+pub(crate) fn piece_key(keys: &[u64], piece: ChessPiece, row: usize, col: usize) -> u64 {
+    piece_index(piece).map_or(0, |kind| keys[64 * kind + 8 * row + col])
+}
+
+// > give the key of the castling rights, white kingside, white queenside, black kingside, black queenside
+pub(crate) fn castling_key(keys: &[u64], castling: &CastlingRights) -> u64 {
+    let rights = [(Colour::White, true), (Colour::White, false), (Colour::Black, true), (Colour::Black, false)];
+    rights
+        .into_iter()
+        .enumerate()
+        .filter(|&(_, (colour, kingside))| castling.allows(colour, kingside))
+        .fold(0, |key, (i, _)| key ^ keys[CASTLE_OFFSET + i])
+}
+
+pub(crate) fn turn_key(keys: &[u64], colour: Colour) -> u64 {
+    if colour == Colour::White { keys[TURN_OFFSET] } else { 0 }
+}
+
+// > compute the key of the pieces, castling rights and side to move from scratch; en passant is left out
+// The en passant part depends on which pawns could capture, so callers add it when they need it.
+pub(crate) fn hash(keys: &[u64], state: &Position) -> u64 {
+    let mut key = castling_key(keys, &state.castling) ^ turn_key(keys, state.current_player);
+    for (square, piece) in state.pieces() {
+        let (row, col) = square.to_row_col();
+        key ^= piece_key(keys, piece, row, col);
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GameState, Move, Square};
+
+    #[test]
+    fn test_keys_are_distinct() {
+        let mut keys = KEYS.to_vec();
+        keys.sort_unstable();
+        keys.dedup();
+        assert_eq!(keys.len(), RANDOM_COUNT);
+    }
+
+    #[test]
+    fn test_incremental_key_matches_full_key() {
+        let mut position = GameState::from_fen("r3k2r/pppq1ppp/8/3Pp3/8/8/PpP2PPP/R3K2R w KQkq e6 0 1").unwrap();
+        let start = position.clone();
+        let mut undos = Vec::new();
+        // En passant, castling both ways, a capture and a promotion with capture.
+        for (from, to) in [(Square::D5, Square::E6), (Square::E8, Square::C8), (Square::E1, Square::G1), (Square::B2, Square::A1)] {
+            let mv = position.legal_moves().into_iter().find(|mv| mv.from == from && mv.to == to).unwrap();
+            undos.push(position.make_move(mv).unwrap());
+            assert_eq!(position.hash, hash(&KEYS, &position), "after {:?}", mv);
+        }
+        for undo in undos.into_iter().rev() {
+            position.unmake_move(undo);
+            assert_eq!(position.hash, hash(&KEYS, &position));
+        }
+        assert_eq!(position, start);
+    }
+
+    #[test]
+    fn test_transpositions_share_a_key() {
+        let play = |moves: &[(Square, Square)]| {
+            let mut position = GameState::new();
+            for &(from, to) in moves {
+                position.make_move(Move::new(from, to)).unwrap();
+            }
+            position.position_key()
+        };
+        let knights_first = play(&[(Square::G1, Square::F3), (Square::G8, Square::F6), (Square::B1, Square::C3)]);
+        let other_order = play(&[(Square::B1, Square::C3), (Square::G8, Square::F6), (Square::G1, Square::F3)]);
+        assert_eq!(knights_first, other_order);
+        assert_ne!(knights_first, GameState::new().position_key());
+    }
+}